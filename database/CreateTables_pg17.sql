@@ -19,6 +19,94 @@ create table if not exists stocks (
                   foreign key(id) references assets(id)
                 );
 
+CREATE TABLE IF NOT EXISTS bonds (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                isin CHAR(12) UNIQUE,
+                issuer TEXT,
+                currency_id INT NOT NULL,
+                maturity DATE NOT NULL,
+                denomination BIGINT NOT NULL,
+                spec JSON NOT NULL,
+                FOREIGN KEY(id) REFERENCES assets(id),
+                FOREIGN KEY(currency_id) REFERENCES currencies(id)
+            );
+
+CREATE TABLE IF NOT EXISTS funds (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                isin CHAR(12) UNIQUE,
+                wkn CHAR(6) UNIQUE,
+                ter FLOAT8,
+                distribution TEXT NOT NULL,
+                domicile TEXT,
+                note TEXT,
+                FOREIGN KEY(id) REFERENCES assets(id)
+            );
+CREATE TABLE IF NOT EXISTS fund_constituents (
+                id SERIAL PRIMARY KEY,
+                fund_id INTEGER NOT NULL,
+                asset_id INTEGER,
+                name TEXT NOT NULL,
+                weight FLOAT8 NOT NULL,
+                FOREIGN KEY(fund_id) REFERENCES funds(id),
+                FOREIGN KEY(asset_id) REFERENCES assets(id)
+            );
+
+CREATE TABLE IF NOT EXISTS cryptos (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                symbol TEXT NOT NULL,
+                chain TEXT,
+                decimals INT NOT NULL,
+                note TEXT,
+                UNIQUE(symbol, chain),
+                FOREIGN KEY(id) REFERENCES assets(id)
+            );
+CREATE TABLE IF NOT EXISTS commodities (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                unit TEXT NOT NULL,
+                note TEXT,
+                FOREIGN KEY(id) REFERENCES assets(id)
+            );
+
+CREATE TABLE IF NOT EXISTS asset_identifiers (
+                id SERIAL PRIMARY KEY,
+                asset_id INTEGER NOT NULL,
+                id_type TEXT NOT NULL,
+                value TEXT NOT NULL,
+                UNIQUE(id_type, value),
+                FOREIGN KEY(asset_id) REFERENCES assets(id)
+            );
+
+CREATE TABLE IF NOT EXISTS corporate_actions (
+                id SERIAL PRIMARY KEY,
+                kind TEXT NOT NULL,
+                asset_id INTEGER NOT NULL,
+                action_date DATE NOT NULL,
+                note TEXT,
+                FOREIGN KEY(asset_id) REFERENCES assets(id)
+            );
+CREATE TABLE IF NOT EXISTS corporate_action_targets (
+                id SERIAL PRIMARY KEY,
+                action_id INTEGER NOT NULL,
+                asset_id INTEGER NOT NULL,
+                ratio FLOAT8 NOT NULL,
+                cost_share FLOAT8 NOT NULL,
+                FOREIGN KEY(action_id) REFERENCES corporate_actions(id),
+                FOREIGN KEY(asset_id) REFERENCES assets(id)
+            );
+
+CREATE TABLE IF NOT EXISTS accounts (
+                id SERIAL PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                currency_id INT NOT NULL,
+                owner TEXT,
+                broker TEXT,
+                FOREIGN KEY(currency_id) REFERENCES currencies(id)
+            );
+
 CREATE TABLE IF NOT EXISTS transactions (
                 id SERIAL PRIMARY KEY,
                 trans_type TEXT NOT NULL,
@@ -29,9 +117,14 @@ CREATE TABLE IF NOT EXISTS transactions (
                 related_trans INTEGER,
                 position FLOAT8,
                 note TEXT,
+                account_id INTEGER,
+                tax_kind TEXT,
+                country TEXT,
+                acquisition_date DATE,
                 FOREIGN KEY(asset_id) REFERENCES assets(id),
                 FOREIGN KEY(cash_currency_id) REFERENCES currencies(id),
-                FOREIGN KEY(related_trans) REFERENCES transactions(id)
+                FOREIGN KEY(related_trans) REFERENCES transactions(id),
+                FOREIGN KEY(account_id) REFERENCES accounts(id)
             );
 CREATE TABLE IF NOT EXISTS ticker (
                 id SERIAL PRIMARY KEY,
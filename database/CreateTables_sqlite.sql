@@ -0,0 +1,152 @@
+CREATE TABLE IF NOT EXISTS assets (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                asset_class VARCHAR(20) NOT NULL
+            );
+
+CREATE TABLE IF NOT EXISTS currencies (
+                    id INTEGER PRIMARY KEY,
+                    iso_code CHAR(3) NOT NULL UNIQUE,
+                    rounding_digits INT NOT NULL,
+                    FOREIGN KEY(id) REFERENCES assets(id)
+                );
+
+CREATE TABLE IF NOT EXISTS stocks (
+                  id INTEGER PRIMARY KEY,
+                  name TEXT NOT NULL UNIQUE,
+                  wkn CHAR(6) UNIQUE,
+                  isin CHAR(12) UNIQUE,
+                  note TEXT,
+                  FOREIGN KEY(id) REFERENCES assets(id)
+                );
+
+CREATE TABLE IF NOT EXISTS bonds (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                isin CHAR(12) UNIQUE,
+                issuer TEXT,
+                currency_id INT NOT NULL,
+                maturity DATE NOT NULL,
+                denomination BIGINT NOT NULL,
+                spec JSON NOT NULL,
+                FOREIGN KEY(id) REFERENCES assets(id),
+                FOREIGN KEY(currency_id) REFERENCES currencies(id)
+            );
+
+CREATE TABLE IF NOT EXISTS funds (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                isin CHAR(12) UNIQUE,
+                wkn CHAR(6) UNIQUE,
+                ter FLOAT8,
+                distribution TEXT NOT NULL,
+                domicile TEXT,
+                note TEXT,
+                FOREIGN KEY(id) REFERENCES assets(id)
+            );
+CREATE TABLE IF NOT EXISTS fund_constituents (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                fund_id INTEGER NOT NULL,
+                asset_id INTEGER,
+                name TEXT NOT NULL,
+                weight FLOAT8 NOT NULL,
+                FOREIGN KEY(fund_id) REFERENCES funds(id),
+                FOREIGN KEY(asset_id) REFERENCES assets(id)
+            );
+
+CREATE TABLE IF NOT EXISTS cryptos (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                symbol TEXT NOT NULL,
+                chain TEXT,
+                decimals INT NOT NULL,
+                note TEXT,
+                UNIQUE(symbol, chain),
+                FOREIGN KEY(id) REFERENCES assets(id)
+            );
+CREATE TABLE IF NOT EXISTS commodities (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                unit TEXT NOT NULL,
+                note TEXT,
+                FOREIGN KEY(id) REFERENCES assets(id)
+            );
+
+CREATE TABLE IF NOT EXISTS asset_identifiers (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                asset_id INTEGER NOT NULL,
+                id_type TEXT NOT NULL,
+                value TEXT NOT NULL,
+                UNIQUE(id_type, value),
+                FOREIGN KEY(asset_id) REFERENCES assets(id)
+            );
+
+CREATE TABLE IF NOT EXISTS corporate_actions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                asset_id INTEGER NOT NULL,
+                action_date DATE NOT NULL,
+                note TEXT,
+                FOREIGN KEY(asset_id) REFERENCES assets(id)
+            );
+CREATE TABLE IF NOT EXISTS corporate_action_targets (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                action_id INTEGER NOT NULL,
+                asset_id INTEGER NOT NULL,
+                ratio FLOAT8 NOT NULL,
+                cost_share FLOAT8 NOT NULL,
+                FOREIGN KEY(action_id) REFERENCES corporate_actions(id),
+                FOREIGN KEY(asset_id) REFERENCES assets(id)
+            );
+
+CREATE TABLE IF NOT EXISTS accounts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                currency_id INT NOT NULL,
+                owner TEXT,
+                broker TEXT,
+                FOREIGN KEY(currency_id) REFERENCES currencies(id)
+            );
+
+CREATE TABLE IF NOT EXISTS transactions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                trans_type TEXT NOT NULL,
+                asset_id INTEGER,
+                cash_amount FLOAT8 NOT NULL,
+                cash_currency_id INT NOT NULL,
+                cash_date DATE NOT NULL,
+                related_trans INTEGER,
+                position FLOAT8,
+                note TEXT,
+                account_id INTEGER,
+                tax_kind TEXT,
+                country TEXT,
+                acquisition_date DATE,
+                FOREIGN KEY(asset_id) REFERENCES assets(id),
+                FOREIGN KEY(cash_currency_id) REFERENCES currencies(id),
+                FOREIGN KEY(related_trans) REFERENCES transactions(id),
+                FOREIGN KEY(account_id) REFERENCES accounts(id)
+            );
+CREATE TABLE IF NOT EXISTS ticker (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                asset_id INTEGER NOT NULL,
+                source TEXT NOT NULL,
+                priority INTEGER NOT NULL,
+                currency_id INT NOT NULL,
+                factor FLOAT8 NOT NULL DEFAULT 1.0,
+                tz TEXT,
+                cal TEXT,
+                FOREIGN KEY(asset_id) REFERENCES assets(id),
+                FOREIGN KEY(currency_id) REFERENCES currencies(id)
+            );
+CREATE TABLE IF NOT EXISTS quotes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                ticker_id INTEGER NOT NULL,
+                price FLOAT8 NOT NULL,
+                time TIMESTAMP NOT NULL,
+                volume FLOAT8,
+                FOREIGN KEY(ticker_id) REFERENCES ticker(id)
+            );
+CREATE TABLE IF NOT EXISTS objects (
+            id TEXT PRIMARY KEY,
+            object JSON NOT NULL);
@@ -4,7 +4,6 @@ use finql::{
     bond::Bond,
     fixed_income::{get_cash_flows_after, FixedIncome},
 };
-use serde_json;
 use std::fs::File;
 use std::io::Read;
 use std::str::FromStr;
@@ -78,6 +77,6 @@ async fn main() {
         if i < cfs2.len() {
             print!("{}", cfs2[i]);
         }
-        println!("");
+        println!();
     }
 }
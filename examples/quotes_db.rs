@@ -1,5 +1,5 @@
-///! Demonstration of storing quotes and related data in PostgreSQL
-///! Please note: All existing content of the database will be deleted!
+//! Demonstration of storing quotes and related data in PostgreSQL
+//! Please note: All existing content of the database will be deleted!
 use std::io::{stdout, Write};
 use std::sync::Arc;
 
@@ -1,5 +1,5 @@
-///! Demonstrate total return calculation by single investment in dividend stock
-///! Please note: All existing content of the database will be deleted!
+//! Demonstrate total return calculation by single investment in dividend stock
+//! Please note: All existing content of the database will be deleted!
 use std::cmp::min;
 use std::error::Error;
 use std::sync::Arc;
@@ -7,7 +7,6 @@ use std::sync::Arc;
 use chrono::{DateTime, Utc};
 use log::debug;
 use plotters::prelude::*;
-use pretty_env_logger;
 use time::{Date, OffsetDateTime};
 
 use cal_calc::last_day_of_month;
@@ -30,7 +29,7 @@ use finql::{
 
 async fn calc_strategy(
     currency: Currency,
-    start_transactions: &Vec<Transaction>,
+    start_transactions: &[Transaction],
     strategy: &dyn Strategy,
     start: Date,
     end: Date,
@@ -38,7 +37,7 @@ async fn calc_strategy(
 ) -> Vec<TimeValue> {
     let mut current_date = start;
     let mut total_return = Vec::new();
-    let mut transactions = start_transactions.clone();
+    let mut transactions = start_transactions.to_vec();
 
     let mut position = PortfolioPosition::new(currency);
     calc_delta_position(
@@ -175,6 +174,7 @@ async fn main() {
         transaction_type: TransactionType::Cash,
         cash_flow,
         note: Some("start capital".to_string()),
+        account_id: None,
     });
     let asset_price = market
         .get_asset_price(asset_id, usd, start_time)
@@ -193,13 +193,13 @@ async fn main() {
             date: start,
         },
         note: Some("Initial asset buy transaction".to_string()),
+        account_id: None,
     });
 
     let mut all_time_series = Vec::new();
 
     let reinvest_strategy_no_tax_no_fee = ReInvestInSingleStock::new(
         asset_id,
-        ticker_id,
         market.clone(),
         dividends.clone(),
         Default::default(),
@@ -221,14 +221,10 @@ async fn main() {
     let costs = StockTransactionCosts {
         fee: StockTransactionFee::new(5.0, Some(30.0), 0.0025),
         tax_rate: 0.25 * 1.07,
+        ..Default::default()
     };
-    let reinvest_strategy = ReInvestInSingleStock::new(
-        asset_id,
-        ticker_id,
-        market.clone(),
-        dividends.clone(),
-        costs.clone(),
-    );
+    let reinvest_strategy =
+        ReInvestInSingleStock::new(asset_id, market.clone(), dividends.clone(), costs.clone());
     let reinvest_returns = calc_strategy(
         usd,
         &transactions,
@@ -294,7 +290,7 @@ async fn main() {
 }
 
 fn convert_to_utc(time: &OffsetDateTime) -> DateTime<Utc> {
-    return DateTime::from_timestamp_secs(time.unix_timestamp()).unwrap();
+    DateTime::from_timestamp_secs(time.unix_timestamp()).unwrap()
 }
 
 fn make_plot(
@@ -307,7 +303,7 @@ fn make_plot(
 
     root.fill(&WHITE)?;
 
-    if all_time_series.len() == 0 {
+    if all_time_series.is_empty() {
         return Err(Box::new(TimeSeriesError::IsEmpty));
     }
     let (mut min_date, mut max_date, mut min_val, mut max_val) = all_time_series[0].min_max()?;
@@ -364,7 +360,7 @@ fn make_plot(
         .axis_desc_style(("sans-serif", 20))
         .draw()?;
 
-    static COLORS: [&'static RGBColor; 5] = [&BLUE, &GREEN, &RED, &CYAN, &MAGENTA];
+    static COLORS: [&RGBColor; 5] = [&BLUE, &GREEN, &RED, &CYAN, &MAGENTA];
     let mut color_index: usize = 0;
     for ts in all_time_series {
         chart
@@ -379,7 +375,7 @@ fn make_plot(
 
     chart
         .configure_series_labels()
-        .border_style(&BLACK)
+        .border_style(BLACK)
         .position(SeriesLabelPosition::UpperLeft)
         .label_font(("sans-serif", 20))
         .draw()?;
@@ -1,6 +1,7 @@
-///! Demonstration of storing Assets in Sqlite3 database
+//! Demonstration of storing Assets in Sqlite3 database
 use finql::datatypes::{
-    Asset, CashFlow, CurrencyISOCode, Stock, Transaction, TransactionHandler, TransactionType,
+    Asset, CashFlow, CurrencyISOCode, Stock, TaxKind, Transaction, TransactionHandler,
+    TransactionType,
 };
 use finql::postgres::PostgresDB;
 use time::{Date, Month};
@@ -41,6 +42,7 @@ async fn transaction_tests(db: &dyn TransactionHandler) {
         transaction_type: TransactionType::Cash,
         cash_flow,
         note: Some("start capital".to_string()),
+        account_id: None,
     };
     let result = db.insert_transaction(&cash_in).await;
     match result {
@@ -63,6 +65,7 @@ async fn transaction_tests(db: &dyn TransactionHandler) {
         },
         cash_flow,
         note: None,
+        account_id: None,
     };
     let trans_id = db.insert_transaction(&asset_buy).await.unwrap();
     println!("ok");
@@ -80,6 +83,7 @@ async fn transaction_tests(db: &dyn TransactionHandler) {
             Date::from_calendar_date(2020, Month::January, 15).unwrap(),
         ),
         note: None,
+        account_id: None,
     };
     let _ = db.insert_transaction(&fee).await.unwrap();
     println!("ok");
@@ -95,6 +99,7 @@ async fn transaction_tests(db: &dyn TransactionHandler) {
             Date::from_calendar_date(2020, Month::January, 30).unwrap(),
         ),
         note: None,
+        account_id: None,
     };
     let dividend_id = db.insert_transaction(&dividend).await.unwrap();
     println!("ok");
@@ -105,6 +110,8 @@ async fn transaction_tests(db: &dyn TransactionHandler) {
         id: None,
         transaction_type: TransactionType::Tax {
             transaction_ref: Some(dividend_id),
+            kind: TaxKind::Domestic,
+            country: None,
         },
         cash_flow: CashFlow::new(
             -40.0,
@@ -112,6 +119,7 @@ async fn transaction_tests(db: &dyn TransactionHandler) {
             Date::from_calendar_date(2020, Month::January, 30).unwrap(),
         ),
         note: None,
+        account_id: None,
     };
     let _ = db.insert_transaction(&tax).await.unwrap();
     println!("ok");
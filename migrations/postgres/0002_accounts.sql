@@ -0,0 +1,10 @@
+-- Accounts (or portfolios) transactions are booked to
+CREATE TABLE IF NOT EXISTS accounts (
+                id SERIAL PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                currency_id INT NOT NULL,
+                owner TEXT,
+                broker TEXT,
+                FOREIGN KEY(currency_id) REFERENCES currencies(id)
+            );
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS account_id INTEGER REFERENCES accounts(id);
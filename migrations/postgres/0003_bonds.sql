@@ -0,0 +1,13 @@
+-- Bonds as first-class assets, the full bond specification is stored as JSON
+CREATE TABLE IF NOT EXISTS bonds (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                isin CHAR(12) UNIQUE,
+                issuer TEXT,
+                currency_id INT NOT NULL,
+                maturity DATE NOT NULL,
+                denomination BIGINT NOT NULL,
+                spec JSON NOT NULL,
+                FOREIGN KEY(id) REFERENCES assets(id),
+                FOREIGN KEY(currency_id) REFERENCES currencies(id)
+            );
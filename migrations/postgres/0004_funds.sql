@@ -0,0 +1,21 @@
+-- Funds and ETFs as assets with optional constituents for look-through analysis
+CREATE TABLE IF NOT EXISTS funds (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                isin CHAR(12) UNIQUE,
+                wkn CHAR(6) UNIQUE,
+                ter FLOAT8,
+                distribution TEXT NOT NULL,
+                domicile TEXT,
+                note TEXT,
+                FOREIGN KEY(id) REFERENCES assets(id)
+            );
+CREATE TABLE IF NOT EXISTS fund_constituents (
+                id SERIAL PRIMARY KEY,
+                fund_id INTEGER NOT NULL,
+                asset_id INTEGER,
+                name TEXT NOT NULL,
+                weight FLOAT8 NOT NULL,
+                FOREIGN KEY(fund_id) REFERENCES funds(id),
+                FOREIGN KEY(asset_id) REFERENCES assets(id)
+            );
@@ -0,0 +1,18 @@
+-- Crypto currencies and commodities as assets
+CREATE TABLE IF NOT EXISTS cryptos (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                symbol TEXT NOT NULL,
+                chain TEXT,
+                decimals INT NOT NULL,
+                note TEXT,
+                UNIQUE(symbol, chain),
+                FOREIGN KEY(id) REFERENCES assets(id)
+            );
+CREATE TABLE IF NOT EXISTS commodities (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                unit TEXT NOT NULL,
+                note TEXT,
+                FOREIGN KEY(id) REFERENCES assets(id)
+            );
@@ -0,0 +1,9 @@
+-- Additional security identifiers (e.g. CUSIP, SEDOL, FIGI) of assets
+CREATE TABLE IF NOT EXISTS asset_identifiers (
+                id SERIAL PRIMARY KEY,
+                asset_id INTEGER NOT NULL,
+                id_type TEXT NOT NULL,
+                value TEXT NOT NULL,
+                UNIQUE(id_type, value),
+                FOREIGN KEY(asset_id) REFERENCES assets(id)
+            );
@@ -0,0 +1,18 @@
+-- Corporate actions transforming positions in one asset into positions in other assets
+CREATE TABLE IF NOT EXISTS corporate_actions (
+                id SERIAL PRIMARY KEY,
+                kind TEXT NOT NULL,
+                asset_id INTEGER NOT NULL,
+                action_date DATE NOT NULL,
+                note TEXT,
+                FOREIGN KEY(asset_id) REFERENCES assets(id)
+            );
+CREATE TABLE IF NOT EXISTS corporate_action_targets (
+                id SERIAL PRIMARY KEY,
+                action_id INTEGER NOT NULL,
+                asset_id INTEGER NOT NULL,
+                ratio FLOAT8 NOT NULL,
+                cost_share FLOAT8 NOT NULL,
+                FOREIGN KEY(action_id) REFERENCES corporate_actions(id),
+                FOREIGN KEY(asset_id) REFERENCES assets(id)
+            );
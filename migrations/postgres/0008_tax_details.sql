@@ -0,0 +1,3 @@
+-- Kind of tax and levying country of tax transactions
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS tax_kind TEXT;
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS country TEXT;
@@ -0,0 +1,2 @@
+-- Acquisition date of securities delivered into the portfolio by transfer transactions
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS acquisition_date DATE;
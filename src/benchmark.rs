@@ -0,0 +1,426 @@
+//! Comparison of a portfolio's performance with a benchmark asset, e.g. an index or an ETF
+//!
+//! The portfolio's values, e.g. the value series of `history::calc_portfolio_history`, are
+//! aligned with the benchmark's prices on the business days of a calendar. The returns of
+//! succeeding sample dates are compared to derive relative performance figures. All annualized
+//! figures assume the number of periods per year observed within the sample.
+use cal_calc::Calendar;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use time::Date;
+
+use crate::datatypes::Currency;
+use crate::helpers::{mean, sample_covariance, sample_variance};
+use crate::market::MarketError;
+use crate::time_series::{TimeSeries, TimeValue};
+use crate::Market;
+
+/// Values below this threshold are considered as no investment
+const VALUE_TOLERANCE: f64 = 1e-8;
+
+#[derive(Error, Debug)]
+pub enum BenchmarkError {
+    #[error("At least two sample dates with portfolio value and benchmark price are required")]
+    NotEnoughData,
+    #[error("Market error")]
+    MarketError(#[from] MarketError),
+}
+
+/// Performance of a portfolio relative to a benchmark
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BenchmarkComparison {
+    pub benchmark_id: i32,
+    /// First sample date
+    pub start: Date,
+    /// Last sample date
+    pub end: Date,
+    /// Number of return periods between the sample dates
+    pub periods: usize,
+    /// Cumulative return of the portfolio
+    pub portfolio_return: f64,
+    /// Cumulative return of the benchmark
+    pub benchmark_return: f64,
+    /// Portfolio return less benchmark return
+    pub excess_return: f64,
+    pub portfolio_return_annualized: f64,
+    pub benchmark_return_annualized: f64,
+    pub excess_return_annualized: f64,
+    /// Annualized standard deviation of the differences of periodic returns
+    pub tracking_error: f64,
+    /// Annualized mean difference of periodic returns divided by the tracking error,
+    /// None if the tracking error is zero
+    pub information_ratio: Option<f64>,
+    /// Sensitivity of the portfolio's returns to the benchmark's returns, None if the
+    /// benchmark's returns do not vary
+    pub beta: Option<f64>,
+    /// Annualized mean periodic return not explained by beta, None if beta is undefined
+    pub alpha: Option<f64>,
+    /// Mean portfolio return divided by mean benchmark return of all periods with rising
+    /// benchmark, None if there are no such periods
+    pub up_capture: Option<f64>,
+    /// Mean portfolio return divided by mean benchmark return of all periods with falling
+    /// benchmark, None if there are no such periods
+    pub down_capture: Option<f64>,
+    /// Cumulative return of the portfolio relative to the benchmark at each sample date,
+    /// i.e. the growth of the portfolio divided by the growth of the benchmark less one
+    pub relative: TimeSeries,
+}
+
+/// Ratio of the mean portfolio return and the mean benchmark return of the selected periods
+fn capture(returns: &[(f64, f64)], select: impl Fn(f64) -> bool) -> Option<f64> {
+    let (portfolio, benchmark): (Vec<f64>, Vec<f64>) =
+        returns.iter().filter(|(_, b)| select(*b)).copied().unzip();
+    if benchmark.is_empty() {
+        None
+    } else {
+        Some(mean(&portfolio) / mean(&benchmark))
+    }
+}
+
+/// Compare the portfolio values with the prices of the benchmark asset in the portfolio's
+/// currency. Only samples at business days of the calendar for which a benchmark price is
+/// available are taken into account, the latest price before the sample's time is used.
+/// If the portfolio received external flows, `invested_capital` must provide the sum of all
+/// flows at each sample, e.g. the invested capital series of the portfolio history, so that
+/// flows are not counted as returns.
+pub async fn compare_to_benchmark(
+    values: &TimeSeries,
+    invested_capital: Option<&TimeSeries>,
+    benchmark_id: i32,
+    currency: Currency,
+    calendar: &Calendar,
+    market: &Market,
+) -> Result<BenchmarkComparison, BenchmarkError> {
+    // aligned samples of time, portfolio value, invested capital and benchmark price
+    let mut samples = Vec::new();
+    for (idx, sample) in values.series.iter().enumerate() {
+        if !calendar.is_business_day(sample.time.date()) {
+            continue;
+        }
+        let capital = match invested_capital {
+            Some(capital) => capital
+                .series
+                .get(idx)
+                .filter(|c| c.time == sample.time)
+                .or_else(|| capital.series.iter().find(|c| c.time == sample.time))
+                .map(|c| c.value),
+            None => Some(0.0),
+        };
+        let price = match market
+            .get_split_adjusted_asset_price(benchmark_id, currency, sample.time)
+            .await
+        {
+            Ok(price) => Some(price),
+            Err(err) if err.is_missing_quote() => None,
+            Err(err) => return Err(err.into()),
+        };
+        if let (Some(capital), Some(price)) = (capital, price) {
+            samples.push((sample.time, sample.value, capital, price));
+        }
+    }
+    if samples.len() < 2 {
+        return Err(BenchmarkError::NotEnoughData);
+    }
+
+    let mut returns = Vec::new();
+    let mut relative = TimeSeries::new("relative return");
+    let (mut portfolio_growth, mut benchmark_growth) = (1.0, 1.0);
+    relative.series.push(TimeValue {
+        time: samples[0].0,
+        value: 0.0,
+    });
+    for window in samples.windows(2) {
+        let (_, last_value, last_capital, last_price) = window[0];
+        let (time, value, capital, price) = window[1];
+        let flow = capital - last_capital;
+        // contributions are invested at the beginning, withdrawals are paid at the end of
+        // the period
+        let invested = last_value + flow.max(0.0);
+        let portfolio_return = if invested.abs() > VALUE_TOLERANCE {
+            (value - flow.min(0.0)) / invested - 1.0
+        } else {
+            0.0
+        };
+        let benchmark_return = price / last_price - 1.0;
+        portfolio_growth *= 1.0 + portfolio_return;
+        benchmark_growth *= 1.0 + benchmark_return;
+        returns.push((portfolio_return, benchmark_return));
+        relative.series.push(TimeValue {
+            time,
+            value: portfolio_growth / benchmark_growth - 1.0,
+        });
+    }
+
+    let start = samples[0].0.date();
+    let end = samples[samples.len() - 1].0.date();
+    if end == start {
+        return Err(BenchmarkError::NotEnoughData);
+    }
+    let years = (end - start).whole_days() as f64 / 365.0;
+    let periods_per_year = returns.len() as f64 / years;
+    let annualize = |growth: f64| growth.powf(1.0 / years) - 1.0;
+    let (portfolio, benchmark): (Vec<f64>, Vec<f64>) = returns.iter().copied().unzip();
+    let active: Vec<f64> = returns.iter().map(|(p, b)| p - b).collect();
+    let tracking_error = if active.len() > 1 {
+        (sample_variance(&active) * periods_per_year).sqrt()
+    } else {
+        0.0
+    };
+    let benchmark_variance = sample_variance(&benchmark);
+    let beta = if benchmark_variance > 0.0 {
+        Some(sample_covariance(&portfolio, &benchmark) / benchmark_variance)
+    } else {
+        None
+    };
+
+    Ok(BenchmarkComparison {
+        benchmark_id,
+        start,
+        end,
+        periods: returns.len(),
+        portfolio_return: portfolio_growth - 1.0,
+        benchmark_return: benchmark_growth - 1.0,
+        excess_return: portfolio_growth - benchmark_growth,
+        portfolio_return_annualized: annualize(portfolio_growth),
+        benchmark_return_annualized: annualize(benchmark_growth),
+        excess_return_annualized: annualize(portfolio_growth) - annualize(benchmark_growth),
+        tracking_error,
+        information_ratio: if tracking_error > 0.0 {
+            Some(mean(&active) * periods_per_year / tracking_error)
+        } else {
+            None
+        },
+        beta,
+        alpha: beta.map(|beta| (mean(&portfolio) - beta * mean(&benchmark)) * periods_per_year),
+        up_capture: capture(&returns, |b| b > 0.0),
+        down_capture: capture(&returns, |b| b < 0.0),
+        relative,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use crate::datatypes::date_time_helper::make_offset_time;
+    use crate::memory::{tests::insert_stock_with_quotes, InMemoryDB};
+    use time::macros::date;
+
+    #[tokio::test]
+    async fn compare_portfolio_to_benchmark() {
+        let tol = 1e-10;
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let index_id = insert_stock_with_quotes(
+            &db,
+            "iShares Core DAX",
+            eur,
+            &[
+                (date!(2021 - 01 - 04), 100.0),
+                (date!(2021 - 01 - 05), 102.0),
+                (date!(2021 - 01 - 06), 101.0),
+                (date!(2021 - 01 - 07), 103.0),
+                (date!(2021 - 01 - 08), 104.0),
+            ],
+        )
+        .await;
+        let series = |values: &[(u32, f64)]| TimeSeries {
+            series: values
+                .iter()
+                .map(|(day, value)| TimeValue {
+                    time: make_offset_time(2021, 1, *day, 20, 0, 0).unwrap(),
+                    value: *value,
+                })
+                .collect(),
+            title: String::new(),
+        };
+        // the sample of the 9th is ignored, since it is a Saturday
+        let values = series(&[
+            (4, 1000.0),
+            (5, 1030.0),
+            (6, 1010.0),
+            (7, 1040.0),
+            (8, 1060.0),
+            (9, 2000.0),
+        ]);
+        let calendar = market.get_calendar("TARGET").unwrap().clone();
+        let comparison = compare_to_benchmark(&values, None, index_id, eur, &calendar, &market)
+            .await
+            .unwrap();
+        assert_eq!(comparison.periods, 4);
+        assert_eq!(comparison.end, date!(2021 - 01 - 08));
+        assert_fuzzy_eq!(comparison.portfolio_return, 0.06, tol);
+        assert_fuzzy_eq!(comparison.benchmark_return, 0.04, tol);
+        assert_fuzzy_eq!(comparison.excess_return, 0.02, tol);
+        let relative = &comparison.relative.series;
+        assert_eq!(relative.len(), 5);
+        assert_fuzzy_eq!(relative[4].value, 1.06 / 1.04 - 1.0, tol);
+
+        let portfolio = [
+            0.03,
+            1010.0 / 1030.0 - 1.0,
+            1040.0 / 1010.0 - 1.0,
+            1060.0 / 1040.0 - 1.0,
+        ];
+        let benchmark = [
+            0.02,
+            101.0 / 102.0 - 1.0,
+            103.0 / 101.0 - 1.0,
+            104.0 / 103.0 - 1.0,
+        ];
+        let periods_per_year = 4.0 / (4.0 / 365.0);
+        let active: Vec<f64> = portfolio
+            .iter()
+            .zip(&benchmark)
+            .map(|(p, b)| p - b)
+            .collect();
+        let tracking_error = (sample_variance(&active) * periods_per_year).sqrt();
+        assert_fuzzy_eq!(comparison.tracking_error, tracking_error, tol);
+        assert_fuzzy_eq!(
+            comparison.information_ratio.unwrap(),
+            mean(&active) * periods_per_year / tracking_error,
+            tol
+        );
+        let beta = comparison.beta.unwrap();
+        assert_fuzzy_eq!(
+            beta,
+            sample_covariance(&portfolio, &benchmark) / sample_variance(&benchmark),
+            tol
+        );
+        // the portfolio moves more strongly than the benchmark
+        assert!(beta > 1.0);
+        assert_fuzzy_eq!(
+            comparison.alpha.unwrap(),
+            (mean(&portfolio) - beta * mean(&benchmark)) * periods_per_year,
+            tol
+        );
+        let up = (portfolio[0] + portfolio[2] + portfolio[3]) / 3.0;
+        let up_benchmark = (benchmark[0] + benchmark[2] + benchmark[3]) / 3.0;
+        assert_fuzzy_eq!(comparison.up_capture.unwrap(), up / up_benchmark, tol);
+        assert_fuzzy_eq!(
+            comparison.down_capture.unwrap(),
+            portfolio[1] / benchmark[1],
+            tol
+        );
+
+        // a deposit of 500 on the 6th is not counted as return
+        let values = series(&[(4, 1000.0), (5, 1030.0), (6, 1510.0)]);
+        let capital = series(&[(4, 1000.0), (5, 1000.0), (6, 1500.0)]);
+        let comparison =
+            compare_to_benchmark(&values, Some(&capital), index_id, eur, &calendar, &market)
+                .await
+                .unwrap();
+        assert_fuzzy_eq!(
+            comparison.portfolio_return,
+            1.03 * 1510.0 / 1530.0 - 1.0,
+            tol
+        );
+        assert!(comparison.down_capture.is_some());
+
+        let values = series(&[(4, 1000.0)]);
+        assert!(matches!(
+            compare_to_benchmark(&values, None, index_id, eur, &calendar, &market).await,
+            Err(BenchmarkError::NotEnoughData)
+        ));
+
+        // samples at the same day can not be annualized
+        let values = TimeSeries {
+            series: vec![
+                TimeValue {
+                    time: make_offset_time(2021, 1, 5, 12, 0, 0).unwrap(),
+                    value: 1000.0,
+                },
+                TimeValue {
+                    time: make_offset_time(2021, 1, 5, 20, 0, 0).unwrap(),
+                    value: 1010.0,
+                },
+            ],
+            title: String::new(),
+        };
+        assert!(matches!(
+            compare_to_benchmark(&values, None, index_id, eur, &calendar, &market).await,
+            Err(BenchmarkError::NotEnoughData)
+        ));
+    }
+
+    #[tokio::test]
+    async fn benchmark_edge_cases() {
+        let tol = 1e-10;
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let usd = market.get_currency_from_str("USD").await.unwrap();
+        let index_id = insert_stock_with_quotes(
+            &db,
+            "iShares Core DAX",
+            eur,
+            &[
+                (date!(2021 - 01 - 04), 100.0),
+                (date!(2021 - 01 - 05), 102.0),
+                (date!(2021 - 01 - 06), 102.0),
+            ],
+        )
+        .await;
+        let series = |values: &[((i32, u8, u8), f64)]| TimeSeries {
+            series: values
+                .iter()
+                .map(|((year, month, day), value)| TimeValue {
+                    time: make_offset_time(*year, *month as u32, *day as u32, 20, 0, 0).unwrap(),
+                    value: *value,
+                })
+                .collect(),
+            title: String::new(),
+        };
+        let calendar = market.get_calendar("TARGET").unwrap().clone();
+
+        // samples before the first benchmark quote are ignored
+        let values = series(&[
+            ((2020, 12, 30), 900.0),
+            ((2021, 1, 4), 1000.0),
+            ((2021, 1, 5), 1030.0),
+        ]);
+        let comparison = compare_to_benchmark(&values, None, index_id, eur, &calendar, &market)
+            .await
+            .unwrap();
+        assert_eq!(comparison.start, date!(2021 - 01 - 04));
+        assert_eq!(comparison.periods, 1);
+        assert_fuzzy_eq!(comparison.portfolio_return, 0.03, tol);
+        // a single period has no tracking error and no benchmark variance
+        assert_fuzzy_eq!(comparison.tracking_error, 0.0, tol);
+        assert!(comparison.information_ratio.is_none());
+        assert!(comparison.beta.is_none());
+        assert!(comparison.alpha.is_none());
+        assert!(comparison.down_capture.is_none());
+        let values = series(&[((2020, 12, 30), 900.0), ((2021, 1, 4), 1000.0)]);
+        assert!(matches!(
+            compare_to_benchmark(&values, None, index_id, eur, &calendar, &market).await,
+            Err(BenchmarkError::NotEnoughData)
+        ));
+
+        // a withdrawal on the last sample date is paid at the end of the period, samples
+        // without invested capital are ignored
+        let values = series(&[
+            ((2021, 1, 4), 1000.0),
+            ((2021, 1, 5), 1050.0),
+            ((2021, 1, 6), 530.0),
+        ]);
+        let capital = series(&[((2021, 1, 4), 1000.0), ((2021, 1, 6), 500.0)]);
+        let comparison =
+            compare_to_benchmark(&values, Some(&capital), index_id, eur, &calendar, &market)
+                .await
+                .unwrap();
+        assert_eq!(comparison.periods, 1);
+        assert_fuzzy_eq!(comparison.portfolio_return, 0.03, tol);
+        assert_fuzzy_eq!(comparison.benchmark_return, 0.02, tol);
+
+        // benchmark prices can't be converted without fx quotes
+        let values = series(&[((2021, 1, 4), 1000.0), ((2021, 1, 5), 1030.0)]);
+        assert!(matches!(
+            compare_to_benchmark(&values, None, index_id, usd, &calendar, &market).await,
+            Err(BenchmarkError::NotEnoughData)
+        ));
+    }
+}
@@ -8,6 +8,7 @@ use time::Date;
 
 use crate::datatypes::cash_flow::CashFlow;
 use crate::datatypes::currency::Currency;
+use crate::datatypes::{DataError, DataItem};
 
 use crate::day_adjust::{AdjustDateError, DayAdjust};
 use crate::day_count_conv::{DayCountConv, DayCountConvError};
@@ -34,8 +35,11 @@ pub enum BondError {
 }
 
 /// Container for bonds and similar fixed income assets
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Bond {
+    /// Asset id, if the bond has been stored to a database
+    #[serde(default)]
+    pub id: Option<i32>,
     /// International security identification number
     isin: Option<String>,
     /// Local or national security identifier
@@ -45,7 +49,7 @@ pub struct Bond {
     /// Issuer of the bond
     issuer: Option<Issuer>,
     bond_type: String,
-    currency: Currency,
+    pub(crate) currency: Currency,
     coupon: Coupon,
     business_day_rule: DayAdjust,
     calendar: String,
@@ -58,7 +62,7 @@ pub struct Bond {
 
 /// Information regarding the issuer of an asset
 /// This is required for determination of some asset's credit worthiness.
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 struct Issuer {
     /// Minimal obligatory information is the name of the issuer
     name: String,
@@ -66,7 +70,7 @@ struct Issuer {
 }
 
 /// Address of an issuer, e.g. city and country of headquarter
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 struct IssuerAddress {
     city: String,
     country: String,
@@ -75,7 +79,7 @@ struct IssuerAddress {
 use super::coupon_date::CouponDate;
 
 /// Coupon specification of fixed income instruments
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 struct Coupon {
     coupon_type: String,
     rate: f64,
@@ -104,6 +108,34 @@ impl Coupon {
 }
 
 impl Bond {
+    /// Short name of the bond, built from issuer (or identifier), coupon rate and maturity
+    pub fn name(&self) -> String {
+        let issuer = match (&self.issuer, &self.isin, &self.security_id) {
+            (Some(issuer), _, _) => issuer.name.clone(),
+            (None, Some(isin), _) => isin.clone(),
+            (None, None, Some(security_id)) => security_id.clone(),
+            (None, None, None) => self.bond_type.clone(),
+        };
+        format!("{} {}% {}", issuer, self.coupon.rate, self.maturity)
+    }
+
+    pub fn isin(&self) -> Option<&str> {
+        self.isin.as_deref()
+    }
+
+    /// Name of the issuer, if known
+    pub fn issuer(&self) -> Option<&str> {
+        self.issuer.as_ref().map(|issuer| issuer.name.as_str())
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    pub fn maturity(&self) -> Date {
+        self.maturity
+    }
+
     /// Calculate first coupon period end date
     fn first_coupon_end(&self, start_date: Date) -> Result<Date, BondError> {
         use std::convert::TryFrom;
@@ -129,6 +161,30 @@ impl Bond {
     }
 }
 
+impl DataItem for Bond {
+    // get id or return error if id hasn't been set yet
+    fn get_id(&self) -> Result<i32, DataError> {
+        match self.id {
+            Some(id) => Ok(id),
+            None => Err(DataError::DataAccessFailure(
+                "Can't get id of temporary bond".to_string(),
+            )),
+        }
+    }
+    // set id or return error if id has already been set
+    fn set_id(&mut self, id: i32) -> Result<(), DataError> {
+        match self.id {
+            Some(_) => Err(DataError::DataAccessFailure(
+                "Can't change id of persistent bond".to_string(),
+            )),
+            None => {
+                self.id = Some(id);
+                Ok(())
+            }
+        }
+    }
+}
+
 impl FixedIncome for Bond {
     type Error = BondError;
 
@@ -222,12 +278,12 @@ mod tests {
             "maturity": [2021, 274],
             "denomination": 1000
         }"#;
-        let bond: Bond = serde_json::from_str(&data).unwrap();
+        let bond: Bond = serde_json::from_str(data).unwrap();
         let calendar = SimpleCalendar::default();
         let cash_flows = bond.rollout_cash_flows(1., &calendar).unwrap();
         assert_eq!(cash_flows.len(), 5);
         let curr = Currency::from_str("EUR").unwrap();
-        let reference_cash_flows = vec![
+        let reference_cash_flows = [
             CashFlow::new(
                 0.05 * 1000. * 183. / 365.,
                 curr,
@@ -281,13 +337,13 @@ mod tests {
             "maturity": [2022, 274],
             "denomination": 1000
         }"#;
-        let bond: Bond = serde_json::from_str(&data).unwrap();
+        let bond: Bond = serde_json::from_str(data).unwrap();
         let sample_calendars = generate_calendars(2020, 2025);
         let calendar = SimpleCalendar::new(&sample_calendars["TARGET"]);
         let cash_flows = bond.rollout_cash_flows(1., &calendar).unwrap();
         assert_eq!(cash_flows.len(), 5);
         let curr = Currency::from_str("EUR").unwrap();
-        let reference_cash_flows = vec![
+        let reference_cash_flows = [
             CashFlow::new(
                 0.05 * 1000. / 2.,
                 curr,
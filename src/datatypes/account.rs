@@ -0,0 +1,57 @@
+//! Implementation of accounts (or portfolios) transactions are booked to
+use super::{Currency, DataError, DataItem};
+use serde::{Deserialize, Serialize};
+
+/// An account groups transactions, e.g. of a single depot, a cash account or a savings plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub id: Option<i32>,
+    /// Unique name of the account
+    pub name: String,
+    /// Base currency the account is kept in
+    pub currency: Currency,
+    pub owner: Option<String>,
+    pub broker: Option<String>,
+}
+
+impl Account {
+    pub fn new(
+        id: Option<i32>,
+        name: String,
+        currency: Currency,
+        owner: Option<String>,
+        broker: Option<String>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            currency,
+            owner,
+            broker,
+        }
+    }
+}
+
+impl DataItem for Account {
+    // get id or return error if id hasn't been set yet
+    fn get_id(&self) -> Result<i32, DataError> {
+        match self.id {
+            Some(id) => Ok(id),
+            None => Err(DataError::DataAccessFailure(
+                "Can't get id of temporary account".to_string(),
+            )),
+        }
+    }
+    // set id or return error if id has already been set
+    fn set_id(&mut self, id: i32) -> Result<(), DataError> {
+        match self.id {
+            Some(_) => Err(DataError::DataAccessFailure(
+                "Can't change id of persistent account".to_string(),
+            )),
+            None => {
+                self.id = Some(id);
+                Ok(())
+            }
+        }
+    }
+}
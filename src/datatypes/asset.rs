@@ -1,13 +1,18 @@
 //! Implementation of a container for basic asset data
 use serde::{Deserialize, Serialize};
 
-use super::{Currency, DataError, DataItem, Stock};
+use super::{Commodity, Crypto, Currency, DataError, DataItem, Fund, Stock};
+use crate::bond::Bond;
 
 /// Asset enum could contain any supported asset
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Asset {
     Currency(Currency),
     Stock(Stock),
+    Bond(Box<Bond>),
+    Fund(Fund),
+    Crypto(Crypto),
+    Commodity(Commodity),
 }
 
 /// AssetSelector is useful for creation of choice list to choose an asset from
@@ -23,6 +28,10 @@ impl Asset {
         match self {
             Self::Currency(_) => "currency".into(),
             Self::Stock(_) => "stock".into(),
+            Self::Bond(_) => "bond".into(),
+            Self::Fund(_) => "fund".into(),
+            Self::Crypto(_) => "crypto".into(),
+            Self::Commodity(_) => "commodity".into(),
         }
     }
 
@@ -30,6 +39,10 @@ impl Asset {
         match self {
             Self::Currency(c) => c.iso_code.to_string(),
             Self::Stock(s) => s.name.clone(),
+            Self::Bond(b) => b.name(),
+            Self::Fund(f) => f.name.clone(),
+            Self::Crypto(c) => c.name.clone(),
+            Self::Commodity(c) => c.name.clone(),
         }
     }
 }
@@ -40,6 +53,10 @@ impl DataItem for Asset {
         match self {
             Asset::Currency(c) => c.get_id(),
             Asset::Stock(s) => s.get_id(),
+            Asset::Bond(b) => b.get_id(),
+            Asset::Fund(f) => f.get_id(),
+            Asset::Crypto(c) => c.get_id(),
+            Asset::Commodity(c) => c.get_id(),
         }
     }
 
@@ -56,6 +73,26 @@ impl DataItem for Asset {
                 s.set_id(id)?;
                 Asset::Stock(s)
             }
+            Asset::Bond(b) => {
+                let mut b = b.clone();
+                b.set_id(id)?;
+                Asset::Bond(b)
+            }
+            Asset::Fund(f) => {
+                let mut f = f.clone();
+                f.set_id(id)?;
+                Asset::Fund(f)
+            }
+            Asset::Crypto(c) => {
+                let mut c = c.clone();
+                c.set_id(id)?;
+                Asset::Crypto(c)
+            }
+            Asset::Commodity(c) => {
+                let mut c = c.clone();
+                c.set_id(id)?;
+                Asset::Commodity(c)
+            }
         };
         Ok(())
     }
@@ -1,6 +1,9 @@
 use async_trait::async_trait;
+use time::Date;
 
-use super::{Asset, AssetSelector, Currency, CurrencyISOCode, DataError};
+use super::{
+    Asset, AssetSelector, CorporateAction, Currency, CurrencyISOCode, DataError, Identifier,
+};
 
 /// Handler for globally available data of transactions and related data
 #[async_trait]
@@ -16,6 +19,36 @@ pub trait AssetHandler {
     async fn get_asset_list(&self) -> Result<Vec<AssetSelector>, DataError>;
     async fn update_asset(&self, asset: &Asset) -> Result<(), DataError>;
     async fn delete_asset(&self, id: i32) -> Result<(), DataError>;
+    /// Store an additional identifier of an asset, e.g. a CUSIP or FIGI; each identifier
+    /// can be assigned to a single asset only
+    async fn add_asset_identifier(
+        &self,
+        asset_id: i32,
+        identifier: &Identifier,
+    ) -> Result<(), DataError>;
+    /// Get all additional identifiers stored for an asset
+    async fn get_asset_identifiers(&self, asset_id: i32) -> Result<Vec<Identifier>, DataError>;
+    async fn delete_asset_identifier(
+        &self,
+        asset_id: i32,
+        identifier: &Identifier,
+    ) -> Result<(), DataError>;
+    /// Find an asset by any of its identifiers. Besides the additional identifiers, the ISIN
+    /// and WKN of stocks, bonds and funds are searched, also for all identifiers equivalent
+    /// to the given one, e.g. a stock stored with ISIN US0378331005 is found by the CUSIP 037833100.
+    async fn find_asset_by_identifier(&self, identifier: &Identifier) -> Result<Asset, DataError>;
+    // insert, get, update and delete for corporate actions
+    async fn insert_corporate_action(&self, action: &CorporateAction) -> Result<i32, DataError>;
+    async fn get_corporate_action_by_id(&self, id: i32) -> Result<CorporateAction, DataError>;
+    /// Return all corporate actions with dates within the given range, both bounds being
+    /// inclusive and optional, ordered by date
+    async fn get_corporate_actions(
+        &self,
+        start: Option<Date>,
+        end: Option<Date>,
+    ) -> Result<Vec<CorporateAction>, DataError>;
+    async fn update_corporate_action(&self, action: &CorporateAction) -> Result<(), DataError>;
+    async fn delete_corporate_action(&self, id: i32) -> Result<(), DataError>;
     async fn get_all_currencies(&self) -> Result<Vec<Currency>, DataError>;
     /// Get a list of currencies as list of AssetSelectors
     async fn get_currency_list(&self) -> Result<Vec<AssetSelector>, DataError>;
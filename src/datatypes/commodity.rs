@@ -0,0 +1,47 @@
+//! Implementation of a container for physical commodities, e.g. precious metals
+use super::{DataError, DataItem};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Commodity {
+    pub id: Option<i32>,
+    pub name: String,
+    /// Unit positions and quotes refer to, e.g. "troy ounce" or "kg"
+    pub unit: String,
+    pub note: Option<String>,
+}
+
+impl Commodity {
+    pub fn new(id: Option<i32>, name: String, unit: String, note: Option<String>) -> Self {
+        Self {
+            id,
+            name,
+            unit,
+            note,
+        }
+    }
+}
+
+impl DataItem for Commodity {
+    // get id or return error if id hasn't been set yet
+    fn get_id(&self) -> Result<i32, DataError> {
+        match self.id {
+            Some(id) => Ok(id),
+            None => Err(DataError::DataAccessFailure(
+                "Can't get id of temporary commodity".to_string(),
+            )),
+        }
+    }
+    // set id or return error if id has already been set
+    fn set_id(&mut self, id: i32) -> Result<(), DataError> {
+        match self.id {
+            Some(_) => Err(DataError::DataAccessFailure(
+                "Can't change id of persistent commodity".to_string(),
+            )),
+            None => {
+                self.id = Some(id);
+                Ok(())
+            }
+        }
+    }
+}
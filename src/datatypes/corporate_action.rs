@@ -0,0 +1,152 @@
+//! Implementation of corporate actions transforming positions in one asset into positions
+//! in one or more other assets, e.g. spin-offs or mergers
+use std::fmt;
+use std::str::FromStr;
+
+use super::{DataError, DataItem};
+use serde::{Deserialize, Serialize};
+use time::Date;
+
+/// Tolerance for checking that cost shares add up to 1
+const COST_SHARE_TOLERANCE: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CorporateActionKind {
+    /// Shares of a new company are distributed to the holders of the source asset
+    SpinOff,
+    /// The source asset is exchanged completely for shares of the target assets
+    Merger,
+    /// Subscription rights are distributed to the holders of the source asset
+    RightsIssue,
+    /// Additional shares, usually of the source asset itself, are distributed instead of cash
+    StockDividend,
+}
+
+impl CorporateActionKind {
+    /// Check whether holders keep their position in the source asset
+    pub fn retains_source(&self) -> bool {
+        !matches!(self, CorporateActionKind::Merger)
+    }
+}
+
+impl fmt::Display for CorporateActionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CorporateActionKind::SpinOff => write!(f, "spin_off"),
+            CorporateActionKind::Merger => write!(f, "merger"),
+            CorporateActionKind::RightsIssue => write!(f, "rights_issue"),
+            CorporateActionKind::StockDividend => write!(f, "stock_dividend"),
+        }
+    }
+}
+
+impl FromStr for CorporateActionKind {
+    type Err = DataError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spin_off" => Ok(CorporateActionKind::SpinOff),
+            "merger" => Ok(CorporateActionKind::Merger),
+            "rights_issue" => Ok(CorporateActionKind::RightsIssue),
+            "stock_dividend" => Ok(CorporateActionKind::StockDividend),
+            _ => Err(DataError::InvalidCorporateAction(format!(
+                "unknown corporate action kind '{s}'"
+            ))),
+        }
+    }
+}
+
+/// Asset received by holders of the source asset of a corporate action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorporateActionTarget {
+    pub asset_id: i32,
+    /// Number of units received per unit of the source asset
+    pub ratio: f64,
+    /// Fraction of the source position's purchase value allocated to this asset
+    pub cost_share: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorporateAction {
+    pub id: Option<i32>,
+    pub kind: CorporateActionKind,
+    /// Asset affected by the corporate action
+    pub asset_id: i32,
+    /// Ex-date, i.e. the first date positions are held in the target assets
+    pub date: Date,
+    pub targets: Vec<CorporateActionTarget>,
+    pub note: Option<String>,
+}
+
+impl CorporateAction {
+    pub fn new(id: Option<i32>, kind: CorporateActionKind, asset_id: i32, date: Date) -> Self {
+        Self {
+            id,
+            kind,
+            asset_id,
+            date,
+            targets: Vec::new(),
+            note: None,
+        }
+    }
+
+    /// Check that ratios are positive and that cost shares are within [0,1] and add up
+    /// to at most 1, or exactly 1 if the source position is given up
+    pub fn validate(&self) -> Result<(), DataError> {
+        if self.targets.is_empty() {
+            return Err(DataError::InvalidCorporateAction(
+                "corporate action without target assets".to_string(),
+            ));
+        }
+        let mut total_cost_share = 0.0;
+        for target in &self.targets {
+            if target.ratio <= 0.0 {
+                return Err(DataError::InvalidCorporateAction(format!(
+                    "ratio of target asset {} must be positive",
+                    target.asset_id
+                )));
+            }
+            if !(0.0..=1.0).contains(&target.cost_share) {
+                return Err(DataError::InvalidCorporateAction(format!(
+                    "cost share of target asset {} must be between 0 and 1",
+                    target.asset_id
+                )));
+            }
+            total_cost_share += target.cost_share;
+        }
+        if total_cost_share > 1.0 + COST_SHARE_TOLERANCE
+            || (!self.kind.retains_source()
+                && (total_cost_share - 1.0).abs() > COST_SHARE_TOLERANCE)
+        {
+            return Err(DataError::InvalidCorporateAction(format!(
+                "cost shares of {} add up to {total_cost_share}",
+                self.kind
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl DataItem for CorporateAction {
+    // get id or return error if id hasn't been set yet
+    fn get_id(&self) -> Result<i32, DataError> {
+        match self.id {
+            Some(id) => Ok(id),
+            None => Err(DataError::DataAccessFailure(
+                "Can't get id of temporary corporate action".to_string(),
+            )),
+        }
+    }
+    // set id or return error if id has already been set
+    fn set_id(&mut self, id: i32) -> Result<(), DataError> {
+        match self.id {
+            Some(_) => Err(DataError::DataAccessFailure(
+                "Can't change id of persistent corporate action".to_string(),
+            )),
+            None => {
+                self.id = Some(id);
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Implementation of a container for crypto currencies and tokens
+use super::{DataError, DataItem};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Crypto {
+    pub id: Option<i32>,
+    pub name: String,
+    /// Trading symbol, e.g. "BTC"
+    pub symbol: String,
+    /// Block chain the token lives on, e.g. "ethereum" for ERC-20 tokens
+    pub chain: Option<String>,
+    /// Number of decimal places of the smallest unit, e.g. 8 for Bitcoin (1 satoshi)
+    pub decimals: i32,
+    pub note: Option<String>,
+}
+
+impl Crypto {
+    pub fn new(
+        id: Option<i32>,
+        name: String,
+        symbol: String,
+        chain: Option<String>,
+        decimals: i32,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            symbol,
+            chain,
+            decimals,
+            note: None,
+        }
+    }
+
+    /// Round a quantity to the smallest unit of the crypto currency.
+    /// Since positions are stored as `f64`, at most 15 decimal places are taken into account.
+    pub fn round_quantity(&self, quantity: f64) -> f64 {
+        let factor = 10f64.powi(self.decimals.clamp(0, 15));
+        (quantity * factor).round() / factor
+    }
+}
+
+impl DataItem for Crypto {
+    // get id or return error if id hasn't been set yet
+    fn get_id(&self) -> Result<i32, DataError> {
+        match self.id {
+            Some(id) => Ok(id),
+            None => Err(DataError::DataAccessFailure(
+                "Can't get id of temporary crypto currency".to_string(),
+            )),
+        }
+    }
+    // set id or return error if id has already been set
+    fn set_id(&mut self, id: i32) -> Result<(), DataError> {
+        match self.id {
+            Some(_) => Err(DataError::DataAccessFailure(
+                "Can't change id of persistent crypto currency".to_string(),
+            )),
+            None => {
+                self.id = Some(id);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_to_smallest_unit() {
+        let btc = Crypto::new(None, "Bitcoin".to_string(), "BTC".to_string(), None, 8);
+        assert_eq!(btc.round_quantity(0.1 + 0.2 - 0.3), 0.0);
+        assert_eq!(btc.round_quantity(0.123456789), 0.12345679);
+    }
+}
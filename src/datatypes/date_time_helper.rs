@@ -195,6 +195,49 @@ pub fn date_from_str(date_str: &str, format: &str) -> Result<Date, DateTimeError
                 Err(DateTimeError::StringParseError)
             }
         }
+        "[year][month][day]" | "%Y%m%d" => {
+            if date_str.len() == 8 && date_str.is_ascii() {
+                let year: i32 = date_str[0..4]
+                    .parse()
+                    .map_err(|_| DateTimeError::StringParseError)?;
+                let month: u8 = date_str[4..6]
+                    .parse()
+                    .map_err(|_| DateTimeError::StringParseError)?;
+                let day: u8 = date_str[6..8]
+                    .parse()
+                    .map_err(|_| DateTimeError::StringParseError)?;
+                Date::from_calendar_date(
+                    year,
+                    Month::try_from(month).map_err(|_| DateTimeError::InvalidDateError)?,
+                    day,
+                )
+                .map_err(|_| DateTimeError::InvalidDateError)
+            } else {
+                Err(DateTimeError::StringParseError)
+            }
+        }
+        "[day].[month].[year]" | "%d.%m.%Y" => {
+            let parts: Vec<&str> = date_str.split('.').collect();
+            if parts.len() == 3 {
+                let day: u8 = parts[0]
+                    .parse()
+                    .map_err(|_| DateTimeError::StringParseError)?;
+                let month: u8 = parts[1]
+                    .parse()
+                    .map_err(|_| DateTimeError::StringParseError)?;
+                let year: i32 = parts[2]
+                    .parse()
+                    .map_err(|_| DateTimeError::StringParseError)?;
+                Date::from_calendar_date(
+                    year,
+                    Month::try_from(month).map_err(|_| DateTimeError::InvalidDateError)?,
+                    day,
+                )
+                .map_err(|_| DateTimeError::InvalidDateError)
+            } else {
+                Err(DateTimeError::StringParseError)
+            }
+        }
         _ => Err(DateTimeError::StringParseError),
     }
 }
@@ -299,5 +342,13 @@ mod tests {
         assert_eq!(date.year(), 2020);
         assert_eq!(date.month() as u8, 2);
         assert_eq!(date.day(), 10);
+        let date = date_from_str("10.02.2020", "%d.%m.%Y").unwrap();
+        assert_eq!(date.year(), 2020);
+        assert_eq!(date.month() as u8, 2);
+        assert_eq!(date.day(), 10);
+        let date = date_from_str("20200210", "%Y%m%d").unwrap();
+        assert_eq!(date.year(), 2020);
+        assert_eq!(date.month() as u8, 2);
+        assert_eq!(date.day(), 10);
     }
 }
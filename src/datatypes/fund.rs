@@ -0,0 +1,113 @@
+//! Implementation of a container for funds and exchange traded funds (ETF)
+use std::fmt;
+use std::str::FromStr;
+
+use super::{DataError, DataItem};
+use serde::{Deserialize, Serialize};
+
+/// Usage of income (dividends, interest) received by the fund
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum DistributionPolicy {
+    /// Income is reinvested by the fund
+    #[default]
+    Accumulating,
+    /// Income is paid out to the investors
+    Distributing,
+}
+
+impl fmt::Display for DistributionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DistributionPolicy::Accumulating => write!(f, "accumulating"),
+            DistributionPolicy::Distributing => write!(f, "distributing"),
+        }
+    }
+}
+
+impl FromStr for DistributionPolicy {
+    type Err = DataError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "accumulating" => Ok(DistributionPolicy::Accumulating),
+            "distributing" => Ok(DistributionPolicy::Distributing),
+            _ => Err(DataError::InvalidAsset(format!(
+                "unknown distribution policy '{s}'"
+            ))),
+        }
+    }
+}
+
+/// Holding of a fund, e.g. a single stock of an index tracked by an ETF
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FundConstituent {
+    /// Id of the constituent, if stored as an asset in the database
+    pub asset_id: Option<i32>,
+    pub name: String,
+    /// Weight of the constituent in the fund as fraction of the fund's net asset value
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Fund {
+    pub id: Option<i32>,
+    pub name: String,
+    pub isin: Option<String>,
+    pub wkn: Option<String>,
+    /// Total expense ratio as fraction per year, e.g. 0.002 for 0.2% p.a.
+    pub ter: Option<f64>,
+    pub distribution: DistributionPolicy,
+    /// Country the fund is domiciled in, e.g. "IE" or "LU"
+    pub domicile: Option<String>,
+    pub note: Option<String>,
+    /// Holdings of the fund, which may cover only part of the fund's net asset value
+    pub constituents: Vec<FundConstituent>,
+}
+
+impl Fund {
+    pub fn new(
+        id: Option<i32>,
+        name: String,
+        isin: Option<String>,
+        wkn: Option<String>,
+        ter: Option<f64>,
+        distribution: DistributionPolicy,
+        domicile: Option<String>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            isin,
+            wkn,
+            ter,
+            distribution,
+            domicile,
+            note: None,
+            constituents: Vec::new(),
+        }
+    }
+}
+
+impl DataItem for Fund {
+    // get id or return error if id hasn't been set yet
+    fn get_id(&self) -> Result<i32, DataError> {
+        match self.id {
+            Some(id) => Ok(id),
+            None => Err(DataError::DataAccessFailure(
+                "Can't get id of temporary fund".to_string(),
+            )),
+        }
+    }
+    // set id or return error if id has already been set
+    fn set_id(&mut self, id: i32) -> Result<(), DataError> {
+        match self.id {
+            Some(_) => Err(DataError::DataAccessFailure(
+                "Can't change id of persistent fund".to_string(),
+            )),
+            None => {
+                self.id = Some(id);
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,310 @@
+//! Security identifiers like ISIN, WKN, CUSIP, SEDOL or FIGI
+//!
+//! Identifiers are normalized to upper case and validated, including their check digit where
+//! the identifier has one. Some national identifiers are part of the ISIN and can be derived
+//! from each other, e.g. the CUSIP of US and Canadian securities or the WKN of most German
+//! securities.
+use std::fmt;
+use std::str::FromStr;
+
+use super::DataError;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum IdentifierType {
+    /// International Securities Identification Number (ISO 6166)
+    Isin,
+    /// German Wertpapierkennnummer
+    Wkn,
+    /// Identifier of the Committee on Uniform Securities Identification Procedures (US, Canada)
+    Cusip,
+    /// Stock Exchange Daily Official List number of the London Stock Exchange
+    Sedol,
+    /// Financial Instrument Global Identifier
+    Figi,
+}
+
+impl fmt::Display for IdentifierType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IdentifierType::Isin => write!(f, "ISIN"),
+            IdentifierType::Wkn => write!(f, "WKN"),
+            IdentifierType::Cusip => write!(f, "CUSIP"),
+            IdentifierType::Sedol => write!(f, "SEDOL"),
+            IdentifierType::Figi => write!(f, "FIGI"),
+        }
+    }
+}
+
+impl FromStr for IdentifierType {
+    type Err = DataError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "ISIN" => Ok(IdentifierType::Isin),
+            "WKN" => Ok(IdentifierType::Wkn),
+            "CUSIP" => Ok(IdentifierType::Cusip),
+            "SEDOL" => Ok(IdentifierType::Sedol),
+            "FIGI" => Ok(IdentifierType::Figi),
+            _ => Err(DataError::InvalidIdentifier(format!(
+                "unknown identifier type '{s}'"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Identifier {
+    pub id_type: IdentifierType,
+    pub value: String,
+}
+
+impl Identifier {
+    /// Create a new identifier, the value is converted to upper case and validated
+    pub fn new(id_type: IdentifierType, value: &str) -> Result<Self, DataError> {
+        let identifier = Self {
+            id_type,
+            value: value.trim().to_uppercase(),
+        };
+        identifier.validate()?;
+        Ok(identifier)
+    }
+
+    /// Check format and check digit of the identifier
+    pub fn validate(&self) -> Result<(), DataError> {
+        let valid = match self.id_type {
+            IdentifierType::Isin => is_valid_isin(&self.value),
+            IdentifierType::Wkn => is_valid_wkn(&self.value),
+            IdentifierType::Cusip => is_valid_cusip(&self.value),
+            IdentifierType::Sedol => is_valid_sedol(&self.value),
+            IdentifierType::Figi => is_valid_figi(&self.value),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(DataError::InvalidIdentifier(format!(
+                "'{}' is not a valid {}",
+                self.value, self.id_type
+            )))
+        }
+    }
+
+    /// Identifiers of the same security which can be derived from this identifier.
+    /// For an ISIN, these are the national identifiers contained in it, i.e. the CUSIP of
+    /// US and Canadian, the WKN of German and the SEDOL of British and Irish securities.
+    /// For a CUSIP, WKN or SEDOL, these are the ISINs the identifier could be part of.
+    pub fn equivalents(&self) -> Vec<Identifier> {
+        let value = self.value.as_str();
+        let candidates = match self.id_type {
+            IdentifierType::Isin if is_valid_isin(value) => match &value[..2] {
+                "US" | "CA" => vec![(IdentifierType::Cusip, Some(value[2..11].to_string()))],
+                "DE" if &value[2..5] == "000" => {
+                    vec![(IdentifierType::Wkn, Some(value[5..11].to_string()))]
+                }
+                "GB" | "IE" if &value[2..4] == "00" => {
+                    vec![(IdentifierType::Sedol, Some(value[4..11].to_string()))]
+                }
+                _ => Vec::new(),
+            },
+            IdentifierType::Cusip => vec![
+                (IdentifierType::Isin, make_isin("US", value)),
+                (IdentifierType::Isin, make_isin("CA", value)),
+            ],
+            IdentifierType::Wkn => vec![(
+                IdentifierType::Isin,
+                make_isin("DE", &format!("000{value}")),
+            )],
+            IdentifierType::Sedol => vec![
+                (IdentifierType::Isin, make_isin("GB", &format!("00{value}"))),
+                (IdentifierType::Isin, make_isin("IE", &format!("00{value}"))),
+            ],
+            _ => Vec::new(),
+        };
+        candidates
+            .into_iter()
+            .filter_map(|(id_type, value)| Identifier::new(id_type, &value?).ok())
+            .collect()
+    }
+
+    /// This identifier, followed by all its equivalents
+    pub fn lookup_keys(&self) -> Vec<Identifier> {
+        let mut keys = vec![self.clone()];
+        keys.extend(self.equivalents());
+        keys
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.id_type, self.value)
+    }
+}
+
+/// Numerical value of an alphanumeric character, digits keep their value,
+/// letters are numbered starting with 10 for 'A'
+fn char_value(c: char) -> Option<u32> {
+    c.to_digit(36)
+}
+
+/// Sum of the decimal digits of a number
+fn digit_sum(n: u32) -> u32 {
+    n / 10 + n % 10
+}
+
+fn is_alphanumeric(s: &str) -> bool {
+    s.chars()
+        .all(|c| c.is_ascii_digit() || c.is_ascii_uppercase())
+}
+
+/// Calculate the check digit of an ISIN given by its first 11 characters
+pub fn isin_check_digit(base: &str) -> Option<char> {
+    if base.len() != 11 || !is_alphanumeric(base) {
+        return None;
+    }
+    // letters are expanded to two digits, then the Luhn algorithm is applied
+    let mut digits = Vec::new();
+    for c in base.chars() {
+        let v = char_value(c)?;
+        if v >= 10 {
+            digits.push(v / 10);
+        }
+        digits.push(v % 10);
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { digit_sum(2 * d) } else { *d })
+        .sum();
+    char::from_digit((10 - sum % 10) % 10, 10)
+}
+
+/// Construct an ISIN from a country code and the 9 characters of the national identifier
+fn make_isin(country: &str, nsin: &str) -> Option<String> {
+    let base = format!("{country}{nsin}");
+    let check = isin_check_digit(&base)?;
+    Some(format!("{base}{check}"))
+}
+
+pub fn is_valid_isin(isin: &str) -> bool {
+    isin.len() == 12
+        && isin.is_ascii()
+        && isin[..2].chars().all(|c| c.is_ascii_uppercase())
+        && isin_check_digit(&isin[..11]) == isin[11..].chars().next()
+}
+
+/// A WKN consists of 6 digits or upper case letters and has no check digit
+pub fn is_valid_wkn(wkn: &str) -> bool {
+    wkn.len() == 6 && is_alphanumeric(wkn)
+}
+
+/// Calculate the check digit of a CUSIP given by its first 8 characters
+pub fn cusip_check_digit(base: &str) -> Option<char> {
+    if base.len() != 8 {
+        return None;
+    }
+    let mut sum = 0;
+    for (i, c) in base.chars().enumerate() {
+        let v = match c {
+            '*' => 36,
+            '@' => 37,
+            '#' => 38,
+            c if c.is_ascii_digit() || c.is_ascii_uppercase() => char_value(c)?,
+            _ => return None,
+        };
+        let v = if i % 2 == 1 { 2 * v } else { v };
+        sum += digit_sum(v);
+    }
+    char::from_digit((10 - sum % 10) % 10, 10)
+}
+
+pub fn is_valid_cusip(cusip: &str) -> bool {
+    cusip.len() == 9
+        && cusip.is_ascii()
+        && cusip_check_digit(&cusip[..8]) == cusip[8..].chars().next()
+}
+
+/// Calculate the check digit of a SEDOL given by its first 6 characters
+pub fn sedol_check_digit(base: &str) -> Option<char> {
+    const WEIGHTS: [u32; 6] = [1, 3, 1, 7, 3, 9];
+    if base.len() != 6 || !is_alphanumeric(base) || base.contains(['A', 'E', 'I', 'O', 'U']) {
+        return None;
+    }
+    let mut sum = 0;
+    for (c, w) in base.chars().zip(WEIGHTS) {
+        sum += char_value(c)? * w;
+    }
+    char::from_digit((10 - sum % 10) % 10, 10)
+}
+
+pub fn is_valid_sedol(sedol: &str) -> bool {
+    sedol.len() == 7
+        && sedol.is_ascii()
+        && sedol_check_digit(&sedol[..6]) == sedol[6..].chars().next()
+}
+
+/// Calculate the check digit of a FIGI given by its first 11 characters
+pub fn figi_check_digit(base: &str) -> Option<char> {
+    if base.len() != 11 || !is_alphanumeric(base) || base.contains(['A', 'E', 'I', 'O', 'U']) {
+        return None;
+    }
+    let mut sum = 0;
+    for (i, c) in base.chars().enumerate() {
+        let v = char_value(c)?;
+        let v = if i % 2 == 1 { 2 * v } else { v };
+        sum += digit_sum(v);
+    }
+    char::from_digit((10 - sum % 10) % 10, 10)
+}
+
+/// A FIGI has 12 characters, the third is always 'G' and certain prefixes
+/// are excluded to avoid confusion with ISINs
+pub fn is_valid_figi(figi: &str) -> bool {
+    figi.len() == 12
+        && figi.is_ascii()
+        && !["BS", "BM", "GG", "GB", "GH", "KY", "VG"].contains(&&figi[..2])
+        && &figi[2..3] == "G"
+        && figi_check_digit(&figi[..11]) == figi[11..].chars().next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_digits() {
+        assert!(is_valid_isin("US0378331005"));
+        assert!(is_valid_isin("DE000BASF111"));
+        assert!(is_valid_isin("GB0002634946"));
+        assert!(!is_valid_isin("US0378331006"));
+        assert!(is_valid_cusip("037833100"));
+        assert!(!is_valid_cusip("037833101"));
+        assert!(is_valid_sedol("0263494"));
+        assert!(is_valid_sedol("B0YBKJ7"));
+        assert!(!is_valid_sedol("0263495"));
+        assert!(is_valid_figi("BBG000BLNNH6"));
+        assert!(!is_valid_figi("BBG000BLNNH7"));
+        assert!(Identifier::new(IdentifierType::Isin, "us0378331005").is_ok());
+        assert!(Identifier::new(IdentifierType::Wkn, "BASF1").is_err());
+    }
+
+    #[test]
+    fn derive_identifiers() {
+        let apple = Identifier::new(IdentifierType::Isin, "US0378331005").unwrap();
+        assert_eq!(
+            apple.equivalents(),
+            vec![Identifier::new(IdentifierType::Cusip, "037833100").unwrap()]
+        );
+        let basf = Identifier::new(IdentifierType::Isin, "DE000BASF111").unwrap();
+        assert_eq!(
+            basf.equivalents(),
+            vec![Identifier::new(IdentifierType::Wkn, "BASF11").unwrap()]
+        );
+        let bae = Identifier::new(IdentifierType::Sedol, "0263494").unwrap();
+        assert!(bae
+            .equivalents()
+            .contains(&Identifier::new(IdentifierType::Isin, "GB0002634946").unwrap()));
+        let wkn = Identifier::new(IdentifierType::Wkn, "BASF11").unwrap();
+        assert_eq!(wkn.equivalents(), vec![basf]);
+    }
+}
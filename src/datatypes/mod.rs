@@ -1,33 +1,49 @@
 //! Implementation of a data handler trait to deal with global data
 use serde_json;
-use sqlx;
 use thiserror::Error;
 
+pub mod account;
 pub mod asset;
 pub mod asset_handler;
 pub mod cash_flow;
+pub mod commodity;
+pub mod corporate_action;
+pub mod crypto;
 pub mod currency;
 pub mod date_time_helper;
+pub mod fund;
+pub mod identifier;
 pub mod object_handler;
 pub mod quote;
 pub mod quote_handler;
 pub mod stock;
 pub mod transaction;
 pub mod transaction_handler;
+pub mod validation;
 
+pub use account::Account;
 pub use asset::{Asset, AssetSelector};
 pub use asset_handler::AssetHandler;
 pub use cash_flow::{CashAmount, CashFlow};
+pub use commodity::Commodity;
+pub use corporate_action::{CorporateAction, CorporateActionKind, CorporateActionTarget};
+pub use crypto::Crypto;
 pub use currency::{Currency, CurrencyConverter, CurrencyError, CurrencyISOCode};
+pub use fund::{DistributionPolicy, Fund, FundConstituent};
+pub use identifier::{Identifier, IdentifierType};
 pub use object_handler::ObjectHandler;
 pub use quote::{Quote, Ticker};
 pub use quote_handler::QuoteHandler;
 pub use stock::Stock;
-pub use transaction::{Transaction, TransactionType};
+pub use transaction::{
+    CountryCode, TaxKind, Transaction, TransactionFilter, TransactionKind, TransactionType,
+};
 pub use transaction_handler::TransactionHandler;
+pub use validation::{Diagnostic, Severity, ValidationRule, Validator};
 
 #[derive(Error, Debug)]
 pub enum DataError {
+    #[cfg(any(feature = "postgres", feature = "sqlite"))]
     #[error("Database transaction error")]
     DatabaseError(#[from] sqlx::Error),
     #[error("Object (de)serialization error")]
@@ -38,14 +54,31 @@ pub enum DataError {
     NotFound(String),
     #[error("invalid asset data: {0}")]
     InvalidAsset(String),
+    #[error("invalid security identifier: {0}")]
+    InvalidIdentifier(String),
     #[error("invalid transaction type: {0}")]
     InvalidTransaction(String),
+    #[error("transaction failed validation: {}", format_diagnostics(.0))]
+    ValidationFailed(Vec<Diagnostic>),
+    #[error("invalid corporate action: {0}")]
+    InvalidCorporateAction(String),
     #[error("Invalid currency")]
     InvalidCurrency(#[from] CurrencyError),
+    #[error("database schema version {0} is newer than the supported version {1}")]
+    UnsupportedSchemaVersion(i64, i64),
     #[error("Indetermined time zone offset")]
     InvalidDateTime(#[from] time::error::IndeterminateOffset),
 }
 
+/// Join the diagnostics of a failed validation to a single message
+fn format_diagnostics(diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 pub trait DataItem {
     // get id or return error if id hasn't been set yet
     fn get_id(&self) -> Result<i32, DataError>;
@@ -1,17 +1,238 @@
 //! Implementation of basic transaction types
-use super::CashFlow;
+use std::fmt;
+use std::str::FromStr;
+
+use super::{CashAmount, CashFlow, Currency};
 use super::{DataError, DataItem};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use time::Date;
+
+/// Kind of tax of a tax transaction
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TaxKind {
+    /// Tax levied in the investor's country of residence
+    #[default]
+    Domestic,
+    /// Foreign tax withheld at source which can't be reclaimed
+    Withholding,
+    /// Foreign withholding tax credited against domestic tax, i.e. usually a positive amount
+    Credited,
+    /// Foreign withholding tax which can be reclaimed from the foreign tax authority, booked
+    /// with negative amount if withheld and with positive amount if refunded
+    Reclaimable,
+}
+
+impl fmt::Display for TaxKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TaxKind::Domestic => write!(f, "domestic"),
+            TaxKind::Withholding => write!(f, "withholding"),
+            TaxKind::Credited => write!(f, "credited"),
+            TaxKind::Reclaimable => write!(f, "reclaimable"),
+        }
+    }
+}
+
+impl FromStr for TaxKind {
+    type Err = DataError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "domestic" => Ok(TaxKind::Domestic),
+            "withholding" => Ok(TaxKind::Withholding),
+            "credited" => Ok(TaxKind::Credited),
+            "reclaimable" => Ok(TaxKind::Reclaimable),
+            _ => Err(DataError::InvalidTransaction(format!(
+                "unknown tax kind '{s}'"
+            ))),
+        }
+    }
+}
+
+/// Two letter country code as defined by ISO 3166-1, e.g. "US" or "CH"
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CountryCode {
+    code: [char; 2],
+}
+
+impl CountryCode {
+    pub fn new(code: &str) -> Result<CountryCode, DataError> {
+        let mut chars = code.chars().map(|c| c.to_ascii_uppercase());
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(a), Some(b), None) if a.is_ascii_alphabetic() && b.is_ascii_alphabetic() => {
+                Ok(Self { code: [a, b] })
+            }
+            _ => Err(DataError::InvalidTransaction(format!(
+                "invalid country code '{code}'"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for CountryCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.code[0], self.code[1])
+    }
+}
+
+impl FromStr for CountryCode {
+    type Err = DataError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl Serialize for CountryCode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for CountryCode {
+    fn deserialize<D>(deserializer: D) -> Result<CountryCode, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let code = String::deserialize(deserializer)?;
+        CountryCode::new(&code).map_err(serde::de::Error::custom)
+    }
+}
 
 /// Type of transaction
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum TransactionType {
     Cash,
-    Asset { asset_id: i32, position: f64 },
-    Dividend { asset_id: i32 },
-    Interest { asset_id: i32 },
-    Tax { transaction_ref: Option<i32> },
-    Fee { transaction_ref: Option<i32> },
+    Asset {
+        asset_id: i32,
+        position: f64,
+    },
+    Dividend {
+        asset_id: i32,
+    },
+    Interest {
+        asset_id: i32,
+    },
+    Tax {
+        transaction_ref: Option<i32>,
+        #[serde(default)]
+        kind: TaxKind,
+        /// Country levying the tax, if foreign
+        #[serde(default)]
+        country: Option<CountryCode>,
+    },
+    Fee {
+        transaction_ref: Option<i32>,
+    },
+    /// Stock split (or reverse split if `ratio` < 1) effective as of the cash flow date,
+    /// `ratio` is the number of new shares per old share, e.g. 4.0 for a 4:1 split
+    Split {
+        asset_id: i32,
+        ratio: f64,
+    },
+    /// Transfer of cash, if `asset_id` is None, or of a security position between accounts or
+    /// into or out of the portfolio without any payment. The purchase value and acquisition
+    /// dates of transferred units are preserved, if the delivering transfer is referred to by
+    /// `transaction_ref`. Otherwise, incoming units are booked with the negative cash flow amount
+    /// as purchase value and acquired at `acquisition_date`, if given, or else at the cash
+    /// flow date. The cash flow of security transfers is not paid.
+    Transfer {
+        asset_id: Option<i32>,
+        /// Number of units received, negative if delivered, 0 for cash transfers
+        position: f64,
+        transaction_ref: Option<i32>,
+        acquisition_date: Option<Date>,
+    },
+}
+
+/// Kind of transaction, i.e. the type of a transaction without any related data
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TransactionKind {
+    Cash,
+    Asset,
+    Dividend,
+    Interest,
+    Tax,
+    Fee,
+    Split,
+    Transfer,
+}
+
+impl TransactionType {
+    pub fn kind(&self) -> TransactionKind {
+        match self {
+            TransactionType::Cash => TransactionKind::Cash,
+            TransactionType::Asset { .. } => TransactionKind::Asset,
+            TransactionType::Dividend { .. } => TransactionKind::Dividend,
+            TransactionType::Interest { .. } => TransactionKind::Interest,
+            TransactionType::Tax { .. } => TransactionKind::Tax,
+            TransactionType::Fee { .. } => TransactionKind::Fee,
+            TransactionType::Split { .. } => TransactionKind::Split,
+            TransactionType::Transfer { .. } => TransactionKind::Transfer,
+        }
+    }
+
+    /// Return the asset id, if the transaction refers directly to an asset
+    pub fn asset_id(&self) -> Option<i32> {
+        match self {
+            TransactionType::Asset { asset_id, .. }
+            | TransactionType::Dividend { asset_id }
+            | TransactionType::Interest { asset_id }
+            | TransactionType::Split { asset_id, .. } => Some(*asset_id),
+            TransactionType::Transfer { asset_id, .. } => *asset_id,
+            _ => None,
+        }
+    }
+}
+
+/// Filter criteria for querying transactions, all criteria given must be met.
+/// The default filter matches all transactions.
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilter {
+    /// First cash flow date (inclusive)
+    pub start: Option<Date>,
+    /// Last cash flow date (inclusive)
+    pub end: Option<Date>,
+    /// Transactions referring to this asset, including fees and taxes related to such transactions
+    pub asset_id: Option<i32>,
+    /// Transactions of any of the given kinds
+    pub kinds: Option<Vec<TransactionKind>>,
+    /// Transactions whose note contains the given text, ignoring case
+    pub note: Option<String>,
+    /// Transactions booked to any of the given accounts
+    pub account_ids: Option<Vec<i32>>,
+}
+
+impl TransactionFilter {
+    /// Check whether a transaction matches the filter. `related` is the transaction a tax or fee
+    /// transaction refers to, if any.
+    pub fn matches(&self, transaction: &Transaction, related: Option<&Transaction>) -> bool {
+        let date = transaction.cash_flow.date;
+        self.start.is_none_or(|start| date >= start)
+            && self.end.is_none_or(|end| date <= end)
+            && self.asset_id.is_none_or(|asset_id| {
+                transaction.transaction_type.asset_id() == Some(asset_id)
+                    || related.and_then(|r| r.transaction_type.asset_id()) == Some(asset_id)
+            })
+            && self
+                .kinds
+                .as_ref()
+                .is_none_or(|kinds| kinds.contains(&transaction.transaction_type.kind()))
+            && self.note.as_ref().is_none_or(|text| {
+                transaction
+                    .note
+                    .as_ref()
+                    .is_some_and(|note| note.to_lowercase().contains(&text.to_lowercase()))
+            })
+            && self.account_ids.as_ref().is_none_or(|account_ids| {
+                transaction
+                    .account_id
+                    .is_some_and(|id| account_ids.contains(&id))
+            })
+    }
 }
 
 /// Basic transaction data
@@ -22,6 +243,9 @@ pub struct Transaction {
     pub transaction_type: TransactionType,
     pub cash_flow: CashFlow,
     pub note: Option<String>,
+    /// Account the transaction is booked to, if any
+    #[serde(default)]
+    pub account_id: Option<i32>,
 }
 
 impl Transaction {
@@ -35,6 +259,20 @@ impl Transaction {
             } => TransactionType::Asset { asset_id, position },
             TransactionType::Dividend { asset_id: _ } => TransactionType::Dividend { asset_id },
             TransactionType::Interest { asset_id: _ } => TransactionType::Interest { asset_id },
+            TransactionType::Split { asset_id: _, ratio } => {
+                TransactionType::Split { asset_id, ratio }
+            }
+            TransactionType::Transfer {
+                asset_id: Some(_),
+                position,
+                transaction_ref,
+                acquisition_date,
+            } => TransactionType::Transfer {
+                asset_id: Some(asset_id),
+                position,
+                transaction_ref,
+                acquisition_date,
+            },
             _ => self.transaction_type,
         }
     }
@@ -42,12 +280,29 @@ impl Transaction {
     /// Assign new transaction reference, if applicable
     pub fn set_transaction_ref(&mut self, trans_ref: i32) {
         self.transaction_type = match self.transaction_type {
-            TransactionType::Tax { transaction_ref: _ } => TransactionType::Tax {
+            TransactionType::Tax {
+                transaction_ref: _,
+                kind,
+                country,
+            } => TransactionType::Tax {
                 transaction_ref: Some(trans_ref),
+                kind,
+                country,
             },
             TransactionType::Fee { transaction_ref: _ } => TransactionType::Fee {
                 transaction_ref: Some(trans_ref),
             },
+            TransactionType::Transfer {
+                asset_id,
+                position,
+                transaction_ref: _,
+                acquisition_date,
+            } => TransactionType::Transfer {
+                asset_id,
+                position,
+                transaction_ref: Some(trans_ref),
+                acquisition_date,
+            },
             _ => self.transaction_type,
         }
     }
@@ -76,3 +331,192 @@ impl DataItem for Transaction {
         }
     }
 }
+
+/// Flat representation of a transaction as stored in a database table
+pub struct RawTransaction {
+    pub id: Option<i32>,
+    pub trans_type: String,
+    pub asset: Option<i32>,
+    pub cash_amount: f64,
+    pub cash_currency: Currency,
+    pub cash_date: Date,
+    pub related_trans: Option<i32>,
+    pub position: Option<f64>,
+    pub note: Option<String>,
+    pub account: Option<i32>,
+    pub tax_kind: Option<String>,
+    pub country: Option<String>,
+    pub acquisition_date: Option<Date>,
+}
+
+/// Raw transaction type constants
+const CASH: &str = "c";
+const ASSET: &str = "a";
+const DIVIDEND: &str = "d";
+const INTEREST: &str = "i";
+const TAX: &str = "t";
+const FEE: &str = "f";
+const SPLIT: &str = "s";
+const TRANSFER: &str = "x";
+
+impl TransactionKind {
+    /// Type code used for transactions of this kind in database tables
+    pub fn type_code(&self) -> &'static str {
+        match self {
+            TransactionKind::Cash => CASH,
+            TransactionKind::Asset => ASSET,
+            TransactionKind::Dividend => DIVIDEND,
+            TransactionKind::Interest => INTEREST,
+            TransactionKind::Tax => TAX,
+            TransactionKind::Fee => FEE,
+            TransactionKind::Split => SPLIT,
+            TransactionKind::Transfer => TRANSFER,
+        }
+    }
+}
+
+impl RawTransaction {
+    pub fn to_transaction(&self) -> Result<Transaction, DataError> {
+        let currency = self.cash_currency;
+
+        let cash_flow = CashFlow {
+            amount: CashAmount {
+                amount: self.cash_amount,
+                currency,
+            },
+            date: self.cash_date,
+        };
+        let note = self.note.clone();
+        let transaction_type = match self.trans_type.as_str() {
+            CASH => TransactionType::Cash,
+            ASSET => TransactionType::Asset {
+                asset_id: self
+                    .asset
+                    .ok_or_else(|| DataError::InvalidTransaction("missing asset id".to_string()))?,
+                position: self.position.ok_or_else(|| {
+                    DataError::InvalidTransaction("missing position value".to_string())
+                })?,
+            },
+            DIVIDEND => TransactionType::Dividend {
+                asset_id: self
+                    .asset
+                    .ok_or_else(|| DataError::InvalidTransaction("missing asset id".to_string()))?,
+            },
+            INTEREST => TransactionType::Interest {
+                asset_id: self
+                    .asset
+                    .ok_or_else(|| DataError::InvalidTransaction("missing asset id".to_string()))?,
+            },
+            TAX => TransactionType::Tax {
+                transaction_ref: self.related_trans,
+                kind: self
+                    .tax_kind
+                    .as_deref()
+                    .map(TaxKind::from_str)
+                    .transpose()?
+                    .unwrap_or_default(),
+                country: self
+                    .country
+                    .as_deref()
+                    .map(CountryCode::from_str)
+                    .transpose()?,
+            },
+            FEE => TransactionType::Fee {
+                transaction_ref: self.related_trans,
+            },
+            SPLIT => TransactionType::Split {
+                asset_id: self
+                    .asset
+                    .ok_or_else(|| DataError::InvalidTransaction("missing asset id".to_string()))?,
+                ratio: self.position.ok_or_else(|| {
+                    DataError::InvalidTransaction("missing split ratio".to_string())
+                })?,
+            },
+            TRANSFER => TransactionType::Transfer {
+                asset_id: self.asset,
+                position: self.position.unwrap_or_default(),
+                transaction_ref: self.related_trans,
+                acquisition_date: self.acquisition_date,
+            },
+            unknown => {
+                return Err(DataError::InvalidTransaction(unknown.to_string()));
+            }
+        };
+        Ok(Transaction {
+            id: self.id,
+            transaction_type,
+            cash_flow,
+            note,
+            account_id: self.account,
+        })
+    }
+
+    pub fn from_transaction(transaction: &Transaction) -> RawTransaction {
+        let cash_amount = transaction.cash_flow.amount.amount;
+        let cash_currency = transaction.cash_flow.amount.currency;
+        let note = transaction.note.clone();
+        let mut raw_transaction = RawTransaction {
+            id: transaction.id,
+            trans_type: String::new(),
+            asset: None,
+            cash_amount,
+            cash_currency,
+            cash_date: transaction.cash_flow.date,
+            related_trans: None,
+            position: None,
+            note,
+            account: transaction.account_id,
+            tax_kind: None,
+            country: None,
+            acquisition_date: None,
+        };
+        match transaction.transaction_type {
+            TransactionType::Cash => raw_transaction.trans_type = CASH.to_string(),
+            TransactionType::Asset { asset_id, position } => {
+                raw_transaction.trans_type = ASSET.to_string();
+                raw_transaction.asset = Some(asset_id);
+                raw_transaction.position = Some(position);
+            }
+            TransactionType::Dividend { asset_id } => {
+                raw_transaction.trans_type = DIVIDEND.to_string();
+                raw_transaction.asset = Some(asset_id);
+            }
+            TransactionType::Interest { asset_id } => {
+                raw_transaction.trans_type = INTEREST.to_string();
+                raw_transaction.asset = Some(asset_id);
+            }
+            TransactionType::Tax {
+                transaction_ref,
+                kind,
+                country,
+            } => {
+                raw_transaction.trans_type = TAX.to_string();
+                raw_transaction.related_trans = transaction_ref;
+                raw_transaction.tax_kind = Some(kind.to_string());
+                raw_transaction.country = country.map(|c| c.to_string());
+            }
+            TransactionType::Fee { transaction_ref } => {
+                raw_transaction.trans_type = FEE.to_string();
+                raw_transaction.related_trans = transaction_ref;
+            }
+            TransactionType::Split { asset_id, ratio } => {
+                raw_transaction.trans_type = SPLIT.to_string();
+                raw_transaction.asset = Some(asset_id);
+                raw_transaction.position = Some(ratio);
+            }
+            TransactionType::Transfer {
+                asset_id,
+                position,
+                transaction_ref,
+                acquisition_date,
+            } => {
+                raw_transaction.trans_type = TRANSFER.to_string();
+                raw_transaction.asset = asset_id;
+                raw_transaction.position = Some(position);
+                raw_transaction.related_trans = transaction_ref;
+                raw_transaction.acquisition_date = acquisition_date;
+            }
+        };
+        raw_transaction
+    }
+}
@@ -1,8 +1,9 @@
 use async_trait::async_trait;
+use time::Date;
 
 use super::AssetHandler;
 use super::DataError;
-use super::Transaction;
+use super::{Account, Transaction, TransactionFilter};
 
 /// Handler for globally available data of transactions and related data
 #[async_trait]
@@ -11,6 +12,29 @@ pub trait TransactionHandler: AssetHandler {
     async fn insert_transaction(&self, transaction: &Transaction) -> Result<i32, DataError>;
     async fn get_transaction_by_id(&self, id: i32) -> Result<Transaction, DataError>;
     async fn get_all_transactions(&self) -> Result<Vec<Transaction>, DataError>;
+    /// Return all transactions matching the filter, ordered by cash flow date
+    async fn get_transactions(
+        &self,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<Transaction>, DataError>;
     async fn update_transaction(&self, transaction: &Transaction) -> Result<(), DataError>;
     async fn delete_transaction(&self, id: i32) -> Result<(), DataError>;
+
+    // insert, get, update and delete for accounts
+    async fn insert_account(&self, account: &Account) -> Result<i32, DataError>;
+    async fn get_account_by_id(&self, id: i32) -> Result<Account, DataError>;
+    /// Return a list of all accounts ordered by name
+    async fn get_all_accounts(&self) -> Result<Vec<Account>, DataError>;
+    async fn update_account(&self, account: &Account) -> Result<(), DataError>;
+    /// Delete an account, fails if any transaction is still booked to the account
+    async fn delete_account(&self, id: i32) -> Result<(), DataError>;
+
+    /// Return all transactions booked to the given account with cash flow dates within the
+    /// given range, both bounds being inclusive and optional, ordered by cash flow date
+    async fn get_transactions_for_account(
+        &self,
+        account_id: i32,
+        start: Option<Date>,
+        end: Option<Date>,
+    ) -> Result<Vec<Transaction>, DataError>;
 }
@@ -0,0 +1,771 @@
+//! Consistency checks of transactions
+//!
+//! A `Validator` checks a set of transactions against a number of rules and returns a
+//! `Diagnostic` for each violation found. Data handlers may be configured to reject new
+//! transactions failing validation, see e.g. `InMemoryDB::with_validator`.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use time::{Date, OffsetDateTime};
+
+use super::{
+    AssetHandler, CorporateAction, CurrencyISOCode, DataError, QuoteHandler, Transaction,
+    TransactionFilter, TransactionHandler, TransactionType,
+};
+
+/// Tolerance below which positions are considered to be closed
+const POSITION_TOLERANCE: f64 = 1e-10;
+
+/// Rules checked by the validator
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ValidationRule {
+    /// Tax, fee or transfer refers to a transaction not contained in the transaction set
+    DanglingReference,
+    /// Sign of cash flow does not match the kind of transaction, e.g. a purchase with a
+    /// positive cash flow
+    SignConvention,
+    /// Sale or transfer results in a negative position
+    NegativePosition,
+    /// Currency of transaction differs from the currency of the related transaction or of
+    /// all tickers of the asset
+    CurrencyMismatch,
+    /// Cash flow date lies in the future
+    FutureCashFlow,
+}
+
+impl fmt::Display for ValidationRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationRule::DanglingReference => write!(f, "dangling reference"),
+            ValidationRule::SignConvention => write!(f, "sign convention"),
+            ValidationRule::NegativePosition => write!(f, "negative position"),
+            ValidationRule::CurrencyMismatch => write!(f, "currency mismatch"),
+            ValidationRule::FutureCashFlow => write!(f, "future cash flow"),
+        }
+    }
+}
+
+/// Severity of a rule violation
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Severity {
+    /// Unusual, but possibly intended, e.g. a negative dividend correcting a previous payment
+    Warning,
+    /// Inconsistent data
+    Error,
+}
+
+/// Rule violation found by the validator
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// Id of the violating transaction, None if the transaction has not been stored yet
+    pub transaction_id: Option<i32>,
+    pub rule: ValidationRule,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match self.transaction_id {
+            Some(id) => write!(
+                f,
+                "{severity} ({}) in transaction {id}: {}",
+                self.rule, self.message
+            ),
+            None => write!(
+                f,
+                "{severity} ({}) in new transaction: {}",
+                self.rule, self.message
+            ),
+        }
+    }
+}
+
+/// Checks transactions for consistency
+#[derive(Debug, Clone, Default)]
+pub struct Validator {
+    allow_short: bool,
+    warnings_as_errors: bool,
+    as_of: Option<Date>,
+    /// Currencies of the tickers per asset id
+    ticker_currencies: BTreeMap<i32, Vec<CurrencyISOCode>>,
+    /// Corporate actions changing positions, ordered by date
+    corporate_actions: Vec<CorporateAction>,
+}
+
+impl Validator {
+    /// Validator checking all rules, negative positions are not allowed and cash flows after
+    /// the current date are reported as future cash flows
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow negative positions, i.e. short selling
+    pub fn with_short_selling(mut self) -> Self {
+        self.allow_short = true;
+        self
+    }
+
+    /// Treat warnings like errors when enforcing validation on insert
+    pub fn with_warnings_as_errors(mut self) -> Self {
+        self.warnings_as_errors = true;
+        self
+    }
+
+    /// Report cash flows after the given date instead of the current date as future cash flows
+    pub fn with_as_of(mut self, date: Date) -> Self {
+        self.as_of = Some(date);
+        self
+    }
+
+    /// Add the currency of a ticker of an asset. Transactions on assets with known tickers
+    /// are expected to be in the currency of one of the tickers.
+    pub fn with_ticker_currency(mut self, asset_id: i32, currency: CurrencyISOCode) -> Self {
+        let currencies = self.ticker_currencies.entry(asset_id).or_default();
+        if !currencies.contains(&currency) {
+            currencies.push(currency);
+        }
+        self
+    }
+
+    /// Add the currencies of all tickers stored in the database
+    pub async fn with_tickers(
+        mut self,
+        quotes: &(dyn QuoteHandler + Send + Sync),
+    ) -> Result<Self, DataError> {
+        for ticker in quotes.get_all_ticker().await? {
+            self = self.with_ticker_currency(ticker.asset, ticker.currency.iso_code);
+        }
+        Ok(self)
+    }
+
+    /// Add a corporate action, which changes the positions of all later transactions
+    pub fn with_corporate_action(mut self, action: CorporateAction) -> Self {
+        let idx = self
+            .corporate_actions
+            .partition_point(|other| other.date <= action.date);
+        self.corporate_actions.insert(idx, action);
+        self
+    }
+
+    /// Add all corporate actions stored in the database
+    pub async fn with_corporate_actions(
+        mut self,
+        assets: &(dyn AssetHandler + Send + Sync),
+    ) -> Result<Self, DataError> {
+        for action in assets.get_corporate_actions(None, None).await? {
+            self = self.with_corporate_action(action);
+        }
+        Ok(self)
+    }
+
+    /// Check all rules for the given transactions and return the violations found, ordered by
+    /// cash flow date of the violating transaction. Corporate actions added to the validator
+    /// are applied to the positions before all transactions on or after their date.
+    pub fn validate(&self, transactions: &[Transaction]) -> Vec<Diagnostic> {
+        self.check(transactions, &self.corporate_actions)
+    }
+
+    fn check(
+        &self,
+        transactions: &[Transaction],
+        corporate_actions: &[CorporateAction],
+    ) -> Vec<Diagnostic> {
+        let mut transactions: Vec<&Transaction> = transactions.iter().collect();
+        // new transactions without id are sorted after stored ones of the same date
+        transactions.sort_by_key(|t| (t.cash_flow.date, t.id.is_none(), t.id));
+        let as_of = self
+            .as_of
+            .unwrap_or_else(|| OffsetDateTime::now_utc().date());
+
+        let by_id: BTreeMap<i32, &Transaction> = transactions
+            .iter()
+            .filter_map(|t| t.id.map(|id| (id, *t)))
+            .collect();
+
+        let mut diagnostics = Vec::new();
+        // positions per account and asset id
+        let mut positions: BTreeMap<(Option<i32>, i32), f64> = BTreeMap::new();
+        let mut corporate_actions = corporate_actions.iter().peekable();
+        for transaction in &transactions {
+            while let Some(action) =
+                corporate_actions.next_if(|a| a.date <= transaction.cash_flow.date)
+            {
+                apply_corporate_action(&mut positions, action);
+            }
+            let mut report = |rule, severity, message| {
+                diagnostics.push(Diagnostic {
+                    transaction_id: transaction.id,
+                    rule,
+                    severity,
+                    message,
+                })
+            };
+            let amount = transaction.cash_flow.amount.amount;
+            let currency = transaction.cash_flow.amount.currency.iso_code;
+
+            match transaction.transaction_type {
+                TransactionType::Asset { asset_id, position } => {
+                    if position == 0.0 {
+                        report(
+                            ValidationRule::SignConvention,
+                            Severity::Error,
+                            "asset transaction without position change".to_string(),
+                        );
+                    } else if amount * position > 0.0 {
+                        let action = if position > 0.0 { "purchase" } else { "sale" };
+                        report(
+                            ValidationRule::SignConvention,
+                            Severity::Error,
+                            format!("{action} with cash flow {amount}"),
+                        );
+                    }
+                    self.update_position(
+                        &mut positions,
+                        (transaction.account_id, asset_id),
+                        position,
+                        &mut report,
+                    );
+                }
+                TransactionType::Dividend { .. } | TransactionType::Interest { .. }
+                    if amount < 0.0 =>
+                {
+                    report(
+                        ValidationRule::SignConvention,
+                        Severity::Warning,
+                        format!("income with negative cash flow {amount}"),
+                    );
+                }
+                TransactionType::Fee { .. } if amount > 0.0 => report(
+                    ValidationRule::SignConvention,
+                    Severity::Warning,
+                    format!("fee with positive cash flow {amount}"),
+                ),
+                TransactionType::Split { asset_id, ratio } => {
+                    if ratio <= 0.0 {
+                        report(
+                            ValidationRule::SignConvention,
+                            Severity::Error,
+                            format!("split with non-positive ratio {ratio}"),
+                        );
+                    }
+                    for ((_, id), position) in positions.iter_mut() {
+                        if *id == asset_id {
+                            *position *= ratio;
+                        }
+                    }
+                }
+                TransactionType::Transfer {
+                    asset_id: Some(asset_id),
+                    position,
+                    ..
+                } => self.update_position(
+                    &mut positions,
+                    (transaction.account_id, asset_id),
+                    position,
+                    &mut report,
+                ),
+                _ => {}
+            }
+
+            if let Some(trans_ref) = reference(transaction) {
+                match by_id.get(&trans_ref) {
+                    None => report(
+                        ValidationRule::DanglingReference,
+                        Severity::Error,
+                        format!("related transaction {trans_ref} does not exist"),
+                    ),
+                    Some(related)
+                        if related.cash_flow.amount.currency.iso_code != currency
+                            && !matches!(
+                                transaction.transaction_type,
+                                TransactionType::Transfer { .. }
+                            ) =>
+                    {
+                        report(
+                            ValidationRule::CurrencyMismatch,
+                            Severity::Warning,
+                            format!(
+                                "currency {currency} differs from currency {} of related \
+                                 transaction {trans_ref}",
+                                related.cash_flow.amount.currency
+                            ),
+                        )
+                    }
+                    _ => {}
+                }
+            }
+
+            let traded_asset = match transaction.transaction_type {
+                TransactionType::Asset { asset_id, .. }
+                | TransactionType::Dividend { asset_id }
+                | TransactionType::Interest { asset_id } => Some(asset_id),
+                _ => None,
+            };
+            if let Some(currencies) = traded_asset.and_then(|id| self.ticker_currencies.get(&id)) {
+                if !currencies.contains(&currency) {
+                    report(
+                        ValidationRule::CurrencyMismatch,
+                        Severity::Warning,
+                        format!("no ticker of the asset is quoted in currency {currency}"),
+                    );
+                }
+            }
+
+            if transaction.cash_flow.date > as_of {
+                report(
+                    ValidationRule::FutureCashFlow,
+                    Severity::Warning,
+                    format!(
+                        "cash flow date {} is after {as_of}",
+                        transaction.cash_flow.date
+                    ),
+                );
+            }
+        }
+        diagnostics
+    }
+
+    /// Add a position change and report the result, if it is negative and not allowed
+    fn update_position(
+        &self,
+        positions: &mut BTreeMap<(Option<i32>, i32), f64>,
+        key: (Option<i32>, i32),
+        change: f64,
+        report: &mut impl FnMut(ValidationRule, Severity, String),
+    ) {
+        let position = positions.entry(key).or_default();
+        *position += change;
+        // only report the transaction that turns the position negative or increases a
+        // negative position
+        if !self.allow_short && change < 0.0 && *position < -POSITION_TOLERANCE {
+            report(
+                ValidationRule::NegativePosition,
+                Severity::Error,
+                format!(
+                    "position of asset {} changes by {change} to {}",
+                    key.1, *position
+                ),
+            );
+        }
+    }
+
+    /// Return the violations caused by adding a new transaction to the existing ones
+    pub fn check_new(
+        &self,
+        transaction: &Transaction,
+        existing: &[Transaction],
+    ) -> Vec<Diagnostic> {
+        self.check_new_with(transaction, existing, &self.corporate_actions)
+    }
+
+    fn check_new_with(
+        &self,
+        transaction: &Transaction,
+        existing: &[Transaction],
+        corporate_actions: &[CorporateAction],
+    ) -> Vec<Diagnostic> {
+        let before = self.check(existing, corporate_actions);
+        let mut transactions = existing.to_vec();
+        transactions.push(transaction.clone());
+        self.check(&transactions, corporate_actions)
+            .into_iter()
+            .filter(|d| !before.contains(d))
+            .collect()
+    }
+
+    /// Check whether the transaction may be added to the transactions stored in the database
+    /// and return an error listing all violations otherwise. Warnings are only rejected if
+    /// warnings are treated as errors. Only the stored transactions the new one may affect are
+    /// checked, i.e. those on the same account in the asset or in assets related to it by
+    /// corporate actions, splits of these assets and the transaction referred to. The
+    /// corporate actions stored in the database are applied instead of those added to the
+    /// validator.
+    pub async fn enforce(
+        &self,
+        transaction: &Transaction,
+        db: &(dyn TransactionHandler + Send + Sync),
+    ) -> Result<(), DataError> {
+        let mut existing = Vec::new();
+        let mut corporate_actions = Vec::new();
+        if let Some(asset_id) = transaction.transaction_type.asset_id() {
+            let all_actions = db.get_corporate_actions(None, None).await?;
+            // assets connected to the asset by corporate actions, in any direction
+            let mut assets = BTreeSet::from([asset_id]);
+            let mut new_assets = vec![asset_id];
+            while let Some(asset_id) = new_assets.pop() {
+                for action in &all_actions {
+                    let related = std::iter::once(action.asset_id)
+                        .chain(action.targets.iter().map(|target| target.asset_id));
+                    if related.clone().any(|id| id == asset_id) {
+                        for id in related {
+                            if assets.insert(id) {
+                                new_assets.push(id);
+                            }
+                        }
+                    }
+                }
+            }
+            corporate_actions = all_actions
+                .into_iter()
+                .filter(|action| assets.contains(&action.asset_id))
+                .collect();
+            for asset_id in assets {
+                let filter = TransactionFilter {
+                    asset_id: Some(asset_id),
+                    ..Default::default()
+                };
+                existing.extend(db.get_transactions(&filter).await?.into_iter().filter(|t| {
+                    t.account_id == transaction.account_id
+                        || matches!(t.transaction_type, TransactionType::Split { .. })
+                }));
+            }
+        }
+        if let Some(trans_ref) = reference(transaction) {
+            if !existing.iter().any(|t| t.id == Some(trans_ref)) {
+                match db.get_transaction_by_id(trans_ref).await {
+                    Ok(related) => existing.push(related),
+                    Err(DataError::NotFound(_)) => {}
+                    #[cfg(any(feature = "postgres", feature = "sqlite"))]
+                    Err(DataError::DatabaseError(sqlx::Error::RowNotFound)) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        let violations: Vec<Diagnostic> = self
+            .check_new_with(transaction, &existing, &corporate_actions)
+            .into_iter()
+            .filter(|d| d.severity == Severity::Error || self.warnings_as_errors)
+            .collect();
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(DataError::ValidationFailed(violations))
+        }
+    }
+}
+
+/// Move the positions in the source asset of a corporate action to the target assets
+fn apply_corporate_action(
+    positions: &mut BTreeMap<(Option<i32>, i32), f64>,
+    action: &CorporateAction,
+) {
+    let sources: Vec<(Option<i32>, f64)> = positions
+        .iter()
+        .filter(|((_, asset_id), position)| *asset_id == action.asset_id && **position != 0.0)
+        .map(|((account_id, _), position)| (*account_id, *position))
+        .collect();
+    for (account_id, position) in sources {
+        if !action.kind.retains_source() {
+            positions.insert((account_id, action.asset_id), 0.0);
+        }
+        for target in &action.targets {
+            *positions.entry((account_id, target.asset_id)).or_default() += position * target.ratio;
+        }
+    }
+}
+
+/// Returns the id of the transaction a tax, fee or transfer transaction refers to
+fn reference(transaction: &Transaction) -> Option<i32> {
+    match transaction.transaction_type {
+        TransactionType::Tax {
+            transaction_ref, ..
+        }
+        | TransactionType::Fee { transaction_ref }
+        | TransactionType::Transfer {
+            transaction_ref, ..
+        } => transaction_ref,
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datatypes::{
+        Asset, CashAmount, CashFlow, CorporateActionKind, CorporateActionTarget, Currency, Stock,
+        Ticker,
+    };
+    use crate::memory::InMemoryDB;
+    use std::str::FromStr;
+    use time::Month;
+
+    fn transaction(
+        id: Option<i32>,
+        transaction_type: TransactionType,
+        amount: f64,
+        currency: Currency,
+        day: u8,
+    ) -> Transaction {
+        Transaction {
+            id,
+            transaction_type,
+            cash_flow: CashFlow {
+                amount: CashAmount { amount, currency },
+                date: Date::from_calendar_date(2022, Month::March, day).unwrap(),
+            },
+            note: None,
+            account_id: None,
+        }
+    }
+
+    fn rules(diagnostics: &[Diagnostic]) -> Vec<(Option<i32>, ValidationRule, Severity)> {
+        diagnostics
+            .iter()
+            .map(|d| (d.transaction_id, d.rule, d.severity))
+            .collect()
+    }
+
+    #[test]
+    fn validate_transactions() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let usd = Currency::from_str("USD").unwrap();
+        let buy = |position| TransactionType::Asset {
+            asset_id: 1,
+            position,
+        };
+        let transactions = vec![
+            transaction(Some(1), buy(10.0), -1000.0, eur, 1),
+            // fee with wrong sign
+            transaction(
+                Some(2),
+                TransactionType::Fee {
+                    transaction_ref: Some(1),
+                },
+                5.0,
+                eur,
+                1,
+            ),
+            // sale with negative cash flow
+            transaction(Some(3), buy(-4.0), -500.0, eur, 2),
+            // tax in other currency than the dividend
+            transaction(
+                Some(4),
+                TransactionType::Dividend { asset_id: 1 },
+                30.0,
+                eur,
+                3,
+            ),
+            transaction(
+                Some(5),
+                TransactionType::Tax {
+                    transaction_ref: Some(4),
+                    kind: Default::default(),
+                    country: None,
+                },
+                -5.0,
+                usd,
+                3,
+            ),
+            // 2:1 split doubles the remaining 6 shares
+            transaction(
+                Some(6),
+                TransactionType::Split {
+                    asset_id: 1,
+                    ratio: 2.0,
+                },
+                0.0,
+                eur,
+                4,
+            ),
+            transaction(Some(7), buy(-12.0), 1300.0, eur, 5),
+            // short sale and fee referring to missing transaction
+            transaction(Some(8), buy(-1.0), 110.0, eur, 6),
+            transaction(
+                Some(9),
+                TransactionType::Fee {
+                    transaction_ref: Some(42),
+                },
+                -1.0,
+                eur,
+                6,
+            ),
+            // quoted in USD only, dated in the future
+            transaction(
+                Some(10),
+                TransactionType::Asset {
+                    asset_id: 2,
+                    position: 1.0,
+                },
+                -100.0,
+                eur,
+                31,
+            ),
+        ];
+        let validator = Validator::new()
+            .with_as_of(Date::from_calendar_date(2022, Month::March, 30).unwrap())
+            .with_ticker_currency(2, usd.iso_code);
+        let diagnostics = validator.validate(&transactions);
+        assert_eq!(
+            rules(&diagnostics),
+            vec![
+                (Some(2), ValidationRule::SignConvention, Severity::Warning),
+                (Some(3), ValidationRule::SignConvention, Severity::Error),
+                (Some(5), ValidationRule::CurrencyMismatch, Severity::Warning),
+                (Some(8), ValidationRule::NegativePosition, Severity::Error),
+                (Some(9), ValidationRule::DanglingReference, Severity::Error),
+                (
+                    Some(10),
+                    ValidationRule::CurrencyMismatch,
+                    Severity::Warning
+                ),
+                (Some(10), ValidationRule::FutureCashFlow, Severity::Warning),
+            ]
+        );
+
+        // short selling allowed
+        let validator = validator.with_short_selling();
+        let diagnostics = validator.validate(&transactions);
+        assert_eq!(diagnostics.len(), 6);
+        assert!(diagnostics
+            .iter()
+            .all(|d| d.rule != ValidationRule::NegativePosition));
+    }
+
+    #[tokio::test]
+    async fn enforce_validation_on_insert() {
+        let db = InMemoryDB::new();
+        let asset_id = db
+            .insert_asset(&Asset::Stock(Stock::new(
+                None,
+                "BASF AG".to_string(),
+                Some("DE000BASF111".to_string()),
+                None,
+                None,
+            )))
+            .await
+            .unwrap();
+        let eur = db
+            .get_or_new_currency(CurrencyISOCode::new("EUR").unwrap())
+            .await
+            .unwrap();
+        db.insert_ticker(&Ticker {
+            id: None,
+            asset: asset_id,
+            name: "BAS.DE".to_string(),
+            currency: eur,
+            source: "manual".to_string(),
+            priority: 10,
+            factor: 1.0,
+            tz: None,
+            cal: None,
+        })
+        .await
+        .unwrap();
+        let validator = Validator::new().with_tickers(&db).await.unwrap();
+        let db = db.with_validator(validator);
+        let trade = |position, amount, day| {
+            transaction(
+                None,
+                TransactionType::Asset { asset_id, position },
+                amount,
+                eur,
+                day,
+            )
+        };
+
+        let id = db
+            .insert_transaction(&trade(10.0, -600.0, 1))
+            .await
+            .unwrap();
+        // selling more than held is rejected
+        match db.insert_transaction(&trade(-20.0, 1200.0, 2)).await {
+            Err(DataError::ValidationFailed(diagnostics)) => {
+                assert_eq!(
+                    rules(&diagnostics),
+                    vec![(None, ValidationRule::NegativePosition, Severity::Error)]
+                );
+            }
+            _ => panic!("sale of more than held position has been accepted"),
+        }
+        db.insert_transaction(&trade(-10.0, 650.0, 2))
+            .await
+            .unwrap();
+        // a backdated sale makes the later sale exceed the position
+        assert!(db.insert_transaction(&trade(-5.0, 300.0, 1)).await.is_err());
+        // fees with positive cash flow are only warnings
+        let refund = transaction(
+            None,
+            TransactionType::Fee {
+                transaction_ref: Some(id),
+            },
+            2.0,
+            eur,
+            1,
+        );
+        db.insert_transaction(&refund).await.unwrap();
+        assert_eq!(db.get_all_transactions().await.unwrap().len(), 3);
+        let strict = db
+            .clone()
+            .with_validator(Validator::new().with_warnings_as_errors());
+        assert!(strict.insert_transaction(&refund).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn enforce_validation_with_spin_off() {
+        let db = InMemoryDB::new();
+        let stock = |name: &str| Asset::Stock(Stock::new(None, name.to_string(), None, None, None));
+        let source_id = db.insert_asset(&stock("Siemens AG")).await.unwrap();
+        let target_id = db.insert_asset(&stock("Siemens Energy AG")).await.unwrap();
+        let eur = db
+            .get_or_new_currency(CurrencyISOCode::new("EUR").unwrap())
+            .await
+            .unwrap();
+        // one share of the spun-off company per two shares held
+        let mut spin_off = CorporateAction::new(
+            None,
+            CorporateActionKind::SpinOff,
+            source_id,
+            Date::from_calendar_date(2022, Month::March, 5).unwrap(),
+        );
+        spin_off.targets.push(CorporateActionTarget {
+            asset_id: target_id,
+            ratio: 0.5,
+            cost_share: 0.1,
+        });
+        db.insert_corporate_action(&spin_off).await.unwrap();
+        let trade = |asset_id, position, amount, day| {
+            transaction(
+                None,
+                TransactionType::Asset { asset_id, position },
+                amount,
+                eur,
+                day,
+            )
+        };
+
+        let transactions = vec![
+            trade(source_id, 10.0, -1000.0, 1),
+            trade(target_id, -5.0, 100.0, 6),
+        ];
+        assert_eq!(
+            rules(&Validator::new().validate(&transactions)),
+            vec![(None, ValidationRule::NegativePosition, Severity::Error)]
+        );
+        let validator = Validator::new().with_corporate_actions(&db).await.unwrap();
+        assert!(validator.validate(&transactions).is_empty());
+
+        let db = db.with_validator(Validator::new());
+        db.insert_transaction(&trade(source_id, 10.0, -1000.0, 1))
+            .await
+            .unwrap();
+        db.insert_transaction(&trade(target_id, -5.0, 100.0, 6))
+            .await
+            .unwrap();
+        // all units received by the spin-off have been sold
+        assert!(db
+            .insert_transaction(&trade(target_id, -1.0, 20.0, 7))
+            .await
+            .is_err());
+        // a backdated sale of the source reduces the units received
+        assert!(db
+            .insert_transaction(&trade(source_id, -2.0, 200.0, 2))
+            .await
+            .is_err());
+    }
+}
@@ -16,7 +16,7 @@ pub enum AdjustDateError {
 /// Rules to adjust dates to business days
 /// The rule "Modified Preceding" commonly referred to in text books
 /// was intentionally left out since
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
 pub enum DayAdjust {
     #[serde(rename = "none")]
     None,
@@ -55,6 +55,19 @@ pub trait FixedIncome {
 pub fn calculate_cash_flows_ytm(
     cash_flows: &[CashFlow],
     init_cash_flow: &CashFlow,
+) -> Result<f64, DiscountError> {
+    calculate_cash_flows_irr(cash_flows, init_cash_flow, 0., 0.5)
+}
+
+/// Calculate the internal rate of return of a stream of cash flows as in
+/// `calculate_cash_flows_ytm`, but search the rate within the given bounds. The aggregate
+/// discounted value must have opposite signs at both bounds, otherwise a `DiscountError`
+/// is returned.
+pub fn calculate_cash_flows_irr(
+    cash_flows: &[CashFlow],
+    init_cash_flow: &CashFlow,
+    min_rate: f64,
+    max_rate: f64,
 ) -> Result<f64, DiscountError> {
     let rate = FlatRate::new(
         0.05,
@@ -62,8 +75,8 @@ pub fn calculate_cash_flows_ytm(
         Compounding::Annual,
         init_cash_flow.amount.currency,
     );
-    let init_param = 0.5;
-    let solver = BrentRoot::new(0., 0.5, 1e-11);
+    let init_param = max_rate;
+    let solver = BrentRoot::new(min_rate, max_rate, 1e-11);
     let func = FlatRateDiscounter {
         init_cash_flow,
         cash_flows,
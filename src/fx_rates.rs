@@ -147,7 +147,7 @@ mod tests {
     use super::*;
     use crate::datatypes::{date_time_helper::make_offset_time, CurrencyISOCode};
     use crate::market::Market;
-    use crate::postgres::PostgresDB;
+    use crate::memory::InMemoryDB;
     use std::sync::Arc;
 
     async fn prepare_db(db: Arc<dyn QuoteHandler + Send + Sync>) {
@@ -165,13 +165,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_fx_rate() {
-        let db_url = std::env::var("FINQL_TEST_DATABASE_URL");
-        assert!(
-            db_url.is_ok(),
-            "environment variable $FINQL_TEST_DATABASE_URL is not set"
-        );
-        let db = PostgresDB::new(&db_url.unwrap()).await.unwrap();
-        db.clean().await.unwrap();
+        let db = InMemoryDB::new();
 
         let qh: Arc<dyn QuoteHandler + Send + Sync> = Arc::new(db);
         prepare_db(qh.clone()).await;
@@ -0,0 +1,279 @@
+//! Development of the value of a portfolio over time, e.g. for charts
+//!
+//! The portfolio given by a list of transactions is rolled forward from one sample date to the
+//! next and valued at the end of each sample date. Besides the total value, the cash balance,
+//! the invested capital, i.e. the sum of all external flows as defined for the time-weighted
+//! return in `performance`, and the resulting profit and loss are recorded.
+use cal_calc::Calendar;
+use serde::{Deserialize, Serialize};
+use time::Date;
+
+use crate::datatypes::{date_time_helper::date_to_offset_date_time, Currency, Transaction};
+use crate::performance::external_flows;
+use crate::portfolio::{delta_position, PortfolioPosition, PositionError};
+use crate::time_series::{TimeSeries, TimeValue};
+use crate::Market;
+
+/// Sampling frequency of the portfolio history
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HistoryFrequency {
+    /// Every business day
+    Daily,
+    /// Last business day of each week
+    Weekly,
+    /// Last business day of each month
+    Monthly,
+}
+
+impl HistoryFrequency {
+    /// Check whether `date` is the last sample date of its period, given the next business day
+    fn is_period_end(&self, date: Date, next_bday: Date) -> bool {
+        match self {
+            HistoryFrequency::Daily => true,
+            HistoryFrequency::Weekly => date.iso_week() != next_bday.iso_week(),
+            HistoryFrequency::Monthly => date.month() != next_bday.month(),
+        }
+    }
+}
+
+/// Development of the value of a portfolio
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PortfolioHistory {
+    /// Total value of all positions including cash
+    pub value: TimeSeries,
+    pub cash: TimeSeries,
+    /// Sum of all contributions less all withdrawals since inception
+    pub invested_capital: TimeSeries,
+    /// Cumulative profit and loss, i.e. the value less the invested capital
+    pub pnl: TimeSeries,
+}
+
+/// Calculate the value of the portfolio given by a list of transactions ordered by cash flow
+/// date at the end of business days of the calendar from `start` to `end` (both inclusive),
+/// sampled with the given frequency. The last business day on or before `end` is always
+/// included. External flows are identified as for the time-weighted return. The portfolio is
+/// rolled forward from one sample date to the next by applying only the transactions and
+/// corporate actions in between, positions are valued with the latest quotes available at
+/// 20:00 of each sample date.
+pub async fn calc_portfolio_history(
+    currency: Currency,
+    transactions: &[Transaction],
+    start: Date,
+    end: Date,
+    frequency: HistoryFrequency,
+    calendar: &Calendar,
+    market: &Market,
+) -> Result<PortfolioHistory, PositionError> {
+    let flows = external_flows(currency, transactions, Date::MIN, end, market).await?;
+    let mut history = PortfolioHistory {
+        value: TimeSeries::new("value"),
+        cash: TimeSeries::new("cash"),
+        invested_capital: TimeSeries::new("invested capital"),
+        pnl: TimeSeries::new("P&L"),
+    };
+    let mut flows = flows
+        .into_iter()
+        .filter_map(|(date, day)| day.get(&None).map(|flow| (date, *flow)))
+        .peekable();
+    let corporate_actions = market.get_corporate_actions(None, Some(end)).await?;
+    let mut invested_capital = 0.0;
+    let mut positions = PortfolioPosition::new(currency);
+    // transactions and corporate actions before these indices have been applied
+    let mut trans_idx = 0;
+    let mut action_idx = 0;
+    let mut date = if calendar.is_business_day(start) {
+        start
+    } else {
+        calendar.next_bday(start)?
+    };
+    while date <= end {
+        let next_bday = calendar.next_bday(date)?;
+        if next_bday <= end && !frequency.is_period_end(date, next_bday) {
+            date = next_bday;
+            continue;
+        }
+        // only the transactions since the previous sample date are applied
+        let next_trans_idx =
+            trans_idx + transactions[trans_idx..].partition_point(|t| t.cash_flow.date <= date);
+        let next_action_idx =
+            action_idx + corporate_actions[action_idx..].partition_point(|a| a.date <= date);
+        delta_position(
+            &mut positions,
+            &transactions[trans_idx..next_trans_idx],
+            &transactions[..trans_idx],
+            &corporate_actions[action_idx..next_action_idx],
+            None,
+            None,
+            market.clone(),
+        )
+        .await?;
+        trans_idx = next_trans_idx;
+        action_idx = next_action_idx;
+        while let Some((_, flow)) = flows.next_if(|(flow_date, _)| *flow_date <= date) {
+            invested_capital += flow;
+        }
+
+        let time = date_to_offset_date_time(&date, 20, None)?;
+        positions.add_quote(time, market).await;
+        let value = positions.calc_totals().value;
+        for (series, value) in [
+            (&mut history.value, value),
+            (&mut history.cash, positions.cash.position),
+            (&mut history.invested_capital, invested_capital),
+            (&mut history.pnl, value - invested_capital),
+        ] {
+            series.series.push(TimeValue { time, value });
+        }
+        date = next_bday;
+    }
+    Ok(history)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use time::Month;
+
+    use crate::datatypes::{CashFlow, TransactionType};
+    use crate::performance::tests::sample_portfolio;
+
+    #[tokio::test]
+    async fn monthly_portfolio_history() {
+        let tol = 1e-8;
+        let (market, eur, _, transactions) = sample_portfolio().await;
+        let calendar = market.get_calendar("TARGET").unwrap().clone();
+        let date = |month, day| Date::from_calendar_date(2021, month, day).unwrap();
+        let start = date(Month::January, 1);
+        let end = date(Month::December, 31);
+        let history = calc_portfolio_history(
+            eur,
+            &transactions,
+            start,
+            end,
+            HistoryFrequency::Monthly,
+            &calendar,
+            &market,
+        )
+        .await
+        .unwrap();
+        assert_eq!(history.value.series.len(), 12);
+        let sample = |series: &TimeSeries, month| {
+            series
+                .series
+                .iter()
+                .find(|v| v.time.month() == month)
+                .unwrap()
+                .value
+        };
+        // last business day of April is the 30th, since May 1st is a holiday
+        assert_eq!(history.value.series[3].time.date(), date(Month::April, 30));
+        for (month, value, cash, invested) in [
+            (Month::January, 10_000.0, 5_000.0, 10_000.0),
+            (Month::June, 11_000.0, 5_000.0, 10_000.0),
+            (Month::July, 21_000.0, 9_000.0, 20_000.0),
+            (Month::October, 21_200.0, 9_200.0, 20_000.0),
+            (Month::December, 20_000.0, 9_200.0, 20_000.0),
+        ] {
+            assert_fuzzy_eq!(sample(&history.value, month), value, tol);
+            assert_fuzzy_eq!(sample(&history.cash, month), cash, tol);
+            assert_fuzzy_eq!(sample(&history.invested_capital, month), invested, tol);
+            assert_fuzzy_eq!(sample(&history.pnl, month), value - invested, tol);
+        }
+
+        // New Year's Day is a holiday, weekly and daily samples end with the last business day
+        let history = calc_portfolio_history(
+            eur,
+            &transactions,
+            start,
+            date(Month::January, 13),
+            HistoryFrequency::Weekly,
+            &calendar,
+            &market,
+        )
+        .await
+        .unwrap();
+        let dates: Vec<Date> = history.value.series.iter().map(|v| v.time.date()).collect();
+        assert_eq!(
+            dates,
+            vec![date(Month::January, 8), date(Month::January, 13)]
+        );
+        let history = calc_portfolio_history(
+            eur,
+            &transactions,
+            start,
+            end,
+            HistoryFrequency::Daily,
+            &calendar,
+            &market,
+        )
+        .await
+        .unwrap();
+        // 261 weekdays less New Year's Day, Good Friday and Easter Monday
+        assert_eq!(history.value.series.len(), 258);
+        assert_eq!(history.cash.series.len(), 258);
+    }
+
+    #[tokio::test]
+    async fn portfolio_history_edge_cases() {
+        let tol = 1e-8;
+        let (market, eur, _, mut transactions) = sample_portfolio().await;
+        let calendar = market.get_calendar("TARGET").unwrap().clone();
+        let date = |month, day| Date::from_calendar_date(2021, month, day).unwrap();
+
+        // transactions on the only sample date and before are included
+        let history = calc_portfolio_history(
+            eur,
+            &transactions,
+            date(Month::July, 1),
+            date(Month::July, 1),
+            HistoryFrequency::Monthly,
+            &calendar,
+            &market,
+        )
+        .await
+        .unwrap();
+        assert_eq!(history.value.series.len(), 1);
+        assert_fuzzy_eq!(history.value.series[0].value, 21_000.0, tol);
+        assert_fuzzy_eq!(history.cash.series[0].value, 9_000.0, tol);
+        assert_fuzzy_eq!(history.invested_capital.series[0].value, 20_000.0, tol);
+
+        // no business day within the range
+        let history = calc_portfolio_history(
+            eur,
+            &transactions,
+            date(Month::July, 3),
+            date(Month::July, 4),
+            HistoryFrequency::Daily,
+            &calendar,
+            &market,
+        )
+        .await
+        .unwrap();
+        assert!(history.value.series.is_empty());
+
+        // deposits in a currency without fx quotes can't be converted
+        let usd = market.get_currency_from_str("USD").await.unwrap();
+        transactions.push(Transaction {
+            id: Some(6),
+            transaction_type: TransactionType::Cash,
+            cash_flow: CashFlow::new(1_000.0, usd, date(Month::November, 1)),
+            note: None,
+            account_id: None,
+        });
+        match calc_portfolio_history(
+            eur,
+            &transactions,
+            date(Month::January, 1),
+            date(Month::December, 31),
+            HistoryFrequency::Monthly,
+            &calendar,
+            &market,
+        )
+        .await
+        {
+            Err(PositionError::MarketDataError(err)) => assert!(err.is_missing_quote()),
+            _ => panic!("missing fx quote has not been reported"),
+        }
+    }
+}
@@ -0,0 +1,438 @@
+//! Configurable mapping of CSV files with transaction data to import records
+//!
+//! The mapping defines the column names, the format of dates and numbers and the values of
+//! the booking type column. Profiles for the transaction exports of some German brokers are
+//! provided and may be adjusted to changes of the export format.
+use std::collections::BTreeMap;
+use std::io;
+
+use csv::{ReaderBuilder, Trim};
+
+use super::{store_records, BookingType, ImportError, ImportRecord, ImportSummary};
+use crate::datatypes::date_time_helper::date_from_str;
+use crate::datatypes::{CurrencyISOCode, TransactionHandler};
+
+/// Names of the columns of a CSV file. Optional columns might be missing in the file; if so,
+/// the related values are taken as empty, zero or the mapping's default currency.
+#[derive(Debug, Clone)]
+pub struct CsvColumns {
+    pub date: String,
+    pub booking_type: String,
+    pub amount: String,
+    pub isin: Option<String>,
+    pub position: Option<String>,
+    pub currency: Option<String>,
+    pub fee: Option<String>,
+    pub tax: Option<String>,
+    pub note: Option<String>,
+}
+
+impl CsvColumns {
+    /// Create mapping of the required columns, all optional columns are unset
+    pub fn new(date: &str, booking_type: &str, amount: &str) -> Self {
+        Self {
+            date: date.to_string(),
+            booking_type: booking_type.to_string(),
+            amount: amount.to_string(),
+            isin: None,
+            position: None,
+            currency: None,
+            fee: None,
+            tax: None,
+            note: None,
+        }
+    }
+}
+
+/// Mapping of a CSV file with one booking per row to import records
+#[derive(Debug, Clone)]
+pub struct CsvMapping {
+    pub columns: CsvColumns,
+    pub delimiter: u8,
+    /// Date format as supported by `date_time_helper::date_from_str`
+    pub date_format: String,
+    pub decimal_separator: char,
+    pub thousands_separator: Option<char>,
+    /// Booking types by the values of the booking type column, values are compared ignoring
+    /// case; rows with any other value are rejected
+    pub booking_types: BTreeMap<String, BookingType>,
+    /// Currency of all amounts, if there is no currency column or its value is empty
+    pub currency: CurrencyISOCode,
+    /// Whether the amount column contains net amounts, i.e. fees and taxes are already deducted
+    /// for inflows or added for outflows
+    pub net_amounts: bool,
+}
+
+impl CsvMapping {
+    /// Create mapping for comma separated files with ISO dates, decimal points and amounts in EUR
+    pub fn new(columns: CsvColumns) -> Self {
+        Self {
+            columns,
+            delimiter: b',',
+            date_format: "%Y-%m-%d".to_string(),
+            decimal_separator: '.',
+            thousands_separator: None,
+            booking_types: BTreeMap::new(),
+            currency: CurrencyISOCode::new("EUR").unwrap(),
+            net_amounts: false,
+        }
+    }
+
+    /// Use German number and date formats, i.e. semicolon separated values, dates like
+    /// "31.12.2020" and numbers like "1.234,56"
+    pub fn with_german_format(mut self) -> Self {
+        self.delimiter = b';';
+        self.date_format = "%d.%m.%Y".to_string();
+        self.decimal_separator = ',';
+        self.thousands_separator = Some('.');
+        self
+    }
+
+    /// Map a value of the booking type column to a booking type
+    pub fn with_booking_type(mut self, value: &str, booking_type: BookingType) -> Self {
+        self.booking_types
+            .insert(value.to_lowercase(), booking_type);
+        self
+    }
+
+    /// Profile for the depot transaction export of comdirect
+    pub fn comdirect() -> Self {
+        let columns = CsvColumns {
+            isin: Some("ISIN".to_string()),
+            position: Some("Stück/Nom.".to_string()),
+            currency: Some("Währung".to_string()),
+            fee: Some("Provision".to_string()),
+            tax: Some("Steuern".to_string()),
+            note: Some("Bezeichnung".to_string()),
+            ..CsvColumns::new("Buchungstag", "Geschäftsart", "Kurswert")
+        };
+        Self::new(columns)
+            .with_german_format()
+            .with_booking_type("Kauf", BookingType::Buy)
+            .with_booking_type("Verkauf", BookingType::Sell)
+            .with_booking_type("Ertrag", BookingType::Dividend)
+            .with_booking_type("Dividende", BookingType::Dividend)
+            .with_booking_type("Zinsen", BookingType::Interest)
+    }
+
+    /// Profile for the transaction export of Consorsbank
+    pub fn consorsbank() -> Self {
+        let columns = CsvColumns {
+            isin: Some("ISIN".to_string()),
+            position: Some("Stück".to_string()),
+            currency: Some("Währung".to_string()),
+            fee: Some("Gebühren".to_string()),
+            tax: Some("Steuern".to_string()),
+            note: Some("Name".to_string()),
+            ..CsvColumns::new("Datum", "Transaktion", "Kurswert")
+        };
+        Self::new(columns)
+            .with_german_format()
+            .with_booking_type("Kauf", BookingType::Buy)
+            .with_booking_type("Verkauf", BookingType::Sell)
+            .with_booking_type("Dividende", BookingType::Dividend)
+            .with_booking_type("Ausschüttung", BookingType::Dividend)
+            .with_booking_type("Zinsen", BookingType::Interest)
+            .with_booking_type("Einzahlung", BookingType::Deposit)
+            .with_booking_type("Auszahlung", BookingType::Withdrawal)
+            .with_booking_type("Gebühren", BookingType::Fee)
+            .with_booking_type("Steuern", BookingType::Tax)
+    }
+
+    /// Profile for the order export of ING, amounts are net of fees and taxes
+    pub fn ing() -> Self {
+        let columns = CsvColumns {
+            isin: Some("ISIN".to_string()),
+            position: Some("Anzahl".to_string()),
+            currency: Some("Währung".to_string()),
+            fee: Some("Provision".to_string()),
+            tax: Some("Steuern".to_string()),
+            note: Some("Wertpapier".to_string()),
+            ..CsvColumns::new("Buchungsdatum", "Ordertyp", "Betrag")
+        };
+        let mut mapping = Self::new(columns)
+            .with_german_format()
+            .with_booking_type("Kauf", BookingType::Buy)
+            .with_booking_type("Sparplan", BookingType::Buy)
+            .with_booking_type("Verkauf", BookingType::Sell)
+            .with_booking_type("Ertrag", BookingType::Dividend)
+            .with_booking_type("Zinsen", BookingType::Interest);
+        mapping.net_amounts = true;
+        mapping
+    }
+
+    /// Parse a number with the mapping's decimal and thousands separators
+    fn parse_number(&self, value: &str) -> Option<f64> {
+        let number: String = value
+            .chars()
+            .filter(|c| !c.is_whitespace() && Some(*c) != self.thousands_separator)
+            .map(|c| if c == self.decimal_separator { '.' } else { c })
+            .collect();
+        number.parse().ok()
+    }
+
+    /// Read all rows of CSV data with a header line into import records
+    pub fn read<R: io::Read>(&self, reader: R) -> Result<Vec<ImportRecord>, ImportError> {
+        let mut reader = ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .trim(Trim::All)
+            .flexible(true)
+            .from_reader(reader);
+        let headers = reader.headers()?.clone();
+        let required = |name: &String| {
+            headers
+                .iter()
+                .position(|h| h == name)
+                .ok_or_else(|| ImportError::MissingColumn(name.clone()))
+        };
+        // optional columns missing in the file are ignored
+        let optional = |name: &Option<String>| {
+            name.as_ref().and_then(|name| {
+                headers
+                    .iter()
+                    .position(|h| h == name)
+                    .map(|idx| (idx, name.clone()))
+            })
+        };
+        let columns = &self.columns;
+        let date_col = (required(&columns.date)?, columns.date.clone());
+        let type_col = (
+            required(&columns.booking_type)?,
+            columns.booking_type.clone(),
+        );
+        let amount_col = (required(&columns.amount)?, columns.amount.clone());
+        let isin_col = optional(&columns.isin);
+        let position_col = optional(&columns.position);
+        let currency_col = optional(&columns.currency);
+        let fee_col = optional(&columns.fee);
+        let tax_col = optional(&columns.tax);
+        let note_col = optional(&columns.note);
+
+        let mut records = Vec::new();
+        for row in reader.records() {
+            let row = row?;
+            let line = row.position().map(|p| p.line()).unwrap_or_default();
+            let text = |column: &(usize, String)| row.get(column.0).filter(|s| !s.is_empty());
+            let invalid = |column: &(usize, String)| ImportError::InvalidValue {
+                line,
+                column: column.1.clone(),
+                value: row.get(column.0).unwrap_or_default().to_string(),
+            };
+            let number = |column: &Option<(usize, String)>| match column {
+                Some(column) => match text(column) {
+                    Some(value) => self.parse_number(value).ok_or_else(|| invalid(column)),
+                    None => Ok(0.0),
+                },
+                None => Ok(0.0),
+            };
+
+            let date = text(&date_col)
+                .and_then(|value| date_from_str(value, &self.date_format).ok())
+                .ok_or_else(|| invalid(&date_col))?;
+            let type_value = text(&type_col).unwrap_or_default();
+            let booking_type = *self
+                .booking_types
+                .get(&type_value.to_lowercase())
+                .ok_or_else(|| ImportError::UnknownBookingType {
+                    line,
+                    value: type_value.to_string(),
+                })?;
+            let amount = text(&amount_col)
+                .and_then(|value| self.parse_number(value))
+                .ok_or_else(|| invalid(&amount_col))?;
+            let currency = match currency_col.as_ref().map(|column| (column, text(column))) {
+                Some((column, Some(value))) => {
+                    CurrencyISOCode::new(value).map_err(|_| invalid(column))?
+                }
+                _ => self.currency,
+            };
+            let fee = number(&fee_col)?;
+            let tax = number(&tax_col)?;
+            let mut record = ImportRecord {
+                date,
+                booking_type,
+                isin: isin_col.as_ref().and_then(text).map(str::to_string),
+                position: number(&position_col)?,
+                amount: amount.abs(),
+                currency,
+                fee,
+                tax,
+                note: note_col.as_ref().and_then(text).map(str::to_string),
+            };
+            if self.net_amounts {
+                record.amount = gross_amount(&record);
+            }
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    /// Read CSV data and store the resulting transactions, see `store_records`
+    pub async fn import<R: io::Read>(
+        &self,
+        reader: R,
+        account_id: Option<i32>,
+        db: &(dyn TransactionHandler + Send + Sync),
+    ) -> Result<ImportSummary, ImportError> {
+        let records = self.read(reader)?;
+        store_records(&records, account_id, db).await
+    }
+}
+
+/// Calculate the gross amount of a record whose amount is net of fees and taxes
+fn gross_amount(record: &ImportRecord) -> f64 {
+    let costs = record.fee.abs() + record.tax;
+    match record.booking_type {
+        BookingType::Buy | BookingType::Withdrawal | BookingType::Fee | BookingType::Tax => {
+            record.amount - costs
+        }
+        _ => record.amount + costs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::datatypes::{
+        Asset, AssetHandler, DataError, Stock, TransactionFilter, TransactionKind, TransactionType,
+    };
+    use crate::memory::InMemoryDB;
+
+    const CONSORSBANK_EXPORT: &str = "\
+Datum;Transaktion;Name;ISIN;Stück;Kurswert;Währung;Gebühren;Steuern
+01.03.2021;Einzahlung;;;;5.000,00;EUR;;
+15.03.2021;Kauf;Apple Inc.;US0378331005;20;2.400,50;EUR;4,95;
+14.05.2021;Dividende;Apple Inc.;US0378331005;;8,80;USD;;1,32
+02.11.2021;Verkauf;Apple Inc.;US0378331005;5;750,00;EUR;4,95;35,10
+";
+
+    #[test]
+    fn read_german_export() {
+        let tol = 1e-10;
+        let records = CsvMapping::consorsbank()
+            .read(CONSORSBANK_EXPORT.as_bytes())
+            .unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].booking_type, BookingType::Deposit);
+        assert_eq!(records[0].isin, None);
+        assert_fuzzy_eq!(records[0].amount, 5_000.0, tol);
+        assert_eq!(records[1].booking_type, BookingType::Buy);
+        assert_eq!(records[1].isin.as_deref(), Some("US0378331005"));
+        assert_eq!(records[1].note.as_deref(), Some("Apple Inc."));
+        assert_fuzzy_eq!(records[1].position, 20.0, tol);
+        assert_fuzzy_eq!(records[1].amount, 2_400.5, tol);
+        assert_fuzzy_eq!(records[1].fee, 4.95, tol);
+        assert_eq!(records[2].currency.to_string(), "USD");
+        assert_fuzzy_eq!(records[2].tax, 1.32, tol);
+
+        // net amounts and unknown booking types
+        let mut mapping = CsvMapping::consorsbank();
+        mapping.net_amounts = true;
+        let records = mapping.read(CONSORSBANK_EXPORT.as_bytes()).unwrap();
+        assert_fuzzy_eq!(records[1].amount, 2_395.55, tol);
+        assert_fuzzy_eq!(records[3].amount, 790.05, tol);
+        let mapping = CsvMapping::new(CsvColumns::new("Datum", "Transaktion", "Kurswert"))
+            .with_german_format()
+            .with_booking_type("kauf", BookingType::Buy);
+        assert!(matches!(
+            mapping.read(CONSORSBANK_EXPORT.as_bytes()),
+            Err(ImportError::UnknownBookingType { line: 2, .. })
+        ));
+        // optional columns might be missing
+        let export = "\
+Datum;Transaktion;Name;ISIN;Stück;Kurswert;Währung
+15.03.2021;Kauf;Apple Inc.;US0378331005;20;2.400,50;EUR
+";
+        let records = CsvMapping::consorsbank().read(export.as_bytes()).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_fuzzy_eq!(records[0].amount, 2_400.5, tol);
+        assert_fuzzy_eq!(records[0].fee, 0.0, tol);
+        assert_fuzzy_eq!(records[0].tax, 0.0, tol);
+        let mapping = CsvMapping::new(CsvColumns::new("Date", "Type", "Amount"));
+        assert!(matches!(
+            mapping.read(CONSORSBANK_EXPORT.as_bytes()),
+            Err(ImportError::MissingColumn(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn import_skips_duplicates() {
+        let tol = 1e-10;
+        let db = InMemoryDB::new();
+        let mapping = CsvMapping::consorsbank();
+
+        // assets must be known
+        assert!(matches!(
+            mapping
+                .import(CONSORSBANK_EXPORT.as_bytes(), None, &db)
+                .await,
+            Err(ImportError::UnknownAsset(_))
+        ));
+        // invalid ISINs are not reported as unknown assets
+        let invalid = CONSORSBANK_EXPORT.replace("US0378331005", "US0378331006");
+        assert!(matches!(
+            mapping.import(invalid.as_bytes(), None, &db).await,
+            Err(ImportError::DataError(DataError::InvalidIdentifier(_)))
+        ));
+        assert!(db.get_all_transactions().await.unwrap().is_empty());
+
+        let asset_id = db
+            .insert_asset(&Asset::Stock(Stock::new(
+                None,
+                "Apple Inc.".to_string(),
+                Some("US0378331005".to_string()),
+                None,
+                None,
+            )))
+            .await
+            .unwrap();
+        let summary = mapping
+            .import(CONSORSBANK_EXPORT.as_bytes(), None, &db)
+            .await
+            .unwrap();
+        assert_eq!(summary.inserted.len(), 4);
+        assert!(summary.duplicates.is_empty());
+        let transactions = db.get_all_transactions().await.unwrap();
+        assert_eq!(transactions.len(), 8);
+
+        let buy = db.get_transaction_by_id(summary.inserted[1]).await.unwrap();
+        assert!(matches!(
+            buy.transaction_type,
+            TransactionType::Asset { asset_id: id, position } if id == asset_id && position == 20.0
+        ));
+        assert_fuzzy_eq!(buy.cash_flow.amount.amount, -2_400.5, tol);
+        let filter = TransactionFilter {
+            kinds: Some(vec![TransactionKind::Fee, TransactionKind::Tax]),
+            ..Default::default()
+        };
+        let costs = db.get_transactions(&filter).await.unwrap();
+        assert_eq!(costs.len(), 4);
+        assert!(costs.iter().all(|t| match t.transaction_type {
+            TransactionType::Fee { transaction_ref } => transaction_ref.is_some(),
+            TransactionType::Tax {
+                transaction_ref, ..
+            } => transaction_ref.is_some(),
+            _ => false,
+        }));
+        let sell_costs: f64 = costs
+            .iter()
+            .filter(|t| {
+                matches!(
+                    t.transaction_type,
+                    TransactionType::Fee { transaction_ref } | TransactionType::Tax { transaction_ref, .. }
+                        if transaction_ref == Some(summary.inserted[3])
+                )
+            })
+            .map(|t| t.cash_flow.amount.amount)
+            .sum();
+        assert_fuzzy_eq!(sell_costs, -40.05, tol);
+
+        // importing an overlapping export only stores new bookings
+        let export = format!("{CONSORSBANK_EXPORT}10.12.2021;Auszahlung;;;;1.000,00;EUR;;\n");
+        let summary = mapping.import(export.as_bytes(), None, &db).await.unwrap();
+        assert_eq!(summary.inserted.len(), 1);
+        assert_eq!(summary.duplicates.len(), 4);
+        assert_eq!(db.get_all_transactions().await.unwrap().len(), 9);
+    }
+}
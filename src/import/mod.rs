@@ -0,0 +1,348 @@
+//! Import and export of transactions from and to files exported by brokers, banks or personal
+//! finance tools
+//!
+//! Readers of the supported file formats produce `ImportRecord`s, which are converted to
+//! transactions and stored by `store_records`. Assets are resolved by their ISIN, fees and taxes
+//! are stored as separate transactions referring to the main transaction, and records which have
+//! already been stored before, e.g. by importing an overlapping export, are skipped.
+//! Statements in OFX (or QFX) and QIF format may also contain securities and prices, they are
+//! read into a `Statement` and stored by `store_statement`. Stored transactions are exported
+//! to these formats via `export_statement`.
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use time::Date;
+
+use crate::datatypes::date_time_helper::DateTimeError;
+use crate::datatypes::{
+    CashFlow, Currency, CurrencyISOCode, DataError, DataItem, Identifier, IdentifierType, TaxKind,
+    Transaction, TransactionFilter, TransactionHandler, TransactionKind, TransactionType,
+};
+
+pub mod csv_mapping;
+pub mod ofx;
+pub mod qif;
+pub mod statement;
+
+pub use csv_mapping::{CsvColumns, CsvMapping};
+pub use statement::{export_statement, store_statement, Security, SecurityPrice, Statement};
+
+/// Cash flows differing by less than this amount are considered equal when checking for duplicates
+const DUPLICATE_TOLERANCE: f64 = 1e-6;
+
+/// Errors related to importing transactions
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("Failed to read CSV data")]
+    CsvError(#[from] csv::Error),
+    #[error("Failed to access stored data")]
+    DataError(#[from] DataError),
+    #[error("Invalid date or time")]
+    DateTimeError(#[from] DateTimeError),
+    #[error("Missing column '{0}'")]
+    MissingColumn(String),
+    #[error("Invalid value '{value}' in column '{column}' of line {line}")]
+    InvalidValue {
+        line: u64,
+        column: String,
+        value: String,
+    },
+    #[error("Unknown booking type '{value}' in line {line}")]
+    UnknownBookingType { line: u64, value: String },
+    #[error("Missing ISIN for booking of type {0:?}")]
+    MissingIsin(BookingType),
+    #[error("No asset found with ISIN {0}")]
+    UnknownAsset(String),
+    #[error("Asset '{0}' has no ISIN")]
+    AssetWithoutIsin(String),
+    #[error("Invalid statement: {0}")]
+    InvalidStatement(String),
+}
+
+/// Type of an imported booking, which determines the type of the stored transaction and the
+/// signs of its amount and position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BookingType {
+    Buy,
+    Sell,
+    Dividend,
+    Interest,
+    Deposit,
+    Withdrawal,
+    Fee,
+    Tax,
+}
+
+impl BookingType {
+    /// Check whether bookings of this type refer to an asset
+    pub fn has_asset(&self) -> bool {
+        matches!(
+            self,
+            BookingType::Buy | BookingType::Sell | BookingType::Dividend | BookingType::Interest
+        )
+    }
+}
+
+/// Single booking read from an import file. Amounts and positions are absolute values, their
+/// signs are given by the booking type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportRecord {
+    pub date: Date,
+    pub booking_type: BookingType,
+    /// ISIN of the asset, required for buys, sells, dividends and interest
+    pub isin: Option<String>,
+    /// Number of units bought or sold
+    pub position: f64,
+    /// Gross amount, i.e. before fees and taxes
+    pub amount: f64,
+    pub currency: CurrencyISOCode,
+    /// Fees charged for the booking
+    pub fee: f64,
+    /// Taxes charged for the booking, negative if tax is refunded
+    pub tax: f64,
+    pub note: Option<String>,
+}
+
+impl ImportRecord {
+    /// Convert the record into the main transaction and the related fee and tax transactions,
+    /// which still need to be linked to the main transaction
+    fn to_transactions(
+        &self,
+        asset_id: Option<i32>,
+        currency: Currency,
+        account_id: Option<i32>,
+    ) -> Result<(Transaction, Vec<Transaction>), ImportError> {
+        let asset = || asset_id.ok_or(ImportError::MissingIsin(self.booking_type));
+        let amount = self.amount.abs();
+        let position = self.position.abs();
+        let (transaction_type, amount) = match self.booking_type {
+            BookingType::Buy => (
+                TransactionType::Asset {
+                    asset_id: asset()?,
+                    position,
+                },
+                -amount,
+            ),
+            BookingType::Sell => (
+                TransactionType::Asset {
+                    asset_id: asset()?,
+                    position: -position,
+                },
+                amount,
+            ),
+            BookingType::Dividend => (TransactionType::Dividend { asset_id: asset()? }, amount),
+            BookingType::Interest => (TransactionType::Interest { asset_id: asset()? }, amount),
+            BookingType::Deposit => (TransactionType::Cash, amount),
+            BookingType::Withdrawal => (TransactionType::Cash, -amount),
+            BookingType::Fee => (
+                TransactionType::Fee {
+                    transaction_ref: None,
+                },
+                -amount,
+            ),
+            BookingType::Tax => (
+                TransactionType::Tax {
+                    transaction_ref: None,
+                    kind: TaxKind::Domestic,
+                    country: None,
+                },
+                -amount,
+            ),
+        };
+        let transaction = |transaction_type, amount| Transaction {
+            id: None,
+            transaction_type,
+            cash_flow: CashFlow::new(amount, currency, self.date),
+            note: self.note.clone(),
+            account_id,
+        };
+        let mut related = Vec::new();
+        if self.fee != 0.0 {
+            related.push(transaction(
+                TransactionType::Fee {
+                    transaction_ref: None,
+                },
+                -self.fee.abs(),
+            ));
+        }
+        if self.tax != 0.0 {
+            related.push(transaction(
+                TransactionType::Tax {
+                    transaction_ref: None,
+                    kind: TaxKind::Domestic,
+                    country: None,
+                },
+                -self.tax,
+            ));
+        }
+        Ok((transaction(transaction_type, amount), related))
+    }
+
+    /// Create a record from a stored transaction and the fees and taxes referring to it.
+    /// Splits and transfers can't be represented as records.
+    fn from_transactions(
+        transaction: &Transaction,
+        related: &[&Transaction],
+        isin: Option<String>,
+    ) -> Option<Self> {
+        let amount = transaction.cash_flow.amount.amount;
+        let (booking_type, position) = match transaction.transaction_type {
+            TransactionType::Asset { position, .. } if position < 0.0 => {
+                (BookingType::Sell, position)
+            }
+            TransactionType::Asset { position, .. } => (BookingType::Buy, position),
+            TransactionType::Dividend { .. } => (BookingType::Dividend, 0.0),
+            TransactionType::Interest { .. } => (BookingType::Interest, 0.0),
+            TransactionType::Cash if amount < 0.0 => (BookingType::Withdrawal, 0.0),
+            TransactionType::Cash => (BookingType::Deposit, 0.0),
+            TransactionType::Fee { .. } => (BookingType::Fee, 0.0),
+            TransactionType::Tax { .. } => (BookingType::Tax, 0.0),
+            TransactionType::Split { .. } | TransactionType::Transfer { .. } => return None,
+        };
+        let costs = |kind: TransactionKind| -> f64 {
+            -related
+                .iter()
+                .filter(|t| t.transaction_type.kind() == kind)
+                .map(|t| t.cash_flow.amount.amount)
+                .sum::<f64>()
+        };
+        Some(Self {
+            date: transaction.cash_flow.date,
+            booking_type,
+            isin: if booking_type.has_asset() { isin } else { None },
+            position: position.abs(),
+            amount: amount.abs(),
+            currency: transaction.cash_flow.amount.currency.iso_code,
+            fee: costs(TransactionKind::Fee),
+            tax: costs(TransactionKind::Tax),
+            note: transaction.note.clone(),
+        })
+    }
+}
+
+/// Result of storing imported records
+#[derive(Debug, Clone, Default)]
+pub struct ImportSummary {
+    /// Ids of the stored main transactions, not including related fees and taxes
+    pub inserted: Vec<i32>,
+    /// Records skipped since a matching transaction had been stored before
+    pub duplicates: Vec<ImportRecord>,
+    /// Ids of assets created for unknown securities of a statement
+    pub assets: Vec<i32>,
+    /// Ids of the stored quotes
+    pub quotes: Vec<i32>,
+}
+
+/// Check whether two transactions describe the same booking, ignoring notes and references
+fn is_same_booking(a: &Transaction, b: &Transaction) -> bool {
+    let position = |t: &Transaction| match t.transaction_type {
+        TransactionType::Asset { position, .. } => Some(position),
+        _ => None,
+    };
+    a.transaction_type.kind() == b.transaction_type.kind()
+        && a.transaction_type.asset_id() == b.transaction_type.asset_id()
+        && position(a) == position(b)
+        && a.account_id == b.account_id
+        && a.cash_flow
+            .fuzzy_cash_flows_cmp_eq(&b.cash_flow, DUPLICATE_TOLERANCE)
+}
+
+/// Store imported records as transactions booked to the given account. All assets are resolved
+/// by ISIN before any transaction is stored, i.e. nothing is stored if an asset is unknown.
+/// A record is skipped if a transaction of the same type, asset, position, date and amount had
+/// been stored before the import. Identical records within the imported data are all stored,
+/// unless they have been stored before as often.
+pub async fn store_records(
+    records: &[ImportRecord],
+    account_id: Option<i32>,
+    db: &(dyn TransactionHandler + Send + Sync),
+) -> Result<ImportSummary, ImportError> {
+    let mut summary = ImportSummary::default();
+    if records.is_empty() {
+        return Ok(summary);
+    }
+
+    let mut assets = BTreeMap::new();
+    let mut currencies = BTreeMap::new();
+    let mut bookings = Vec::with_capacity(records.len());
+    for record in records {
+        let asset_id = match &record.isin {
+            Some(isin) if record.booking_type.has_asset() => {
+                if !assets.contains_key(isin) {
+                    let identifier = Identifier::new(IdentifierType::Isin, isin)?;
+                    let asset = match db.find_asset_by_identifier(&identifier).await {
+                        Ok(asset) => asset,
+                        Err(DataError::NotFound(_)) => {
+                            return Err(ImportError::UnknownAsset(isin.clone()))
+                        }
+                        Err(err) => return Err(err.into()),
+                    };
+                    assets.insert(isin.clone(), asset.get_id()?);
+                }
+                assets.get(isin).copied()
+            }
+            _ => None,
+        };
+        let iso_code = record.currency.to_string();
+        if !currencies.contains_key(&iso_code) {
+            let currency = db.get_or_new_currency(record.currency).await?;
+            currencies.insert(iso_code.clone(), currency);
+        }
+        bookings.push(record.to_transactions(asset_id, currencies[&iso_code], account_id)?);
+    }
+
+    let filter = TransactionFilter {
+        start: records.iter().map(|r| r.date).min(),
+        end: records.iter().map(|r| r.date).max(),
+        account_ids: account_id.map(|id| vec![id]),
+        ..Default::default()
+    };
+    let mut existing = db.get_transactions(&filter).await?;
+    // fees and taxes linked to another transaction are covered by the main transaction
+    existing.retain(|t| {
+        !matches!(
+            t.transaction_type,
+            TransactionType::Fee {
+                transaction_ref: Some(_)
+            } | TransactionType::Tax {
+                transaction_ref: Some(_),
+                ..
+            }
+        )
+    });
+    for (record, (transaction, related)) in records.iter().zip(bookings) {
+        if let Some(idx) = existing
+            .iter()
+            .position(|t| is_same_booking(t, &transaction))
+        {
+            existing.swap_remove(idx);
+            summary.duplicates.push(record.clone());
+            continue;
+        }
+        let id = db.insert_transaction(&transaction).await?;
+        for mut related_transaction in related {
+            related_transaction.set_transaction_ref(id);
+            db.insert_transaction(&related_transaction).await?;
+        }
+        summary.inserted.push(id);
+    }
+    Ok(summary)
+}
+
+/// Assert that two lists of records are equal up to rounding differences of amounts
+#[cfg(test)]
+fn assert_same_records(left: &[ImportRecord], right: &[ImportRecord]) {
+    let tol = 1e-8;
+    assert_eq!(left.len(), right.len());
+    for (l, r) in left.iter().zip(right) {
+        assert_eq!(
+            (l.date, l.booking_type, &l.isin, l.currency, &l.note),
+            (r.date, r.booking_type, &r.isin, r.currency, &r.note)
+        );
+        assert_fuzzy_eq!(l.position, r.position, tol);
+        assert_fuzzy_eq!(l.amount, r.amount, tol);
+        assert_fuzzy_eq!(l.fee, r.fee, tol);
+        assert_fuzzy_eq!(l.tax, r.tax, tol);
+    }
+}
@@ -0,0 +1,643 @@
+//! Reading and writing statements in the Open Financial Exchange (OFX) format
+//!
+//! Both the SGML based OFX 1.x format, where closing tags of elements with a value are optional,
+//! and the XML based OFX 2.x format are read; QFX files used by Quicken are OFX files as well.
+//! Bank and credit card statements, buys, sells, income and expenses of investment statements,
+//! the security list and the prices of positions are read, other investment transactions like
+//! reinvestments or splits are ignored. Statements are written as OFX 2.2 investment statements.
+use std::fmt::Write;
+
+use time::Date;
+
+use super::{BookingType, ImportError, ImportRecord, Security, SecurityPrice, Statement};
+use crate::datatypes::date_time_helper::date_from_str;
+use crate::datatypes::{CurrencyISOCode, Identifier, IdentifierType};
+
+/// Element of an OFX document, either an aggregate with child elements or an element with value
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    value: Option<String>,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|e| e.name == name)
+    }
+
+    /// Value of the child element with the given name
+    fn text(&self, name: &str) -> Option<&str> {
+        self.child(name).and_then(|e| e.value.as_deref())
+    }
+
+    /// All elements with the given name in the tree below this element
+    fn descendants<'a>(&'a self, name: &str, found: &mut Vec<&'a Element>) {
+        for child in &self.children {
+            if child.name == name {
+                found.push(child);
+            }
+            child.descendants(name, found);
+        }
+    }
+
+    fn number(&self, name: &str) -> Result<Option<f64>, ImportError> {
+        self.text(name)
+            .map(|value| {
+                value
+                    .replace(',', ".")
+                    .parse()
+                    .map_err(|_| invalid(name, value))
+            })
+            .transpose()
+    }
+
+    /// Date part of a date time value like "20210315120000.000[-5:EST]"
+    fn date(&self, name: &str) -> Result<Date, ImportError> {
+        let value = self
+            .text(name)
+            .ok_or_else(|| ImportError::InvalidStatement(format!("missing element {name}")))?;
+        value
+            .get(0..8)
+            .and_then(|date| date_from_str(date, "%Y%m%d").ok())
+            .ok_or_else(|| invalid(name, value))
+    }
+
+    /// Currency of a transaction or position, if it differs from the statement's currency
+    fn currency(&self, default: CurrencyISOCode) -> Result<CurrencyISOCode, ImportError> {
+        match self
+            .child("CURRENCY")
+            .or_else(|| self.child("ORIGCURRENCY"))
+            .and_then(|c| c.text("CURSYM"))
+        {
+            Some(code) => CurrencyISOCode::new(code).map_err(|_| invalid("CURSYM", code)),
+            None => Ok(default),
+        }
+    }
+
+    /// ISIN of the security identified by the SECID child element, CUSIPs are converted to ISINs
+    fn isin(&self) -> Option<String> {
+        let secid = self.child("SECID")?;
+        let id = secid.text("UNIQUEID")?;
+        match secid.text("UNIQUEIDTYPE")? {
+            "ISIN" => Some(id.to_string()),
+            "CUSIP" => Identifier::new(IdentifierType::Cusip, id)
+                .ok()?
+                .equivalents()
+                .into_iter()
+                .find(|i| i.id_type == IdentifierType::Isin)
+                .map(|i| i.value),
+            _ => None,
+        }
+    }
+}
+
+fn invalid(name: &str, value: &str) -> ImportError {
+    ImportError::InvalidStatement(format!("invalid value '{value}' of element {name}"))
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Move the last element of the stack to the children of its parent
+fn close_last(stack: &mut Vec<Element>) {
+    if stack.len() > 1 {
+        let element = stack.pop().unwrap();
+        stack.last_mut().unwrap().children.push(element);
+    }
+}
+
+/// Parse the OFX document into a tree of elements below a root element without name. Headers
+/// and processing instructions are skipped, elements with a value may lack their closing tag.
+fn parse_elements(content: &str) -> Result<Element, ImportError> {
+    let start = content
+        .find("<OFX>")
+        .ok_or_else(|| ImportError::InvalidStatement("missing element OFX".to_string()))?;
+    let mut stack = vec![Element::default()];
+    let mut rest = &content[start..];
+    while let Some(open) = rest.find('<') {
+        let text = rest[..open].trim();
+        if !text.is_empty() {
+            stack.last_mut().unwrap().value = Some(unescape(text));
+        }
+        let close = rest[open..]
+            .find('>')
+            .ok_or_else(|| ImportError::InvalidStatement("unterminated tag".to_string()))?
+            + open;
+        let tag = rest[open + 1..close].trim();
+        rest = &rest[close + 1..];
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+        // an element with value is complete as soon as any other tag follows
+        if stack.len() > 1 && stack.last().unwrap().value.is_some() {
+            let is_own_end = tag.strip_prefix('/') == Some(stack.last().unwrap().name.as_str());
+            close_last(&mut stack);
+            if is_own_end {
+                continue;
+            }
+        }
+        match tag.strip_prefix('/') {
+            Some(name) => {
+                if stack.iter().skip(1).any(|e| e.name == name) {
+                    while stack.last().unwrap().name != name {
+                        close_last(&mut stack);
+                    }
+                    close_last(&mut stack);
+                }
+            }
+            None => stack.push(Element {
+                name: tag
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                ..Default::default()
+            }),
+        }
+    }
+    while stack.len() > 1 {
+        close_last(&mut stack);
+    }
+    Ok(stack.pop().unwrap())
+}
+
+/// Read a bank statement transaction
+fn bank_record(
+    transaction: &Element,
+    currency: CurrencyISOCode,
+) -> Result<ImportRecord, ImportError> {
+    let amount = transaction.number("TRNAMT")?.unwrap_or_default();
+    let booking_type = match transaction.text("TRNTYPE") {
+        Some("FEE") | Some("SRVCHG") => BookingType::Fee,
+        _ if amount < 0.0 => BookingType::Withdrawal,
+        _ => BookingType::Deposit,
+    };
+    Ok(ImportRecord {
+        date: transaction.date("DTPOSTED")?,
+        booking_type,
+        isin: None,
+        position: 0.0,
+        amount: amount.abs(),
+        currency: transaction.currency(currency)?,
+        fee: 0.0,
+        tax: 0.0,
+        note: transaction
+            .text("MEMO")
+            .or_else(|| transaction.text("NAME"))
+            .map(str::to_string),
+    })
+}
+
+/// Read the INVBUY or INVSELL aggregate of a buy or sell
+fn trade_record(
+    trade: &Element,
+    booking_type: BookingType,
+    currency: CurrencyISOCode,
+) -> Result<ImportRecord, ImportError> {
+    let invtran = trade
+        .child("INVTRAN")
+        .ok_or_else(|| ImportError::InvalidStatement("missing element INVTRAN".to_string()))?;
+    let position = trade.number("UNITS")?.unwrap_or_default().abs();
+    let fee =
+        trade.number("COMMISSION")?.unwrap_or_default() + trade.number("FEES")?.unwrap_or_default();
+    let tax = trade.number("TAXES")?.unwrap_or_default();
+    let amount = match trade.number("TOTAL")? {
+        Some(total) if booking_type == BookingType::Buy => total.abs() - fee - tax,
+        Some(total) => total.abs() + fee + tax,
+        None => position * trade.number("UNITPRICE")?.unwrap_or_default(),
+    };
+    Ok(ImportRecord {
+        date: invtran.date("DTTRADE")?,
+        booking_type,
+        isin: trade.isin(),
+        position,
+        amount,
+        currency: trade.currency(currency)?,
+        fee,
+        tax,
+        note: invtran.text("MEMO").map(str::to_string),
+    })
+}
+
+/// Read income or expenses related to a security
+fn security_record(
+    transaction: &Element,
+    booking_type: BookingType,
+    currency: CurrencyISOCode,
+) -> Result<ImportRecord, ImportError> {
+    let invtran = transaction
+        .child("INVTRAN")
+        .ok_or_else(|| ImportError::InvalidStatement("missing element INVTRAN".to_string()))?;
+    Ok(ImportRecord {
+        date: invtran.date("DTTRADE")?,
+        booking_type,
+        isin: transaction.isin(),
+        position: 0.0,
+        amount: transaction.number("TOTAL")?.unwrap_or_default().abs(),
+        currency: transaction.currency(currency)?,
+        fee: 0.0,
+        tax: transaction.number("WITHHOLDING")?.unwrap_or_default(),
+        note: invtran.text("MEMO").map(str::to_string),
+    })
+}
+
+/// Read a statement in OFX format
+pub fn parse(content: &str) -> Result<Statement, ImportError> {
+    let root = parse_elements(content)?;
+    let mut statement = Statement::default();
+
+    let mut infos = Vec::new();
+    root.descendants("SECINFO", &mut infos);
+    for info in infos {
+        if let Some(isin) = info.isin() {
+            statement.securities.push(Security {
+                name: info.text("SECNAME").unwrap_or(&isin).to_string(),
+                isin,
+                symbol: info.text("TICKER").map(str::to_string),
+            });
+        }
+    }
+
+    let mut statements = Vec::new();
+    for name in ["STMTRS", "CCSTMTRS", "INVSTMTRS"] {
+        root.descendants(name, &mut statements);
+    }
+    for content in statements {
+        let currency = content
+            .text("CURDEF")
+            .ok_or_else(|| ImportError::InvalidStatement("missing element CURDEF".to_string()))?;
+        let currency = CurrencyISOCode::new(currency).map_err(|_| invalid("CURDEF", currency))?;
+        if let Some(list) = content.child("BANKTRANLIST") {
+            for transaction in list.children.iter().filter(|e| e.name == "STMTTRN") {
+                statement.records.push(bank_record(transaction, currency)?);
+            }
+        }
+        if let Some(list) = content.child("INVTRANLIST") {
+            for transaction in &list.children {
+                let record = match transaction.name.as_str() {
+                    "BUYSTOCK" | "BUYMF" | "BUYDEBT" | "BUYOTHER" => transaction
+                        .child("INVBUY")
+                        .map(|buy| trade_record(buy, BookingType::Buy, currency)),
+                    "SELLSTOCK" | "SELLMF" | "SELLDEBT" | "SELLOTHER" => transaction
+                        .child("INVSELL")
+                        .map(|sell| trade_record(sell, BookingType::Sell, currency)),
+                    "INCOME" => {
+                        let booking_type = match transaction.text("INCOMETYPE") {
+                            Some("INTEREST") => BookingType::Interest,
+                            _ => BookingType::Dividend,
+                        };
+                        Some(security_record(transaction, booking_type, currency))
+                    }
+                    "INVEXPENSE" => Some(security_record(transaction, BookingType::Fee, currency)),
+                    "INVBANKTRAN" => transaction
+                        .child("STMTTRN")
+                        .map(|t| bank_record(t, currency)),
+                    _ => None,
+                };
+                if let Some(record) = record {
+                    statement.records.push(record?);
+                }
+            }
+        }
+        if let Some(list) = content.child("INVPOSLIST") {
+            for position in list.children.iter().filter_map(|p| p.child("INVPOS")) {
+                if let (Some(isin), Some(price)) = (position.isin(), position.number("UNITPRICE")?)
+                {
+                    statement.prices.push(SecurityPrice {
+                        isin,
+                        date: position.date("DTPRICEASOF")?,
+                        price,
+                        currency: position.currency(currency)?,
+                    });
+                }
+            }
+        }
+    }
+    Ok(statement)
+}
+
+/// Writer of OFX 2 documents with one element per line
+struct OfxWriter {
+    out: String,
+    depth: usize,
+}
+
+impl OfxWriter {
+    fn open(&mut self, name: &str) {
+        let _ = writeln!(self.out, "{:indent$}<{name}>", "", indent = 2 * self.depth);
+        self.depth += 1;
+    }
+
+    fn close(&mut self, name: &str) {
+        self.depth -= 1;
+        let _ = writeln!(self.out, "{:indent$}</{name}>", "", indent = 2 * self.depth);
+    }
+
+    fn value(&mut self, name: &str, value: &str) {
+        let _ = writeln!(
+            self.out,
+            "{:indent$}<{name}>{}</{name}>",
+            "",
+            escape(value),
+            indent = 2 * self.depth
+        );
+    }
+
+    fn date(&mut self, name: &str, date: Date) {
+        let value = format!(
+            "{:04}{:02}{:02}",
+            date.year(),
+            date.month() as u8,
+            date.day()
+        );
+        self.value(name, &value);
+    }
+
+    fn status(&mut self) {
+        self.open("STATUS");
+        self.value("CODE", "0");
+        self.value("SEVERITY", "INFO");
+        self.close("STATUS");
+    }
+
+    fn secid(&mut self, isin: &str) {
+        self.open("SECID");
+        self.value("UNIQUEID", isin);
+        self.value("UNIQUEIDTYPE", "ISIN");
+        self.close("SECID");
+    }
+
+    fn currency(&mut self, currency: CurrencyISOCode, default: CurrencyISOCode) {
+        if currency != default {
+            self.open("CURRENCY");
+            self.value("CURRATE", "1");
+            self.value("CURSYM", &currency.to_string());
+            self.close("CURRENCY");
+        }
+    }
+
+    fn invtran(&mut self, id: usize, record: &ImportRecord) {
+        self.open("INVTRAN");
+        self.value("FITID", &id.to_string());
+        self.date("DTTRADE", record.date);
+        if let Some(note) = &record.note {
+            self.value("MEMO", note);
+        }
+        self.close("INVTRAN");
+    }
+
+    fn trade(&mut self, id: usize, record: &ImportRecord, default: CurrencyISOCode) {
+        let (name, aggregate, sign) = match record.booking_type {
+            BookingType::Buy => ("BUYSTOCK", "INVBUY", 1.0),
+            _ => ("SELLSTOCK", "INVSELL", -1.0),
+        };
+        self.open(name);
+        self.open(aggregate);
+        self.invtran(id, record);
+        self.secid(record.isin.as_deref().unwrap_or_default());
+        self.value("UNITS", &(sign * record.position).to_string());
+        let price = if record.position != 0.0 {
+            record.amount / record.position
+        } else {
+            0.0
+        };
+        self.value("UNITPRICE", &price.to_string());
+        if record.fee != 0.0 {
+            self.value("COMMISSION", &record.fee.to_string());
+        }
+        if record.tax != 0.0 {
+            self.value("TAXES", &record.tax.to_string());
+        }
+        let total = -sign * record.amount - record.fee - record.tax;
+        self.value("TOTAL", &total.to_string());
+        self.currency(record.currency, default);
+        self.value("SUBACCTSEC", "CASH");
+        self.value("SUBACCTFUND", "CASH");
+        self.close(aggregate);
+        match record.booking_type {
+            BookingType::Buy => self.value("BUYTYPE", "BUY"),
+            _ => self.value("SELLTYPE", "SELL"),
+        }
+        self.close(name);
+    }
+
+    fn income(&mut self, id: usize, record: &ImportRecord, default: CurrencyISOCode) {
+        self.open("INCOME");
+        self.invtran(id, record);
+        self.secid(record.isin.as_deref().unwrap_or_default());
+        let income_type = match record.booking_type {
+            BookingType::Interest => "INTEREST",
+            _ => "DIV",
+        };
+        self.value("INCOMETYPE", income_type);
+        self.value("TOTAL", &record.amount.to_string());
+        self.value("SUBACCTSEC", "CASH");
+        self.value("SUBACCTFUND", "CASH");
+        if record.tax != 0.0 {
+            self.value("WITHHOLDING", &record.tax.to_string());
+        }
+        self.currency(record.currency, default);
+        self.close("INCOME");
+    }
+
+    fn bank_transaction(&mut self, id: usize, record: &ImportRecord, default: CurrencyISOCode) {
+        let (transaction_type, amount) = match record.booking_type {
+            BookingType::Deposit => ("CREDIT", record.amount),
+            BookingType::Fee => ("FEE", -record.amount),
+            _ => ("DEBIT", -record.amount),
+        };
+        self.open("INVBANKTRAN");
+        self.open("STMTTRN");
+        self.value("TRNTYPE", transaction_type);
+        self.date("DTPOSTED", record.date);
+        self.value("TRNAMT", &amount.to_string());
+        self.value("FITID", &id.to_string());
+        if let Some(note) = &record.note {
+            self.value("MEMO", note);
+        }
+        self.currency(record.currency, default);
+        self.close("STMTTRN");
+        self.value("SUBACCTFUND", "CASH");
+        self.close("INVBANKTRAN");
+    }
+}
+
+/// Write a statement as OFX investment statement in the currency of its first record.
+/// Prices and fees charged on income are not written. Taxes not related to any other booking
+/// are written as debits, since OFX has no transaction type for taxes. Amounts in foreign
+/// currencies are written with an exchange rate of 1, since the rate is unknown.
+pub fn write(statement: &Statement) -> String {
+    let currency = statement
+        .records
+        .first()
+        .map(|r| r.currency)
+        .unwrap_or_else(|| CurrencyISOCode::new("EUR").unwrap());
+    let start = statement.records.iter().map(|r| r.date).min();
+    let end = statement.records.iter().map(|r| r.date).max();
+
+    let mut ofx = OfxWriter {
+        out: String::new(),
+        depth: 0,
+    };
+    ofx.out
+        .push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n");
+    ofx.out.push_str(
+        "<?OFX OFXHEADER=\"200\" VERSION=\"220\" SECURITY=\"NONE\" OLDFILEUID=\"NONE\" NEWFILEUID=\"NONE\"?>\n",
+    );
+    ofx.open("OFX");
+    ofx.open("SIGNONMSGSRSV1");
+    ofx.open("SONRS");
+    ofx.status();
+    if let Some(end) = end {
+        ofx.date("DTSERVER", end);
+    }
+    ofx.value("LANGUAGE", "ENG");
+    ofx.close("SONRS");
+    ofx.close("SIGNONMSGSRSV1");
+
+    ofx.open("INVSTMTMSGSRSV1");
+    ofx.open("INVSTMTTRNRS");
+    ofx.value("TRNUID", "0");
+    ofx.status();
+    ofx.open("INVSTMTRS");
+    if let Some(end) = end {
+        ofx.date("DTASOF", end);
+    }
+    ofx.value("CURDEF", &currency.to_string());
+    ofx.open("INVACCTFROM");
+    ofx.value("BROKERID", "finql");
+    ofx.value("ACCTID", "0");
+    ofx.close("INVACCTFROM");
+    ofx.open("INVTRANLIST");
+    if let (Some(start), Some(end)) = (start, end) {
+        ofx.date("DTSTART", start);
+        ofx.date("DTEND", end);
+    }
+    for (idx, record) in statement.records.iter().enumerate() {
+        let id = idx + 1;
+        match record.booking_type {
+            BookingType::Buy | BookingType::Sell => ofx.trade(id, record, currency),
+            BookingType::Dividend | BookingType::Interest => ofx.income(id, record, currency),
+            _ => ofx.bank_transaction(id, record, currency),
+        }
+    }
+    ofx.close("INVTRANLIST");
+    ofx.close("INVSTMTRS");
+    ofx.close("INVSTMTTRNRS");
+    ofx.close("INVSTMTMSGSRSV1");
+
+    if !statement.securities.is_empty() {
+        ofx.open("SECLISTMSGSRSV1");
+        ofx.open("SECLIST");
+        for security in &statement.securities {
+            ofx.open("STOCKINFO");
+            ofx.open("SECINFO");
+            ofx.secid(&security.isin);
+            ofx.value("SECNAME", &security.name);
+            if let Some(symbol) = &security.symbol {
+                ofx.value("TICKER", symbol);
+            }
+            ofx.close("SECINFO");
+            ofx.close("STOCKINFO");
+        }
+        ofx.close("SECLIST");
+        ofx.close("SECLISTMSGSRSV1");
+    }
+    ofx.close("OFX");
+    ofx.out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::assert_same_records;
+
+    const INVESTMENT: &str = include_str!("fixtures/investment.ofx");
+    const BANK: &str = include_str!("fixtures/bank.qfx");
+
+    #[test]
+    fn parse_investment_statement() {
+        let tol = 1e-10;
+        let usd = CurrencyISOCode::new("USD").unwrap();
+        let statement = parse(INVESTMENT).unwrap();
+
+        assert_eq!(statement.securities.len(), 2);
+        let apple = &statement.securities[0];
+        assert_eq!(apple.isin, "US0378331005");
+        assert_eq!(apple.name, "Apple Inc.");
+        assert_eq!(apple.symbol.as_deref(), Some("AAPL"));
+
+        // the reinvestment is ignored
+        let records = &statement.records;
+        assert_eq!(records.len(), 5);
+        assert_eq!(records[0].booking_type, BookingType::Deposit);
+        assert_eq!(records[0].note.as_deref(), Some("Initial deposit"));
+        assert_eq!(records[1].booking_type, BookingType::Buy);
+        assert_eq!(
+            records[1].date,
+            Date::from_calendar_date(2021, time::Month::March, 15).unwrap()
+        );
+        assert_eq!(records[1].isin.as_deref(), Some("US0378331005"));
+        assert_eq!(records[1].currency, usd);
+        assert_fuzzy_eq!(records[1].position, 20.0, tol);
+        assert_fuzzy_eq!(records[1].amount, 2_400.5, tol);
+        assert_fuzzy_eq!(records[1].fee, 4.95, tol);
+        assert_eq!(records[2].booking_type, BookingType::Dividend);
+        assert_fuzzy_eq!(records[2].amount, 4.4, tol);
+        assert_fuzzy_eq!(records[2].tax, 0.66, tol);
+        assert_eq!(records[3].booking_type, BookingType::Sell);
+        assert_fuzzy_eq!(records[3].position, 5.0, tol);
+        assert_fuzzy_eq!(records[3].amount, 750.0, tol);
+        assert_fuzzy_eq!(records[3].tax, 35.1, tol);
+        assert_eq!(records[4].booking_type, BookingType::Fee);
+        assert_fuzzy_eq!(records[4].amount, 10.0, tol);
+
+        assert_eq!(statement.prices.len(), 1);
+        assert_eq!(statement.prices[0].isin, "US0378331005");
+        assert_fuzzy_eq!(statement.prices[0].price, 177.57, tol);
+    }
+
+    #[test]
+    fn parse_bank_statement() {
+        let tol = 1e-10;
+        let statement = parse(BANK).unwrap();
+        assert!(statement.securities.is_empty());
+        let records = &statement.records;
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].booking_type, BookingType::Deposit);
+        assert_eq!(records[0].note.as_deref(), Some("Salary"));
+        assert_eq!(records[1].booking_type, BookingType::Withdrawal);
+        assert_fuzzy_eq!(records[1].amount, 49.99, tol);
+        assert_eq!(records[1].note.as_deref(), Some("Card payment <1234>"));
+        assert_eq!(records[2].currency.to_string(), "USD");
+        assert_eq!(records[3].booking_type, BookingType::Fee);
+        assert_eq!(records[3].currency.to_string(), "EUR");
+
+        assert!(parse("no OFX content").is_err());
+    }
+
+    #[test]
+    fn write_and_read_again() {
+        for content in [INVESTMENT, BANK] {
+            let statement = parse(content).unwrap();
+            let written = write(&statement);
+            let read = parse(&written).unwrap();
+            assert_same_records(&statement.records, &read.records);
+            assert_eq!(statement.securities, read.securities);
+            assert!(read.prices.is_empty());
+        }
+    }
+}
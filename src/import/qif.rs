@@ -0,0 +1,457 @@
+//! Reading and writing statements in the Quicken Interchange Format (QIF)
+//!
+//! A QIF file consists of sections started by a header like `!Type:Bank`, containing records
+//! of lines starting with a field code and terminated by a line `^`. Bank, cash and credit card
+//! sections, investment sections, the security list and prices are read. QIF files contain
+//! neither currencies nor ISINs, therefore all amounts are taken to be in a given currency and
+//! securities are identified by an ISIN given as symbol in the security list or as name.
+//! Investment actions other than buys, sells, income, cash transfers and miscellaneous expenses
+//! are ignored.
+use std::fmt::Write;
+
+use time::Date;
+
+use super::{BookingType, ImportError, ImportRecord, Security, SecurityPrice, Statement};
+use crate::datatypes::date_time_helper::date_from_str;
+use crate::datatypes::identifier::is_valid_isin;
+use crate::datatypes::CurrencyISOCode;
+
+/// Category marking miscellaneous expenses as taxes
+const TAX_CATEGORY: &str = "Tax";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Section {
+    Bank,
+    Investment,
+    Securities,
+    Prices,
+    Other,
+}
+
+impl Section {
+    fn from_header(header: &str) -> Self {
+        match header.trim().to_lowercase().as_str() {
+            "!type:bank" | "!type:cash" | "!type:ccard" | "!type:oth a" | "!type:oth l" => {
+                Section::Bank
+            }
+            "!type:invst" => Section::Investment,
+            "!type:security" => Section::Securities,
+            "!type:prices" => Section::Prices,
+            _ => Section::Other,
+        }
+    }
+}
+
+/// Fields of a record by their field code
+struct Fields(Vec<(char, String)>);
+
+impl Fields {
+    fn get(&self, code: char) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn number(&self, code: char) -> Result<Option<f64>, ImportError> {
+        self.get(code).map(parse_number).transpose()
+    }
+
+    fn date(&self) -> Result<Date, ImportError> {
+        parse_date(
+            self.get('D')
+                .ok_or_else(|| ImportError::InvalidStatement("record without date".to_string()))?,
+        )
+    }
+}
+
+fn invalid(value: &str) -> ImportError {
+    ImportError::InvalidStatement(format!("invalid value '{value}'"))
+}
+
+fn parse_number(value: &str) -> Result<f64, ImportError> {
+    value.replace(',', "").parse().map_err(|_| invalid(value))
+}
+
+/// Parse dates like "12/31/2021", "12/31'21", " 1/ 2'21" or "31.12.2021"
+fn parse_date(value: &str) -> Result<Date, ImportError> {
+    let date: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+    if date.contains('.') {
+        return date_from_str(&date, "%d.%m.%Y").map_err(|_| invalid(value));
+    }
+    let century = if date.contains('\'') { 2000 } else { 1900 };
+    let parts: Vec<&str> = date.split(['/', '\'']).collect();
+    match parts[..] {
+        [month, day, year] => {
+            let year: i32 = year.parse().map_err(|_| invalid(value))?;
+            let year = if year < 100 { year + century } else { year };
+            date_from_str(&format!("{year}-{month}-{day}"), "%Y-%m-%d").map_err(|_| invalid(value))
+        }
+        _ => Err(invalid(value)),
+    }
+}
+
+fn format_date(date: Date) -> String {
+    format!(
+        "{:02}/{:02}/{:04}",
+        date.month() as u8,
+        date.day(),
+        date.year()
+    )
+}
+
+/// Imported investment record with the name of its security, which is resolved to an ISIN
+/// after the whole file has been read
+struct SecurityRecord {
+    security: Option<String>,
+    record: ImportRecord,
+}
+
+fn bank_record(fields: &Fields, currency: CurrencyISOCode) -> Result<ImportRecord, ImportError> {
+    let amount = fields
+        .number('T')?
+        .or(fields.number('U')?)
+        .unwrap_or_default();
+    let category = fields.get('L').unwrap_or_default().to_lowercase();
+    let booking_type = if category.starts_with("tax") {
+        BookingType::Tax
+    } else if category.starts_with("fee") {
+        BookingType::Fee
+    } else if amount < 0.0 {
+        BookingType::Withdrawal
+    } else {
+        BookingType::Deposit
+    };
+    Ok(ImportRecord {
+        date: fields.date()?,
+        booking_type,
+        isin: None,
+        position: 0.0,
+        amount: amount.abs(),
+        currency,
+        fee: 0.0,
+        tax: 0.0,
+        note: fields.get('M').or(fields.get('P')).map(str::to_string),
+    })
+}
+
+/// Add an investment record, expenses of the same security at the same date as the previous
+/// record are added to its fees or taxes
+fn add_investment_record(
+    fields: &Fields,
+    currency: CurrencyISOCode,
+    records: &mut Vec<SecurityRecord>,
+) -> Result<(), ImportError> {
+    let action = fields.get('N').unwrap_or_default().to_lowercase();
+    let action = action.strip_suffix('x').unwrap_or(&action);
+    let is_tax = fields
+        .get('L')
+        .is_some_and(|c| c.to_lowercase().starts_with(&TAX_CATEGORY.to_lowercase()));
+    let booking_type = match action {
+        "buy" => BookingType::Buy,
+        "sell" => BookingType::Sell,
+        "div" | "cglong" | "cgmid" | "cgshort" => BookingType::Dividend,
+        "intinc" => BookingType::Interest,
+        "xin" | "miscinc" => BookingType::Deposit,
+        "xout" => BookingType::Withdrawal,
+        "miscexp" if is_tax => BookingType::Tax,
+        "miscexp" => BookingType::Fee,
+        _ => return Ok(()),
+    };
+    let date = fields.date()?;
+    let security = fields.get('Y').map(str::to_string);
+    let total = fields.number('T')?.or(fields.number('U')?);
+
+    if matches!(booking_type, BookingType::Fee | BookingType::Tax) && security.is_some() {
+        if let Some(previous) = records.last_mut() {
+            if previous.security == security
+                && previous.record.date == date
+                && previous.record.booking_type.has_asset()
+            {
+                let amount = total.unwrap_or_default();
+                match booking_type {
+                    BookingType::Tax => previous.record.tax += amount,
+                    _ => previous.record.fee += amount,
+                }
+                return Ok(());
+            }
+        }
+    }
+
+    let position = fields.number('Q')?.unwrap_or_default().abs();
+    let fee = fields.number('O')?.unwrap_or_default();
+    let amount = match (booking_type, total) {
+        (BookingType::Buy, Some(total)) => total.abs() - fee,
+        (BookingType::Sell, Some(total)) => total.abs() + fee,
+        (_, Some(total)) => total.abs(),
+        (_, None) => position * fields.number('I')?.unwrap_or_default(),
+    };
+    records.push(SecurityRecord {
+        security,
+        record: ImportRecord {
+            date,
+            booking_type,
+            isin: None,
+            position,
+            amount,
+            currency,
+            fee,
+            tax: 0.0,
+            note: fields.get('M').map(str::to_string),
+        },
+    });
+    Ok(())
+}
+
+/// Read a statement in QIF format with all amounts in the given currency
+pub fn parse(content: &str, currency: CurrencyISOCode) -> Result<Statement, ImportError> {
+    let mut statement = Statement::default();
+    let mut section = Section::Other;
+    let mut fields = Fields(Vec::new());
+    let mut records = Vec::new();
+    // symbol or ISIN, date and price
+    let mut prices = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('!') {
+            if !line.to_lowercase().starts_with("!option") && !line.starts_with("!Clear") {
+                section = Section::from_header(line);
+            }
+            continue;
+        }
+        if line.starts_with('^') {
+            match section {
+                Section::Bank => records.push(SecurityRecord {
+                    security: None,
+                    record: bank_record(&fields, currency)?,
+                }),
+                Section::Investment => add_investment_record(&fields, currency, &mut records)?,
+                Section::Securities => {
+                    let name = fields.get('N').unwrap_or_default();
+                    let isin = fields.get('S').filter(|s| is_valid_isin(s)).or_else(|| {
+                        if is_valid_isin(name) {
+                            Some(name)
+                        } else {
+                            None
+                        }
+                    });
+                    if let Some(isin) = isin {
+                        statement.securities.push(Security {
+                            isin: isin.to_string(),
+                            name: name.to_string(),
+                            symbol: None,
+                        });
+                    }
+                }
+                Section::Prices | Section::Other => {}
+            }
+            fields.0.clear();
+            continue;
+        }
+        if section == Section::Prices {
+            let parts: Vec<&str> = line
+                .split(',')
+                .map(|p| p.trim().trim_matches('"'))
+                .collect();
+            if let [symbol, price, date] = parts[..] {
+                prices.push((symbol.to_string(), parse_date(date)?, parse_number(price)?));
+            }
+            continue;
+        }
+        let mut chars = line.chars();
+        if let Some(code) = chars.next() {
+            fields.0.push((code, chars.as_str().trim().to_string()));
+        }
+    }
+
+    let isin = |name: &str| {
+        statement
+            .securities
+            .iter()
+            .find(|s| s.name == name)
+            .map(|s| s.isin.clone())
+            .or_else(|| is_valid_isin(name).then(|| name.to_string()))
+    };
+    let records = records
+        .into_iter()
+        .map(
+            |SecurityRecord {
+                 security,
+                 mut record,
+             }| {
+                if record.booking_type.has_asset() {
+                    record.isin = security.as_deref().and_then(isin);
+                }
+                record
+            },
+        )
+        .collect();
+    let prices = prices
+        .into_iter()
+        .filter_map(|(symbol, date, price)| {
+            Some(SecurityPrice {
+                isin: isin(&symbol)?,
+                date,
+                price,
+                currency,
+            })
+        })
+        .collect();
+    statement.records = records;
+    statement.prices = prices;
+    Ok(statement)
+}
+
+/// Write an expense related to the previously written record
+fn write_expense(out: &mut String, record: &ImportRecord, security: &str, amount: f64, tax: bool) {
+    let _ = writeln!(out, "D{}", format_date(record.date));
+    let _ = writeln!(out, "NMiscExp");
+    let _ = writeln!(out, "Y{security}");
+    let _ = writeln!(out, "T{amount}");
+    if tax {
+        let _ = writeln!(out, "L{TAX_CATEGORY}");
+    }
+    let _ = writeln!(out, "^");
+}
+
+/// Write a statement as QIF investment account. Currencies and prices are not written. Fees and
+/// taxes of a record, which can't be given as commission, are written as miscellaneous expenses
+/// of the same security following the record.
+pub fn write(statement: &Statement) -> String {
+    let mut out = String::new();
+    if !statement.securities.is_empty() {
+        out.push_str("!Type:Security\n");
+        for security in &statement.securities {
+            let _ = writeln!(out, "N{}", security.name);
+            let _ = writeln!(out, "S{}", security.isin);
+            let _ = writeln!(out, "TStock");
+            let _ = writeln!(out, "^");
+        }
+    }
+    out.push_str("!Type:Invst\n");
+    for record in &statement.records {
+        let security = record
+            .isin
+            .as_deref()
+            .map(|isin| statement.security(isin).map_or(isin, |s| s.name.as_str()));
+        let (action, total) = match record.booking_type {
+            BookingType::Buy => ("Buy", record.amount + record.fee),
+            BookingType::Sell => ("Sell", record.amount - record.fee),
+            BookingType::Dividend => ("Div", record.amount),
+            BookingType::Interest => ("IntInc", record.amount),
+            BookingType::Deposit => ("XIn", record.amount),
+            BookingType::Withdrawal => ("XOut", record.amount),
+            BookingType::Fee | BookingType::Tax => ("MiscExp", record.amount),
+        };
+        let _ = writeln!(out, "D{}", format_date(record.date));
+        let _ = writeln!(out, "N{action}");
+        if let Some(security) = security {
+            let _ = writeln!(out, "Y{security}");
+        }
+        let is_trade = matches!(record.booking_type, BookingType::Buy | BookingType::Sell);
+        if is_trade {
+            if record.position != 0.0 {
+                let _ = writeln!(out, "I{}", record.amount / record.position);
+            }
+            let _ = writeln!(out, "Q{}", record.position);
+            if record.fee != 0.0 {
+                let _ = writeln!(out, "O{}", record.fee);
+            }
+        }
+        let _ = writeln!(out, "T{total}");
+        if record.booking_type == BookingType::Tax {
+            let _ = writeln!(out, "L{TAX_CATEGORY}");
+        }
+        if let Some(note) = &record.note {
+            let _ = writeln!(out, "M{note}");
+        }
+        let _ = writeln!(out, "^");
+        if let Some(security) = security {
+            if !is_trade && record.fee != 0.0 {
+                write_expense(&mut out, record, security, record.fee, false);
+            }
+            if record.tax != 0.0 {
+                write_expense(&mut out, record, security, record.tax, true);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::assert_same_records;
+    use time::Month;
+
+    const INVESTMENT: &str = include_str!("fixtures/investment.qif");
+
+    #[test]
+    fn parse_dates() {
+        let date = Date::from_calendar_date(2021, Month::March, 1).unwrap();
+        assert_eq!(parse_date("03/01/2021").unwrap(), date);
+        assert_eq!(parse_date(" 3/ 1'21").unwrap(), date);
+        assert_eq!(parse_date("01.03.2021").unwrap(), date);
+        assert_eq!(
+            parse_date("3/1/99").unwrap(),
+            Date::from_calendar_date(1999, Month::March, 1).unwrap()
+        );
+        assert!(parse_date("2021-03-01").is_err());
+    }
+
+    #[test]
+    fn parse_investment_account() {
+        let tol = 1e-10;
+        let eur = CurrencyISOCode::new("EUR").unwrap();
+        let statement = parse(INVESTMENT, eur).unwrap();
+
+        // securities without ISIN are not listed
+        assert_eq!(statement.securities.len(), 1);
+        assert_eq!(statement.securities[0].isin, "US0378331005");
+
+        // expenses of the same security at the same day are taxes of the previous record,
+        // reinvestments are ignored
+        let records = &statement.records;
+        assert_eq!(records.len(), 5);
+        assert_eq!(records[0].booking_type, BookingType::Deposit);
+        assert_fuzzy_eq!(records[0].amount, 5_000.0, tol);
+        assert_eq!(records[1].booking_type, BookingType::Buy);
+        assert_eq!(records[1].isin.as_deref(), Some("US0378331005"));
+        assert_eq!(records[1].currency, eur);
+        assert_fuzzy_eq!(records[1].amount, 2_400.5, tol);
+        assert_fuzzy_eq!(records[1].fee, 4.95, tol);
+        assert_fuzzy_eq!(records[2].tax, 0.66, tol);
+        assert_eq!(records[3].booking_type, BookingType::Sell);
+        assert_fuzzy_eq!(records[3].amount, 750.0, tol);
+        assert_fuzzy_eq!(records[3].tax, 35.1, tol);
+        assert_eq!(records[4].booking_type, BookingType::Fee);
+        assert_eq!(records[4].isin, None);
+        assert_eq!(records[4].note.as_deref(), Some("Account fee"));
+
+        assert_eq!(statement.prices.len(), 1);
+        assert_eq!(
+            statement.prices[0].date,
+            Date::from_calendar_date(2021, Month::December, 31).unwrap()
+        );
+    }
+
+    #[test]
+    fn write_and_read_again() {
+        let eur = CurrencyISOCode::new("EUR").unwrap();
+        let statement = parse(INVESTMENT, eur).unwrap();
+        let read = parse(&write(&statement), eur).unwrap();
+        assert_same_records(&statement.records, &read.records);
+        assert_eq!(statement.securities, read.securities);
+
+        // the OFX fixture contains the same transactions in USD
+        let usd = CurrencyISOCode::new("USD").unwrap();
+        let ofx_statement =
+            crate::import::ofx::parse(include_str!("fixtures/investment.ofx")).unwrap();
+        let read = parse(&write(&ofx_statement), usd).unwrap();
+        assert_same_records(&ofx_statement.records, &read.records);
+    }
+}
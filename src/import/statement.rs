@@ -0,0 +1,317 @@
+//! Bank and investment statements independent of the file format
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+
+use time::Date;
+
+use super::{store_records, ImportError, ImportRecord, ImportSummary};
+use crate::datatypes::date_time_helper::date_to_offset_date_time;
+use crate::datatypes::{
+    Asset, CurrencyISOCode, DataError, DataItem, Identifier, IdentifierType, Quote, QuoteHandler,
+    Stock, Ticker, Transaction, TransactionFilter, TransactionHandler, TransactionKind,
+    TransactionType,
+};
+
+/// Hour of the day assigned to imported prices
+const PRICE_HOUR: u32 = 18;
+
+/// Security listed in a statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct Security {
+    pub isin: String,
+    pub name: String,
+    /// Ticker symbol
+    pub symbol: Option<String>,
+}
+
+/// Price of a security at a given date, e.g. the price a position is valued with
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecurityPrice {
+    pub isin: String,
+    pub date: Date,
+    pub price: f64,
+    pub currency: CurrencyISOCode,
+}
+
+/// Content of a bank or investment statement
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Statement {
+    pub securities: Vec<Security>,
+    pub records: Vec<ImportRecord>,
+    pub prices: Vec<SecurityPrice>,
+}
+
+impl Statement {
+    /// Find a listed security by its ISIN
+    pub fn security(&self, isin: &str) -> Option<&Security> {
+        self.securities.iter().find(|s| s.isin == isin)
+    }
+}
+
+/// Return the id of the asset with the given ISIN, if there is any
+async fn find_asset_id(
+    isin: &str,
+    db: &(dyn TransactionHandler + Send + Sync),
+) -> Result<Option<i32>, ImportError> {
+    let identifier = Identifier::new(IdentifierType::Isin, isin)
+        .map_err(|_| ImportError::InvalidStatement(format!("invalid ISIN '{isin}'")))?;
+    match db.find_asset_by_identifier(&identifier).await {
+        Ok(asset) => Ok(Some(asset.get_id()?)),
+        Err(DataError::NotFound(_)) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Return the id of the manual ticker of the asset quoted in the given currency,
+/// the ticker is created if it does not exist yet
+async fn manual_ticker_id(
+    asset_id: i32,
+    name: &str,
+    currency: CurrencyISOCode,
+    quotes: &(dyn QuoteHandler + Send + Sync),
+) -> Result<i32, ImportError> {
+    let existing = quotes
+        .get_all_ticker_for_asset(asset_id)
+        .await?
+        .into_iter()
+        .find(|t| t.source == "manual" && t.currency.iso_code == currency)
+        .and_then(|t| t.id);
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+    let ticker = Ticker {
+        id: None,
+        asset: asset_id,
+        name: name.to_string(),
+        currency: quotes.get_or_new_currency(currency).await?,
+        source: "manual".to_string(),
+        priority: 10,
+        factor: 1.0,
+        tz: None,
+        cal: None,
+    };
+    Ok(quotes.insert_ticker(&ticker).await?)
+}
+
+/// Store the content of a statement. Securities not known yet are stored as stocks, records
+/// are stored as transactions booked to the given account as described for `store_records`,
+/// and prices are stored as quotes of a manual ticker of the security, unless a quote at the
+/// same date has been stored before.
+pub async fn store_statement(
+    statement: &Statement,
+    account_id: Option<i32>,
+    db: &(dyn TransactionHandler + Send + Sync),
+    quotes: &(dyn QuoteHandler + Send + Sync),
+) -> Result<ImportSummary, ImportError> {
+    let mut assets = Vec::new();
+    for security in &statement.securities {
+        if find_asset_id(&security.isin, db).await?.is_none() {
+            let stock = Stock::new(
+                None,
+                security.name.clone(),
+                Some(security.isin.clone()),
+                None,
+                None,
+            );
+            assets.push(db.insert_asset(&Asset::Stock(stock)).await?);
+        }
+    }
+    let mut summary = store_records(&statement.records, account_id, db).await?;
+    summary.assets = assets;
+
+    // quote dates per ticker id
+    let mut quote_dates: BTreeMap<i32, Vec<Date>> = BTreeMap::new();
+    for price in &statement.prices {
+        let asset_id = find_asset_id(&price.isin, db)
+            .await?
+            .ok_or_else(|| ImportError::UnknownAsset(price.isin.clone()))?;
+        let name = statement
+            .security(&price.isin)
+            .and_then(|s| s.symbol.as_deref())
+            .unwrap_or(&price.isin);
+        let ticker_id = manual_ticker_id(asset_id, name, price.currency, quotes).await?;
+        let dates = match quote_dates.entry(ticker_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(
+                quotes
+                    .get_all_quotes_for_ticker(ticker_id)
+                    .await?
+                    .iter()
+                    .map(|q| q.time.date())
+                    .collect(),
+            ),
+        };
+        if dates.contains(&price.date) {
+            continue;
+        }
+        let quote = Quote {
+            id: None,
+            ticker: ticker_id,
+            price: price.price,
+            time: date_to_offset_date_time(&price.date, PRICE_HOUR, None)?,
+            volume: None,
+        };
+        summary.quotes.push(quotes.insert_quote(&quote).await?);
+        dates.push(price.date);
+    }
+    Ok(summary)
+}
+
+/// Return the security data of an asset, the ISIN is either part of the asset data or given
+/// as additional identifier
+async fn security_of_asset(
+    asset_id: i32,
+    db: &(dyn TransactionHandler + Send + Sync),
+) -> Result<Security, ImportError> {
+    let asset = db.get_asset_by_id(asset_id).await?;
+    let isin = match &asset {
+        Asset::Stock(stock) => stock.isin.clone(),
+        Asset::Fund(fund) => fund.isin.clone(),
+        Asset::Bond(bond) => bond.isin().map(str::to_string),
+        _ => None,
+    };
+    let isin = match isin {
+        Some(isin) => Some(isin),
+        None => db
+            .get_asset_identifiers(asset_id)
+            .await?
+            .into_iter()
+            .find(|id| id.id_type == IdentifierType::Isin)
+            .map(|id| id.value),
+    };
+    let name = asset.name();
+    match isin {
+        Some(isin) => Ok(Security {
+            isin,
+            name,
+            symbol: None,
+        }),
+        None => Err(ImportError::AssetWithoutIsin(name)),
+    }
+}
+
+/// Collect the transactions booked to the given account, or all transactions if no account is
+/// given, within the date range (both bounds being inclusive) as a statement for export. Fees and
+/// taxes are merged into the record of the transaction they refer to, if it is part of the
+/// statement. Splits and transfers are not exported. The statement lists the securities of all
+/// exported transactions, but no prices.
+pub async fn export_statement(
+    account_id: Option<i32>,
+    start: Date,
+    end: Date,
+    db: &(dyn TransactionHandler + Send + Sync),
+) -> Result<Statement, ImportError> {
+    let filter = TransactionFilter {
+        start: Some(start),
+        end: Some(end),
+        account_ids: account_id.map(|id| vec![id]),
+        ..Default::default()
+    };
+    let transactions = db.get_transactions(&filter).await?;
+    let ids: Vec<i32> = transactions.iter().filter_map(|t| t.id).collect();
+    let reference = |t: &Transaction| match t.transaction_type {
+        TransactionType::Fee {
+            transaction_ref: Some(id),
+        }
+        | TransactionType::Tax {
+            transaction_ref: Some(id),
+            ..
+        } if ids.contains(&id) => Some(id),
+        _ => None,
+    };
+    let mut related: BTreeMap<i32, Vec<&Transaction>> = BTreeMap::new();
+    for transaction in &transactions {
+        if let Some(id) = reference(transaction) {
+            related.entry(id).or_default().push(transaction);
+        }
+    }
+
+    let mut statement = Statement::default();
+    for transaction in &transactions {
+        let kind = transaction.transaction_type.kind();
+        if reference(transaction).is_some()
+            || kind == TransactionKind::Split
+            || kind == TransactionKind::Transfer
+        {
+            continue;
+        }
+        let isin = match transaction.transaction_type.asset_id() {
+            Some(asset_id) => {
+                let security = security_of_asset(asset_id, db).await?;
+                let isin = security.isin.clone();
+                if statement.security(&isin).is_none() {
+                    statement.securities.push(security);
+                }
+                Some(isin)
+            }
+            None => None,
+        };
+        let related = transaction
+            .id
+            .and_then(|id| related.get(&id))
+            .map(|r| r.as_slice())
+            .unwrap_or_default();
+        if let Some(record) = ImportRecord::from_transactions(transaction, related, isin) {
+            statement.records.push(record);
+        }
+    }
+    Ok(statement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datatypes::AssetHandler;
+    use crate::import::{assert_same_records, ofx, qif};
+    use crate::memory::InMemoryDB;
+    use time::Month;
+
+    #[tokio::test]
+    async fn store_and_export_statement() {
+        let db = InMemoryDB::new();
+        let statement = ofx::parse(include_str!("fixtures/investment.ofx")).unwrap();
+
+        let summary = store_statement(&statement, None, &db, &db).await.unwrap();
+        assert_eq!(summary.assets.len(), 2);
+        assert_eq!(summary.inserted.len(), 5);
+        assert_eq!(summary.quotes.len(), 1);
+        let apple = db.get_asset_by_isin("US0378331005").await.unwrap();
+        assert_eq!(apple.name(), "Apple Inc.");
+        let ticker = &db
+            .get_all_ticker_for_asset(apple.get_id().unwrap())
+            .await
+            .unwrap()[0];
+        assert_eq!(ticker.name, "AAPL");
+        assert_eq!(ticker.currency.iso_code.to_string(), "USD");
+        // fees and taxes of buy, dividend and sale are stored separately
+        assert_eq!(db.get_all_transactions().await.unwrap().len(), 9);
+
+        // storing the statement again does not change anything
+        let summary = store_statement(&statement, None, &db, &db).await.unwrap();
+        assert!(summary.assets.is_empty());
+        assert!(summary.inserted.is_empty());
+        assert_eq!(summary.duplicates.len(), 5);
+        assert!(summary.quotes.is_empty());
+
+        let start = Date::from_calendar_date(2021, Month::January, 1).unwrap();
+        let end = Date::from_calendar_date(2021, Month::December, 31).unwrap();
+        let exported = export_statement(None, start, end, &db).await.unwrap();
+        assert_same_records(&statement.records, &exported.records);
+        // only securities with transactions are exported
+        assert_eq!(exported.securities.len(), 1);
+        assert_eq!(exported.securities[0].isin, "US0378331005");
+
+        // export a part of the transactions as QIF and import them into another database
+        let end = Date::from_calendar_date(2021, Month::June, 30).unwrap();
+        let exported = export_statement(None, start, end, &db).await.unwrap();
+        let usd = CurrencyISOCode::new("USD").unwrap();
+        let statement = qif::parse(&qif::write(&exported), usd).unwrap();
+        let other_db = InMemoryDB::new();
+        let summary = store_statement(&statement, None, &other_db, &other_db)
+            .await
+            .unwrap();
+        assert_eq!(summary.assets.len(), 1);
+        assert_eq!(summary.inserted.len(), 3);
+        assert_eq!(other_db.get_all_transactions().await.unwrap().len(), 5);
+    }
+}
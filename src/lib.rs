@@ -38,6 +38,7 @@
 pub mod macros;
 
 // module exports
+pub mod benchmark;
 pub mod bond;
 pub mod coupon_date;
 pub mod datatypes;
@@ -46,13 +47,24 @@ pub mod day_count_conv;
 pub mod fixed_income;
 pub mod fx_rates;
 pub mod helpers;
+pub mod history;
+pub mod import;
 pub mod market;
 pub mod market_quotes;
+pub mod memory;
+pub mod performance;
 pub mod period_date;
 pub mod portfolio;
+#[cfg(feature = "postgres")]
 pub mod postgres;
 pub mod rates;
+pub mod risk;
+pub mod simulation;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 pub mod strategy;
+pub mod tax;
+pub mod tax_report;
 pub mod time_period;
 pub mod time_series;
 
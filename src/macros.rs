@@ -6,7 +6,8 @@ macro_rules! assert_fuzzy_eq {
     ( $ left : expr , $ right : expr, $ tol : expr ) => {{
         match (&($left), &($right), &($tol)) {
             (left_val, right_val, tol) => {
-                if !((*left_val - *right_val).abs() < *tol) {
+                if (*left_val - *right_val).abs().partial_cmp(tol) != Some(std::cmp::Ordering::Less)
+                {
                     panic!(
                         "assertion failed: left differs from right by more than `{:?}` \
                          (left: `{:?}`, right: `{:?}`)",
@@ -13,13 +13,19 @@ use log::{debug, error};
 use thiserror::Error;
 
 use crate::datatypes::{
-    date_time_helper::date_to_offset_date_time, Asset, Currency, CurrencyConverter, CurrencyError,
-    CurrencyISOCode, QuoteHandler,
+    date_time_helper::date_to_offset_date_time, Asset, CorporateAction, Currency,
+    CurrencyConverter, CurrencyError, CurrencyISOCode, QuoteHandler, Ticker, Transaction,
+    TransactionFilter, TransactionHandler, TransactionKind, TransactionType,
 };
 
 use crate::market_quotes::{self, MarketDataSourceError, MarketQuoteProvider};
+use crate::time_series::{TimeSeriesError, TimeValue};
 use cal_calc::Calendar;
 
+/// Name of the calendar without any holidays or weekends, to be used as `cal` of tickers
+/// quoted on all days of the week, e.g. for crypto currencies
+pub const ALL_DAYS_CALENDAR: &str = "24/7";
+
 /// Error related to market data object
 #[derive(Error, Debug)]
 pub enum MarketError {
@@ -51,6 +57,24 @@ pub enum MarketError {
     InvalidCalendarDate(#[from] cal_calc::CalendarError),
     #[error("Indetermined time zone offset")]
     InvalidDateTime(#[from] time::error::IndeterminateOffset),
+    #[error("Time series error")]
+    TimeSeriesError(#[from] TimeSeriesError),
+}
+
+impl MarketError {
+    /// True if the error is caused by a missing quote, i.e. no price is available
+    pub(crate) fn is_missing_quote(&self) -> bool {
+        match self {
+            MarketError::CacheFailure
+            | MarketError::MissingQuoteForCurrencyPair(_, _)
+            | MarketError::DBError(crate::datatypes::DataError::NotFound(_)) => true,
+            #[cfg(any(feature = "postgres", feature = "sqlite"))]
+            MarketError::DBError(crate::datatypes::DataError::DatabaseError(
+                sqlx::Error::RowNotFound,
+            )) => true,
+            _ => false,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -94,6 +118,10 @@ struct MarketImpl {
     cache_policy: RwLock<CachePolicy>,
     /// List of currency for fast access
     currencies: RwLock<BTreeMap<i32, Currency>>,
+    /// Known stock splits per asset as ratio per ex-date
+    splits: RwLock<BTreeMap<i32, BTreeMap<Date, f64>>>,
+    /// Stored corporate actions ordered by date, read from the database on first use
+    corporate_actions: RwLock<Option<Arc<Vec<CorporateAction>>>>,
 }
 
 #[derive(Clone)]
@@ -112,6 +140,8 @@ impl Market {
                 db: db.clone(),
                 cache_policy: RwLock::new(CachePolicy::None),
                 currencies: RwLock::new(currency_map(db).await),
+                splits: RwLock::new(BTreeMap::new()),
+                corporate_actions: RwLock::new(None),
             }),
         }
     }
@@ -134,6 +164,8 @@ impl Market {
                 db: db.clone(),
                 cache_policy: RwLock::new(cache_policy),
                 currencies: RwLock::new(currency_map(db).await),
+                splits: RwLock::new(BTreeMap::new()),
+                corporate_actions: RwLock::new(None),
             }),
         })
     }
@@ -173,6 +205,31 @@ impl Market {
         }
     }
 
+    /// Get the calendar of business days of a ticker as given by the ticker's `cal`,
+    /// or the TARGET calendar if no calendar is set
+    pub fn get_ticker_calendar(&self, ticker: &Ticker) -> Result<&Calendar, MarketError> {
+        self.get_calendar(ticker.cal.as_deref().unwrap_or("TARGET"))
+    }
+
+    /// Find gaps of at least `min_size` business days in the quote history of a ticker,
+    /// where business days are determined by the ticker's calendar
+    pub async fn find_quote_gaps(
+        &self,
+        ticker_id: i32,
+        min_size: usize,
+    ) -> Result<Vec<(Date, Date)>, MarketError> {
+        let ticker = self.inner.db.get_ticker_by_id(ticker_id).await?;
+        let cal = self.get_ticker_calendar(&ticker)?;
+        let mut quotes = crate::time_series::TimeSeries::new(&ticker.name);
+        for quote in self.inner.db.get_all_quotes_for_ticker(ticker_id).await? {
+            quotes.series.push(TimeValue {
+                time: quote.time,
+                value: quote.price,
+            });
+        }
+        Ok(quotes.find_gaps(cal, min_size)?)
+    }
+
     /// Store currency in cache
     fn store_currency_in_cache(&self, currency: Currency) {
         if let Some(id) = currency.id {
@@ -235,9 +292,10 @@ impl Market {
             .inner
             .providers
             .read()
-            .map_err(|_| MarketError::CacheFailure)?;
+            .map_err(|_| MarketError::CacheFailure)?
+            .clone();
         for ticker in tickers {
-            if let Some(provider) = (*providers).get(&ticker.source) {
+            if let Some(provider) = providers.get(&ticker.source) {
                 if market_quotes::update_ticker((*provider).clone(), &ticker, self.inner.db.clone())
                     .await
                     .is_err()
@@ -300,7 +358,8 @@ impl Market {
         end: OffsetDateTime,
     ) -> Result<(), MarketError> {
         let tickers = self.inner.db.get_all_ticker_for_asset(asset_id).await?;
-        if let Ok(providers) = self.inner.providers.read() {
+        let providers = self.inner.providers.read().map(|p| p.clone());
+        if let Ok(providers) = providers {
             for ticker in tickers {
                 if let Some(provider) = providers.get(&ticker.source) {
                     market_quotes::update_ticker_history(
@@ -318,24 +377,264 @@ impl Market {
     }
 
     pub fn try_from_cache(&self, asset_id: i32, time: OffsetDateTime) -> Option<(f64, i32)> {
+        self.try_from_cache_with_time(asset_id, time)
+            .map(|(_, price, currency_id)| (price, currency_id))
+    }
+
+    /// Get latest cached quote before `time` together with the quote's time
+    fn try_from_cache_with_time(
+        &self,
+        asset_id: i32,
+        time: OffsetDateTime,
+    ) -> Option<(OffsetDateTime, f64, i32)> {
         if let Ok(prices) = self.inner.prices.read() {
             if let Some(series) = (*prices).get(&asset_id) {
-                return series.range(..time).last().map(|entry| *entry.1);
+                return series
+                    .range(..time)
+                    .last()
+                    .map(|(time, (price, currency_id))| (*time, *price, *currency_id));
             }
         }
         None
     }
 
+    /// Create a new market and register all stock splits stored as split transactions in
+    /// `transactions`, see `load_splits`
+    pub async fn new_with_splits(
+        db: Arc<dyn QuoteHandler + Sync + Send>,
+        transactions: &(dyn TransactionHandler + Send + Sync),
+    ) -> Result<Self, MarketError> {
+        let market = Self::new(db).await;
+        market.load_splits(transactions).await?;
+        Ok(market)
+    }
+
+    /// Replace the registered stock splits by all split transactions stored in the database.
+    /// Call this again after new split transactions have been stored.
+    pub async fn load_splits(
+        &self,
+        db: &(dyn TransactionHandler + Send + Sync),
+    ) -> Result<(), MarketError> {
+        let filter = TransactionFilter {
+            kinds: Some(vec![TransactionKind::Split]),
+            ..Default::default()
+        };
+        let transactions = db.get_transactions(&filter).await?;
+        self.inner
+            .splits
+            .write()
+            .map_err(|_| MarketError::CacheFailure)?
+            .clear();
+        self.add_splits(&transactions)
+    }
+
+    /// Register a stock split of an asset, `ratio` is the number of new shares per old share
+    /// and `date` the first date quotes are based on the new number of shares.
+    pub fn add_split(&self, asset_id: i32, date: Date, ratio: f64) -> Result<(), MarketError> {
+        let mut splits = self
+            .inner
+            .splits
+            .write()
+            .map_err(|_| MarketError::CacheFailure)?;
+        splits.entry(asset_id).or_default().insert(date, ratio);
+        Ok(())
+    }
+
+    /// Register all stock splits contained in a list of transactions
+    pub fn add_splits(&self, transactions: &[Transaction]) -> Result<(), MarketError> {
+        for trans in transactions {
+            if let TransactionType::Split { asset_id, ratio } = trans.transaction_type {
+                self.add_split(asset_id, trans.cash_flow.date, ratio)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get all corporate actions stored in the database with dates within the given range,
+    /// both bounds being inclusive and optional, ordered by date. The corporate actions are
+    /// read from the database once and cached, see `clear_corporate_actions`.
+    pub async fn get_corporate_actions(
+        &self,
+        start: Option<Date>,
+        end: Option<Date>,
+    ) -> Result<Vec<CorporateAction>, MarketError> {
+        let cached = self
+            .inner
+            .corporate_actions
+            .read()
+            .map_err(|_| MarketError::CacheFailure)?
+            .clone();
+        let actions = match cached {
+            Some(actions) => actions,
+            None => {
+                let actions = Arc::new(self.inner.db.get_corporate_actions(None, None).await?);
+                *self
+                    .inner
+                    .corporate_actions
+                    .write()
+                    .map_err(|_| MarketError::CacheFailure)? = Some(actions.clone());
+                actions
+            }
+        };
+        Ok(actions
+            .iter()
+            .filter(|a| start.is_none_or(|start| a.date >= start))
+            .filter(|a| end.is_none_or(|end| a.date <= end))
+            .cloned()
+            .collect())
+    }
+
+    /// Drop the cached corporate actions, e.g. after corporate actions have been stored or
+    /// changed, to read them again from the database on next use
+    pub fn clear_corporate_actions(&self) -> Result<(), MarketError> {
+        *self
+            .inner
+            .corporate_actions
+            .write()
+            .map_err(|_| MarketError::CacheFailure)? = None;
+        Ok(())
+    }
+
+    /// Factor to convert a quote of an asset as of `quote_date` to the number of shares as of
+    /// `basis_date`, or after all known splits if `basis_date` is `None`
+    fn split_factor(&self, asset_id: i32, quote_date: Date, basis_date: Option<Date>) -> f64 {
+        let splits = match self.inner.splits.read() {
+            Ok(splits) => splits,
+            Err(_) => return 1.0,
+        };
+        splits
+            .get(&asset_id)
+            .map(|splits| {
+                splits
+                    .iter()
+                    .filter(|(date, _)| {
+                        **date > quote_date && basis_date.is_none_or(|basis| **date <= basis)
+                    })
+                    .map(|(_, ratio)| 1.0 / ratio)
+                    .product()
+            })
+            .unwrap_or(1.0)
+    }
+
+    /// Get the latest price of an asset before `time` in the given currency. If the latest
+    /// quote dates from before a registered split effective at `time`, the quote is adjusted
+    /// to the number of shares after the split. Splits become effective after midnight of
+    /// their ex-date, in line with positions valued at midnight of a date, which do not
+    /// include the transactions of that date.
     pub async fn get_asset_price(
         &self,
         asset_id: i32,
         currency: Currency,
         time: OffsetDateTime,
     ) -> Result<f64, MarketError> {
-        let (price, quote_currency_id) = if let Some((quote, curr)) =
-            self.try_from_cache(asset_id, time)
+        let basis_date = if time.time() == Time::MIDNIGHT {
+            time.date().previous_day().unwrap_or(time.date())
+        } else {
+            time.date()
+        };
+        self.get_price_for_share_basis(asset_id, currency, time, Some(basis_date))
+            .await
+    }
+
+    /// Get the latest price of an asset before `time` in the given currency, adjusted for all
+    /// registered splits, i.e. based on the current number of shares. Use this to get a
+    /// consistent quote history across stock splits.
+    pub async fn get_split_adjusted_asset_price(
+        &self,
+        asset_id: i32,
+        currency: Currency,
+        time: OffsetDateTime,
+    ) -> Result<f64, MarketError> {
+        self.get_price_for_share_basis(asset_id, currency, time, None)
+            .await
+    }
+
+    /// Convert an amount from `currency` to `to_currency` with the fx rate at the end of `date`
+    pub async fn convert(
+        &self,
+        amount: f64,
+        currency: Currency,
+        to_currency: Currency,
+        date: Date,
+    ) -> Result<f64, MarketError> {
+        if currency == to_currency {
+            return Ok(amount);
+        }
+        let fx_rate = self
+            .get_fx_rate(
+                currency,
+                to_currency,
+                date_to_offset_date_time(&date, 20, None)?,
+            )
+            .await?;
+        Ok(amount * fx_rate)
+    }
+
+    /// Get the fx rate to convert `base_currency` into `quote_currency` at the given time,
+    /// keeping the underlying error if the rate could not be found
+    async fn get_fx_rate(
+        &self,
+        base_currency: Currency,
+        quote_currency: Currency,
+        time: OffsetDateTime,
+    ) -> Result<f64, MarketError> {
+        if base_currency == quote_currency {
+            return Ok(1.0);
+        } else {
+            debug!("convert currency {} to {}", base_currency, quote_currency);
+            let base_curr_id = base_currency.id.ok_or(MarketError::CurrencyNotInDatabase(
+                base_currency.to_string(),
+            ))?;
+            let (fx_quote, quote_curr_id) =
+                if let Some((fx_quote, quote_curr_id)) = self.try_from_cache(base_curr_id, time) {
+                    (fx_quote, quote_curr_id)
+                } else {
+                    let mut invert = false;
+                    let mut fx_quote = self
+                        .inner
+                        .db
+                        .get_last_quote_before_by_id(base_curr_id, time)
+                        .await;
+                    if fx_quote.is_err() {
+                        fx_quote = self
+                            .inner
+                            .db
+                            .get_last_quote_before_by_id(quote_currency.id.unwrap(), time)
+                            .await;
+                        invert = true;
+                    }
+                    let fx_quote = fx_quote?;
+                    if invert {
+                        if fx_quote.1.id.unwrap() == base_curr_id {
+                            (1.0 / fx_quote.0.price, quote_currency.id.unwrap())
+                        } else {
+                            (0.0, -1)
+                        }
+                    } else {
+                        (fx_quote.0.price, fx_quote.1.id.unwrap())
+                    }
+                };
+            if quote_currency.id == Some(quote_curr_id) {
+                return Ok(fx_quote);
+            }
+        }
+        Err(MarketError::MissingQuoteForCurrencyPair(
+            base_currency.to_string(),
+            quote_currency.to_string(),
+        ))
+    }
+
+    async fn get_price_for_share_basis(
+        &self,
+        asset_id: i32,
+        currency: Currency,
+        time: OffsetDateTime,
+        basis_date: Option<Date>,
+    ) -> Result<f64, MarketError> {
+        let (quote_time, price, quote_currency_id) = if let Some((quote_time, quote, curr)) =
+            self.try_from_cache_with_time(asset_id, time)
         {
-            (quote, curr)
+            (quote_time, quote, curr)
         } else {
             let cache_policy = if let Ok(cache_policy) = self.inner.cache_policy.read() {
                 (*cache_policy).clone()
@@ -349,7 +648,7 @@ impl Market {
                         .db
                         .get_last_quote_before_by_id(asset_id, time)
                         .await?;
-                    (quote.price, currency.id.unwrap())
+                    (quote.time, quote.price, currency.id.unwrap())
                 }
                 CachePolicy::PredefinedPeriod(time_range) => {
                     let date_start = time.replace_time(Time::from_hms(0, 0, 0).unwrap());
@@ -369,19 +668,17 @@ impl Market {
                             asset_prices.insert(quote.0.time, (quote.0.price, quote.1));
                         }
                     }
-                    self.try_from_cache(asset_id, time)
+                    self.try_from_cache_with_time(asset_id, time)
                         .ok_or(MarketError::CacheFailure)?
                 }
             }
         };
+        let price = price * self.split_factor(asset_id, quote_time.date(), basis_date);
         if currency.id == Some(quote_currency_id) {
             Ok(price)
         } else {
             let quote_currency = self.get_currency_by_id(quote_currency_id).await?;
-            let fx_rate = self
-                .fx_rate(quote_currency, currency, time)
-                .await
-                .map_err(|_| MarketError::CurrencyConversionError)?;
+            let fx_rate = self.get_fx_rate(quote_currency, currency, time).await?;
             Ok(price * fx_rate)
         }
     }
@@ -395,53 +692,17 @@ impl CurrencyConverter for Market {
         quote_currency: Currency,
         time: OffsetDateTime,
     ) -> Result<f64, CurrencyError> {
-        if base_currency == quote_currency {
-            return Ok(1.0);
-        } else {
-            debug!("convert currency {} to {}", base_currency, quote_currency);
-            let base_curr_id = base_currency
-                .id
-                .ok_or(CurrencyError::CurrencyNotInDatabase(
-                    base_currency.to_string(),
-                ))?;
-            let (fx_quote, quote_curr_id) = if let Some((fx_quote, quote_curr_id)) =
-                self.try_from_cache(base_curr_id, time)
-            {
-                (fx_quote, quote_curr_id)
-            } else {
-                let mut invert = false;
-                let mut fx_quote = self
-                    .inner
-                    .db
-                    .get_last_quote_before_by_id(base_curr_id, time)
-                    .await;
-                if fx_quote.is_err() {
-                    fx_quote = self
-                        .inner
-                        .db
-                        .get_last_quote_before_by_id(quote_currency.id.unwrap(), time)
-                        .await;
-                    invert = true;
+        self.get_fx_rate(base_currency, quote_currency, time)
+            .await
+            .map_err(|err| match err {
+                MarketError::CurrencyNotInDatabase(currency) => {
+                    CurrencyError::CurrencyNotInDatabase(currency)
                 }
-                let fx_quote = fx_quote.map_err(|e| CurrencyError::DataBaseError(e.to_string()))?;
-                if invert {
-                    if fx_quote.1.id.unwrap() == base_curr_id {
-                        (1.0 / fx_quote.0.price, quote_currency.id.unwrap())
-                    } else {
-                        (0.0, -1)
-                    }
-                } else {
-                    (fx_quote.0.price, fx_quote.1.id.unwrap())
+                MarketError::MissingQuoteForCurrencyPair(base, quote) => {
+                    CurrencyError::MissingQuoteForCurrencyPair(base, quote)
                 }
-            };
-            if quote_currency.id == Some(quote_curr_id) {
-                return Ok(fx_quote);
-            }
-        }
-        Err(CurrencyError::MissingQuoteForCurrencyPair(
-            base_currency.to_string(),
-            quote_currency.to_string(),
-        ))
+                err => CurrencyError::DataBaseError(err.to_string()),
+            })
     }
 }
 
@@ -457,5 +718,78 @@ pub fn generate_calendars(start_year: i32, end_year: i32) -> BTreeMap<String, Ca
     let target_cal = Calendar::calc_calendar(&target_holidays(), start_year, end_year).unwrap();
     calendars.insert("TARGET".to_string(), target_cal);
 
+    let all_days_cal = Calendar::calc_calendar(&[], start_year, end_year).unwrap();
+    calendars.insert(ALL_DAYS_CALENDAR.to_string(), all_days_cal);
+
     calendars
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datatypes::{date_time_helper::make_offset_time, AssetHandler, Crypto, Quote};
+    use crate::memory::InMemoryDB;
+
+    #[tokio::test]
+    async fn quote_gaps_for_all_days_calendar() {
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let btc_id = db
+            .insert_asset(&Asset::Crypto(Crypto::new(
+                None,
+                "Bitcoin".to_string(),
+                "BTC".to_string(),
+                None,
+                8,
+            )))
+            .await
+            .unwrap();
+        let mut ticker = Ticker {
+            id: None,
+            name: "BTC-EUR".to_string(),
+            asset: btc_id,
+            source: "manual".to_string(),
+            priority: 10,
+            currency: eur,
+            factor: 1.0,
+            tz: None,
+            cal: Some(ALL_DAYS_CALENDAR.to_string()),
+        };
+        let all_days_id = db.insert_ticker(&ticker).await.unwrap();
+        ticker.name = "BTC-EUR.DE".to_string();
+        ticker.cal = None;
+        let target_id = db.insert_ticker(&ticker).await.unwrap();
+        // quotes on Friday and the following Monday
+        for ticker_id in [all_days_id, target_id] {
+            for day in [5, 8] {
+                db.insert_quote(&Quote {
+                    id: None,
+                    ticker: ticker_id,
+                    price: 50_000.123_456,
+                    time: make_offset_time(2021, 11, day, 20, 0, 0).unwrap(),
+                    volume: None,
+                })
+                .await
+                .unwrap();
+            }
+        }
+        let date = |day| Date::from_calendar_date(2021, time::Month::November, day).unwrap();
+        let gaps = market.find_quote_gaps(all_days_id, 1).await.unwrap();
+        assert_eq!(gaps[0], (date(6), date(7)));
+        let gaps = market.find_quote_gaps(target_id, 1).await.unwrap();
+        assert_eq!(gaps[0].0, date(9));
+    }
+
+    #[tokio::test]
+    async fn convert_without_fx_quote_is_missing_quote() {
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db)).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let usd = market.get_currency_from_str("USD").await.unwrap();
+        let date = Date::from_calendar_date(2021, time::Month::November, 5).unwrap();
+        assert_eq!(market.convert(100.0, eur, eur, date).await.unwrap(), 100.0);
+        let err = market.convert(100.0, usd, eur, date).await.unwrap_err();
+        assert!(err.is_missing_quote());
+    }
+}
@@ -125,7 +125,7 @@ mod tests {
             .fetch_quote_history(&ticker, start, end)
             .await
             .unwrap();
-        assert!(quotes.len() != 0);
+        assert!(!quotes.is_empty());
         assert!(quotes[0].price != 0.0);
     }
 }
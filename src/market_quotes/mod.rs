@@ -3,23 +3,31 @@ use std::str::FromStr;
 use std::sync::Arc;
 
 use crate::datatypes::{CashFlow, Quote, QuoteHandler, Ticker};
-use alpha_vantage;
 use async_trait::async_trait;
-use gurufocus_api;
 use serde::{Deserialize, Serialize};
 use serde_json;
 use thiserror::Error;
 use time::OffsetDateTime;
 
+#[cfg(feature = "alpha_vantage")]
 pub mod alpha_vantage_wrapper;
+#[cfg(feature = "eodhistdata")]
 pub mod eod_historical_data;
+#[cfg(feature = "gurufocus")]
 pub mod guru_focus;
+#[cfg(feature = "yahoo")]
 pub mod yahoo;
 
 #[derive(Error, Debug)]
 pub enum MarketQuoteError {
     #[error("Storing quote in database failed")]
     StoringFailed(#[from] crate::datatypes::DataError),
+    #[cfg(any(
+        feature = "yahoo",
+        feature = "gurufocus",
+        feature = "eodhistdata",
+        feature = "alpha_vantage"
+    ))]
     #[error("Fetching quote(s) from provider failed")]
     FetchFailed(#[from] reqwest::Error),
     #[error("Parsing quote date failed")]
@@ -30,12 +38,16 @@ pub enum MarketQuoteError {
     InvalidCurrency(#[from] crate::datatypes::currency::CurrencyError),
     #[error("Conversion of date/time failed")]
     DateTimeError(#[from] crate::datatypes::date_time_helper::DateTimeError),
+    #[cfg(feature = "yahoo")]
     #[error("Yahoo error")]
     YahooError(#[from] yahoo_finance_api::YahooError),
+    #[cfg(feature = "eodhistdata")]
     #[error("EOD historical data error")]
     EodHistDataError(#[from] eodhistoricaldata_api::EodHistDataError),
+    #[cfg(feature = "alpha_vantage")]
     #[error("AlphaVantage error")]
     AlphaVantageError(#[from] alpha_vantage::error::Error),
+    #[cfg(feature = "gurufocus")]
     #[error("GuruFocus error")]
     GuruFocusError(#[from] gurufocus_api::GuruFocusError),
     #[error("JSON parsing error")]
@@ -137,14 +149,21 @@ impl fmt::Display for MarketDataSource {
 }
 
 impl MarketDataSource {
+    /// Return the quote provider for this source, or `None` for manual quotes or
+    /// if support for this vendor has not been enabled as cargo feature
+    #[allow(unused_variables)]
     pub fn get_provider(
         &self,
         token: String,
     ) -> Option<Arc<dyn MarketQuoteProvider + Send + Sync>> {
         match self {
+            #[cfg(feature = "yahoo")]
             Self::Yahoo => Some(Arc::new(yahoo::Yahoo {})),
+            #[cfg(feature = "gurufocus")]
             Self::GuruFocus => Some(Arc::new(guru_focus::GuruFocus::new(token))),
+            #[cfg(feature = "eodhistdata")]
             Self::EodHistData => Some(Arc::new(eod_historical_data::EODHistData::new(token))),
+            #[cfg(feature = "alpha_vantage")]
             Self::AlphaVantage => Some(Arc::new(alpha_vantage_wrapper::AlphaVantage::new(token))),
             _ => None,
         }
@@ -171,7 +190,7 @@ mod tests {
     use crate::datatypes::{
         date_time_helper::make_offset_time, Asset, CurrencyISOCode, QuoteHandler, Stock,
     };
-    use crate::postgres::PostgresDB;
+    use crate::memory::InMemoryDB;
     use rand::Rng;
     use time::Duration;
     struct DummyProvider {}
@@ -206,7 +225,7 @@ mod tests {
                     time: date,
                     volume: None,
                 });
-                date = date + Duration::days(1);
+                date += Duration::days(1);
                 price *= (0.0001 + 0.2 * rng.random::<f64>()).exp();
             }
             Ok(quotes)
@@ -258,13 +277,7 @@ mod tests {
     async fn test_fetch_latest_quote() {
         let tol = 1.0e-6;
 
-        let db_url = std::env::var("FINQL_TEST_DATABASE_URL");
-        assert!(
-            db_url.is_ok(),
-            "environment variable $FINQL_TEST_DATABASE_URL is not set"
-        );
-        let db = PostgresDB::new(&db_url.unwrap()).await.unwrap();
-        db.clean().await.unwrap();
+        let db = InMemoryDB::new();
 
         let db = Arc::new(db);
         let ticker = prepare_db(db.clone()).await;
@@ -284,13 +297,7 @@ mod tests {
     async fn test_fetch_quote_history() {
         let tol = 1.0e-6;
 
-        let db_url = std::env::var("FINQL_TEST_DATABASE_URL");
-        assert!(
-            db_url.is_ok(),
-            "environment variable $FINQL_TEST_DATABASE_URL is not set"
-        );
-        let db = PostgresDB::new(&db_url.unwrap()).await.unwrap();
-        db.clean().await.unwrap();
+        let db = InMemoryDB::new();
 
         let db = Arc::new(db);
         let ticker = prepare_db(db.clone()).await;
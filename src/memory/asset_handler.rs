@@ -0,0 +1,462 @@
+use async_trait::async_trait;
+use time::Date;
+
+use crate::datatypes::{
+    Asset, AssetHandler, AssetSelector, CorporateAction, Currency, CurrencyError, CurrencyISOCode,
+    DataError, Identifier, IdentifierType, TransactionType,
+};
+
+use super::{next_id, InMemoryDB, MemoryStore};
+
+/// Return a copy of the asset with the given id assigned
+fn with_id(asset: &Asset, id: i32) -> Asset {
+    let mut asset = asset.clone();
+    match &mut asset {
+        Asset::Currency(c) => c.id = Some(id),
+        Asset::Stock(s) => s.id = Some(id),
+        Asset::Bond(b) => b.id = Some(id),
+        Asset::Fund(f) => f.id = Some(id),
+        Asset::Crypto(c) => c.id = Some(id),
+        Asset::Commodity(c) => c.id = Some(id),
+    }
+    asset
+}
+
+impl MemoryStore {
+    /// Check that no other asset than `id` violates a unique constraint of `asset`
+    fn check_unique(&self, asset: &Asset, id: Option<i32>) -> Result<(), DataError> {
+        let is_equal = |a: &Option<String>, b: &Option<String>| a.is_some() && a == b;
+        for (other_id, other) in &self.assets {
+            if Some(*other_id) == id {
+                continue;
+            }
+            let duplicate = match (asset, other) {
+                (Asset::Currency(c), Asset::Currency(o)) => c.iso_code == o.iso_code,
+                (Asset::Stock(s), Asset::Stock(o)) => {
+                    s.name == o.name || is_equal(&s.isin, &o.isin) || is_equal(&s.wkn, &o.wkn)
+                }
+                (Asset::Bond(b), Asset::Bond(o)) => b.isin().is_some() && b.isin() == o.isin(),
+                (Asset::Fund(f), Asset::Fund(o)) => {
+                    f.name == o.name || is_equal(&f.isin, &o.isin) || is_equal(&f.wkn, &o.wkn)
+                }
+                (Asset::Crypto(c), Asset::Crypto(o)) => {
+                    c.name == o.name || (c.symbol == o.symbol && is_equal(&c.chain, &o.chain))
+                }
+                (Asset::Commodity(c), Asset::Commodity(o)) => c.name == o.name,
+                _ => false,
+            };
+            if duplicate {
+                return Err(DataError::InvalidAsset(format!(
+                    "asset '{}' already exists",
+                    asset.name()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the currency of a bond by the stored currency with the same ISO code,
+    /// like a join with the currencies table would do, and check that all assets a fund
+    /// refers to as constituents exist
+    fn with_stored_currency(&self, asset: &Asset) -> Result<Asset, DataError> {
+        if let Asset::Fund(f) = asset {
+            for asset_id in f.constituents.iter().filter_map(|c| c.asset_id) {
+                if !self.assets.contains_key(&asset_id) {
+                    return Err(DataError::InvalidAsset(format!(
+                        "constituent asset with id {asset_id} does not exist"
+                    )));
+                }
+            }
+        }
+        let mut asset = asset.clone();
+        if let Asset::Bond(b) = &mut asset {
+            b.currency = self
+                .assets
+                .values()
+                .find_map(|a| match a {
+                    Asset::Currency(c) if c.iso_code == b.currency.iso_code => Some(*c),
+                    _ => None,
+                })
+                .ok_or_else(|| {
+                    DataError::InvalidCurrency(CurrencyError::CurrencyNotInDatabase(
+                        b.currency.to_string(),
+                    ))
+                })?;
+        }
+        Ok(asset)
+    }
+
+    pub(super) fn insert_asset(&mut self, asset: &Asset) -> Result<i32, DataError> {
+        self.check_unique(asset, None)?;
+        let asset = self.with_stored_currency(asset)?;
+        let id = next_id(&mut self.last_asset_id);
+        self.assets.insert(id, with_id(&asset, id));
+        Ok(id)
+    }
+
+    /// Find the id of the asset with the given identifier, without considering equivalent identifiers
+    fn find_asset_id(&self, identifier: &Identifier) -> Option<i32> {
+        let value = Some(identifier.value.as_str());
+        self.asset_identifiers
+            .iter()
+            .find(|(_, i)| i == identifier)
+            .map(|(id, _)| *id)
+            .or_else(|| {
+                self.assets
+                    .iter()
+                    .find(|(_, a)| match (identifier.id_type, a) {
+                        (IdentifierType::Isin, Asset::Stock(s)) => s.isin.as_deref() == value,
+                        (IdentifierType::Isin, Asset::Bond(b)) => b.isin() == value,
+                        (IdentifierType::Isin, Asset::Fund(f)) => f.isin.as_deref() == value,
+                        (IdentifierType::Wkn, Asset::Stock(s)) => s.wkn.as_deref() == value,
+                        (IdentifierType::Wkn, Asset::Fund(f)) => f.wkn.as_deref() == value,
+                        _ => false,
+                    })
+                    .map(|(id, _)| *id)
+            })
+    }
+
+    /// Check whether an asset is referenced by any ticker, transaction, bond or fund
+    fn is_asset_referenced(&self, id: i32) -> bool {
+        self.ticker
+            .values()
+            .any(|t| t.asset == id || t.currency.id == Some(id))
+            || self.transactions.values().any(|t| {
+                t.cash_flow.amount.currency.id == Some(id)
+                    || match t.transaction_type {
+                        TransactionType::Asset { asset_id, .. }
+                        | TransactionType::Dividend { asset_id }
+                        | TransactionType::Interest { asset_id }
+                        | TransactionType::Split { asset_id, .. }
+                        | TransactionType::Transfer {
+                            asset_id: Some(asset_id),
+                            ..
+                        } => asset_id == id,
+                        _ => false,
+                    }
+            })
+            || self.assets.values().any(|a| match a {
+                Asset::Bond(b) => b.currency.id == Some(id),
+                Asset::Fund(f) => f.constituents.iter().any(|c| c.asset_id == Some(id)),
+                _ => false,
+            })
+            || self
+                .corporate_actions
+                .values()
+                .any(|a| a.asset_id == id || a.targets.iter().any(|t| t.asset_id == id))
+    }
+
+    /// Check that a corporate action is valid and refers to existing assets only
+    fn check_corporate_action(&self, action: &CorporateAction) -> Result<(), DataError> {
+        action.validate()?;
+        let asset_ids =
+            std::iter::once(action.asset_id).chain(action.targets.iter().map(|t| t.asset_id));
+        for asset_id in asset_ids {
+            if !self.assets.contains_key(&asset_id) {
+                return Err(DataError::InvalidCorporateAction(format!(
+                    "asset with id {asset_id} does not exist"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Handler for globally available Asset data
+#[async_trait]
+impl AssetHandler for InMemoryDB {
+    async fn insert_asset(&self, asset: &Asset) -> Result<i32, DataError> {
+        self.write()?.insert_asset(asset)
+    }
+
+    async fn get_asset_id(&self, asset: &Asset) -> Option<i32> {
+        let store = self.read().ok()?;
+        let mut assets = store.assets.iter();
+        let found = match asset {
+            Asset::Currency(c) => assets.find(|(_, a)| match a {
+                Asset::Currency(o) => o.iso_code == c.iso_code,
+                _ => false,
+            }),
+            Asset::Stock(s) => assets.find(|(_, a)| match a {
+                Asset::Stock(o) => {
+                    if s.wkn.is_some() {
+                        o.wkn == s.wkn
+                    } else if s.isin.is_some() {
+                        o.isin == s.isin
+                    } else {
+                        o.name == s.name
+                    }
+                }
+                _ => false,
+            }),
+            Asset::Bond(b) => assets.find(|(_, a)| match a {
+                Asset::Bond(o) => {
+                    if b.isin().is_some() {
+                        o.isin() == b.isin()
+                    } else {
+                        o.name() == b.name()
+                    }
+                }
+                _ => false,
+            }),
+            Asset::Fund(f) => assets.find(|(_, a)| match a {
+                Asset::Fund(o) => {
+                    if f.wkn.is_some() {
+                        o.wkn == f.wkn
+                    } else if f.isin.is_some() {
+                        o.isin == f.isin
+                    } else {
+                        o.name == f.name
+                    }
+                }
+                _ => false,
+            }),
+            Asset::Crypto(c) => assets.find(|(_, a)| match a {
+                Asset::Crypto(o) => o.symbol == c.symbol && o.chain == c.chain,
+                _ => false,
+            }),
+            Asset::Commodity(c) => assets.find(|(_, a)| match a {
+                Asset::Commodity(o) => o.name == c.name,
+                _ => false,
+            }),
+        };
+        found.map(|(id, _)| *id)
+    }
+
+    async fn get_asset_by_id(&self, id: i32) -> Result<Asset, DataError> {
+        self.read()?
+            .assets
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| DataError::NotFound(format!("asset with id {id}")))
+    }
+
+    async fn get_asset_by_isin(&self, isin: &str) -> Result<Asset, DataError> {
+        self.read()?
+            .assets
+            .values()
+            .find(|a| match a {
+                Asset::Stock(s) => s.isin.as_deref() == Some(isin),
+                Asset::Bond(b) => b.isin() == Some(isin),
+                Asset::Fund(f) => f.isin.as_deref() == Some(isin),
+                _ => false,
+            })
+            .cloned()
+            .ok_or_else(|| DataError::NotFound(format!("asset with ISIN {isin}")))
+    }
+
+    async fn get_all_assets(&self) -> Result<Vec<Asset>, DataError> {
+        let mut assets: Vec<Asset> = self.read()?.assets.values().cloned().collect();
+        assets.sort_by_key(|a| a.name());
+        Ok(assets)
+    }
+
+    /// Return AssetSelector for all assets
+    async fn get_asset_list(&self) -> Result<Vec<AssetSelector>, DataError> {
+        Ok(self
+            .read()?
+            .assets
+            .iter()
+            .map(|(id, asset)| AssetSelector {
+                id: *id,
+                name: asset.name(),
+                class: asset.class(),
+            })
+            .collect())
+    }
+
+    async fn update_asset(&self, asset: &Asset) -> Result<(), DataError> {
+        let id = match asset {
+            Asset::Currency(c) => c.id,
+            Asset::Stock(s) => s.id,
+            Asset::Bond(b) => b.id,
+            Asset::Fund(f) => f.id,
+            Asset::Crypto(c) => c.id,
+            Asset::Commodity(c) => c.id,
+        }
+        .ok_or_else(|| DataError::NotFound("not yet stored to database".to_string()))?;
+        let mut store = self.write()?;
+        match store.assets.get(&id) {
+            Some(stored) if stored.class() == asset.class() => {}
+            _ => return Err(DataError::NotFound(format!("asset with id {id}"))),
+        }
+        store.check_unique(asset, Some(id))?;
+        let asset = store.with_stored_currency(asset)?;
+        store.assets.insert(id, asset);
+        Ok(())
+    }
+
+    async fn delete_asset(&self, id: i32) -> Result<(), DataError> {
+        let mut store = self.write()?;
+        if !store.assets.contains_key(&id) {
+            return Err(DataError::NotFound(format!("asset with id {id}")));
+        }
+        if store.is_asset_referenced(id) {
+            return Err(DataError::InvalidAsset(format!(
+                "asset with id {id} is still referenced"
+            )));
+        }
+        store.assets.remove(&id);
+        store
+            .asset_identifiers
+            .retain(|(asset_id, _)| *asset_id != id);
+        Ok(())
+    }
+
+    async fn add_asset_identifier(
+        &self,
+        asset_id: i32,
+        identifier: &Identifier,
+    ) -> Result<(), DataError> {
+        identifier.validate()?;
+        let mut store = self.write()?;
+        if !store.assets.contains_key(&asset_id) {
+            return Err(DataError::NotFound(format!("asset with id {asset_id}")));
+        }
+        if store.asset_identifiers.iter().any(|(_, i)| i == identifier) {
+            return Err(DataError::InvalidIdentifier(format!(
+                "{identifier} is already assigned to an asset"
+            )));
+        }
+        store.asset_identifiers.push((asset_id, identifier.clone()));
+        Ok(())
+    }
+
+    async fn get_asset_identifiers(&self, asset_id: i32) -> Result<Vec<Identifier>, DataError> {
+        Ok(self
+            .read()?
+            .asset_identifiers
+            .iter()
+            .filter(|(id, _)| *id == asset_id)
+            .map(|(_, identifier)| identifier.clone())
+            .collect())
+    }
+
+    async fn delete_asset_identifier(
+        &self,
+        asset_id: i32,
+        identifier: &Identifier,
+    ) -> Result<(), DataError> {
+        self.write()?
+            .asset_identifiers
+            .retain(|(id, i)| *id != asset_id || i != identifier);
+        Ok(())
+    }
+
+    async fn find_asset_by_identifier(&self, identifier: &Identifier) -> Result<Asset, DataError> {
+        let store = self.read()?;
+        identifier
+            .lookup_keys()
+            .iter()
+            .find_map(|key| store.find_asset_id(key))
+            .and_then(|id| store.assets.get(&id).cloned())
+            .ok_or_else(|| DataError::NotFound(format!("asset with {identifier}")))
+    }
+
+    async fn insert_corporate_action(&self, action: &CorporateAction) -> Result<i32, DataError> {
+        let mut store = self.write()?;
+        store.check_corporate_action(action)?;
+        let id = next_id(&mut store.last_corporate_action_id);
+        let mut action = action.clone();
+        action.id = Some(id);
+        store.corporate_actions.insert(id, action);
+        Ok(id)
+    }
+
+    async fn get_corporate_action_by_id(&self, id: i32) -> Result<CorporateAction, DataError> {
+        self.read()?
+            .corporate_actions
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| DataError::NotFound(format!("corporate action with id {id}")))
+    }
+
+    async fn get_corporate_actions(
+        &self,
+        start: Option<Date>,
+        end: Option<Date>,
+    ) -> Result<Vec<CorporateAction>, DataError> {
+        let mut actions: Vec<CorporateAction> = self
+            .read()?
+            .corporate_actions
+            .values()
+            .filter(|a| start.is_none_or(|start| a.date >= start))
+            .filter(|a| end.is_none_or(|end| a.date <= end))
+            .cloned()
+            .collect();
+        // ids are ascending already, the stable sort keeps this order for equal dates
+        actions.sort_by_key(|a| a.date);
+        Ok(actions)
+    }
+
+    async fn update_corporate_action(&self, action: &CorporateAction) -> Result<(), DataError> {
+        let id = action
+            .id
+            .ok_or_else(|| DataError::NotFound("not yet stored to database".to_string()))?;
+        let mut store = self.write()?;
+        if !store.corporate_actions.contains_key(&id) {
+            return Err(DataError::NotFound(format!(
+                "corporate action with id {id}"
+            )));
+        }
+        store.check_corporate_action(action)?;
+        store.corporate_actions.insert(id, action.clone());
+        Ok(())
+    }
+
+    async fn delete_corporate_action(&self, id: i32) -> Result<(), DataError> {
+        self.write()?
+            .corporate_actions
+            .remove(&id)
+            .map(|_| ())
+            .ok_or_else(|| DataError::NotFound(format!("corporate action with id {id}")))
+    }
+
+    async fn get_all_currencies(&self) -> Result<Vec<Currency>, DataError> {
+        Ok(self
+            .read()?
+            .assets
+            .values()
+            .filter_map(|a| match a {
+                Asset::Currency(c) => Some(*c),
+                _ => None,
+            })
+            .collect())
+    }
+
+    async fn get_currency_list(&self) -> Result<Vec<AssetSelector>, DataError> {
+        Ok(self
+            .get_all_currencies()
+            .await?
+            .into_iter()
+            .map(|c| AssetSelector {
+                id: c.id.unwrap(),
+                name: c.iso_code.to_string(),
+                class: "currency".to_string(),
+            })
+            .collect())
+    }
+
+    async fn get_or_new_currency(&self, iso_code: CurrencyISOCode) -> Result<Currency, DataError> {
+        self.get_or_new_currency_with_digits(iso_code, 2).await
+    }
+
+    async fn get_or_new_currency_with_digits(
+        &self,
+        iso_code: CurrencyISOCode,
+        rounding_digits: i32,
+    ) -> Result<Currency, DataError> {
+        let mut store = self.write()?;
+        let existing = store.assets.values().find_map(|a| match a {
+            Asset::Currency(c) if c.iso_code == iso_code => Some(*c),
+            _ => None,
+        });
+        if let Some(currency) = existing {
+            Ok(currency)
+        } else {
+            let mut currency = Currency::new(None, iso_code, Some(rounding_digits));
+            currency.id = Some(store.insert_asset(&Asset::Currency(currency))?);
+            Ok(currency)
+        }
+    }
+}
@@ -0,0 +1,412 @@
+//! Implementation of an in-memory data handler
+//!
+//! `InMemoryDB` implements all data handler traits without any external storage. Its content
+//! is lost as soon as the last clone of the handler is dropped, which makes it useful for
+//! unit tests, quick scripts or backtests where no persistent database is required.
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::datatypes::{
+    Account, Asset, CorporateAction, Currency, DataError, Identifier, Quote, Ticker, Transaction,
+    Validator,
+};
+
+pub mod asset_handler;
+pub mod object_handler;
+pub mod quote_handler;
+pub mod transaction_handler;
+
+/// Tables of the in-memory store, each entry is stored with its id set
+#[derive(Default)]
+struct MemoryStore {
+    last_asset_id: i32,
+    last_ticker_id: i32,
+    last_quote_id: i32,
+    last_transaction_id: i32,
+    last_account_id: i32,
+    last_corporate_action_id: i32,
+    assets: BTreeMap<i32, Asset>,
+    /// Additional identifiers as pairs of asset id and identifier
+    asset_identifiers: Vec<(i32, Identifier)>,
+    corporate_actions: BTreeMap<i32, CorporateAction>,
+    ticker: BTreeMap<i32, Ticker>,
+    quotes: BTreeMap<i32, Quote>,
+    transactions: BTreeMap<i32, Transaction>,
+    accounts: BTreeMap<i32, Account>,
+    objects: BTreeMap<String, serde_json::Value>,
+}
+
+/// Return the next free id of a table, ids start with 1 like a SERIAL column
+fn next_id(last_id: &mut i32) -> i32 {
+    *last_id += 1;
+    *last_id
+}
+
+impl MemoryStore {
+    /// Look up a stored currency by its asset id
+    fn currency_by_id(&self, id: i32) -> Option<Currency> {
+        match self.assets.get(&id) {
+            Some(Asset::Currency(c)) => Some(*c),
+            _ => None,
+        }
+    }
+}
+
+/// Struct to handle data stored in memory
+/// Clones share the same underlying storage, similar to clones of `PostgresDB`
+/// sharing the same connection pool.
+#[derive(Clone, Default)]
+pub struct InMemoryDB {
+    store: Arc<RwLock<MemoryStore>>,
+    /// Validator new transactions must pass, if any
+    validator: Option<Arc<Validator>>,
+}
+
+impl InMemoryDB {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reject new transactions failing the validator's checks on insert
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validator = Some(Arc::new(validator));
+        self
+    }
+
+    /// Remove all stored data
+    pub fn clean(&self) -> Result<(), DataError> {
+        *self.write()? = MemoryStore::default();
+        Ok(())
+    }
+
+    fn read(&self) -> Result<RwLockReadGuard<'_, MemoryStore>, DataError> {
+        self.store
+            .read()
+            .map_err(|_| DataError::DataAccessFailure("memory store is poisoned".to_string()))
+    }
+
+    fn write(&self) -> Result<RwLockWriteGuard<'_, MemoryStore>, DataError> {
+        self.store
+            .write()
+            .map_err(|_| DataError::DataAccessFailure("memory store is poisoned".to_string()))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::datatypes::{
+        date_time_helper::make_offset_time, AssetHandler, CashFlow, CurrencyISOCode, DataItem,
+        IdentifierType, ObjectHandler, QuoteHandler, Stock, TaxKind, TransactionFilter,
+        TransactionHandler, TransactionKind, TransactionType,
+    };
+    use time::{Date, Month};
+
+    /// Store a stock together with a manual ticker in the given currency and quotes at 18:00 of
+    /// the given dates, returns the stock's asset id
+    pub(crate) async fn insert_stock_with_quotes(
+        db: &InMemoryDB,
+        name: &str,
+        currency: Currency,
+        quotes: &[(Date, f64)],
+    ) -> i32 {
+        let asset_id = db
+            .insert_asset(&Asset::Stock(Stock::new(
+                None,
+                name.to_string(),
+                None,
+                None,
+                None,
+            )))
+            .await
+            .unwrap();
+        let ticker = db
+            .insert_ticker(&Ticker {
+                id: None,
+                asset: asset_id,
+                name: name.to_string(),
+                currency,
+                source: "manual".to_string(),
+                priority: 1,
+                factor: 1.0,
+                tz: None,
+                cal: None,
+            })
+            .await
+            .unwrap();
+        for (date, price) in quotes {
+            db.insert_quote(&Quote {
+                id: None,
+                ticker,
+                price: *price,
+                time: make_offset_time(
+                    date.year(),
+                    date.month() as u32,
+                    u32::from(date.day()),
+                    18,
+                    0,
+                    0,
+                )
+                .unwrap(),
+                volume: None,
+            })
+            .await
+            .unwrap();
+        }
+        asset_id
+    }
+
+    fn stock(name: &str, isin: &str) -> Asset {
+        Asset::Stock(Stock::new(
+            None,
+            name.to_string(),
+            Some(isin.to_string()),
+            None,
+            None,
+        ))
+    }
+
+    #[tokio::test]
+    async fn asset_ids_and_currencies() {
+        let db = InMemoryDB::new();
+        let basf_id = db
+            .insert_asset(&stock("BASF AG", "DE000BASF111"))
+            .await
+            .unwrap();
+        let eur = db
+            .get_or_new_currency(CurrencyISOCode::new("EUR").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(basf_id, 1);
+        assert_eq!(eur.get_id().unwrap(), 2);
+
+        // existing currency is returned, not inserted again
+        let eur2 = db
+            .get_or_new_currency_with_digits(CurrencyISOCode::new("EUR").unwrap(), 4)
+            .await
+            .unwrap();
+        assert_eq!(eur2.id, eur.id);
+        assert_eq!(eur2.rounding_digits, 2);
+        assert_eq!(db.get_all_currencies().await.unwrap().len(), 1);
+
+        // unique constraints
+        assert!(db
+            .insert_asset(&stock("BASF AG", "DE000BASF111"))
+            .await
+            .is_err());
+        assert_eq!(
+            db.get_asset_id(&stock("BASF", "DE000BASF111")).await,
+            Some(basf_id)
+        );
+        let basf = db.get_asset_by_isin("DE000BASF111").await.unwrap();
+        assert_eq!(basf.name(), "BASF AG");
+        assert_eq!(db.get_asset_list().await.unwrap().len(), 2);
+
+        db.delete_asset(basf_id).await.unwrap();
+        assert!(db.get_asset_by_id(basf_id).await.is_err());
+        // ids are not reused
+        let bhp_id = db
+            .insert_asset(&stock("BHP", "AU000000BHP4"))
+            .await
+            .unwrap();
+        assert_eq!(bhp_id, 3);
+    }
+
+    #[tokio::test]
+    async fn find_assets_by_identifier() {
+        let db = InMemoryDB::new();
+        let basf_id = db
+            .insert_asset(&stock("BASF AG", "DE000BASF111"))
+            .await
+            .unwrap();
+        let apple_id = db
+            .insert_asset(&Asset::Stock(Stock::new(
+                None,
+                "Apple Inc.".to_string(),
+                None,
+                None,
+                None,
+            )))
+            .await
+            .unwrap();
+        let apple_isin = Identifier::new(IdentifierType::Isin, "US0378331005").unwrap();
+        db.add_asset_identifier(apple_id, &apple_isin)
+            .await
+            .unwrap();
+        db.add_asset_identifier(
+            apple_id,
+            &Identifier::new(IdentifierType::Figi, "BBG000B9XRY4").unwrap(),
+        )
+        .await
+        .unwrap();
+        // each identifier belongs to a single asset
+        assert!(db.add_asset_identifier(basf_id, &apple_isin).await.is_err());
+        assert_eq!(db.get_asset_identifiers(apple_id).await.unwrap().len(), 2);
+
+        // WKN is derived from the German ISIN, CUSIP from the US ISIN
+        let by_wkn = Identifier::new(IdentifierType::Wkn, "BASF11").unwrap();
+        let by_cusip = Identifier::new(IdentifierType::Cusip, "037833100").unwrap();
+        let by_figi = Identifier::new(IdentifierType::Figi, "BBG000B9XRY4").unwrap();
+        for (identifier, id) in [(by_wkn, basf_id), (by_cusip, apple_id), (by_figi, apple_id)] {
+            let asset = db.find_asset_by_identifier(&identifier).await.unwrap();
+            assert_eq!(asset.get_id().unwrap(), id);
+        }
+        db.delete_asset_identifier(apple_id, &apple_isin)
+            .await
+            .unwrap();
+        assert!(db
+            .find_asset_by_identifier(&Identifier::new(IdentifierType::Cusip, "037833100").unwrap())
+            .await
+            .is_err());
+        db.delete_asset(apple_id).await.unwrap();
+        assert!(db.get_asset_identifiers(apple_id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn last_quote_and_duplicates() {
+        let db = InMemoryDB::new();
+        let asset_id = db
+            .insert_asset(&stock("BASF AG", "DE000BASF111"))
+            .await
+            .unwrap();
+        let eur = db
+            .get_or_new_currency(CurrencyISOCode::new("EUR").unwrap())
+            .await
+            .unwrap();
+        let mut ticker = Ticker {
+            id: None,
+            asset: asset_id,
+            name: "BAS.DE".to_string(),
+            currency: eur,
+            source: "manual".to_string(),
+            priority: 10,
+            factor: 1.0,
+            tz: None,
+            cal: None,
+        };
+        let low_prio_id = db.insert_ticker(&ticker).await.unwrap();
+        ticker.name = "BAS.F".to_string();
+        ticker.priority = 1;
+        let high_prio_id = db.insert_ticker(&ticker).await.unwrap();
+
+        let quote = |ticker, price, day| Quote {
+            id: None,
+            ticker,
+            price,
+            time: make_offset_time(2020, 1, day, 20, 0, 0).unwrap(),
+            volume: None,
+        };
+        db.insert_quote(&quote(low_prio_id, 10.0, 2)).await.unwrap();
+        db.insert_quote(&quote(low_prio_id, 11.0, 3)).await.unwrap();
+        db.insert_quote(&quote(high_prio_id, 11.5, 3))
+            .await
+            .unwrap();
+        db.insert_quote(&quote(low_prio_id, 12.0, 6)).await.unwrap();
+
+        let time = make_offset_time(2020, 1, 4, 0, 0, 0).unwrap();
+        let (last_quote, currency) = db
+            .get_last_quote_before_by_id(asset_id, time)
+            .await
+            .unwrap();
+        assert_eq!(last_quote.ticker, high_prio_id);
+        assert_eq!(last_quote.price, 11.5);
+        assert_eq!(currency, eur);
+        let time = make_offset_time(2020, 1, 1, 0, 0, 0).unwrap();
+        assert!(db
+            .get_last_quote_before_by_id(asset_id, time)
+            .await
+            .is_err());
+
+        db.insert_quote(&quote(low_prio_id, 11.0, 3)).await.unwrap();
+        assert_eq!(
+            db.get_all_quotes_for_ticker(low_prio_id)
+                .await
+                .unwrap()
+                .len(),
+            4
+        );
+        db.remove_duplicates().await.unwrap();
+        let quotes = db.get_all_quotes_for_ticker(low_prio_id).await.unwrap();
+        assert_eq!(quotes.len(), 3);
+        assert_eq!(quotes[1].id, Some(2));
+
+        // ticker with quotes can't be deleted
+        assert!(db.delete_ticker(high_prio_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn transactions_and_objects() {
+        let db = InMemoryDB::new();
+        let eur = db
+            .get_or_new_currency(CurrencyISOCode::new("EUR").unwrap())
+            .await
+            .unwrap();
+        let date = Date::from_calendar_date(2020, Month::January, 15).unwrap();
+        let mut dividend = Transaction {
+            id: None,
+            transaction_type: TransactionType::Dividend { asset_id: 42 },
+            cash_flow: CashFlow::new(90.0, eur, date),
+            note: None,
+            account_id: None,
+        };
+        // unknown asset
+        assert!(db.insert_transaction(&dividend).await.is_err());
+        let split = Transaction {
+            id: None,
+            transaction_type: TransactionType::Split {
+                asset_id: 42,
+                ratio: 2.0,
+            },
+            cash_flow: CashFlow::new(0.0, eur, date),
+            note: None,
+            account_id: None,
+        };
+        assert!(db.insert_transaction(&split).await.is_err());
+        let asset_id = db
+            .insert_asset(&stock("BASF AG", "DE000BASF111"))
+            .await
+            .unwrap();
+        dividend.transaction_type = TransactionType::Dividend { asset_id };
+        let dividend_id = db.insert_transaction(&dividend).await.unwrap();
+        let tax = Transaction {
+            id: None,
+            transaction_type: TransactionType::Tax {
+                transaction_ref: Some(dividend_id),
+                kind: TaxKind::Domestic,
+                country: None,
+            },
+            cash_flow: CashFlow::new(-25.0, eur, date),
+            note: None,
+            account_id: None,
+        };
+        let tax_id = db.insert_transaction(&tax).await.unwrap();
+        let filter = TransactionFilter {
+            asset_id: Some(asset_id),
+            ..Default::default()
+        };
+        assert_eq!(db.get_transactions(&filter).await.unwrap().len(), 2);
+        let filter = TransactionFilter {
+            kinds: Some(vec![TransactionKind::Tax, TransactionKind::Fee]),
+            end: date.previous_day(),
+            ..Default::default()
+        };
+        assert!(db.get_transactions(&filter).await.unwrap().is_empty());
+        let filter = TransactionFilter {
+            kinds: Some(vec![TransactionKind::Tax, TransactionKind::Fee]),
+            start: Some(date),
+            ..Default::default()
+        };
+        assert_eq!(
+            db.get_transactions(&filter).await.unwrap()[0].id,
+            Some(tax_id)
+        );
+        assert!(db.delete_transaction(dividend_id).await.is_err());
+        db.delete_transaction(tax_id).await.unwrap();
+        assert_eq!(db.get_all_transactions().await.unwrap().len(), 1);
+
+        db.store_object("settings", &vec![1, 2, 3]).await.unwrap();
+        db.update_object("settings", &vec![4]).await.unwrap();
+        let object: Vec<i32> = db.get_object("settings").await.unwrap();
+        assert_eq!(object, vec![4]);
+    }
+}
@@ -0,0 +1,50 @@
+//! Implementation of in-memory object handler
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json;
+
+use super::InMemoryDB;
+use crate::datatypes::{DataError, ObjectHandler};
+
+/// Handler for general, serializable objects kept as JSON values
+#[async_trait]
+impl ObjectHandler for InMemoryDB {
+    async fn store_object<T: Serialize + Sync>(
+        &self,
+        id: &str,
+        object: &T,
+    ) -> Result<(), DataError> {
+        let object_json = serde_json::to_value(object)?;
+        let mut store = self.write()?;
+        if store.objects.contains_key(id) {
+            return Err(DataError::DataAccessFailure(format!(
+                "object '{id}' already exists"
+            )));
+        }
+        store.objects.insert(id.to_string(), object_json);
+        Ok(())
+    }
+
+    async fn update_object<T: Serialize + Sync>(
+        &self,
+        id: &str,
+        object: &T,
+    ) -> Result<(), DataError> {
+        let object_json = serde_json::to_value(object)?;
+        if let Some(stored) = self.write()?.objects.get_mut(id) {
+            *stored = object_json;
+        }
+        Ok(())
+    }
+
+    async fn get_object<T: DeserializeOwned>(&self, id: &str) -> Result<T, DataError> {
+        let object_json = self
+            .read()?
+            .objects
+            .get(id)
+            .cloned()
+            .ok_or_else(|| DataError::NotFound(format!("object '{id}'")))?;
+        let object: T = serde_json::from_value(object_json)?;
+        Ok(object)
+    }
+}
@@ -0,0 +1,300 @@
+//! Implementation of quote handler with data kept in memory
+use async_trait::async_trait;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+use crate::datatypes::{
+    Asset, AssetHandler, Currency, CurrencyISOCode, DataError, Quote, QuoteHandler, Ticker,
+};
+
+use super::{next_id, InMemoryDB, MemoryStore};
+
+impl MemoryStore {
+    /// Return ticker with its currency as currently stored
+    fn ticker_with_currency(&self, ticker: &Ticker) -> Ticker {
+        let mut ticker = ticker.clone();
+        if let Some(currency) = ticker.currency.id.and_then(|id| self.currency_by_id(id)) {
+            ticker.currency = currency;
+        }
+        ticker
+    }
+
+    fn check_ticker(&self, ticker: &Ticker) -> Result<(), DataError> {
+        if !self.assets.contains_key(&ticker.asset) {
+            return Err(DataError::NotFound(format!(
+                "asset with id {}",
+                ticker.asset
+            )));
+        }
+        match ticker.currency.id.and_then(|id| self.currency_by_id(id)) {
+            Some(_) => Ok(()),
+            None => Err(DataError::NotFound(format!(
+                "currency {} not yet stored to database",
+                ticker.currency
+            ))),
+        }
+    }
+
+    /// Find the latest quote on or before `time` among all quotes whose ticker satisfies
+    /// `filter`. Quotes of the same time are ranked by ticker priority.
+    fn last_quote_before<F>(&self, time: OffsetDateTime, filter: F) -> Option<(&Quote, &Ticker)>
+    where
+        F: Fn(&Ticker) -> bool,
+    {
+        self.quotes
+            .values()
+            .filter(|q| q.time <= time)
+            .filter_map(|q| self.ticker.get(&q.ticker).map(|t| (q, t)))
+            .filter(|(_, t)| filter(t))
+            .min_by(|(q1, t1), (q2, t2)| by_time_desc_and_priority(q1, t1, q2, t2))
+    }
+}
+
+/// Order quotes by time descending and ticker priority ascending
+fn by_time_desc_and_priority(q1: &Quote, t1: &Ticker, q2: &Quote, t2: &Ticker) -> Ordering {
+    q2.time.cmp(&q1.time).then(t1.priority.cmp(&t2.priority))
+}
+
+/// In-memory implementation of quote handler
+#[async_trait]
+impl QuoteHandler for InMemoryDB {
+    fn into_arc_dispatch(self: Arc<Self>) -> Arc<dyn AssetHandler + Send + Sync> {
+        self
+    }
+
+    // insert, get, update and delete for market data sources
+    async fn insert_ticker(&self, ticker: &Ticker) -> Result<i32, DataError> {
+        let mut store = self.write()?;
+        store.check_ticker(ticker)?;
+        let id = next_id(&mut store.last_ticker_id);
+        let mut ticker = ticker.clone();
+        ticker.id = Some(id);
+        store.ticker.insert(id, ticker);
+        Ok(id)
+    }
+
+    async fn get_ticker_id(&self, ticker: &str) -> Option<i32> {
+        let store = self.read().ok()?;
+        store
+            .ticker
+            .iter()
+            .find(|(_, t)| t.name == ticker)
+            .map(|(id, _)| *id)
+    }
+
+    async fn insert_if_new_ticker(&self, ticker: &Ticker) -> Result<i32, DataError> {
+        match self.get_ticker_id(&ticker.name).await {
+            Some(id) => Ok(id),
+            None => self.insert_ticker(ticker).await,
+        }
+    }
+
+    async fn get_ticker_by_id(&self, id: i32) -> Result<Ticker, DataError> {
+        let store = self.read()?;
+        store
+            .ticker
+            .get(&id)
+            .map(|t| store.ticker_with_currency(t))
+            .ok_or_else(|| DataError::NotFound(format!("ticker with id {id}")))
+    }
+
+    async fn get_all_ticker(&self) -> Result<Vec<Ticker>, DataError> {
+        let store = self.read()?;
+        Ok(store
+            .ticker
+            .values()
+            .map(|t| store.ticker_with_currency(t))
+            .collect())
+    }
+
+    async fn get_all_ticker_for_source(&self, source: &str) -> Result<Vec<Ticker>, DataError> {
+        let store = self.read()?;
+        Ok(store
+            .ticker
+            .values()
+            .filter(|t| t.source == source)
+            .map(|t| store.ticker_with_currency(t))
+            .collect())
+    }
+
+    async fn get_all_ticker_for_asset(&self, asset_id: i32) -> Result<Vec<Ticker>, DataError> {
+        let store = self.read()?;
+        Ok(store
+            .ticker
+            .values()
+            .filter(|t| t.asset == asset_id)
+            .map(|t| store.ticker_with_currency(t))
+            .collect())
+    }
+
+    async fn update_ticker(&self, ticker: &Ticker) -> Result<(), DataError> {
+        let id = ticker
+            .id
+            .ok_or_else(|| DataError::NotFound("not yet stored to database".to_string()))?;
+        let mut store = self.write()?;
+        if !store.ticker.contains_key(&id) {
+            return Err(DataError::NotFound(format!("ticker with id {id}")));
+        }
+        store.check_ticker(ticker)?;
+        store.ticker.insert(id, ticker.clone());
+        Ok(())
+    }
+
+    async fn delete_ticker(&self, id: i32) -> Result<(), DataError> {
+        let mut store = self.write()?;
+        if store.quotes.values().any(|q| q.ticker == id) {
+            return Err(DataError::DataAccessFailure(format!(
+                "ticker with id {id} is still referenced by quotes"
+            )));
+        }
+        store.ticker.remove(&id);
+        Ok(())
+    }
+
+    async fn insert_quote(&self, quote: &Quote) -> Result<i32, DataError> {
+        let mut store = self.write()?;
+        if !store.ticker.contains_key(&quote.ticker) {
+            return Err(DataError::NotFound(format!(
+                "ticker with id {}",
+                quote.ticker
+            )));
+        }
+        let id = next_id(&mut store.last_quote_id);
+        let mut quote = quote.clone();
+        quote.id = Some(id);
+        store.quotes.insert(id, quote);
+        Ok(id)
+    }
+
+    async fn get_last_fx_quote_before(
+        &self,
+        curr: &CurrencyISOCode,
+        time: OffsetDateTime,
+    ) -> Result<(Quote, Currency), DataError> {
+        let store = self.read()?;
+        let is_fx_ticker = |t: &Ticker| match store.assets.get(&t.asset) {
+            Some(Asset::Currency(c)) => c.iso_code == *curr,
+            _ => false,
+        };
+        let (quote, ticker) = store
+            .last_quote_before(time, is_fx_ticker)
+            .ok_or_else(|| DataError::NotFound(format!("fx quote for {curr} before {time}")))?;
+        let currency = store.ticker_with_currency(ticker).currency;
+        Ok((quote.clone(), currency))
+    }
+
+    async fn get_last_quote_before_by_id(
+        &self,
+        asset_id: i32,
+        time: OffsetDateTime,
+    ) -> Result<(Quote, Currency), DataError> {
+        let store = self.read()?;
+        let (quote, ticker) = store
+            .last_quote_before(time, |t| t.asset == asset_id)
+            .ok_or_else(|| {
+                DataError::NotFound(format!("quote for asset id {asset_id} before {time}"))
+            })?;
+        let currency_id = ticker.currency.id.unwrap_or_default();
+        match store.currency_by_id(currency_id) {
+            Some(currency) => Ok((quote.clone(), currency)),
+            None => Err(DataError::InvalidAsset(format!(
+                "Couldn't find currency with id={}",
+                currency_id
+            ))),
+        }
+    }
+
+    async fn get_quotes_in_range_by_id(
+        &self,
+        asset_id: i32,
+        start: OffsetDateTime,
+        end: OffsetDateTime,
+    ) -> Result<Vec<(Quote, i32)>, DataError> {
+        let store = self.read()?;
+        let mut quotes: Vec<(&Quote, &Ticker)> = store
+            .quotes
+            .values()
+            .filter(|q| q.time >= start && q.time <= end)
+            .filter_map(|q| store.ticker.get(&q.ticker).map(|t| (q, t)))
+            .filter(|(_, t)| t.asset == asset_id)
+            .collect();
+        quotes.sort_by(|(q1, t1), (q2, t2)| by_time_desc_and_priority(q1, t1, q2, t2));
+        Ok(quotes
+            .into_iter()
+            .map(|(q, t)| (q.clone(), t.currency.id.unwrap_or_default()))
+            .collect())
+    }
+
+    async fn get_latest_quote_date_for_ticker(
+        &self,
+        ticker_id: i32,
+    ) -> Result<Option<OffsetDateTime>, DataError> {
+        Ok(self
+            .read()?
+            .quotes
+            .values()
+            .filter(|q| q.ticker == ticker_id)
+            .map(|q| q.time)
+            .max())
+    }
+
+    async fn get_all_quotes_for_ticker(&self, ticker_id: i32) -> Result<Vec<Quote>, DataError> {
+        let mut quotes: Vec<Quote> = self
+            .read()?
+            .quotes
+            .values()
+            .filter(|q| q.ticker == ticker_id)
+            .cloned()
+            .collect();
+        // stable sort keeps quotes of equal time in order of insertion
+        quotes.sort_by_key(|q| q.time);
+        Ok(quotes)
+    }
+
+    async fn update_quote(&self, quote: &Quote) -> Result<(), DataError> {
+        let id = quote
+            .id
+            .ok_or_else(|| DataError::NotFound("not yet stored to database".to_string()))?;
+        let mut store = self.write()?;
+        if !store.quotes.contains_key(&id) {
+            return Err(DataError::NotFound(format!("quote with id {id}")));
+        }
+        if !store.ticker.contains_key(&quote.ticker) {
+            return Err(DataError::NotFound(format!(
+                "ticker with id {}",
+                quote.ticker
+            )));
+        }
+        store.quotes.insert(id, quote.clone());
+        Ok(())
+    }
+
+    async fn delete_quote(&self, id: i32) -> Result<(), DataError> {
+        self.write()?.quotes.remove(&id);
+        Ok(())
+    }
+
+    async fn delete_quotes_for_ticker_id_in_range(
+        &self,
+        ticker_id: i32,
+        start: OffsetDateTime,
+        end: OffsetDateTime,
+    ) -> Result<(), DataError> {
+        self.write()?
+            .quotes
+            .retain(|_, q| q.ticker != ticker_id || q.time <= start || q.time > end);
+        Ok(())
+    }
+
+    async fn remove_duplicates(&self) -> Result<(), DataError> {
+        let mut store = self.write()?;
+        let mut seen = HashSet::new();
+        // quotes are iterated in order of ascending id, therefore the first one is kept
+        store
+            .quotes
+            .retain(|_, q| seen.insert((q.ticker, q.time, q.price.to_bits())));
+        Ok(())
+    }
+}
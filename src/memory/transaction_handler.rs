@@ -0,0 +1,243 @@
+use async_trait::async_trait;
+use time::Date;
+
+use crate::datatypes::{
+    Account, CurrencyError, DataError, Transaction, TransactionFilter, TransactionHandler,
+    TransactionType,
+};
+
+use super::{next_id, InMemoryDB, MemoryStore};
+
+impl MemoryStore {
+    /// Check that all assets and transactions referred to by a transaction exist
+    fn check_transaction(&self, transaction: &Transaction) -> Result<(), DataError> {
+        let currency = transaction.cash_flow.amount.currency;
+        if currency.id.and_then(|id| self.currency_by_id(id)).is_none() {
+            return Err(DataError::InvalidTransaction(format!(
+                "currency {currency} not yet stored to database"
+            )));
+        }
+        if let Some(account_id) = transaction.account_id {
+            if !self.accounts.contains_key(&account_id) {
+                return Err(DataError::InvalidTransaction(format!(
+                    "unknown account id {account_id}"
+                )));
+            }
+        }
+        match transaction.transaction_type {
+            TransactionType::Asset { asset_id, .. }
+            | TransactionType::Dividend { asset_id }
+            | TransactionType::Interest { asset_id }
+            | TransactionType::Split { asset_id, .. }
+            | TransactionType::Transfer {
+                asset_id: Some(asset_id),
+                ..
+            } if !self.assets.contains_key(&asset_id) => Err(DataError::InvalidTransaction(
+                format!("unknown asset id {asset_id}"),
+            )),
+            TransactionType::Tax {
+                transaction_ref: Some(trans_ref),
+                ..
+            }
+            | TransactionType::Fee {
+                transaction_ref: Some(trans_ref),
+            }
+            | TransactionType::Transfer {
+                transaction_ref: Some(trans_ref),
+                ..
+            } if !self.transactions.contains_key(&trans_ref) => Err(DataError::InvalidTransaction(
+                format!("unknown related transaction id {trans_ref}"),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that the account's currency is stored and its name is unique
+    fn check_account(&self, account: &Account) -> Result<(), DataError> {
+        if account
+            .currency
+            .id
+            .and_then(|id| self.currency_by_id(id))
+            .is_none()
+        {
+            return Err(DataError::InvalidCurrency(
+                CurrencyError::CurrencyNotInDatabase(account.currency.to_string()),
+            ));
+        }
+        if self
+            .accounts
+            .values()
+            .any(|a| a.name == account.name && a.id != account.id)
+        {
+            return Err(DataError::DataAccessFailure(format!(
+                "account '{}' already exists",
+                account.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the id of the transaction a tax, fee or transfer transaction refers to
+fn related_transaction(transaction: &Transaction) -> Option<i32> {
+    match transaction.transaction_type {
+        TransactionType::Tax {
+            transaction_ref, ..
+        }
+        | TransactionType::Fee { transaction_ref }
+        | TransactionType::Transfer {
+            transaction_ref, ..
+        } => transaction_ref,
+        _ => None,
+    }
+}
+
+/// Handler for globally available data
+#[async_trait]
+impl TransactionHandler for InMemoryDB {
+    // insert, get, update and delete for transactions
+    async fn insert_transaction(&self, transaction: &Transaction) -> Result<i32, DataError> {
+        if let Some(validator) = &self.validator {
+            validator.enforce(transaction, self).await?;
+        }
+        let mut store = self.write()?;
+        store.check_transaction(transaction)?;
+        let id = next_id(&mut store.last_transaction_id);
+        let mut transaction = transaction.clone();
+        transaction.id = Some(id);
+        store.transactions.insert(id, transaction);
+        Ok(id)
+    }
+
+    async fn get_transaction_by_id(&self, id: i32) -> Result<Transaction, DataError> {
+        self.read()?
+            .transactions
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| DataError::NotFound(format!("transaction with id {id}")))
+    }
+
+    async fn get_all_transactions(&self) -> Result<Vec<Transaction>, DataError> {
+        Ok(self.read()?.transactions.values().cloned().collect())
+    }
+
+    async fn get_transactions(
+        &self,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<Transaction>, DataError> {
+        let store = self.read()?;
+        let mut transactions: Vec<Transaction> = store
+            .transactions
+            .values()
+            .filter(|t| {
+                let related = related_transaction(t).and_then(|id| store.transactions.get(&id));
+                filter.matches(t, related)
+            })
+            .cloned()
+            .collect();
+        // stable sort keeps transactions of the same date in order of their ids
+        transactions.sort_by_key(|t| t.cash_flow.date);
+        Ok(transactions)
+    }
+
+    async fn update_transaction(&self, transaction: &Transaction) -> Result<(), DataError> {
+        let id = transaction
+            .id
+            .ok_or_else(|| DataError::NotFound("not yet stored to database".to_string()))?;
+        let mut store = self.write()?;
+        if !store.transactions.contains_key(&id) {
+            return Err(DataError::NotFound(format!("transaction with id {id}")));
+        }
+        store.check_transaction(transaction)?;
+        store.transactions.insert(id, transaction.clone());
+        Ok(())
+    }
+
+    async fn delete_transaction(&self, id: i32) -> Result<(), DataError> {
+        let mut store = self.write()?;
+        if store
+            .transactions
+            .values()
+            .any(|t| related_transaction(t) == Some(id))
+        {
+            return Err(DataError::InvalidTransaction(format!(
+                "transaction with id {id} is still referenced"
+            )));
+        }
+        store.transactions.remove(&id);
+        Ok(())
+    }
+
+    async fn insert_account(&self, account: &Account) -> Result<i32, DataError> {
+        let mut store = self.write()?;
+        let mut account = account.clone();
+        account.id = None;
+        store.check_account(&account)?;
+        let id = next_id(&mut store.last_account_id);
+        account.id = Some(id);
+        store.accounts.insert(id, account);
+        Ok(id)
+    }
+
+    async fn get_account_by_id(&self, id: i32) -> Result<Account, DataError> {
+        self.read()?
+            .accounts
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| DataError::NotFound(format!("account with id {id}")))
+    }
+
+    async fn get_all_accounts(&self) -> Result<Vec<Account>, DataError> {
+        let mut accounts: Vec<Account> = self.read()?.accounts.values().cloned().collect();
+        accounts.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(accounts)
+    }
+
+    async fn update_account(&self, account: &Account) -> Result<(), DataError> {
+        let id = account
+            .id
+            .ok_or_else(|| DataError::NotFound("not yet stored to database".to_string()))?;
+        let mut store = self.write()?;
+        if !store.accounts.contains_key(&id) {
+            return Err(DataError::NotFound(format!("account with id {id}")));
+        }
+        store.check_account(account)?;
+        store.accounts.insert(id, account.clone());
+        Ok(())
+    }
+
+    async fn delete_account(&self, id: i32) -> Result<(), DataError> {
+        let mut store = self.write()?;
+        if store
+            .transactions
+            .values()
+            .any(|t| t.account_id == Some(id))
+        {
+            return Err(DataError::DataAccessFailure(format!(
+                "account with id {id} has still transactions booked"
+            )));
+        }
+        store.accounts.remove(&id);
+        Ok(())
+    }
+
+    async fn get_transactions_for_account(
+        &self,
+        account_id: i32,
+        start: Option<Date>,
+        end: Option<Date>,
+    ) -> Result<Vec<Transaction>, DataError> {
+        let mut transactions: Vec<Transaction> = self
+            .read()?
+            .transactions
+            .values()
+            .filter(|t| t.account_id == Some(account_id))
+            .filter(|t| start.is_none_or(|start| t.cash_flow.date >= start))
+            .filter(|t| end.is_none_or(|end| t.cash_flow.date <= end))
+            .cloned()
+            .collect();
+        // stable sort keeps transactions of the same date in order of their ids
+        transactions.sort_by_key(|t| t.cash_flow.date);
+        Ok(transactions)
+    }
+}
@@ -0,0 +1,596 @@
+//! Time-weighted and money-weighted returns of a portfolio and its positions
+//!
+//! The time-weighted return (TWR) chains the returns of the sub-periods between external cash
+//! flows and is therefore independent of the timing and size of these flows. For the whole
+//! portfolio, external cash flows are cash transactions and transfers into or out of the
+//! portfolio. For a single position, all payments between the position and the cash account,
+//! i.e. purchases, sales, income, fees and taxes, are external. Values are taken at the end of
+//! each day with external flows. Contributions are assumed to be invested at the beginning of
+//! that day, withdrawals to be paid at its end.
+//!
+//! The money-weighted return (MWR) is the internal rate of return of the start value, all
+//! external cash flows and the end value, i.e. it measures the investor's return including
+//! the effect of the timing of contributions and withdrawals.
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use time::Date;
+
+use crate::datatypes::{
+    date_time_helper::date_to_offset_date_time, CashAmount, CashFlow, Currency, Transaction,
+    TransactionFilter, TransactionHandler, TransactionType,
+};
+use crate::fixed_income::calculate_cash_flows_irr;
+use crate::portfolio::{
+    calc_delta_position, calc_transfer_basis, PortfolioPosition, PositionError,
+};
+use crate::Market;
+
+/// Values below this threshold are considered as no investment
+const VALUE_TOLERANCE: f64 = 1e-8;
+/// Bounds of the annual money-weighted return searched for
+const MIN_RATE: f64 = -0.99;
+const MAX_RATE: f64 = 100.0;
+
+/// Returns of the portfolio or a single position within a date range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Performance {
+    /// Asset id of the position, None for the whole portfolio
+    pub asset_id: Option<i32>,
+    pub name: String,
+    /// Value at the beginning of the first day
+    pub start_value: f64,
+    /// Value at the end of the last day
+    pub end_value: f64,
+    /// Sum of all contributions less all withdrawals
+    pub net_flows: f64,
+    /// Cumulative time-weighted return
+    pub twr: f64,
+    /// Time-weighted return per year
+    pub twr_annualized: f64,
+    /// Cumulative money-weighted return, None if the internal rate of return could not be
+    /// determined, e.g. if there was no investment at all
+    pub mwr: Option<f64>,
+    /// Money-weighted return per year, i.e. the internal rate of return
+    pub mwr_annualized: Option<f64>,
+}
+
+/// Returns of the portfolio and its positions within a date range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerformanceReport {
+    /// First date of the period (inclusive)
+    pub start: Date,
+    /// Last date of the period (inclusive)
+    pub end: Date,
+    pub currency: Currency,
+    pub portfolio: Performance,
+    /// Positions held or traded within the period, ordered by asset id
+    pub positions: Vec<Performance>,
+}
+
+/// Running calculation of the returns of the portfolio or a single position
+struct Track {
+    start_value: f64,
+    /// Value at the end of the last sub-period
+    value: f64,
+    /// Growth factor of all sub-periods so far
+    growth: f64,
+    net_flows: f64,
+    /// Cash flows from the investor's point of view, i.e. negative if contributed
+    cash_flows: Vec<CashFlow>,
+    currency: Currency,
+}
+
+impl Track {
+    fn new(start: Date, start_value: f64, currency: Currency) -> Self {
+        Self {
+            start_value,
+            value: start_value,
+            growth: 1.0,
+            net_flows: 0.0,
+            cash_flows: vec![cash_flow(-start_value, start, currency)],
+            currency,
+        }
+    }
+
+    /// Close the sub-period ending with the given day, `flow` is the sum of all external cash
+    /// flows of that day and `value` the value at the end of the day
+    fn add_period(&mut self, date: Date, flow: f64, value: f64) {
+        let (contribution, withdrawal) = if flow > 0.0 {
+            (flow, 0.0)
+        } else {
+            (0.0, -flow)
+        };
+        let invested = self.value + contribution;
+        if invested.abs() > VALUE_TOLERANCE {
+            self.growth *= (value + withdrawal) / invested;
+        }
+        self.value = value;
+        self.net_flows += flow;
+        // flows of the first day are part of the initial cash flow
+        if date == self.cash_flows[0].date {
+            self.cash_flows[0].amount.amount -= flow;
+        } else if flow != 0.0 {
+            self.cash_flows.push(cash_flow(-flow, date, self.currency));
+        }
+    }
+
+    fn performance(
+        mut self,
+        asset_id: Option<i32>,
+        name: String,
+        end: Date,
+        years: f64,
+    ) -> Performance {
+        let twr = self.growth - 1.0;
+        self.cash_flows
+            .push(cash_flow(self.value, end, self.currency));
+        let init_cash_flow = self.cash_flows[0];
+        let mwr_annualized = if self.cash_flows.iter().all(|cf| cf.amount.amount == 0.0) {
+            None
+        } else {
+            calculate_cash_flows_irr(&self.cash_flows[1..], &init_cash_flow, MIN_RATE, MAX_RATE)
+                .ok()
+        };
+        Performance {
+            asset_id,
+            name,
+            start_value: self.start_value,
+            end_value: self.value,
+            net_flows: self.net_flows,
+            twr,
+            twr_annualized: (1.0 + twr).powf(1.0 / years) - 1.0,
+            mwr: mwr_annualized.map(|rate| (1.0 + rate).powf(years) - 1.0),
+            mwr_annualized,
+        }
+    }
+}
+
+fn cash_flow(amount: f64, date: Date, currency: Currency) -> CashFlow {
+    CashFlow {
+        amount: CashAmount { amount, currency },
+        date,
+    }
+}
+
+/// Collect the external cash flows per day and position, converted to the report currency.
+/// Flows of the whole portfolio are stored with key None, contributions are positive.
+pub(crate) async fn external_flows(
+    currency: Currency,
+    transactions: &[Transaction],
+    start: Date,
+    end: Date,
+    market: &Market,
+) -> Result<BTreeMap<Date, BTreeMap<Option<i32>, f64>>, PositionError> {
+    let asset_of: BTreeMap<i32, i32> = transactions
+        .iter()
+        .filter_map(|t| match t.transaction_type {
+            TransactionType::Asset { asset_id, .. }
+            | TransactionType::Dividend { asset_id }
+            | TransactionType::Interest { asset_id } => t.id.map(|id| (id, asset_id)),
+            _ => None,
+        })
+        .collect();
+    let mut flows: BTreeMap<Date, BTreeMap<Option<i32>, f64>> = BTreeMap::new();
+    for trans in transactions {
+        let date = trans.cash_flow.date;
+        if date < start || date > end {
+            continue;
+        }
+        let amount = market
+            .convert(
+                trans.cash_flow.amount.amount,
+                trans.cash_flow.amount.currency,
+                currency,
+                date,
+            )
+            .await?;
+        let day = flows.entry(date).or_default();
+        match trans.transaction_type {
+            TransactionType::Cash | TransactionType::Transfer { asset_id: None, .. } => {
+                *day.entry(None).or_default() += amount;
+            }
+            TransactionType::Asset { asset_id, .. }
+            | TransactionType::Dividend { asset_id }
+            | TransactionType::Interest { asset_id } => {
+                *day.entry(Some(asset_id)).or_default() -= amount;
+            }
+            TransactionType::Fee { transaction_ref }
+            | TransactionType::Tax {
+                transaction_ref, ..
+            } => {
+                if let Some(asset_id) = transaction_ref.and_then(|id| asset_of.get(&id)) {
+                    *day.entry(Some(*asset_id)).or_default() -= amount;
+                }
+            }
+            TransactionType::Transfer {
+                asset_id: Some(asset_id),
+                position,
+                ..
+            } => {
+                // transferred units are valued at the end of the day, or at the transfer's
+                // cash flow if no price is available
+                let time = date_to_offset_date_time(
+                    &date.next_day().ok_or(PositionError::InvalidDate)?,
+                    0,
+                    None,
+                )?;
+                let value = match market.get_asset_price(asset_id, currency, time).await {
+                    Ok(price) => position * price,
+                    Err(_) => -amount,
+                };
+                *day.entry(None).or_default() += value;
+                *day.entry(Some(asset_id)).or_default() += value;
+            }
+            TransactionType::Split { .. } => {}
+        }
+    }
+    Ok(flows)
+}
+
+/// Calculate time-weighted and money-weighted returns of the portfolio and each position for
+/// the date range from `start` to `end` (both inclusive) from a list of transactions ordered
+/// by cash flow date, including all transactions before `start`. Positions are valued with the
+/// latest quotes before midnight, all amounts are converted to the given currency.
+pub async fn calc_performance(
+    currency: Currency,
+    transactions: &[Transaction],
+    start: Date,
+    end: Date,
+    market: &Market,
+) -> Result<PerformanceReport, PositionError> {
+    performance(
+        PortfolioPosition::new(currency),
+        transactions,
+        start,
+        end,
+        market,
+    )
+    .await
+}
+
+async fn performance(
+    mut positions: PortfolioPosition,
+    transactions: &[Transaction],
+    start: Date,
+    end: Date,
+    market: &Market,
+) -> Result<PerformanceReport, PositionError> {
+    let currency = positions.cash.currency;
+    let next_day = end.next_day().ok_or(PositionError::InvalidDate)?;
+    if next_day <= start {
+        return Err(PositionError::InvalidDate);
+    }
+    let years = (next_day - start).whole_days() as f64 / 365.0;
+    let flows = external_flows(currency, transactions, start, end, market).await?;
+
+    // value at the beginning of the period
+    calc_delta_position(
+        &mut positions,
+        transactions,
+        None,
+        Some(start),
+        market.clone(),
+    )
+    .await?;
+    positions
+        .add_quote(date_to_offset_date_time(&start, 0, None)?, market)
+        .await;
+    let mut tracks: BTreeMap<Option<i32>, Track> = BTreeMap::new();
+    tracks.insert(
+        None,
+        Track::new(start, positions.calc_totals().value, currency),
+    );
+    for (asset_id, pos) in &positions.assets {
+        tracks.insert(Some(*asset_id), Track::new(start, pos.value(), currency));
+    }
+
+    // sub-periods end with each day with external flows and with the last day
+    let mut period_ends: Vec<Date> = flows.keys().copied().collect();
+    if period_ends.last() != Some(&end) {
+        period_ends.push(end);
+    }
+    let mut last = start;
+    for date in period_ends {
+        let day_after = date.next_day().ok_or(PositionError::InvalidDate)?;
+        calc_delta_position(
+            &mut positions,
+            transactions,
+            Some(last),
+            Some(day_after),
+            market.clone(),
+        )
+        .await?;
+        last = day_after;
+        positions
+            .add_quote(date_to_offset_date_time(&day_after, 0, None)?, market)
+            .await;
+        let mut values: BTreeMap<Option<i32>, f64> = positions
+            .assets
+            .iter()
+            .map(|(asset_id, pos)| (Some(*asset_id), pos.value()))
+            .collect();
+        values.insert(None, positions.calc_totals().value);
+        let day_flows = flows.get(&date);
+        for (key, value) in values {
+            let flow = day_flows
+                .and_then(|f| f.get(&key))
+                .copied()
+                .unwrap_or_default();
+            tracks
+                .entry(key)
+                .or_insert_with(|| Track::new(start, 0.0, currency))
+                .add_period(date, flow, value);
+        }
+    }
+
+    positions
+        .get_asset_names(market.db().into_arc_dispatch())
+        .await?;
+    let portfolio = tracks
+        .remove(&None)
+        .unwrap()
+        .performance(None, String::new(), next_day, years);
+    let positions = tracks
+        .into_iter()
+        .filter(|(_, track)| {
+            track.start_value.abs() > VALUE_TOLERANCE || track.cash_flows.len() > 1
+        })
+        .filter_map(|(asset_id, track)| {
+            let name = positions.assets.get(&asset_id?)?.name.clone();
+            Some(track.performance(asset_id, name, next_day, years))
+        })
+        .collect();
+    Ok(PerformanceReport {
+        start,
+        end,
+        currency,
+        portfolio,
+        positions,
+    })
+}
+
+/// Calculate the performance report for all transactions booked to the given accounts, or
+/// all transactions if no accounts are given, see `calc_performance`
+pub async fn calc_performance_for_accounts(
+    currency: Currency,
+    account_ids: Option<&[i32]>,
+    start: Date,
+    end: Date,
+    db: &(dyn TransactionHandler + Send + Sync),
+    market: &Market,
+) -> Result<PerformanceReport, PositionError> {
+    let filter = TransactionFilter {
+        end: Some(end),
+        account_ids: account_ids.map(|ids| ids.to_vec()),
+        ..Default::default()
+    };
+    let transactions = db.get_transactions(&filter).await?;
+    let mut positions = PortfolioPosition::new(currency);
+    if account_ids.is_some() {
+        positions.transfers = calc_transfer_basis(currency, Some(end), None, db, market).await?;
+    }
+    performance(positions, &transactions, start, end, market).await
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use time::Month;
+
+    use crate::day_count_conv::DayCountConv;
+    use crate::memory::{tests::insert_stock_with_quotes, InMemoryDB};
+    use crate::rates::{Compounding, Discounter, FlatRate};
+
+    /// Check that the cash flows have zero net present value at the given annual rate
+    fn assert_irr(rate: f64, cash_flows: &[(Date, f64)], currency: Currency) {
+        let discounter = FlatRate::new(rate, DayCountConv::Act365, Compounding::Annual, currency);
+        let cash_flows: Vec<CashFlow> = cash_flows
+            .iter()
+            .map(|(date, amount)| cash_flow(*amount, *date, currency))
+            .collect();
+        let npv = discounter
+            .discount_cash_flow_stream(&cash_flows, cash_flows[0].date)
+            .unwrap();
+        assert_fuzzy_eq!(npv.amount, 0.0, 1e-6);
+    }
+
+    /// Portfolio with deposits in January and July, purchases of a stock at the same dates and
+    /// a dividend in October of 2021
+    pub(crate) async fn sample_portfolio() -> (Market, Currency, i32, Vec<Transaction>) {
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let date = |month, day| Date::from_calendar_date(2021, month, day).unwrap();
+        let asset_id = insert_stock_with_quotes(
+            &db,
+            "BASF AG",
+            eur,
+            &[
+                (date(Month::January, 4), 50.0),
+                (date(Month::June, 30), 60.0),
+                (date(Month::December, 31), 54.0),
+            ],
+        )
+        .await;
+
+        let buy = |position| TransactionType::Asset { asset_id, position };
+        let transactions = [
+            (TransactionType::Cash, 10_000.0, date(Month::January, 1)),
+            (buy(100.0), -5_000.0, date(Month::January, 4)),
+            (TransactionType::Cash, 10_000.0, date(Month::July, 1)),
+            (buy(100.0), -6_000.0, date(Month::July, 1)),
+            (
+                TransactionType::Dividend { asset_id },
+                200.0,
+                date(Month::October, 1),
+            ),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(idx, &(transaction_type, amount, date))| Transaction {
+            id: Some(idx as i32 + 1),
+            transaction_type,
+            cash_flow: cash_flow(amount, date, eur),
+            note: None,
+            account_id: None,
+        })
+        .collect();
+        (market, eur, asset_id, transactions)
+    }
+
+    #[tokio::test]
+    async fn time_and_money_weighted_returns() {
+        let tol = 1e-8;
+        let (market, eur, asset_id, transactions) = sample_portfolio().await;
+        let date = |month, day| Date::from_calendar_date(2021, month, day).unwrap();
+
+        let start = date(Month::January, 1);
+        let end = date(Month::December, 31);
+        let report = calc_performance(eur, &transactions, start, end, &market)
+            .await
+            .unwrap();
+        let next_year = Date::from_calendar_date(2022, Month::January, 1).unwrap();
+
+        // dividends are kept as cash, i.e. the gain in the first half of the year is lost
+        // in the second half
+        let portfolio = &report.portfolio;
+        assert_fuzzy_eq!(portfolio.start_value, 0.0, tol);
+        assert_fuzzy_eq!(portfolio.end_value, 20_000.0, tol);
+        assert_fuzzy_eq!(portfolio.net_flows, 20_000.0, tol);
+        assert_fuzzy_eq!(portfolio.twr, 0.0, tol);
+        assert_fuzzy_eq!(portfolio.twr_annualized, 0.0, tol);
+        // and so is the investor's return
+        assert_fuzzy_eq!(portfolio.mwr.unwrap(), 0.0, tol);
+
+        assert_eq!(report.positions.len(), 1);
+        let position = &report.positions[0];
+        assert_eq!(position.asset_id, Some(asset_id));
+        assert_eq!(position.name, "BASF AG");
+        assert_fuzzy_eq!(position.end_value, 10_800.0, tol);
+        assert_fuzzy_eq!(position.net_flows, 11_000.0 - 200.0, tol);
+        let twr = 12_000.0 / 11_000.0 * 12_200.0 / 12_000.0 * 10_800.0 / 12_000.0 - 1.0;
+        assert_fuzzy_eq!(position.twr, twr, tol);
+        let mwr = position.mwr_annualized.unwrap();
+        assert!(mwr < 0.0);
+        assert_irr(
+            mwr,
+            &[
+                (start, 0.0),
+                (date(Month::January, 4), -5_000.0),
+                (date(Month::July, 1), -6_000.0),
+                (date(Month::October, 1), 200.0),
+                (next_year, 10_800.0),
+            ],
+            eur,
+        );
+        // the period is exactly one year
+        assert_fuzzy_eq!(position.mwr.unwrap(), mwr, tol);
+
+        // the second half year with positions at start
+        let report = calc_performance(eur, &transactions, date(Month::July, 1), end, &market)
+            .await
+            .unwrap();
+        let portfolio = &report.portfolio;
+        assert_fuzzy_eq!(portfolio.start_value, 11_000.0, tol);
+        assert_fuzzy_eq!(
+            portfolio.twr,
+            21_200.0 / 21_000.0 * 20_000.0 / 21_200.0 * 21_000.0 / 21_000.0 - 1.0,
+            tol
+        );
+        let years = (next_year - date(Month::July, 1)).whole_days() as f64 / 365.0;
+        assert_fuzzy_eq!(
+            portfolio.twr_annualized,
+            (1.0 + portfolio.twr).powf(1.0 / years) - 1.0,
+            tol
+        );
+        assert_irr(
+            portfolio.mwr_annualized.unwrap(),
+            &[
+                (date(Month::July, 1), -11_000.0 - 10_000.0),
+                (next_year, 20_000.0),
+            ],
+            eur,
+        );
+    }
+
+    #[tokio::test]
+    async fn returns_at_period_boundaries() {
+        let tol = 1e-8;
+        let (market, eur, asset_id, mut transactions) = sample_portfolio().await;
+        let date = |month, day| Date::from_calendar_date(2021, month, day).unwrap();
+
+        // the deposit on the first and the purchase on the last day are both within the period
+        let report = calc_performance(
+            eur,
+            &transactions,
+            date(Month::January, 1),
+            date(Month::January, 4),
+            &market,
+        )
+        .await
+        .unwrap();
+        let portfolio = &report.portfolio;
+        assert_fuzzy_eq!(portfolio.start_value, 0.0, tol);
+        assert_fuzzy_eq!(portfolio.net_flows, 10_000.0, tol);
+        assert_fuzzy_eq!(portfolio.end_value, 10_000.0, tol);
+        assert_fuzzy_eq!(portfolio.twr, 0.0, tol);
+        let position = &report.positions[0];
+        assert_eq!(position.asset_id, Some(asset_id));
+        assert_fuzzy_eq!(position.net_flows, 5_000.0, tol);
+        assert_fuzzy_eq!(position.end_value, 5_000.0, tol);
+        assert_fuzzy_eq!(position.twr, 0.0, tol);
+
+        // a period starting the day after the last flow has no flows at all
+        let report = calc_performance(
+            eur,
+            &transactions,
+            date(Month::October, 2),
+            date(Month::December, 31),
+            &market,
+        )
+        .await
+        .unwrap();
+        let portfolio = &report.portfolio;
+        assert_fuzzy_eq!(portfolio.net_flows, 0.0, tol);
+        assert_fuzzy_eq!(portfolio.start_value, 21_200.0, tol);
+        assert_fuzzy_eq!(portfolio.twr, 20_000.0 / 21_200.0 - 1.0, tol);
+
+        // the end must not be before the start
+        assert!(matches!(
+            calc_performance(
+                eur,
+                &transactions,
+                date(Month::July, 1),
+                date(Month::June, 30),
+                &market,
+            )
+            .await,
+            Err(PositionError::InvalidDate)
+        ));
+
+        // deposits in a currency without fx quotes can't be converted
+        let usd = market.get_currency_from_str("USD").await.unwrap();
+        transactions.push(Transaction {
+            id: Some(6),
+            transaction_type: TransactionType::Cash,
+            cash_flow: cash_flow(1_000.0, date(Month::November, 1), usd),
+            note: None,
+            account_id: None,
+        });
+        match calc_performance(
+            eur,
+            &transactions,
+            date(Month::January, 1),
+            date(Month::December, 31),
+            &market,
+        )
+        .await
+        {
+            Err(PositionError::MarketDataError(err)) => assert!(err.is_missing_quote()),
+            _ => panic!("missing fx quote has not been reported"),
+        }
+    }
+}
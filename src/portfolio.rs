@@ -4,14 +4,18 @@ use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::vec::Vec;
 use thiserror::Error;
-use time::{Date, OffsetDateTime};
+use time::{Date, Month, OffsetDateTime};
 
+use crate::bond::Bond;
 use crate::datatypes::{
     currency::CurrencyConverter,
     date_time_helper::{date_to_offset_date_time, DateTimeError},
-    Asset, AssetHandler, Currency, CurrencyError, DataError, Transaction, TransactionType,
+    Asset, AssetHandler, CorporateAction, Currency, CurrencyError, DataError, DistributionPolicy,
+    TaxKind, Transaction, TransactionFilter, TransactionHandler, TransactionType,
 };
+use crate::fixed_income::FixedIncome;
 use crate::period_date::PeriodDateError;
+use crate::tax::{IncomeCategory, SharedTaxModel};
 use crate::Market;
 
 /// Errors related to position calculation
@@ -27,10 +31,76 @@ pub enum PositionError {
     CurrencyError(#[from] CurrencyError),
     #[error("Failed to access market data")]
     MarketDataError(#[from] crate::market::MarketError),
+    #[error("Calendar error")]
+    CalendarError(#[from] cal_calc::CalendarError),
     #[error("Invalid date")]
     InvalidDate,
 }
 
+/// Positions of lots below this threshold are considered as closed
+const LOT_TOLERANCE: f64 = 1e-10;
+
+/// Method to select the purchase lots a sale is realized against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum CostBasisMethod {
+    /// All lots are reduced proportionally, i.e. at the average purchase price
+    #[default]
+    Average,
+    /// First in, first out
+    Fifo,
+    /// Last in, first out
+    Lifo,
+    /// Lots with the highest purchase price per unit first
+    HighestCost,
+}
+
+/// Purchase lot of an asset position, `position` and `purchase_value` have the same
+/// signs as in `Position`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lot {
+    /// Id of the transaction the lot has been opened by
+    pub transaction_id: Option<i32>,
+    pub date: Date,
+    pub position: f64,
+    pub purchase_value: f64,
+}
+
+impl Lot {
+    fn price(&self) -> f64 {
+        -self.purchase_value / self.position
+    }
+}
+
+/// Purchase value and lots delivered by an outgoing security transfer, which are taken over
+/// by the receiving transfer referring to it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransferBasis {
+    pub purchase_value: f64,
+    pub lots: Vec<Lot>,
+    /// Vorabpauschale already taxed on the delivered units
+    #[serde(default)]
+    pub vorabpauschale: f64,
+}
+
+/// Realized profit or loss of (a part of) a sale realized against a single purchase lot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RealizedGain {
+    pub asset_id: i32,
+    /// Id of the sale transaction
+    pub transaction_id: Option<i32>,
+    pub date: Date,
+    /// Id of the transaction the lot has been opened by
+    pub lot_transaction_id: Option<i32>,
+    pub lot_date: Date,
+    /// Reduction of the position, i.e. negative if a long position has been reduced
+    pub position: f64,
+    /// Part of the sale's cash flow
+    pub proceeds: f64,
+    /// Part of the lot's purchase value
+    pub purchase_value: f64,
+    pub pnl: f64,
+}
+
 /// Calculate the total position as of a given date by applying a specified set of filters
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Position {
@@ -47,6 +117,27 @@ pub struct Position {
     pub currency: Currency,
     pub last_quote: Option<f64>,
     pub last_quote_time: Option<OffsetDateTime>,
+    /// Accrued interest per unit included in `last_quote`, only set for bonds
+    #[serde(default)]
+    pub accrued_interest: Option<f64>,
+    /// Open purchase lots, only tracked if a cost basis method is set for the portfolio
+    #[serde(default)]
+    pub lots: Vec<Lot>,
+    /// Realized profits and losses per sale and lot, only tracked together with lots
+    #[serde(default)]
+    pub realized_gains: Vec<RealizedGain>,
+    /// Taxes of all kinds referring to dividend payments, included in `tax`
+    #[serde(default)]
+    pub dividend_tax: f64,
+    /// Foreign withholding tax credited against domestic tax, included in `tax`
+    #[serde(default)]
+    pub credited_tax: f64,
+    /// Reclaimable withholding tax not refunded yet
+    #[serde(default)]
+    pub outstanding_reclaims: f64,
+    /// Vorabpauschale taxed on the units held, deducted from the taxable gain when they are sold
+    #[serde(default)]
+    pub vorabpauschale: f64,
 }
 
 /// Calculate the total position as of a given date by applying a specified set of filters
@@ -59,6 +150,10 @@ pub struct PositionTotals {
     interest: f64,
     tax: f64,
     fees: f64,
+    /// Dividends after all taxes referring to them
+    pub net_dividend: f64,
+    pub credited_tax: f64,
+    pub outstanding_reclaims: f64,
 }
 
 impl Position {
@@ -76,6 +171,196 @@ impl Position {
             tax: 0.0,
             last_quote: None,
             last_quote_time: None,
+            accrued_interest: None,
+            lots: Vec::new(),
+            realized_gains: Vec::new(),
+            dividend_tax: 0.0,
+            credited_tax: 0.0,
+            outstanding_reclaims: 0.0,
+            vorabpauschale: 0.0,
+        }
+    }
+
+    /// Dividends received after all taxes referring to them
+    pub fn net_dividend(&self) -> f64 {
+        self.dividend + self.dividend_tax
+    }
+
+    /// Book tax paid or refunded, `on_dividend` indicates whether the tax refers to a dividend
+    fn add_tax(&mut self, amount: f64, kind: TaxKind, on_dividend: bool) {
+        self.tax += amount;
+        if on_dividend {
+            self.dividend_tax += amount;
+        }
+        match kind {
+            TaxKind::Credited => self.credited_tax += amount,
+            TaxKind::Reclaimable => self.outstanding_reclaims -= amount,
+            TaxKind::Domestic | TaxKind::Withholding => {}
+        }
+    }
+
+    /// Add a new purchase lot
+    fn open_lot(&mut self, transaction: &Transaction, position: f64, purchase_value: f64) {
+        self.lots.push(Lot {
+            transaction_id: transaction.id,
+            date: transaction.cash_flow.date,
+            position,
+            purchase_value,
+        });
+    }
+
+    /// Reduce open lots by `close` units, which must have the same sign as the lots and must
+    /// not exceed the open position, according to the cost basis method and return the
+    /// removed parts of the lots
+    fn take_lots(&mut self, close: f64, method: CostBasisMethod) -> Vec<Lot> {
+        let open: f64 = self.lots.iter().map(|lot| lot.position).sum();
+        let mut order: Vec<usize> = (0..self.lots.len()).collect();
+        match method {
+            CostBasisMethod::Average | CostBasisMethod::Fifo => {}
+            CostBasisMethod::Lifo => order.reverse(),
+            CostBasisMethod::HighestCost => order.sort_by(|a, b| {
+                let (a, b) = (&self.lots[*a], &self.lots[*b]);
+                (b.price() * b.position.signum()).total_cmp(&(a.price() * a.position.signum()))
+            }),
+        }
+        let mut remaining = close;
+        let mut taken = Vec::new();
+        for i in order {
+            let lot = &mut self.lots[i];
+            let reduction = match method {
+                CostBasisMethod::Average => lot.position * close / open,
+                _ if lot.position.abs() <= remaining.abs() + LOT_TOLERANCE => lot.position,
+                _ => remaining,
+            };
+            if reduction.abs() <= LOT_TOLERANCE {
+                continue;
+            }
+            remaining -= reduction;
+            let purchase_value = lot.purchase_value * reduction / lot.position;
+            lot.position -= reduction;
+            lot.purchase_value -= purchase_value;
+            taken.push(Lot {
+                position: reduction,
+                purchase_value,
+                ..lot.clone()
+            });
+        }
+        self.lots.retain(|lot| lot.position.abs() > LOT_TOLERANCE);
+        taken
+    }
+
+    /// Reduce open lots by a sale of `position` units for `amount` according to the cost
+    /// basis method, record the realized gains and return the total realized pnl.
+    /// If the sale exceeds the open position, a new lot is opened for the remainder.
+    fn realize_lots(
+        &mut self,
+        transaction: &Transaction,
+        position: f64,
+        amount: f64,
+        method: CostBasisMethod,
+    ) -> f64 {
+        let open: f64 = self.lots.iter().map(|lot| lot.position).sum();
+        // part of the open position to be closed, with the same sign as the lots
+        let close = if position.abs() > open.abs() {
+            open
+        } else {
+            -position
+        };
+        let mut total_pnl = 0.0;
+        for lot in self.take_lots(close, method) {
+            let proceeds = amount * lot.position / -position;
+            let pnl = proceeds + lot.purchase_value;
+            total_pnl += pnl;
+            self.realized_gains.push(RealizedGain {
+                asset_id: self.asset_id.unwrap_or_default(),
+                transaction_id: transaction.id,
+                date: transaction.cash_flow.date,
+                lot_transaction_id: lot.transaction_id,
+                lot_date: lot.date,
+                position: -lot.position,
+                proceeds,
+                purchase_value: lot.purchase_value,
+                pnl,
+            });
+        }
+        if position.abs() > open.abs() {
+            let exceeding = position + open;
+            self.open_lot(transaction, exceeding, amount * exceeding / position);
+        }
+        total_pnl
+    }
+
+    /// Deliver `position` (negative) units without realizing any profit or loss and return
+    /// the purchase value and, if lots are tracked, the lots of the delivered units
+    fn transfer_out(&mut self, position: f64, method: Option<CostBasisMethod>) -> TransferBasis {
+        let (purchase_value, lots) = match method {
+            Some(method) if !self.lots.is_empty() => {
+                let open: f64 = self.lots.iter().map(|lot| lot.position).sum();
+                let lots = self.take_lots(-position.max(-open), method);
+                (lots.iter().map(|lot| lot.purchase_value).sum(), lots)
+            }
+            _ if self.position != 0.0 => {
+                (self.purchase_value * -position / self.position, Vec::new())
+            }
+            _ => (0.0, Vec::new()),
+        };
+        let vorabpauschale = self.vorabpauschale_share(position);
+        self.position += position;
+        self.purchase_value -= purchase_value;
+        self.vorabpauschale -= vorabpauschale;
+        TransferBasis {
+            purchase_value,
+            lots,
+            vorabpauschale,
+        }
+    }
+
+    /// Receive `position` units with the given purchase value, keeping the lots of the
+    /// delivering position or opening a new lot acquired at `acquisition_date`
+    fn transfer_in(
+        &mut self,
+        transaction: &Transaction,
+        position: f64,
+        TransferBasis {
+            purchase_value,
+            lots,
+            vorabpauschale,
+        }: TransferBasis,
+        acquisition_date: Option<Date>,
+        track_lots: bool,
+    ) {
+        self.position += position;
+        self.purchase_value += purchase_value;
+        self.vorabpauschale += vorabpauschale;
+        // lots are only tracked for positions opened with lot tracking enabled
+        if track_lots && (self.position == position || !self.lots.is_empty()) {
+            if lots.is_empty() {
+                self.open_lot(transaction, position, purchase_value);
+                if let (Some(lot), Some(date)) = (self.lots.last_mut(), acquisition_date) {
+                    lot.date = date;
+                }
+            } else {
+                self.lots.extend(lots);
+            }
+        }
+    }
+
+    /// Part of the Vorabpauschale taxed so far which is attributed to `position` units
+    /// leaving the position, i.e. `position` has the opposite sign of the held units
+    fn vorabpauschale_share(&self, position: f64) -> f64 {
+        if self.position == 0.0 {
+            0.0
+        } else {
+            self.vorabpauschale * (-position / self.position).min(1.0)
+        }
+    }
+
+    /// Value of the position based on the last quote or, if not available, on the purchase value
+    pub fn value(&self) -> f64 {
+        if let Some(quote) = self.last_quote {
+            self.position * quote
+        } else {
+            -self.purchase_value
         }
     }
 
@@ -87,6 +372,24 @@ impl Position {
         }
     }
 
+    /// Bond quotes are clean prices in percent of the denomination, convert the last quote
+    /// to the dirty price per bond by adding the accrued interest as of the quote's date.
+    async fn add_accrued_interest(&mut self, bond: &Bond, time: OffsetDateTime, market: &Market) {
+        let fx_rate = if bond.currency() == self.currency {
+            Ok(1.0)
+        } else {
+            market.fx_rate(bond.currency(), self.currency, time).await
+        };
+        if let (Some(clean_price), Ok(accrued_interest), Ok(fx_rate)) =
+            (self.last_quote, bond.accrued_interest(time.date()), fx_rate)
+        {
+            self.accrued_interest = Some(accrued_interest * fx_rate);
+            // the clean price has already been converted to the position's currency
+            self.last_quote =
+                Some(clean_price / 100. * (bond.denomination as f64) + accrued_interest * fx_rate);
+        }
+    }
+
     /// Add quote information to position
     /// If no quote is available (or no conversion to position currency), calculate
     /// from purchase value.
@@ -95,6 +398,10 @@ impl Position {
             if let Ok(price) = market.get_asset_price(asset_id, self.currency, time).await {
                 self.last_quote = Some(price);
                 self.last_quote_time = Some(time);
+                self.accrued_interest = None;
+                if let Ok(Asset::Bond(bond)) = market.db().get_asset_by_id(asset_id).await {
+                    self.add_accrued_interest(&bond, time, &market).await;
+                }
             } else {
                 // No price found
                 self.last_quote = self.quote_from_purchase();
@@ -108,10 +415,35 @@ impl Position {
     }
 }
 
+/// Exposure of a portfolio to a single asset, held directly or via funds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exposure {
+    /// Asset id, if the asset is stored in the database
+    pub asset_id: Option<i32>,
+    pub name: String,
+    /// Value of the exposure in the portfolio's base currency
+    pub value: f64,
+    /// Fraction of the total value of all asset positions
+    pub weight: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PortfolioPosition {
     pub cash: Position,
     pub assets: BTreeMap<i32, Position>,
+    /// If set, purchase lots are tracked for all positions opened afterwards and profit or loss
+    /// of sales is realized against these lots according to the given method
+    #[serde(default)]
+    pub cost_basis: Option<CostBasisMethod>,
+    /// If set, tax on realized gains and losses of sales is calculated with this model and
+    /// booked to the position's tax and the cash position, e.g. to simulate strategies. Taxes
+    /// on dividends and interest are expected as tax transactions.
+    #[serde(skip)]
+    pub tax_model: Option<SharedTaxModel>,
+    /// Purchase value and lots delivered by outgoing security transfers by transaction id,
+    /// kept until the end of the calculation to be taken over by the receiving transfers
+    #[serde(default)]
+    pub transfers: BTreeMap<i32, TransferBasis>,
 }
 
 impl PortfolioPosition {
@@ -119,23 +451,138 @@ impl PortfolioPosition {
         PortfolioPosition {
             cash: Position::new(None, base_currency),
             assets: BTreeMap::new(),
+            cost_basis: None,
+            tax_model: None,
+            transfers: BTreeMap::new(),
+        }
+    }
+
+    /// Create a new portfolio position which tracks purchase lots
+    pub fn with_cost_basis(base_currency: Currency, method: CostBasisMethod) -> PortfolioPosition {
+        PortfolioPosition {
+            cost_basis: Some(method),
+            ..Self::new(base_currency)
         }
     }
 
+    /// Return realized gains of all positions ordered by date of sale
+    pub fn realized_gains(&self) -> Vec<RealizedGain> {
+        let mut gains: Vec<RealizedGain> = self
+            .assets
+            .values()
+            .flat_map(|pos| pos.realized_gains.iter().cloned())
+            .collect();
+        gains.sort_by_key(|gain| gain.date);
+        gains
+    }
+
     pub async fn get_asset_names(
         &mut self,
         db: Arc<dyn AssetHandler + Send + Sync>,
     ) -> Result<(), DataError> {
         for (id, pos) in &mut self.assets {
-            let asset = db.get_asset_by_id(*id).await?;
-            pos.name = match asset {
-                Asset::Currency(c) => c.iso_code.to_string(),
-                Asset::Stock(s) => s.name.clone(),
-            };
+            pos.name = db.get_asset_by_id(*id).await?.name();
         }
         Ok(())
     }
 
+    /// Aggregate the exposure of all asset positions to the underlying assets by looking through
+    /// fund positions to the fund's constituents. The share of a fund not covered by its stored
+    /// constituents remains an exposure to the fund itself. Cash is not taken into account and
+    /// positions should have been valued by `add_quote` before. The exposures are sorted by
+    /// descending value.
+    pub async fn look_through(
+        &self,
+        db: Arc<dyn AssetHandler + Send + Sync>,
+    ) -> Result<Vec<Exposure>, DataError> {
+        let mut exposures: Vec<Exposure> = Vec::new();
+        let mut add_exposure = |asset_id: Option<i32>, name: &str, value: f64| {
+            let existing = exposures.iter_mut().find(|e| match asset_id {
+                Some(_) => e.asset_id == asset_id,
+                None => e.asset_id.is_none() && e.name == name,
+            });
+            match existing {
+                Some(exposure) => exposure.value += value,
+                None => exposures.push(Exposure {
+                    asset_id,
+                    name: name.to_string(),
+                    value,
+                    weight: 0.0,
+                }),
+            }
+        };
+        let mut total = 0.0;
+        for (id, pos) in &self.assets {
+            let value = pos.value();
+            total += value;
+            match db.get_asset_by_id(*id).await? {
+                Asset::Fund(fund) if !fund.constituents.is_empty() => {
+                    let mut covered = 0.0;
+                    for constituent in &fund.constituents {
+                        add_exposure(
+                            constituent.asset_id,
+                            &constituent.name,
+                            value * constituent.weight,
+                        );
+                        covered += constituent.weight;
+                    }
+                    if covered < 1.0 {
+                        add_exposure(Some(*id), &fund.name, value * (1.0 - covered));
+                    }
+                }
+                asset => add_exposure(Some(*id), &asset.name(), value),
+            }
+        }
+        for exposure in exposures.iter_mut() {
+            exposure.weight = if total != 0.0 {
+                exposure.value / total
+            } else {
+                0.0
+            };
+        }
+        exposures.sort_by(|a, b| b.value.total_cmp(&a.value));
+        Ok(exposures)
+    }
+
+    /// Transform the position in the source asset of a corporate action into positions in the
+    /// target assets. The purchase value is allocated to the target assets according to their
+    /// cost shares, the remaining purchase value stays with the source position unless the
+    /// source position is given up. No profit or loss is realized.
+    pub fn apply_corporate_action(&mut self, action: &CorporateAction) {
+        let source = match self.assets.get(&action.asset_id) {
+            Some(pos) if pos.position != 0.0 => pos.clone(),
+            _ => return,
+        };
+        let base_currency = self.cash.currency;
+        if let Some(pos) = self.assets.get_mut(&action.asset_id) {
+            if action.kind.retains_source() {
+                let retained = 1.0 - action.targets.iter().map(|t| t.cost_share).sum::<f64>();
+                pos.purchase_value = source.purchase_value * retained;
+                for lot in pos.lots.iter_mut() {
+                    lot.purchase_value *= retained;
+                }
+            } else {
+                pos.position = 0.0;
+                pos.purchase_value = 0.0;
+                pos.lots.clear();
+            }
+        }
+        for target in &action.targets {
+            let pos = self
+                .assets
+                .entry(target.asset_id)
+                .or_insert_with(|| Position::new(Some(target.asset_id), base_currency));
+            pos.position += source.position * target.ratio;
+            pos.purchase_value += source.purchase_value * target.cost_share;
+            // received units keep the purchase dates of the source lots
+            pos.lots.extend(source.lots.iter().map(|lot| Lot {
+                position: lot.position * target.ratio,
+                purchase_value: lot.purchase_value * target.cost_share,
+                ..lot.clone()
+            }));
+        }
+    }
+
     pub async fn add_quote(&mut self, time: OffsetDateTime, market: &Market) {
         let mut get_quote_futures = Vec::new();
         for pos in self.assets.values_mut() {
@@ -144,6 +591,91 @@ impl PortfolioPosition {
         let _ = join_all(get_quote_futures).await;
     }
 
+    /// Register the Vorabpauschale of all accumulating funds held at the end of `year` with the
+    /// tax model and book the tax due at the first day of the following year. The lump sum is
+    /// added to `Position::vorabpauschale` to be deducted from the gain of a later sale. Units
+    /// bought during the year are taken into account pro rata temporis if purchase lots are
+    /// tracked, otherwise all units are considered to be held for the whole year. Nothing is
+    /// done if no tax model is set.
+    pub async fn apply_vorabpauschale(
+        &mut self,
+        year: i32,
+        market: &Market,
+    ) -> Result<(), PositionError> {
+        let tax_model = match &self.tax_model {
+            Some(tax_model) => tax_model.clone(),
+            None => return Ok(()),
+        };
+        let year_start = Date::from_calendar_date(year, Month::January, 1)
+            .map_err(|_| PositionError::InvalidDate)?;
+        let tax_date = Date::from_calendar_date(year + 1, Month::January, 1)
+            .map_err(|_| PositionError::InvalidDate)?;
+        // the latest prices before midnight are the prices at the end of the previous day
+        let start_time = date_to_offset_date_time(&year_start, 0, None)?;
+        let end_time = date_to_offset_date_time(&tax_date, 0, None)?;
+        for (asset_id, pos) in self.assets.iter_mut() {
+            if pos.position <= 0.0 {
+                continue;
+            }
+            match market.db().get_asset_by_id(*asset_id).await? {
+                Asset::Fund(fund) if fund.distribution == DistributionPolicy::Accumulating => {}
+                _ => continue,
+            }
+            let price_end = market
+                .get_asset_price(*asset_id, pos.currency, end_time)
+                .await?;
+            // the growth of split adjusted prices does not depend on the number of shares
+            let growth = match market
+                .get_split_adjusted_asset_price(*asset_id, pos.currency, start_time)
+                .await
+            {
+                Ok(price) => {
+                    market
+                        .get_split_adjusted_asset_price(*asset_id, pos.currency, end_time)
+                        .await?
+                        / price
+                }
+                // no Vorabpauschale without a price at the start of the year
+                Err(err) if err.is_missing_quote() => continue,
+                Err(err) => return Err(err.into()),
+            };
+            let price_start = price_end / growth;
+            let lump_sum = {
+                let model = tax_model.lock().map_err(|_| {
+                    DataError::DataAccessFailure("tax model is poisoned".to_string())
+                })?;
+                let per_unit =
+                    |months_held| model.vorabpauschale(year, price_start, price_end, months_held);
+                if pos.lots.is_empty() {
+                    pos.position * per_unit(12)
+                } else {
+                    pos.lots
+                        .iter()
+                        .map(|lot| {
+                            let months_held = if lot.date.year() < year {
+                                12
+                            } else {
+                                13 - lot.date.month() as u8
+                            };
+                            lot.position * per_unit(months_held)
+                        })
+                        .sum()
+                }
+            };
+            if lump_sum == 0.0 {
+                continue;
+            }
+            let tax = tax_model
+                .lock()
+                .map_err(|_| DataError::DataAccessFailure("tax model is poisoned".to_string()))?
+                .tax(tax_date, IncomeCategory::Vorabpauschale, lump_sum);
+            pos.tax -= tax;
+            pos.vorabpauschale += lump_sum;
+            self.cash.position -= tax;
+        }
+        Ok(())
+    }
+
     pub fn calc_totals(&mut self) -> PositionTotals {
         let mut totals = PositionTotals {
             value: self.cash.position,
@@ -153,13 +685,12 @@ impl PortfolioPosition {
             interest: self.cash.interest,
             tax: self.cash.tax,
             fees: self.cash.fees,
+            net_dividend: self.cash.net_dividend(),
+            credited_tax: self.cash.credited_tax,
+            outstanding_reclaims: self.cash.outstanding_reclaims,
         };
         for pos in self.assets.values() {
-            let pos_value = if let Some(quote) = pos.last_quote {
-                pos.position * quote
-            } else {
-                -pos.purchase_value
-            };
+            let pos_value = pos.value();
             totals.value += pos_value;
             totals.trading_pnl += pos.trading_pnl;
             totals.unrealized_pnl += pos_value + pos.purchase_value;
@@ -167,6 +698,9 @@ impl PortfolioPosition {
             totals.interest += pos.interest;
             totals.tax += pos.tax;
             totals.fees += pos.fees;
+            totals.net_dividend += pos.net_dividend();
+            totals.credited_tax += pos.credited_tax;
+            totals.outstanding_reclaims += pos.outstanding_reclaims;
         }
         totals
     }
@@ -180,13 +714,21 @@ impl PortfolioPosition {
         self.cash.interest = 0.0;
         self.cash.fees = 0.0;
         self.cash.tax = 0.0;
+        self.cash.dividend_tax = 0.0;
+        self.cash.credited_tax = 0.0;
         for pos in self.assets.iter_mut() {
             pos.1.trading_pnl = 0.0;
             pos.1.dividend = 0.0;
             pos.1.interest = 0.0;
             pos.1.fees = 0.0;
             pos.1.tax = 0.0;
+            pos.1.dividend_tax = 0.0;
+            pos.1.credited_tax = 0.0;
             pos.1.purchase_value = -pos.1.position * pos.1.last_quote.unwrap_or(0.0);
+            pos.1.realized_gains.clear();
+            for lot in pos.1.lots.iter_mut() {
+                lot.purchase_value = -lot.position * pos.1.last_quote.unwrap_or(0.0);
+            }
         }
     }
 
@@ -204,22 +746,16 @@ impl PortfolioPosition {
 }
 
 /// Search for transaction referred to by transaction_ref and return associated asset_id
-fn get_asset_id(transactions: &[Transaction], trans_ref: Option<i32>) -> Option<i32> {
-    trans_ref?;
-    for trans in transactions {
-        if trans.id == trans_ref {
-            return match trans.transaction_type {
-                TransactionType::Asset {
-                    asset_id,
-                    position: _,
-                } => Some(asset_id),
-                TransactionType::Dividend { asset_id } => Some(asset_id),
-                TransactionType::Interest { asset_id } => Some(asset_id),
-                _ => None,
-            };
-        }
+fn get_asset_id(related: Option<&Transaction>) -> Option<i32> {
+    match related?.transaction_type {
+        TransactionType::Asset {
+            asset_id,
+            position: _,
+        } => Some(asset_id),
+        TransactionType::Dividend { asset_id } => Some(asset_id),
+        TransactionType::Interest { asset_id } => Some(asset_id),
+        _ => None,
     }
-    None
 }
 
 /// Calculate the total position since inception caused by a given set of transactions.
@@ -235,14 +771,55 @@ pub async fn calc_position(
 }
 
 /// Given a PortfolioPosition, calculate changes to position by a given set of transactions.
+/// Corporate actions stored in the market's database within the same date range, as cached by
+/// `Market::get_corporate_actions`, are applied in date order, i.e. before all transactions with cash flow dates on or after the
+/// corporate action's date. Transactions are expected to be ordered by cash flow date.
 pub async fn calc_delta_position(
     positions: &mut PortfolioPosition,
     transactions: &[Transaction],
     start: Option<Date>,
     end: Option<Date>,
     market: Market,
+) -> Result<(), PositionError> {
+    let corporate_actions = market
+        .get_corporate_actions(start, end.and_then(|end| end.previous_day()))
+        .await?;
+    delta_position(
+        positions,
+        transactions,
+        &[],
+        &corporate_actions,
+        start,
+        end,
+        market,
+    )
+    .await
+}
+
+/// Same as `calc_delta_position`, but fees and taxes might refer to the `referenced`
+/// transactions as well, which are not applied to the position themselves. The given
+/// corporate actions, ordered by date, are applied instead of those stored in the database.
+pub(crate) async fn delta_position(
+    positions: &mut PortfolioPosition,
+    transactions: &[Transaction],
+    referenced: &[Transaction],
+    corporate_actions: &[CorporateAction],
+    start: Option<Date>,
+    end: Option<Date>,
+    market: Market,
 ) -> Result<(), PositionError> {
     let base_currency = positions.cash.currency;
+    let find_ref = |trans_ref: Option<i32>| {
+        trans_ref.and_then(|id| {
+            transactions
+                .iter()
+                .chain(referenced)
+                .find(|trans| trans.id == Some(id))
+        })
+    };
+    let mut corporate_actions = corporate_actions.iter().peekable();
+    // income category of sales per asset
+    let mut sale_categories = BTreeMap::new();
     for trans in transactions {
         if start.is_some() && trans.cash_flow.date < start.unwrap() {
             continue;
@@ -250,6 +827,9 @@ pub async fn calc_delta_position(
         if end.is_some() && trans.cash_flow.date >= end.unwrap() {
             continue;
         }
+        while let Some(action) = corporate_actions.next_if(|a| a.date <= trans.cash_flow.date) {
+            positions.apply_corporate_action(action);
+        }
         let curr_factor = if trans.cash_flow.amount.currency != base_currency {
             market
                 .fx_rate(
@@ -261,19 +841,31 @@ pub async fn calc_delta_position(
         } else {
             1.0
         };
-        // adjust cash balance
-        positions.cash.position += trans.cash_flow.amount.amount * curr_factor;
+        // adjust cash balance, the cash flow of security transfers is not paid
+        if !matches!(
+            trans.transaction_type,
+            TransactionType::Transfer {
+                asset_id: Some(_),
+                ..
+            }
+        ) {
+            positions.cash.position += trans.cash_flow.amount.amount * curr_factor;
+        }
 
         match trans.transaction_type {
             TransactionType::Cash => {
                 // Do nothing, cash position has already been updated
             }
             TransactionType::Asset { asset_id, position } => {
+                let cost_basis = positions.cost_basis;
                 match positions.assets.get_mut(&asset_id) {
                     None => {
                         let mut new_pos = Position::new(Some(asset_id), base_currency);
                         new_pos.position = position;
                         new_pos.purchase_value = trans.cash_flow.amount.amount;
+                        if cost_basis.is_some() {
+                            new_pos.open_lot(trans, position, trans.cash_flow.amount.amount);
+                        }
                         positions.assets.insert(asset_id, new_pos);
                     }
                     Some(pos) => {
@@ -282,14 +874,55 @@ pub async fn calc_delta_position(
                             // Increase position
                             pos.position += position;
                             pos.purchase_value += amount;
+                            // lots are only tracked for positions opened with lot tracking enabled
+                            if cost_basis.is_some()
+                                && (pos.position == position || !pos.lots.is_empty())
+                            {
+                                pos.open_lot(trans, position, amount);
+                            }
                         } else {
                             // Reduce position, calculate realized p&l part
-                            let eff_price = -pos.purchase_value / pos.position;
-                            let sell_price = -amount / position;
-                            let pnl = -position * (sell_price - eff_price);
+                            let pnl = match cost_basis {
+                                Some(method) if !pos.lots.is_empty() => {
+                                    pos.realize_lots(trans, position, amount, method)
+                                }
+                                _ => {
+                                    let eff_price = -pos.purchase_value / pos.position;
+                                    let sell_price = -amount / position;
+                                    -position * (sell_price - eff_price)
+                                }
+                            };
+                            // the Vorabpauschale taxed before reduces the taxable gain
+                            let vorabpauschale = pos.vorabpauschale_share(position);
+                            pos.vorabpauschale -= vorabpauschale;
                             pos.trading_pnl += pnl;
                             pos.position += position;
                             pos.purchase_value += amount - pnl;
+                            if let Some(tax_model) = &positions.tax_model {
+                                let category = match sale_categories.get(&asset_id) {
+                                    Some(category) => *category,
+                                    None => {
+                                        let asset = market.db().get_asset_by_id(asset_id).await?;
+                                        let category = IncomeCategory::sale_of(&asset);
+                                        sale_categories.insert(asset_id, category);
+                                        category
+                                    }
+                                };
+                                let tax = tax_model
+                                    .lock()
+                                    .map_err(|_| {
+                                        DataError::DataAccessFailure(
+                                            "tax model is poisoned".to_string(),
+                                        )
+                                    })?
+                                    .tax(
+                                        trans.cash_flow.date,
+                                        category,
+                                        pnl * curr_factor - vorabpauschale,
+                                    );
+                                pos.tax -= tax / curr_factor;
+                                positions.cash.position -= tax;
+                            }
                         }
                     }
                 };
@@ -319,7 +952,7 @@ pub async fn calc_delta_position(
                 };
             }
             TransactionType::Fee { transaction_ref } => {
-                let asset_id = get_asset_id(transactions, transaction_ref);
+                let asset_id = get_asset_id(find_ref(transaction_ref));
                 if let Some(asset_id) = asset_id {
                     match positions.assets.get_mut(&asset_id) {
                         None => {
@@ -335,28 +968,135 @@ pub async fn calc_delta_position(
                     positions.cash.fees += trans.cash_flow.amount.amount;
                 }
             }
-            TransactionType::Tax { transaction_ref } => {
-                let asset_id = get_asset_id(transactions, transaction_ref);
-                if let Some(asset_id) = asset_id {
-                    match positions.assets.get_mut(&asset_id) {
-                        None => {
-                            let mut new_pos = Position::new(Some(asset_id), base_currency);
-                            new_pos.tax = trans.cash_flow.amount.amount;
-                            positions.assets.insert(asset_id, new_pos);
-                        }
-                        Some(pos) => {
-                            pos.tax += trans.cash_flow.amount.amount;
-                        }
-                    };
+            TransactionType::Tax {
+                transaction_ref,
+                kind,
+                ..
+            } => {
+                let related = find_ref(transaction_ref);
+                let on_dividend = related.is_some_and(|related| {
+                    matches!(related.transaction_type, TransactionType::Dividend { .. })
+                });
+                let pos = match get_asset_id(related) {
+                    Some(asset_id) => positions
+                        .assets
+                        .entry(asset_id)
+                        .or_insert_with(|| Position::new(Some(asset_id), base_currency)),
+                    None => &mut positions.cash,
+                };
+                pos.add_tax(trans.cash_flow.amount.amount, kind, on_dividend);
+            }
+            TransactionType::Split { asset_id, ratio } => {
+                // The purchase value is kept, only the number of shares changes. Any cash in
+                // lieu of fractional shares has already been added to the cash position.
+                if let Some(pos) = positions.assets.get_mut(&asset_id) {
+                    pos.position *= ratio;
+                    for lot in pos.lots.iter_mut() {
+                        lot.position *= ratio;
+                    }
+                }
+            }
+            TransactionType::Transfer { asset_id: None, .. } => {
+                // Do nothing, cash position has already been updated
+            }
+            TransactionType::Transfer {
+                asset_id: Some(asset_id),
+                position,
+                transaction_ref,
+                acquisition_date,
+            } => {
+                let cost_basis = positions.cost_basis;
+                // the purchase value and lots of the delivering transfer are taken over, if
+                // it has been processed by this or an earlier calculation
+                let received = transaction_ref
+                    .filter(|_| position > 0.0)
+                    .and_then(|id| positions.transfers.get(&id).cloned());
+                let pos = positions
+                    .assets
+                    .entry(asset_id)
+                    .or_insert_with(|| Position::new(Some(asset_id), base_currency));
+                if position < 0.0 {
+                    let delivered = pos.transfer_out(position, cost_basis);
+                    if let Some(id) = trans.id {
+                        positions.transfers.insert(id, delivered);
+                    }
                 } else {
-                    positions.cash.tax += trans.cash_flow.amount.amount;
+                    let received = received.unwrap_or(TransferBasis {
+                        purchase_value: trans.cash_flow.amount.amount,
+                        lots: Vec::new(),
+                        vorabpauschale: 0.0,
+                    });
+                    pos.transfer_in(
+                        trans,
+                        position,
+                        received,
+                        acquisition_date,
+                        cost_basis.is_some(),
+                    );
                 }
             }
         }
     }
+    for action in corporate_actions {
+        positions.apply_corporate_action(action);
+    }
     Ok(())
 }
 
+/// Given a PortfolioPosition, calculate changes to position by all transactions stored in the
+/// database matching the given filter. Only transactions with cash flow dates in the range
+/// from `start` (inclusive) to `end` (exclusive) are loaded, any date range of the filter
+/// is replaced by this range. Fees and taxes referring to transactions outside of this range
+/// are still attributed to the asset of the referenced transaction.
+pub async fn calc_delta_position_from_db(
+    positions: &mut PortfolioPosition,
+    filter: &TransactionFilter,
+    start: Option<Date>,
+    end: Option<Date>,
+    db: &(dyn TransactionHandler + Send + Sync),
+    market: Market,
+) -> Result<(), PositionError> {
+    let filter = TransactionFilter {
+        start,
+        end: end.and_then(|end| end.previous_day()),
+        ..filter.clone()
+    };
+    let transactions = db.get_transactions(&filter).await?;
+    // fees and taxes might refer to transactions not matching the filter, e.g. before `start`
+    let mut referenced: Vec<Transaction> = Vec::new();
+    for trans in &transactions {
+        let trans_ref = match trans.transaction_type {
+            TransactionType::Fee { transaction_ref }
+            | TransactionType::Tax {
+                transaction_ref, ..
+            } => transaction_ref,
+            _ => None,
+        };
+        if let Some(id) = trans_ref {
+            let known = transactions
+                .iter()
+                .chain(&referenced)
+                .any(|known| known.id == Some(id));
+            if !known {
+                referenced.push(db.get_transaction_by_id(id).await?);
+            }
+        }
+    }
+    let corporate_actions = market
+        .get_corporate_actions(start, end.and_then(|end| end.previous_day()))
+        .await?;
+    delta_position(
+        positions,
+        &transactions,
+        &referenced,
+        &corporate_actions,
+        start,
+        end,
+        market,
+    )
+    .await
+}
+
 /// Calculate position and P&L since for list of transactions.
 /// All transaction with cash flow dates before the given date are taken into account and valued
 /// using the latest available quote before midnight of that date.
@@ -366,7 +1106,16 @@ pub async fn calculate_position_and_pnl(
     date: Option<Date>,
     market: &Market,
 ) -> Result<(PortfolioPosition, PositionTotals), PositionError> {
-    let mut position = calc_position(currency, transactions, date, market.clone()).await?;
+    position_and_pnl(PortfolioPosition::new(currency), transactions, date, market).await
+}
+
+async fn position_and_pnl(
+    mut position: PortfolioPosition,
+    transactions: &[Transaction],
+    date: Option<Date>,
+    market: &Market,
+) -> Result<(PortfolioPosition, PositionTotals), PositionError> {
+    calc_delta_position(&mut position, transactions, None, date, market.clone()).await?;
     position
         .get_asset_names(market.db().into_arc_dispatch())
         .await?;
@@ -380,6 +1129,50 @@ pub async fn calculate_position_and_pnl(
     Ok((position, totals))
 }
 
+/// Purchase value and lots delivered by the outgoing security transfers of all transactions
+/// stored in the database with cash flow dates on or before `end`. Use this as `transfers` of
+/// a `PortfolioPosition` calculated for a selection of accounts, to take over the cost basis
+/// of transfers from accounts not included in the selection.
+pub async fn calc_transfer_basis(
+    currency: Currency,
+    end: Option<Date>,
+    cost_basis: Option<CostBasisMethod>,
+    db: &(dyn TransactionHandler + Send + Sync),
+    market: &Market,
+) -> Result<BTreeMap<i32, TransferBasis>, PositionError> {
+    let filter = TransactionFilter {
+        end,
+        ..Default::default()
+    };
+    let transactions = db.get_transactions(&filter).await?;
+    let mut positions = PortfolioPosition::new(currency);
+    positions.cost_basis = cost_basis;
+    calc_delta_position(&mut positions, &transactions, None, None, market.clone()).await?;
+    Ok(positions.transfers)
+}
+
+/// Calculate position and P&L for all transactions booked to a selected group of accounts,
+/// e.g. a single depot or all accounts of one owner. Transactions of all accounts are
+/// aggregated and valued as in `calculate_position_and_pnl`.
+pub async fn calculate_position_and_pnl_for_accounts(
+    currency: Currency,
+    account_ids: &[i32],
+    date: Option<Date>,
+    db: &(dyn TransactionHandler + Send + Sync),
+    market: &Market,
+) -> Result<(PortfolioPosition, PositionTotals), PositionError> {
+    let end = date.and_then(|date| date.previous_day());
+    let filter = TransactionFilter {
+        end,
+        account_ids: Some(account_ids.to_vec()),
+        ..Default::default()
+    };
+    let transactions = db.get_transactions(&filter).await?;
+    let mut position = PortfolioPosition::new(currency);
+    position.transfers = calc_transfer_basis(currency, end, None, db, market).await?;
+    position_and_pnl(position, &transactions, date, market).await
+}
+
 /// Calculate position and P&L changes for a given range of dates.
 /// The date range is inclusive, i.e. all transactions with cash flow dates on or after `start`
 /// and on or before `end` a taken into account. The initial positions at `start` are valued
@@ -417,26 +1210,21 @@ pub async fn calculate_position_for_period(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::assert_fuzzy_eq;
+
     use crate::datatypes::QuoteHandler;
     use crate::datatypes::{
-        date_time_helper::make_offset_time, Asset, AssetHandler, CashAmount, CashFlow, Currency,
-        CurrencyISOCode, Quote, Stock, Ticker,
+        date_time_helper::make_offset_time, Account, Asset, AssetHandler, CashAmount, CashFlow,
+        CorporateActionKind, CorporateActionTarget, CountryCode, Currency, CurrencyISOCode,
+        DistributionPolicy, Fund, FundConstituent, Quote, Stock, Ticker,
     };
-    use crate::postgres::PostgresDB;
+    use crate::memory::InMemoryDB;
+    use crate::tax::{shared_tax_model, GermanTax};
     use std::convert::TryFrom;
 
     #[tokio::test]
     async fn test_portfolio_position() {
         let tol = 1e-4;
-        // Setup database connection
-        let db_url = std::env::var("FINQL_TEST_DATABASE_URL");
-        assert!(
-            db_url.is_ok(),
-            "environment variable $FINQL_TEST_DATABASE_URL is not set"
-        );
-        let db = PostgresDB::new(&db_url.unwrap()).await.unwrap();
-        db.clean().await.unwrap();
+        let db = InMemoryDB::new();
 
         let market = Market::new(Arc::new(db)).await;
         let eur = market.get_currency_from_str("EUR").await.unwrap();
@@ -457,6 +1245,7 @@ mod tests {
                 date: Date::from_calendar_date(2020, time::Month::try_from(1).unwrap(), 1).unwrap(),
             },
             note: None,
+            account_id: None,
         });
         let positions = calc_position(eur, &transactions, None, market.clone())
             .await
@@ -478,6 +1267,7 @@ mod tests {
                 date: Date::from_calendar_date(2020, time::Month::try_from(1).unwrap(), 2).unwrap(),
             },
             note: None,
+            account_id: None,
         });
         transactions.push(Transaction {
             id: Some(3),
@@ -492,6 +1282,7 @@ mod tests {
                 date: Date::from_calendar_date(2020, time::Month::try_from(1).unwrap(), 2).unwrap(),
             },
             note: None,
+            account_id: None,
         });
         let positions = calc_position(eur, &transactions, None, market.clone())
             .await
@@ -519,6 +1310,7 @@ mod tests {
                     .unwrap(),
             },
             note: None,
+            account_id: None,
         });
         transactions.push(Transaction {
             id: Some(5),
@@ -534,11 +1326,14 @@ mod tests {
                     .unwrap(),
             },
             note: None,
+            account_id: None,
         });
         transactions.push(Transaction {
             id: Some(6),
             transaction_type: TransactionType::Tax {
                 transaction_ref: Some(4),
+                kind: TaxKind::Domestic,
+                country: None,
             },
             cash_flow: CashFlow {
                 amount: CashAmount {
@@ -549,6 +1344,7 @@ mod tests {
                     .unwrap(),
             },
             note: None,
+            account_id: None,
         });
         let positions = calc_position(eur, &transactions, None, market.clone())
             .await
@@ -581,6 +1377,7 @@ mod tests {
                     .unwrap(),
             },
             note: None,
+            account_id: None,
         });
         transactions.push(Transaction {
             id: Some(8),
@@ -596,11 +1393,14 @@ mod tests {
                     .unwrap(),
             },
             note: None,
+            account_id: None,
         });
         transactions.push(Transaction {
             id: Some(9),
             transaction_type: TransactionType::Tax {
                 transaction_ref: None,
+                kind: TaxKind::Domestic,
+                country: None,
             },
             cash_flow: CashFlow {
                 amount: CashAmount {
@@ -611,6 +1411,7 @@ mod tests {
                     .unwrap(),
             },
             note: None,
+            account_id: None,
         });
         transactions.push(Transaction {
             id: Some(10),
@@ -624,6 +1425,7 @@ mod tests {
                     .unwrap(),
             },
             note: None,
+            account_id: None,
         });
         transactions.push(Transaction {
             id: Some(11),
@@ -637,6 +1439,7 @@ mod tests {
                     .unwrap(),
             },
             note: None,
+            account_id: None,
         });
         let positions = calc_position(eur, &transactions, None, market.clone())
             .await
@@ -669,14 +1472,7 @@ mod tests {
         use crate::datatypes::DataItem;
 
         let tol = 1e-4;
-        // Setup database connection
-        let db_url = std::env::var("FINQL_TEST_DATABASE_URL");
-        assert!(
-            db_url.is_ok(),
-            "environment variable $FINQL_TEST_DATABASE_URL is not set"
-        );
-        let db = PostgresDB::new(&db_url.unwrap()).await.unwrap();
-        db.clean().await.unwrap();
+        let db = InMemoryDB::new();
 
         // first add some assets and currencies
         let eur_stock_id = db
@@ -795,4 +1591,1063 @@ mod tests {
             "2019-12-30T10:00:00"
         );
     }
+
+    #[tokio::test]
+    async fn test_account_positions() {
+        let tol = 1e-4;
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let depot = db
+            .insert_account(&Account::new(
+                None,
+                "Depot".to_string(),
+                eur,
+                Some("Alice".to_string()),
+                Some("Some Bank".to_string()),
+            ))
+            .await
+            .unwrap();
+        let savings = db
+            .insert_account(&Account::new(
+                None,
+                "Savings plan".to_string(),
+                eur,
+                Some("Bob".to_string()),
+                None,
+            ))
+            .await
+            .unwrap();
+        let asset_id = db
+            .insert_asset(&Asset::Stock(Stock::new(
+                None,
+                "BASF AG".to_string(),
+                None,
+                None,
+                None,
+            )))
+            .await
+            .unwrap();
+        let date = |day| Date::from_calendar_date(2020, time::Month::January, day).unwrap();
+        let transaction = |transaction_type, amount, day, account_id| Transaction {
+            id: None,
+            transaction_type,
+            cash_flow: CashFlow::new(amount, eur, date(day)),
+            note: None,
+            account_id,
+        };
+        db.insert_transaction(&transaction(TransactionType::Cash, 1000.0, 1, Some(depot)))
+            .await
+            .unwrap();
+        let buy_id = db
+            .insert_transaction(&transaction(
+                TransactionType::Asset {
+                    asset_id,
+                    position: 10.0,
+                },
+                -500.0,
+                2,
+                Some(depot),
+            ))
+            .await
+            .unwrap();
+        db.insert_transaction(&transaction(
+            TransactionType::Fee {
+                transaction_ref: Some(buy_id),
+            },
+            -5.0,
+            2,
+            Some(depot),
+        ))
+        .await
+        .unwrap();
+        db.insert_transaction(&transaction(TransactionType::Cash, 200.0, 3, Some(savings)))
+            .await
+            .unwrap();
+        db.insert_transaction(&transaction(TransactionType::Cash, 50.0, 3, None))
+            .await
+            .unwrap();
+
+        let depot_transactions = db
+            .get_transactions_for_account(depot, Some(date(2)), None)
+            .await
+            .unwrap();
+        assert_eq!(depot_transactions.len(), 2);
+        assert!(db.delete_account(savings).await.is_err());
+
+        let (position, _) =
+            calculate_position_and_pnl_for_accounts(eur, &[depot], Some(date(10)), &db, &market)
+                .await
+                .unwrap();
+        assert_fuzzy_eq!(position.cash.position, 495.0, tol);
+        let asset_position = position.assets.get(&asset_id).unwrap();
+        assert_fuzzy_eq!(asset_position.position, 10.0, tol);
+        assert_fuzzy_eq!(asset_position.fees, -5.0, tol);
+
+        let (position, _) = calculate_position_and_pnl_for_accounts(
+            eur,
+            &[depot, savings],
+            Some(date(10)),
+            &db,
+            &market,
+        )
+        .await
+        .unwrap();
+        assert_fuzzy_eq!(position.cash.position, 695.0, tol);
+
+        let filter = TransactionFilter {
+            asset_id: Some(asset_id),
+            ..Default::default()
+        };
+        let mut position = PortfolioPosition::new(eur);
+        calc_delta_position_from_db(&mut position, &filter, None, Some(date(3)), &db, market)
+            .await
+            .unwrap();
+        assert_fuzzy_eq!(position.cash.position, -505.0, tol);
+        assert_fuzzy_eq!(position.assets.get(&asset_id).unwrap().fees, -5.0, tol);
+    }
+
+    #[tokio::test]
+    async fn delta_position_from_db_resolves_references_before_start() {
+        let tol = 1e-4;
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let asset_id = db
+            .insert_asset(&Asset::Stock(Stock::new(
+                None,
+                "BASF AG".to_string(),
+                None,
+                None,
+                None,
+            )))
+            .await
+            .unwrap();
+        let date = |day| Date::from_calendar_date(2020, time::Month::January, day).unwrap();
+        let transaction = |transaction_type, amount, day| Transaction {
+            id: None,
+            transaction_type,
+            cash_flow: CashFlow::new(amount, eur, date(day)),
+            note: None,
+            account_id: None,
+        };
+        let buy_id = db
+            .insert_transaction(&transaction(
+                TransactionType::Asset {
+                    asset_id,
+                    position: 10.0,
+                },
+                -500.0,
+                2,
+            ))
+            .await
+            .unwrap();
+        let dividend_id = db
+            .insert_transaction(&transaction(
+                TransactionType::Dividend { asset_id },
+                20.0,
+                3,
+            ))
+            .await
+            .unwrap();
+        // fee and tax are booked after the transactions they refer to
+        db.insert_transaction(&transaction(
+            TransactionType::Fee {
+                transaction_ref: Some(buy_id),
+            },
+            -5.0,
+            5,
+        ))
+        .await
+        .unwrap();
+        db.insert_transaction(&transaction(
+            TransactionType::Tax {
+                transaction_ref: Some(dividend_id),
+                kind: TaxKind::Domestic,
+                country: None,
+            },
+            -3.0,
+            5,
+        ))
+        .await
+        .unwrap();
+
+        let mut position = PortfolioPosition::new(eur);
+        calc_delta_position_from_db(
+            &mut position,
+            &TransactionFilter::default(),
+            Some(date(4)),
+            Some(date(10)),
+            &db,
+            market,
+        )
+        .await
+        .unwrap();
+        assert_fuzzy_eq!(position.cash.position, -8.0, tol);
+        assert_fuzzy_eq!(position.cash.fees, 0.0, tol);
+        assert_fuzzy_eq!(position.cash.tax, 0.0, tol);
+        let asset_position = position.assets.get(&asset_id).unwrap();
+        assert_fuzzy_eq!(asset_position.position, 0.0, tol);
+        assert_fuzzy_eq!(asset_position.fees, -5.0, tol);
+        assert_fuzzy_eq!(asset_position.dividend_tax, -3.0, tol);
+    }
+
+    #[tokio::test]
+    async fn test_bond_position() {
+        let tol = 1e-4;
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let data = r#"{
+            "isin": "DE0001234567",
+            "bond_type": "bond",
+            "currency": "EUR",
+            "coupon" : {
+                "coupon_type": "fixed",
+                "rate": 5,
+                "coupon_date": "01.04",
+                "period": "6M",
+                "day_count_convention": "act/365"
+            },
+            "business_day_rule": "none",
+            "calendar": "TARGET",
+            "issue_date": [2019, 274],
+            "maturity": [2021, 274],
+            "denomination": 1000
+        }"#;
+        let bond: Bond = serde_json::from_str(data).unwrap();
+        let bond_id = db.insert_asset(&Asset::Bond(Box::new(bond))).await.unwrap();
+        match db.get_asset_by_isin("DE0001234567").await.unwrap() {
+            Asset::Bond(bond) => {
+                assert_eq!(bond.id, Some(bond_id));
+                assert_eq!(bond.currency().id, eur.id);
+            }
+            _ => panic!("expected a bond"),
+        }
+        let ticker_id = db
+            .insert_ticker(&Ticker {
+                id: None,
+                name: "DE0001234567".to_string(),
+                asset: bond_id,
+                priority: 10,
+                currency: eur,
+                source: "manual".to_string(),
+                factor: 1.0,
+                tz: None,
+                cal: None,
+            })
+            .await
+            .unwrap();
+        db.insert_quote(&Quote {
+            id: None,
+            ticker: ticker_id,
+            price: 101.0,
+            time: make_offset_time(2020, 1, 10, 10, 0, 0).unwrap(),
+            volume: None,
+        })
+        .await
+        .unwrap();
+        let transactions = vec![Transaction {
+            id: Some(1),
+            transaction_type: TransactionType::Asset {
+                asset_id: bond_id,
+                position: 10.0,
+            },
+            cash_flow: CashFlow::new(
+                -10_120.0,
+                eur,
+                Date::from_calendar_date(2020, time::Month::January, 2).unwrap(),
+            ),
+            note: None,
+            account_id: None,
+        }];
+        let date = Date::from_calendar_date(2020, time::Month::January, 15).unwrap();
+        let (position, totals) =
+            calculate_position_and_pnl(eur, &transactions, Some(date), &market)
+                .await
+                .unwrap();
+        // 106 days of the coupon period starting at 2019-10-01
+        let accrued_interest = 1000.0 * 0.05 * 106.0 / 365.0;
+        let bond_position = position.assets.get(&bond_id).unwrap();
+        assert_fuzzy_eq!(
+            bond_position.accrued_interest.unwrap(),
+            accrued_interest,
+            tol
+        );
+        assert_fuzzy_eq!(
+            bond_position.last_quote.unwrap(),
+            1010.0 + accrued_interest,
+            tol
+        );
+        assert_fuzzy_eq!(
+            totals.value,
+            -10_120.0 + 10.0 * (1010.0 + accrued_interest),
+            tol
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fund_look_through() {
+        let tol = 1e-4;
+        let db = InMemoryDB::new();
+        let eur = db
+            .get_or_new_currency(CurrencyISOCode::new("EUR").unwrap())
+            .await
+            .unwrap();
+        let stock = |name: &str| Asset::Stock(Stock::new(None, name.to_string(), None, None, None));
+        let basf_id = db.insert_asset(&stock("BASF AG")).await.unwrap();
+        let sap_id = db.insert_asset(&stock("SAP SE")).await.unwrap();
+        let mut fund = Fund::new(
+            None,
+            "DAX ETF".to_string(),
+            Some("DE0005933931".to_string()),
+            None,
+            Some(0.0015),
+            DistributionPolicy::Accumulating,
+            Some("DE".to_string()),
+        );
+        fund.constituents = vec![
+            FundConstituent {
+                asset_id: Some(basf_id),
+                name: "BASF AG".to_string(),
+                weight: 0.5,
+            },
+            FundConstituent {
+                asset_id: None,
+                name: "Siemens AG".to_string(),
+                weight: 0.3,
+            },
+        ];
+        let fund_id = db.insert_asset(&Asset::Fund(fund)).await.unwrap();
+        assert!(db.delete_asset(basf_id).await.is_err());
+
+        let mut portfolio = PortfolioPosition::new(eur);
+        for (asset_id, position, quote) in [
+            (basf_id, 10.0, 50.0),
+            (sap_id, 5.0, 100.0),
+            (fund_id, 20.0, 100.0),
+        ] {
+            let mut pos = Position::new(Some(asset_id), eur);
+            pos.position = position;
+            pos.last_quote = Some(quote);
+            portfolio.assets.insert(asset_id, pos);
+        }
+        let exposures = portfolio.look_through(Arc::new(db)).await.unwrap();
+        assert_eq!(exposures.len(), 4);
+        assert_eq!(exposures[0].asset_id, Some(basf_id));
+        assert_fuzzy_eq!(exposures[0].value, 1500.0, tol);
+        assert_fuzzy_eq!(exposures[0].weight, 0.5, tol);
+        assert_eq!(exposures[1].name, "Siemens AG");
+        assert_fuzzy_eq!(exposures[1].value, 600.0, tol);
+        assert_eq!(exposures[2].asset_id, Some(sap_id));
+        assert_eq!(exposures[3].asset_id, Some(fund_id));
+        assert_fuzzy_eq!(exposures[3].value, 400.0, tol);
+    }
+
+    #[tokio::test]
+    async fn test_split_position() {
+        let tol = 1e-4;
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let asset_id = db
+            .insert_asset(&Asset::Stock(Stock::new(
+                None,
+                "Apple Inc.".to_string(),
+                None,
+                None,
+                None,
+            )))
+            .await
+            .unwrap();
+        let ticker_id = db
+            .insert_ticker(&Ticker {
+                id: None,
+                name: "APC.DE".to_string(),
+                asset: asset_id,
+                priority: 10,
+                currency: eur,
+                source: "manual".to_string(),
+                factor: 1.0,
+                tz: None,
+                cal: None,
+            })
+            .await
+            .unwrap();
+        for (day, price) in [(28, 420.0), (31, 110.0)] {
+            db.insert_quote(&Quote {
+                id: None,
+                ticker: ticker_id,
+                price,
+                time: make_offset_time(2020, 8, day, 20, 0, 0).unwrap(),
+                volume: None,
+            })
+            .await
+            .unwrap();
+        }
+        let date = |day| Date::from_calendar_date(2020, time::Month::August, day).unwrap();
+        let mut transactions = vec![
+            Transaction {
+                id: None,
+                transaction_type: TransactionType::Asset {
+                    asset_id,
+                    position: 10.0,
+                },
+                cash_flow: CashFlow::new(-4_000.0, eur, date(3)),
+                note: None,
+                account_id: None,
+            },
+            Transaction {
+                id: None,
+                transaction_type: TransactionType::Split {
+                    asset_id,
+                    ratio: 4.0,
+                },
+                cash_flow: CashFlow::new(0.0, eur, date(31)),
+                note: None,
+                account_id: None,
+            },
+        ];
+        for trans in transactions.iter_mut() {
+            trans.id = Some(db.insert_transaction(trans).await.unwrap());
+        }
+        // a market without the stored splits returns unadjusted quotes
+        let time = make_offset_time(2020, 8, 29, 10, 0, 0).unwrap();
+        let price = market
+            .get_split_adjusted_asset_price(asset_id, eur, time)
+            .await
+            .unwrap();
+        assert_fuzzy_eq!(price, 420.0, tol);
+        let market = Market::new_with_splits(Arc::new(db.clone()), &db)
+            .await
+            .unwrap();
+
+        // valued at the ex-date, before the first quote after the split is available
+        let (position, totals) =
+            calculate_position_and_pnl(eur, &transactions, Some(date(31)), &market)
+                .await
+                .unwrap();
+        let pos = position.assets.get(&asset_id).unwrap();
+        assert_fuzzy_eq!(pos.position, 10.0, tol);
+        assert_fuzzy_eq!(pos.last_quote.unwrap(), 420.0, tol);
+        let (position, totals_after_split) =
+            calculate_position_and_pnl(eur, &transactions, date(31).next_day(), &market)
+                .await
+                .unwrap();
+        let pos = position.assets.get(&asset_id).unwrap();
+        assert_fuzzy_eq!(pos.position, 40.0, tol);
+        assert_fuzzy_eq!(pos.purchase_value, -4_000.0, tol);
+        // the split itself does not change the portfolio's value
+        assert_fuzzy_eq!(totals.value, -4_000.0 + 4_200.0, tol);
+        assert_fuzzy_eq!(totals_after_split.value, -4_000.0 + 4_400.0, tol);
+
+        // quote of the last trading day before the split is adjusted at the ex-date
+        let time = make_offset_time(2020, 8, 31, 10, 0, 0).unwrap();
+        let price = market.get_asset_price(asset_id, eur, time).await.unwrap();
+        assert_fuzzy_eq!(price, 105.0, tol);
+        // split-adjusted history
+        let time = make_offset_time(2020, 8, 29, 10, 0, 0).unwrap();
+        let price = market.get_asset_price(asset_id, eur, time).await.unwrap();
+        assert_fuzzy_eq!(price, 420.0, tol);
+        let price = market
+            .get_split_adjusted_asset_price(asset_id, eur, time)
+            .await
+            .unwrap();
+        assert_fuzzy_eq!(price, 105.0, tol);
+    }
+
+    #[tokio::test]
+    async fn test_corporate_actions() {
+        let tol = 1e-4;
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let stock = |name: &str| Asset::Stock(Stock::new(None, name.to_string(), None, None, None));
+        let parent_id = db.insert_asset(&stock("Parent AG")).await.unwrap();
+        let spin_off_id = db.insert_asset(&stock("Spin-off AG")).await.unwrap();
+        let acquirer_id = db.insert_asset(&stock("Acquirer Inc.")).await.unwrap();
+        let date = |month, day| {
+            Date::from_calendar_date(2021, time::Month::try_from(month).unwrap(), day).unwrap()
+        };
+        let action = |kind, asset_id, date, targets| {
+            let mut action = CorporateAction::new(None, kind, asset_id, date);
+            action.targets = targets;
+            action
+        };
+        let target = |asset_id, ratio, cost_share| CorporateActionTarget {
+            asset_id,
+            ratio,
+            cost_share,
+        };
+        // a merger must allocate the full purchase value
+        assert!(db
+            .insert_corporate_action(&action(
+                CorporateActionKind::Merger,
+                spin_off_id,
+                date(6, 1),
+                vec![target(acquirer_id, 2.0, 0.5)],
+            ))
+            .await
+            .is_err());
+        for corporate_action in [
+            action(
+                CorporateActionKind::Merger,
+                spin_off_id,
+                date(6, 1),
+                vec![target(acquirer_id, 2.0, 1.0)],
+            ),
+            action(
+                CorporateActionKind::SpinOff,
+                parent_id,
+                date(3, 1),
+                vec![target(spin_off_id, 0.5, 0.2)],
+            ),
+            action(
+                CorporateActionKind::StockDividend,
+                parent_id,
+                date(4, 1),
+                vec![target(parent_id, 0.1, 0.0)],
+            ),
+        ] {
+            db.insert_corporate_action(&corporate_action).await.unwrap();
+        }
+        assert!(db.delete_asset(acquirer_id).await.is_err());
+        let trade = |id, position, amount, date| Transaction {
+            id: Some(id),
+            transaction_type: TransactionType::Asset {
+                asset_id: if id == 1 { parent_id } else { acquirer_id },
+                position,
+            },
+            cash_flow: CashFlow::new(amount, eur, date),
+            note: None,
+            account_id: None,
+        };
+        let transactions = vec![
+            trade(1, 100.0, -10_000.0, date(1, 4)),
+            trade(2, -50.0, 1_500.0, date(7, 1)),
+        ];
+
+        // before the merger
+        let positions = calc_position(eur, &transactions, Some(date(6, 1)), market.clone())
+            .await
+            .unwrap();
+        let parent = positions.assets.get(&parent_id).unwrap();
+        assert_fuzzy_eq!(parent.position, 110.0, tol);
+        assert_fuzzy_eq!(parent.purchase_value, -8_000.0, tol);
+        let spin_off = positions.assets.get(&spin_off_id).unwrap();
+        assert_fuzzy_eq!(spin_off.position, 50.0, tol);
+        assert_fuzzy_eq!(spin_off.purchase_value, -2_000.0, tol);
+        assert!(!positions.assets.contains_key(&acquirer_id));
+
+        let positions = calc_position(eur, &transactions, None, market.clone())
+            .await
+            .unwrap();
+        let spin_off = positions.assets.get(&spin_off_id).unwrap();
+        assert_fuzzy_eq!(spin_off.position, 0.0, tol);
+        assert_fuzzy_eq!(spin_off.purchase_value, 0.0, tol);
+        let acquirer = positions.assets.get(&acquirer_id).unwrap();
+        assert_fuzzy_eq!(acquirer.position, 50.0, tol);
+        assert_fuzzy_eq!(acquirer.purchase_value, -1_000.0, tol);
+        // only the sale realizes a profit
+        assert_fuzzy_eq!(acquirer.trading_pnl, 500.0, tol);
+        assert_fuzzy_eq!(
+            positions.assets.get(&parent_id).unwrap().trading_pnl,
+            0.0,
+            tol
+        );
+        assert_fuzzy_eq!(spin_off.trading_pnl, 0.0, tol);
+    }
+
+    #[tokio::test]
+    async fn test_cost_basis_methods() {
+        let tol = 1e-4;
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let asset_id = db
+            .insert_asset(&Asset::Stock(Stock::new(
+                None,
+                "BASF AG".to_string(),
+                None,
+                None,
+                None,
+            )))
+            .await
+            .unwrap();
+        let trade = |id, month, position, amount| Transaction {
+            id: Some(id),
+            transaction_type: TransactionType::Asset { asset_id, position },
+            cash_flow: CashFlow::new(
+                amount,
+                eur,
+                Date::from_calendar_date(2021, time::Month::try_from(month).unwrap(), 10).unwrap(),
+            ),
+            note: None,
+            account_id: None,
+        };
+        let mut transactions = vec![
+            trade(1, 1, 10.0, -1_000.0),
+            trade(2, 2, 10.0, -1_200.0),
+            trade(3, 3, 10.0, -1_100.0),
+            trade(4, 4, -15.0, 1_950.0),
+        ];
+        for (method, pnl, open_lots) in [
+            (CostBasisMethod::Fifo, 350.0, vec![(2, 5.0), (3, 10.0)]),
+            (CostBasisMethod::Lifo, 250.0, vec![(1, 10.0), (2, 5.0)]),
+            (
+                CostBasisMethod::HighestCost,
+                200.0,
+                vec![(1, 10.0), (3, 5.0)],
+            ),
+            (
+                CostBasisMethod::Average,
+                300.0,
+                vec![(1, 5.0), (2, 5.0), (3, 5.0)],
+            ),
+        ] {
+            let mut positions = PortfolioPosition::with_cost_basis(eur, method);
+            calc_delta_position(&mut positions, &transactions, None, None, market.clone())
+                .await
+                .unwrap();
+            let pos = positions.assets.get(&asset_id).unwrap();
+            assert_fuzzy_eq!(pos.trading_pnl, pnl, tol);
+            assert_fuzzy_eq!(pos.position, 15.0, tol);
+            assert_fuzzy_eq!(pos.purchase_value, -3_300.0 + 1_950.0 - pnl, tol);
+            assert_eq!(pos.lots.len(), open_lots.len());
+            for (lot, (id, position)) in pos.lots.iter().zip(open_lots) {
+                assert_eq!(lot.transaction_id, Some(id));
+                assert_fuzzy_eq!(lot.position, position, tol);
+            }
+            let gains = positions.realized_gains();
+            let total: f64 = gains.iter().map(|gain| gain.pnl).sum();
+            assert_fuzzy_eq!(total, pnl, tol);
+            assert!(gains.iter().all(|gain| gain.transaction_id == Some(4)));
+        }
+
+        // selling more than the open position opens a short lot
+        transactions.push(trade(5, 5, -20.0, 2_000.0));
+        let mut positions = PortfolioPosition::with_cost_basis(eur, CostBasisMethod::Fifo);
+        calc_delta_position(&mut positions, &transactions, None, None, market.clone())
+            .await
+            .unwrap();
+        let pos = positions.assets.get(&asset_id).unwrap();
+        assert_fuzzy_eq!(pos.trading_pnl, 350.0 - 200.0, tol);
+        assert_fuzzy_eq!(pos.position, -5.0, tol);
+        assert_fuzzy_eq!(pos.purchase_value, 500.0, tol);
+        assert_eq!(pos.lots.len(), 1);
+        assert_eq!(pos.lots[0].transaction_id, Some(5));
+        assert_fuzzy_eq!(pos.lots[0].purchase_value, 500.0, tol);
+        assert_eq!(pos.realized_gains.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_tax_on_sales() {
+        let tol = 1e-4;
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let asset_id = db
+            .insert_asset(&Asset::Stock(Stock::new(
+                None,
+                "BASF AG".to_string(),
+                None,
+                None,
+                None,
+            )))
+            .await
+            .unwrap();
+        let trade = |id, month, position, amount| Transaction {
+            id: Some(id),
+            transaction_type: TransactionType::Asset { asset_id, position },
+            cash_flow: CashFlow::new(
+                amount,
+                eur,
+                Date::from_calendar_date(2021, time::Month::try_from(month).unwrap(), 10).unwrap(),
+            ),
+            note: None,
+            account_id: None,
+        };
+        let transactions = [
+            trade(1, 1, 10.0, -1_000.0),
+            trade(2, 2, -5.0, 750.0),
+            trade(3, 3, -5.0, 400.0),
+        ];
+        let mut positions = PortfolioPosition::new(eur);
+        positions.tax_model = Some(shared_tax_model(GermanTax::new(100.0)));
+        calc_delta_position(
+            &mut positions,
+            &transactions[..2],
+            None,
+            None,
+            market.clone(),
+        )
+        .await
+        .unwrap();
+        let tax = (250.0 - 100.0) * 0.26375;
+        assert_fuzzy_eq!(positions.assets[&asset_id].tax, -tax, tol);
+        assert_fuzzy_eq!(positions.cash.position, -250.0 - tax, tol);
+        // the loss of the second sale refunds a part of the tax paid
+        calc_delta_position(
+            &mut positions,
+            &transactions[2..],
+            None,
+            None,
+            market.clone(),
+        )
+        .await
+        .unwrap();
+        let tax = (250.0 - 100.0 - 100.0) * 0.26375;
+        assert_fuzzy_eq!(positions.assets[&asset_id].tax, -tax, tol);
+        assert_fuzzy_eq!(positions.cash.position, 150.0 - tax, tol);
+    }
+
+    #[tokio::test]
+    async fn test_vorabpauschale() {
+        let tol = 1e-4;
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let fund_id = db
+            .insert_asset(&Asset::Fund(Fund::new(
+                None,
+                "MSCI World ETF".to_string(),
+                None,
+                None,
+                None,
+                DistributionPolicy::Accumulating,
+                None,
+            )))
+            .await
+            .unwrap();
+        let ticker = db
+            .insert_ticker(&Ticker {
+                id: None,
+                asset: fund_id,
+                name: "EUNL.DE".to_string(),
+                currency: eur,
+                source: "manual".to_string(),
+                priority: 1,
+                factor: 1.0,
+                tz: None,
+                cal: None,
+            })
+            .await
+            .unwrap();
+        for (year, day, price) in [(2022, 30, 100.0), (2023, 29, 110.0)] {
+            db.insert_quote(&Quote {
+                id: None,
+                ticker,
+                price,
+                time: make_offset_time(year, 12, day, 18, 0, 0).unwrap(),
+                volume: None,
+            })
+            .await
+            .unwrap();
+        }
+        let buy = |id, date, amount| Transaction {
+            id: Some(id),
+            transaction_type: TransactionType::Asset {
+                asset_id: fund_id,
+                position: 10.0,
+            },
+            cash_flow: CashFlow::new(amount, eur, date),
+            note: None,
+            account_id: None,
+        };
+        let transactions = [
+            buy(
+                1,
+                Date::from_calendar_date(2022, Month::June, 1).unwrap(),
+                -900.0,
+            ),
+            buy(
+                2,
+                Date::from_calendar_date(2023, Month::April, 15).unwrap(),
+                -1_000.0,
+            ),
+        ];
+        let mut positions = PortfolioPosition::with_cost_basis(eur, CostBasisMethod::Fifo);
+        positions.tax_model = Some(shared_tax_model(
+            GermanTax::new(0.0)
+                .with_base_rate(2023, 0.0255)
+                .with_partial_exemption(0.3),
+        ));
+        calc_delta_position(&mut positions, &transactions, None, None, market.clone())
+            .await
+            .unwrap();
+        positions.apply_vorabpauschale(2023, &market).await.unwrap();
+        // 70% of the base return of 2.55% after partial exemption of 30%, the units bought in
+        // April are held for nine months
+        let lump_sum = 10.0 * 1.2495 + 10.0 * 1.2495 * 9.0 / 12.0;
+        let tax = lump_sum * 0.26375;
+        assert_fuzzy_eq!(positions.assets[&fund_id].tax, -tax, tol);
+        assert_fuzzy_eq!(positions.cash.position, -1_900.0 - tax, tol);
+        assert_fuzzy_eq!(positions.assets[&fund_id].vorabpauschale, lump_sum, tol);
+        // no base rate is given for 2022
+        positions.apply_vorabpauschale(2022, &market).await.unwrap();
+        assert_fuzzy_eq!(positions.assets[&fund_id].tax, -tax, tol);
+
+        // the Vorabpauschale taxed on the sold half of the units reduces the taxable gain
+        let sale = Transaction {
+            id: Some(3),
+            transaction_type: TransactionType::Asset {
+                asset_id: fund_id,
+                position: -10.0,
+            },
+            cash_flow: CashFlow::new(
+                1_200.0,
+                eur,
+                Date::from_calendar_date(2024, Month::March, 1).unwrap(),
+            ),
+            note: None,
+            account_id: None,
+        };
+        calc_delta_position(&mut positions, &[sale], None, None, market.clone())
+            .await
+            .unwrap();
+        let sale_tax = (300.0 - lump_sum / 2.0) * 0.26375;
+        assert_fuzzy_eq!(positions.assets[&fund_id].tax, -tax - sale_tax, tol);
+        assert_fuzzy_eq!(
+            positions.assets[&fund_id].vorabpauschale,
+            lump_sum / 2.0,
+            tol
+        );
+        assert_fuzzy_eq!(positions.cash.position, -700.0 - tax - sale_tax, tol);
+    }
+
+    #[tokio::test]
+    async fn test_foreign_dividend_taxes() {
+        let tol = 1e-4;
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let asset_id = db
+            .insert_asset(&Asset::Stock(Stock::new(
+                None,
+                "Nestle SA".to_string(),
+                None,
+                None,
+                None,
+            )))
+            .await
+            .unwrap();
+        let date = Date::from_calendar_date(2021, time::Month::April, 20).unwrap();
+        let transaction = |id, transaction_type, amount| Transaction {
+            id: Some(id),
+            transaction_type,
+            cash_flow: CashFlow::new(amount, eur, date),
+            note: None,
+            account_id: None,
+        };
+        let tax = |id, kind, amount| {
+            transaction(
+                id,
+                TransactionType::Tax {
+                    transaction_ref: Some(1),
+                    kind,
+                    country: CountryCode::new("CH").ok(),
+                },
+                amount,
+            )
+        };
+        // 35% Swiss withholding tax, 15% are credited and 20% can be reclaimed
+        let mut transactions = vec![
+            transaction(1, TransactionType::Dividend { asset_id }, 100.0),
+            tax(2, TaxKind::Withholding, -15.0),
+            tax(3, TaxKind::Reclaimable, -20.0),
+            tax(4, TaxKind::Credited, 15.0),
+            transaction(
+                5,
+                TransactionType::Tax {
+                    transaction_ref: Some(1),
+                    kind: TaxKind::Domestic,
+                    country: None,
+                },
+                -26.375,
+            ),
+        ];
+        let positions = calc_position(eur, &transactions, None, market.clone())
+            .await
+            .unwrap();
+        let pos = &positions.assets[&asset_id];
+        assert_fuzzy_eq!(pos.tax, -46.375, tol);
+        assert_fuzzy_eq!(pos.net_dividend(), 53.625, tol);
+        assert_fuzzy_eq!(pos.credited_tax, 15.0, tol);
+        assert_fuzzy_eq!(pos.outstanding_reclaims, 20.0, tol);
+
+        // refund of the reclaimed tax
+        transactions.push(tax(6, TaxKind::Reclaimable, 20.0));
+        let mut positions = calc_position(eur, &transactions, None, market.clone())
+            .await
+            .unwrap();
+        let totals = positions.calc_totals();
+        assert_fuzzy_eq!(totals.net_dividend, 73.625, tol);
+        assert_fuzzy_eq!(totals.credited_tax, 15.0, tol);
+        assert_fuzzy_eq!(totals.outstanding_reclaims, 0.0, tol);
+        assert_fuzzy_eq!(totals.value, 73.625, tol);
+    }
+
+    #[tokio::test]
+    async fn test_transfers() {
+        let tol = 1e-4;
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let asset_id = db
+            .insert_asset(&Asset::Stock(Stock::new(
+                None,
+                "BASF AG".to_string(),
+                None,
+                None,
+                None,
+            )))
+            .await
+            .unwrap();
+        let date = |month| {
+            Date::from_calendar_date(2021, time::Month::try_from(month).unwrap(), 10).unwrap()
+        };
+        let transaction = |id, transaction_type, amount, month, account_id| Transaction {
+            id: Some(id),
+            transaction_type,
+            cash_flow: CashFlow::new(amount, eur, date(month)),
+            note: None,
+            account_id: Some(account_id),
+        };
+        let transfer = |position, transaction_ref, acquisition_date| TransactionType::Transfer {
+            asset_id: Some(asset_id),
+            position,
+            transaction_ref,
+            acquisition_date,
+        };
+        let early = Date::from_calendar_date(2019, time::Month::June, 1).unwrap();
+        let transactions = [
+            transaction(
+                1,
+                TransactionType::Asset {
+                    asset_id,
+                    position: 10.0,
+                },
+                -1_000.0,
+                1,
+                1,
+            ),
+            transaction(
+                2,
+                TransactionType::Asset {
+                    asset_id,
+                    position: 10.0,
+                },
+                -1_200.0,
+                2,
+                1,
+            ),
+            // move 15 units from depot 1 to depot 2
+            transaction(3, transfer(-15.0, None, None), 0.0, 3, 1),
+            transaction(4, transfer(15.0, Some(3), None), -1_600.0, 3, 2),
+            // deliver units bought elsewhere into depot 2
+            transaction(5, transfer(5.0, None, Some(early)), -400.0, 3, 2),
+            transaction(
+                6,
+                TransactionType::Asset {
+                    asset_id,
+                    position: -25.0,
+                },
+                3_000.0,
+                4,
+                2,
+            ),
+        ];
+
+        let mut positions = PortfolioPosition::with_cost_basis(eur, CostBasisMethod::Fifo);
+        calc_delta_position(
+            &mut positions,
+            &transactions[..5],
+            None,
+            None,
+            market.clone(),
+        )
+        .await
+        .unwrap();
+        let pos = &positions.assets[&asset_id];
+        assert_fuzzy_eq!(pos.position, 25.0, tol);
+        assert_fuzzy_eq!(pos.purchase_value, -2_600.0, tol);
+        assert_fuzzy_eq!(pos.trading_pnl, 0.0, tol);
+        assert_fuzzy_eq!(positions.cash.position, -2_200.0, tol);
+        assert_eq!(pos.lots.len(), 4);
+
+        // units keep their acquisition dates
+        calc_delta_position(
+            &mut positions,
+            &transactions[5..],
+            None,
+            None,
+            market.clone(),
+        )
+        .await
+        .unwrap();
+        let gains = positions.realized_gains();
+        assert_fuzzy_eq!(gains.iter().map(|gain| gain.pnl).sum::<f64>(), 400.0, tol);
+        let mut lot_dates: Vec<Date> = gains.iter().map(|gain| gain.lot_date).collect();
+        lot_dates.sort();
+        assert_eq!(lot_dates, vec![early, date(1), date(2), date(2)]);
+
+        // depots on their own
+        let depot_1: Vec<Transaction> = transactions[..3].to_vec();
+        let positions = calc_position(eur, &depot_1, None, market.clone())
+            .await
+            .unwrap();
+        let pos = &positions.assets[&asset_id];
+        assert_fuzzy_eq!(pos.position, 5.0, tol);
+        assert_fuzzy_eq!(pos.purchase_value, -550.0, tol);
+        assert_fuzzy_eq!(pos.trading_pnl, 0.0, tol);
+        let depot_2: Vec<Transaction> = transactions[3..5].to_vec();
+        let positions = calc_position(eur, &depot_2, None, market.clone())
+            .await
+            .unwrap();
+        let pos = &positions.assets[&asset_id];
+        assert_fuzzy_eq!(pos.position, 20.0, tol);
+        assert_fuzzy_eq!(pos.purchase_value, -2_000.0, tol);
+        assert_fuzzy_eq!(positions.cash.position, 0.0, tol);
+
+        // legs of a transfer processed by different calls keep the lots
+        let mut positions = PortfolioPosition::with_cost_basis(eur, CostBasisMethod::Fifo);
+        for transactions in [&transactions[..3], &transactions[3..]].iter() {
+            calc_delta_position(&mut positions, transactions, None, None, market.clone())
+                .await
+                .unwrap();
+        }
+        let gains = positions.realized_gains();
+        assert_fuzzy_eq!(gains.iter().map(|gain| gain.pnl).sum::<f64>(), 400.0, tol);
+        assert_eq!(gains.len(), 4);
+
+        // the delivering depot is not part of the selected accounts
+        let account = |name: &str| Account::new(None, name.to_string(), eur, None, None);
+        let depot_1 = db.insert_account(&account("Depot 1")).await.unwrap();
+        let depot_2 = db.insert_account(&account("Depot 2")).await.unwrap();
+        let mut ids = BTreeMap::new();
+        for trans in transactions.iter() {
+            let mut trans = trans.clone();
+            trans.account_id = Some(if trans.account_id == Some(1) {
+                depot_1
+            } else {
+                depot_2
+            });
+            if let TransactionType::Transfer {
+                transaction_ref: Some(ref mut id),
+                ..
+            } = trans.transaction_type
+            {
+                *id = ids[id];
+            }
+            let old_id = trans.id.take().unwrap();
+            ids.insert(old_id, db.insert_transaction(&trans).await.unwrap());
+        }
+        let (positions, _) =
+            calculate_position_and_pnl_for_accounts(eur, &[depot_2], Some(date(4)), &db, &market)
+                .await
+                .unwrap();
+        let pos = &positions.assets[&asset_id];
+        assert_fuzzy_eq!(pos.position, 20.0, tol);
+        assert_fuzzy_eq!(pos.purchase_value, -1_650.0 - 400.0, tol);
+    }
 }
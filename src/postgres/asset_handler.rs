@@ -1,28 +1,123 @@
 use async_trait::async_trait;
+use sqlx::{Postgres, Transaction};
+use std::str::FromStr;
+use time::Date;
 
 use crate::datatypes::{
-    Asset, AssetHandler, AssetSelector, Currency, CurrencyISOCode, DataError, DataItem, Stock,
+    Asset, AssetHandler, AssetSelector, Commodity, CorporateAction, CorporateActionKind,
+    CorporateActionTarget, Crypto, Currency, CurrencyISOCode, DataError, DataItem,
+    DistributionPolicy, Fund, FundConstituent, Identifier, IdentifierType, Stock,
 };
 
 use super::PostgresDB;
+use crate::bond::Bond;
 
 /// helper struct
 struct ID {
     id: i32,
 }
 
+/// Store the constituents of a fund, replacing all previously stored constituents
+async fn store_fund_constituents(
+    tx: &mut Transaction<'_, Postgres>,
+    fund_id: i32,
+    constituents: &[FundConstituent],
+) -> Result<(), DataError> {
+    sqlx::query!("DELETE FROM fund_constituents WHERE fund_id=$1", fund_id)
+        .execute(&mut **tx)
+        .await?;
+    for constituent in constituents {
+        sqlx::query!(
+            "INSERT INTO fund_constituents (fund_id, asset_id, name, weight)
+                VALUES ($1, $2, $3, $4)",
+            fund_id,
+            constituent.asset_id,
+            constituent.name,
+            constituent.weight,
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Store the target assets of a corporate action, replacing all previously stored targets
+async fn store_corporate_action_targets(
+    tx: &mut Transaction<'_, Postgres>,
+    action_id: i32,
+    targets: &[CorporateActionTarget],
+) -> Result<(), DataError> {
+    sqlx::query!(
+        "DELETE FROM corporate_action_targets WHERE action_id=$1",
+        action_id
+    )
+    .execute(&mut **tx)
+    .await?;
+    for target in targets {
+        sqlx::query!(
+            "INSERT INTO corporate_action_targets (action_id, asset_id, ratio, cost_share)
+                VALUES ($1, $2, $3, $4)",
+            action_id,
+            target.asset_id,
+            target.ratio,
+            target.cost_share,
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
+
+impl PostgresDB {
+    async fn get_fund_constituents(&self, fund_id: i32) -> Result<Vec<FundConstituent>, DataError> {
+        Ok(sqlx::query!(
+            "SELECT asset_id, name, weight FROM fund_constituents WHERE fund_id=$1 ORDER BY id",
+            fund_id,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| FundConstituent {
+            asset_id: row.asset_id,
+            name: row.name,
+            weight: row.weight,
+        })
+        .collect())
+    }
+
+    async fn get_corporate_action_targets(
+        &self,
+        action_id: i32,
+    ) -> Result<Vec<CorporateActionTarget>, DataError> {
+        Ok(sqlx::query!(
+            "SELECT asset_id, ratio, cost_share FROM corporate_action_targets
+            WHERE action_id=$1 ORDER BY id",
+            action_id,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| CorporateActionTarget {
+            asset_id: row.asset_id,
+            ratio: row.ratio,
+            cost_share: row.cost_share,
+        })
+        .collect())
+    }
+}
+
 /// Handler for globally available Asset data
 #[async_trait]
 impl AssetHandler for PostgresDB {
     async fn insert_asset(&self, asset: &Asset) -> Result<i32, DataError> {
         let asset = asset.to_owned();
         // begin transaction
-        let tx = self.pool.begin().await?;
+        let mut tx = self.pool.begin().await?;
         let row = sqlx::query!(
             "INSERT INTO assets (asset_class) VALUES ($1) RETURNING id",
             asset.class(),
         )
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *tx)
         .await?;
         let id = row.id;
 
@@ -34,7 +129,7 @@ impl AssetHandler for PostgresDB {
                     c.iso_code.to_string(),
                     c.rounding_digits,
                 )
-                .execute(&self.pool)
+                .execute(&mut *tx)
                 .await?;
                 tx.commit().await?;
                 Ok(id)
@@ -48,7 +143,73 @@ impl AssetHandler for PostgresDB {
                     s.wkn,
                     s.note
                 )
-                .execute(&self.pool)
+                .execute(&mut *tx)
+                .await?;
+                tx.commit().await?;
+                Ok(id)
+            }
+            Asset::Bond(b) => {
+                sqlx::query!(
+                    "INSERT INTO bonds (id, name, isin, issuer, currency_id, maturity, denomination, spec)
+                    VALUES ($1, $2, $3, $4, (SELECT id FROM currencies WHERE iso_code = $5), $6, $7, $8)",
+                    id,
+                    b.name(),
+                    b.isin(),
+                    b.issuer(),
+                    b.currency().iso_code.to_string(),
+                    b.maturity(),
+                    b.denomination as i64,
+                    serde_json::to_value(&b)?,
+                )
+                .execute(&mut *tx)
+                .await?;
+                tx.commit().await?;
+                Ok(id)
+            }
+            Asset::Fund(f) => {
+                sqlx::query!(
+                    "INSERT INTO funds (id, name, isin, wkn, ter, distribution, domicile, note)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                    id,
+                    f.name,
+                    f.isin,
+                    f.wkn,
+                    f.ter,
+                    f.distribution.to_string(),
+                    f.domicile,
+                    f.note,
+                )
+                .execute(&mut *tx)
+                .await?;
+                store_fund_constituents(&mut tx, id, &f.constituents).await?;
+                tx.commit().await?;
+                Ok(id)
+            }
+            Asset::Crypto(c) => {
+                sqlx::query!(
+                    "INSERT INTO cryptos (id, name, symbol, chain, decimals, note)
+                    VALUES ($1, $2, $3, $4, $5, $6)",
+                    id,
+                    c.name,
+                    c.symbol,
+                    c.chain,
+                    c.decimals,
+                    c.note,
+                )
+                .execute(&mut *tx)
+                .await?;
+                tx.commit().await?;
+                Ok(id)
+            }
+            Asset::Commodity(c) => {
+                sqlx::query!(
+                    "INSERT INTO commodities (id, name, unit, note) VALUES ($1, $2, $3, $4)",
+                    id,
+                    c.name,
+                    c.unit,
+                    c.note,
+                )
+                .execute(&mut *tx)
                 .await?;
                 tx.commit().await?;
                 Ok(id)
@@ -84,6 +245,52 @@ impl AssetHandler for PostgresDB {
                         .ok()
                 }
             }
+            Asset::Bond(b) => {
+                if let Some(isin) = b.isin() {
+                    sqlx::query_as!(ID, "SELECT id FROM bonds WHERE isin = $1", isin)
+                        .fetch_one(&self.pool)
+                        .await
+                        .ok()
+                } else {
+                    sqlx::query_as!(ID, "SELECT id FROM bonds WHERE name = $1", b.name())
+                        .fetch_one(&self.pool)
+                        .await
+                        .ok()
+                }
+            }
+            Asset::Fund(f) => {
+                if let Some(wkn) = &f.wkn {
+                    sqlx::query_as!(ID, "SELECT id FROM funds WHERE wkn = $1", wkn)
+                        .fetch_one(&self.pool)
+                        .await
+                        .ok()
+                } else if let Some(isin) = &f.isin {
+                    sqlx::query_as!(ID, "SELECT id FROM funds WHERE isin = $1", isin)
+                        .fetch_one(&self.pool)
+                        .await
+                        .ok()
+                } else {
+                    sqlx::query_as!(ID, "SELECT id FROM funds WHERE name = $1", f.name)
+                        .fetch_one(&self.pool)
+                        .await
+                        .ok()
+                }
+            }
+            Asset::Crypto(c) => sqlx::query_as!(
+                ID,
+                "SELECT id FROM cryptos WHERE symbol = $1 AND chain IS NOT DISTINCT FROM $2",
+                c.symbol,
+                c.chain,
+            )
+            .fetch_one(&self.pool)
+            .await
+            .ok(),
+            Asset::Commodity(c) => {
+                sqlx::query_as!(ID, "SELECT id FROM commodities WHERE name = $1", c.name)
+                    .fetch_one(&self.pool)
+                    .await
+                    .ok()
+            }
         };
 
         id.map(|x| x.id)
@@ -143,32 +350,123 @@ impl AssetHandler for PostgresDB {
                     row.note,
                 )))
             }
+            "bond" => {
+                let row = sqlx::query!(
+                    r#"SELECT
+                        b.id,
+                        b.spec,
+                        c.id AS currency_id,
+                        c.iso_code,
+                        c.rounding_digits
+                     FROM bonds b
+                     JOIN currencies c ON c.id = b.currency_id
+                     WHERE b.id = $1"#,
+                    id,
+                )
+                .fetch_one(&self.pool)
+                .await?;
+
+                let mut bond: Bond = serde_json::from_value(row.spec)?;
+                bond.id = Some(row.id);
+                bond.currency = Currency::new(
+                    Some(row.currency_id),
+                    CurrencyISOCode::new(&row.iso_code)?,
+                    Some(row.rounding_digits),
+                );
+                Ok(Asset::Bond(Box::new(bond)))
+            }
+            "fund" => {
+                let row = sqlx::query!(
+                    r#"SELECT
+                        id,
+                        name,
+                        isin,
+                        wkn,
+                        ter,
+                        distribution,
+                        domicile,
+                        note
+                     FROM funds
+                     WHERE id = $1"#,
+                    id,
+                )
+                .fetch_one(&self.pool)
+                .await?;
+
+                Ok(Asset::Fund(Fund {
+                    id: Some(row.id),
+                    name: row.name,
+                    isin: row.isin,
+                    wkn: row.wkn,
+                    ter: row.ter,
+                    distribution: DistributionPolicy::from_str(&row.distribution)?,
+                    domicile: row.domicile,
+                    note: row.note,
+                    constituents: self.get_fund_constituents(id).await?,
+                }))
+            }
+            "crypto" => {
+                let row = sqlx::query!(
+                    r#"SELECT
+                        id,
+                        name,
+                        symbol,
+                        chain,
+                        decimals,
+                        note
+                     FROM cryptos
+                     WHERE id = $1"#,
+                    id,
+                )
+                .fetch_one(&self.pool)
+                .await?;
+
+                Ok(Asset::Crypto(Crypto {
+                    id: Some(row.id),
+                    name: row.name,
+                    symbol: row.symbol,
+                    chain: row.chain,
+                    decimals: row.decimals,
+                    note: row.note,
+                }))
+            }
+            "commodity" => {
+                let row = sqlx::query!(
+                    r#"SELECT
+                        id,
+                        name,
+                        unit,
+                        note
+                     FROM commodities
+                     WHERE id = $1"#,
+                    id,
+                )
+                .fetch_one(&self.pool)
+                .await?;
+
+                Ok(Asset::Commodity(Commodity::new(
+                    Some(row.id),
+                    row.name,
+                    row.unit,
+                    row.note,
+                )))
+            }
             _ => Err(DataError::InvalidAsset(row.asset_class)),
         }
     }
 
     async fn get_asset_by_isin(&self, isin: &str) -> Result<Asset, DataError> {
         let row = sqlx::query!(
-            r#"SELECT
-                   id,
-                   name,
-                   isin,
-                   wkn,
-                   note
-                 FROM stocks
-                 WHERE isin = $1"#,
+            r#"SELECT id AS "id!" FROM stocks WHERE isin = $1
+               UNION ALL
+               SELECT id FROM bonds WHERE isin = $1
+               UNION ALL
+               SELECT id FROM funds WHERE isin = $1"#,
             isin.to_string(),
         )
         .fetch_one(&self.pool)
         .await?;
-
-        Ok(Asset::Stock(Stock::new(
-            Some(row.id),
-            row.name,
-            row.isin,
-            row.wkn,
-            row.note,
-        )))
+        self.get_asset_by_id(row.id).await
     }
 
     async fn get_all_assets(&self) -> Result<Vec<Asset>, DataError> {
@@ -195,12 +493,20 @@ impl AssetHandler for PostgresDB {
                 a.asset_class as "asset_class!",
                 CASE
                     WHEN a.asset_class='currency' THEN c.iso_code
+                    WHEN a.asset_class='bond' THEN b.name
+                    WHEN a.asset_class='fund' THEN f.name
+                    WHEN a.asset_class='crypto' THEN cr.name
+                    WHEN a.asset_class='commodity' THEN co.name
                     ELSE s.name
                 END as "name!"
             FROM
                 assets a
                 LEFT JOIN stocks s ON a.id = s.id
-                LEFT JOIN currencies c ON a.id = c.id"#
+                LEFT JOIN currencies c ON a.id = c.id
+                LEFT JOIN bonds b ON a.id = b.id
+                LEFT JOIN funds f ON a.id = f.id
+                LEFT JOIN cryptos cr ON a.id = cr.id
+                LEFT JOIN commodities co ON a.id = co.id"#
         )
         .fetch_all(&self.pool)
         .await?
@@ -262,6 +568,121 @@ impl AssetHandler for PostgresDB {
                     ))
                 }
             }
+            Asset::Bond(b) => {
+                if let Some(id) = b.id {
+                    sqlx::query!(
+                        "UPDATE bonds
+                        SET
+                            name=$2,
+                            isin=$3,
+                            issuer=$4,
+                            currency_id=(SELECT id FROM currencies WHERE iso_code = $5),
+                            maturity=$6,
+                            denomination=$7,
+                            spec=$8
+                        WHERE id=$1;",
+                        id,
+                        b.name(),
+                        b.isin(),
+                        b.issuer(),
+                        b.currency().iso_code.to_string(),
+                        b.maturity(),
+                        b.denomination as i64,
+                        serde_json::to_value(b)?,
+                    )
+                    .execute(&self.pool)
+                    .await?;
+                    Ok(())
+                } else {
+                    Err(DataError::NotFound(
+                        "not yet stored to database".to_string(),
+                    ))
+                }
+            }
+            Asset::Fund(f) => {
+                if let Some(id) = f.id {
+                    let mut tx = self.pool.begin().await?;
+                    sqlx::query!(
+                        "UPDATE funds
+                        SET
+                            name=$2,
+                            isin=$3,
+                            wkn=$4,
+                            ter=$5,
+                            distribution=$6,
+                            domicile=$7,
+                            note=$8
+                        WHERE id=$1;",
+                        id,
+                        f.name,
+                        f.isin,
+                        f.wkn,
+                        f.ter,
+                        f.distribution.to_string(),
+                        f.domicile,
+                        f.note,
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    store_fund_constituents(&mut tx, id, &f.constituents).await?;
+                    tx.commit().await?;
+                    Ok(())
+                } else {
+                    Err(DataError::NotFound(
+                        "not yet stored to database".to_string(),
+                    ))
+                }
+            }
+            Asset::Crypto(c) => {
+                if let Some(id) = c.id {
+                    sqlx::query!(
+                        "UPDATE cryptos
+                        SET
+                            name=$2,
+                            symbol=$3,
+                            chain=$4,
+                            decimals=$5,
+                            note=$6
+                        WHERE id=$1;",
+                        id,
+                        c.name,
+                        c.symbol,
+                        c.chain,
+                        c.decimals,
+                        c.note,
+                    )
+                    .execute(&self.pool)
+                    .await?;
+                    Ok(())
+                } else {
+                    Err(DataError::NotFound(
+                        "not yet stored to database".to_string(),
+                    ))
+                }
+            }
+            Asset::Commodity(c) => {
+                if let Some(id) = c.id {
+                    sqlx::query!(
+                        "UPDATE commodities
+                        SET
+                            name=$2,
+                            unit=$3,
+                            note=$4
+                        WHERE id=$1;",
+                        id,
+                        c.name,
+                        c.unit,
+                        c.note,
+                    )
+                    .execute(&self.pool)
+                    .await?;
+                    Ok(())
+                } else {
+                    Err(DataError::NotFound(
+                        "not yet stored to database".to_string(),
+                    ))
+                }
+            }
         }
     }
 
@@ -269,33 +690,222 @@ impl AssetHandler for PostgresDB {
         let row = sqlx::query!("SELECT asset_class FROM assets WHERE id=$1", id as i32,)
             .fetch_one(&self.pool)
             .await?;
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!("DELETE FROM asset_identifiers WHERE asset_id=$1;", id)
+            .execute(&mut *tx)
+            .await?;
         match row.asset_class.as_str() {
             "currency" => {
-                let tx = self.pool.begin().await?;
                 sqlx::query!("DELETE FROM currencies WHERE id=$1;", id)
-                    .execute(&self.pool)
+                    .execute(&mut *tx)
                     .await?;
-                sqlx::query!("DELETE FROM assets WHERE id=$1;", id)
-                    .execute(&self.pool)
-                    .await?;
-                tx.commit().await?;
-                Ok(())
             }
             "stock" => {
-                let tx = self.pool.begin().await?;
                 sqlx::query!("DELETE FROM stocks WHERE id=$1;", id)
-                    .execute(&self.pool)
+                    .execute(&mut *tx)
                     .await?;
-                sqlx::query!("DELETE FROM assets WHERE id=$1;", id)
-                    .execute(&self.pool)
+            }
+            "bond" => {
+                sqlx::query!("DELETE FROM bonds WHERE id=$1;", id)
+                    .execute(&mut *tx)
                     .await?;
-                tx.commit().await?;
-                Ok(())
             }
-            _ => Err(DataError::InvalidAsset(
-                "Could not delete unknown asset".to_string(),
-            )),
+            "fund" => {
+                sqlx::query!("DELETE FROM fund_constituents WHERE fund_id=$1;", id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query!("DELETE FROM funds WHERE id=$1;", id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            "crypto" => {
+                sqlx::query!("DELETE FROM cryptos WHERE id=$1;", id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            "commodity" => {
+                sqlx::query!("DELETE FROM commodities WHERE id=$1;", id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            _ => {
+                return Err(DataError::InvalidAsset(
+                    "Could not delete unknown asset".to_string(),
+                ))
+            }
+        }
+        sqlx::query!("DELETE FROM assets WHERE id=$1;", id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn add_asset_identifier(
+        &self,
+        asset_id: i32,
+        identifier: &Identifier,
+    ) -> Result<(), DataError> {
+        identifier.validate()?;
+        sqlx::query!(
+            "INSERT INTO asset_identifiers (asset_id, id_type, value) VALUES ($1, $2, $3)",
+            asset_id,
+            identifier.id_type.to_string(),
+            identifier.value,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_asset_identifiers(&self, asset_id: i32) -> Result<Vec<Identifier>, DataError> {
+        let mut identifiers = Vec::new();
+        for row in sqlx::query!(
+            "SELECT id_type, value FROM asset_identifiers WHERE asset_id=$1 ORDER BY id",
+            asset_id,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        {
+            identifiers.push(Identifier {
+                id_type: IdentifierType::from_str(&row.id_type)?,
+                value: row.value,
+            });
         }
+        Ok(identifiers)
+    }
+
+    async fn delete_asset_identifier(
+        &self,
+        asset_id: i32,
+        identifier: &Identifier,
+    ) -> Result<(), DataError> {
+        sqlx::query!(
+            "DELETE FROM asset_identifiers WHERE asset_id=$1 AND id_type=$2 AND value=$3",
+            asset_id,
+            identifier.id_type.to_string(),
+            identifier.value,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn find_asset_by_identifier(&self, identifier: &Identifier) -> Result<Asset, DataError> {
+        for key in identifier.lookup_keys() {
+            let row = sqlx::query!(
+                r#"SELECT asset_id AS "id!" FROM asset_identifiers WHERE id_type = $1 AND value = $2
+                   UNION ALL
+                   SELECT id FROM stocks WHERE ($1 = 'ISIN' AND isin = $2) OR ($1 = 'WKN' AND wkn = $2)
+                   UNION ALL
+                   SELECT id FROM bonds WHERE $1 = 'ISIN' AND isin = $2
+                   UNION ALL
+                   SELECT id FROM funds WHERE ($1 = 'ISIN' AND isin = $2) OR ($1 = 'WKN' AND wkn = $2)
+                   LIMIT 1"#,
+                key.id_type.to_string(),
+                key.value,
+            )
+            .fetch_optional(&self.pool)
+            .await?;
+            if let Some(row) = row {
+                return self.get_asset_by_id(row.id).await;
+            }
+        }
+        Err(DataError::NotFound(format!("asset with {identifier}")))
+    }
+
+    async fn insert_corporate_action(&self, action: &CorporateAction) -> Result<i32, DataError> {
+        action.validate()?;
+        let mut tx = self.pool.begin().await?;
+        let row = sqlx::query!(
+            "INSERT INTO corporate_actions (kind, asset_id, action_date, note)
+            VALUES ($1, $2, $3, $4) RETURNING id",
+            action.kind.to_string(),
+            action.asset_id,
+            action.date,
+            action.note,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        store_corporate_action_targets(&mut tx, row.id, &action.targets).await?;
+        tx.commit().await?;
+        Ok(row.id)
+    }
+
+    async fn get_corporate_action_by_id(&self, id: i32) -> Result<CorporateAction, DataError> {
+        let row = sqlx::query!(
+            "SELECT kind, asset_id, action_date, note FROM corporate_actions WHERE id=$1",
+            id,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(CorporateAction {
+            id: Some(id),
+            kind: CorporateActionKind::from_str(&row.kind)?,
+            asset_id: row.asset_id,
+            date: row.action_date,
+            targets: self.get_corporate_action_targets(id).await?,
+            note: row.note,
+        })
+    }
+
+    async fn get_corporate_actions(
+        &self,
+        start: Option<Date>,
+        end: Option<Date>,
+    ) -> Result<Vec<CorporateAction>, DataError> {
+        let mut actions = Vec::new();
+        for row in sqlx::query!(
+            "SELECT id, kind, asset_id, action_date, note FROM corporate_actions
+            WHERE ($1::DATE IS NULL OR action_date >= $1)
+                AND ($2::DATE IS NULL OR action_date <= $2)
+            ORDER BY action_date, id",
+            start,
+            end,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        {
+            actions.push(CorporateAction {
+                id: Some(row.id),
+                kind: CorporateActionKind::from_str(&row.kind)?,
+                asset_id: row.asset_id,
+                date: row.action_date,
+                targets: self.get_corporate_action_targets(row.id).await?,
+                note: row.note,
+            });
+        }
+        Ok(actions)
+    }
+
+    async fn update_corporate_action(&self, action: &CorporateAction) -> Result<(), DataError> {
+        let id = action.get_id()?;
+        action.validate()?;
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            "UPDATE corporate_actions SET kind=$2, asset_id=$3, action_date=$4, note=$5
+            WHERE id=$1",
+            id,
+            action.kind.to_string(),
+            action.asset_id,
+            action.date,
+            action.note,
+        )
+        .execute(&mut *tx)
+        .await?;
+        store_corporate_action_targets(&mut tx, id, &action.targets).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_corporate_action(&self, id: i32) -> Result<(), DataError> {
+        let mut tx = self.pool.begin().await?;
+        store_corporate_action_targets(&mut tx, id, &[]).await?;
+        sqlx::query!("DELETE FROM corporate_actions WHERE id=$1", id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
     }
 
     async fn get_all_currencies(&self) -> Result<Vec<Currency>, DataError> {
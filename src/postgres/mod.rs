@@ -1,16 +1,27 @@
 //! Implementation of PostgreSQL data handler
+use std::sync::Arc;
+
+use sqlx::migrate::{Migrate, Migrator};
 use sqlx::postgres::{PgPoolOptions, Postgres};
 
+use crate::datatypes::{DataError, Validator};
+
 pub mod asset_handler;
 pub mod object_handler;
 pub mod quote_handler;
 pub mod transaction_handler;
 
+/// Ordered schema migrations, embedded from `migrations/postgres` at compile time.
+/// The versions of all applied migrations are recorded in the table `_sqlx_migrations`.
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+
 /// Struct to handle connections to postgres databases
 #[derive(Clone)]
 pub struct PostgresDB {
     /// pool is made public to allow extending this struct outside of the library
     pub pool: sqlx::Pool<Postgres>,
+    /// Validator new transactions must pass, if any
+    validator: Option<Arc<Validator>>,
 }
 
 impl PostgresDB {
@@ -19,14 +30,26 @@ impl PostgresDB {
             .max_connections(5)
             .connect(connection_string)
             .await?;
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            validator: None,
+        })
     }
 
-    /// Clean database by dropping all tables and than run init
-    pub async fn clean(&self) -> Result<(), sqlx::Error> {
+    /// Reject new transactions failing the validator's checks on insert
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validator = Some(Arc::new(validator));
+        self
+    }
+
+    /// Clean database by dropping all tables and than run all migrations
+    pub async fn clean(&self) -> Result<(), DataError> {
         sqlx::query!("DROP TABLE IF EXISTS transactions")
             .execute(&self.pool)
             .await?;
+        sqlx::query!("DROP TABLE IF EXISTS accounts")
+            .execute(&self.pool)
+            .await?;
         sqlx::query!("DROP TABLE IF EXISTS quotes")
             .execute(&self.pool)
             .await?;
@@ -36,6 +59,30 @@ impl PostgresDB {
         sqlx::query!("DROP TYPE IF EXISTS market_data_source")
             .execute(&self.pool)
             .await?;
+        sqlx::query!("DROP TABLE IF EXISTS corporate_action_targets")
+            .execute(&self.pool)
+            .await?;
+        sqlx::query!("DROP TABLE IF EXISTS corporate_actions")
+            .execute(&self.pool)
+            .await?;
+        sqlx::query!("DROP TABLE IF EXISTS asset_identifiers")
+            .execute(&self.pool)
+            .await?;
+        sqlx::query!("DROP TABLE IF EXISTS cryptos")
+            .execute(&self.pool)
+            .await?;
+        sqlx::query!("DROP TABLE IF EXISTS commodities")
+            .execute(&self.pool)
+            .await?;
+        sqlx::query!("DROP TABLE IF EXISTS fund_constituents")
+            .execute(&self.pool)
+            .await?;
+        sqlx::query!("DROP TABLE IF EXISTS funds")
+            .execute(&self.pool)
+            .await?;
+        sqlx::query!("DROP TABLE IF EXISTS bonds")
+            .execute(&self.pool)
+            .await?;
         sqlx::query!("DROP TABLE IF EXISTS currencies")
             .execute(&self.pool)
             .await?;
@@ -48,97 +95,84 @@ impl PostgresDB {
         sqlx::query!("DROP TABLE IF EXISTS objects")
             .execute(&self.pool)
             .await?;
+        sqlx::query!("DROP TABLE IF EXISTS _sqlx_migrations")
+            .execute(&self.pool)
+            .await?;
         self.init().await
     }
 
-    /// Initialize new database by creating table
-    pub async fn init(&self) -> Result<(), sqlx::Error> {
-        sqlx::query!(
-            "CREATE TABLE IF NOT EXISTS assets (
-                id SERIAL PRIMARY KEY,
-                asset_class VARCHAR(20) NOT NULL
-            )"
-        )
-        .execute(&self.pool)
-        .await?;
-        sqlx::query!(
-            "CREATE TABLE IF NOT EXISTS currencies (
-                    id INTEGER PRIMARY KEY,
-                    iso_code CHAR(3) NOT NULL UNIQUE,
-                    rounding_digits INT NOT NULL,
-                    FOREIGN KEY(id) REFERENCES assets(id)
-                )"
-        )
-        .execute(&self.pool)
-        .await?;
-        sqlx::query!(
-            "CREATE TABLE IF NOT EXISTS stocks (
-                  id INTEGER PRIMARY KEY,
-                  name TEXT NOT NULL UNIQUE,
-                  wkn CHAR(6) UNIQUE,
-                  isin CHAR(12) UNIQUE,
-                  note TEXT,
-                  FOREIGN KEY(id) REFERENCES assets(id)
-                )"
-        )
-        .execute(&self.pool)
-        .await?;
-        sqlx::query!(
-            "CREATE TABLE IF NOT EXISTS transactions (
-                id SERIAL PRIMARY KEY,
-                trans_type TEXT NOT NULL,
-                asset_id INTEGER,
-                cash_amount FLOAT8 NOT NULL,
-                cash_currency_id INT NOT NULL,
-                cash_date DATE NOT NULL,
-                related_trans INTEGER,
-                position FLOAT8,
-                note TEXT,
-                FOREIGN KEY(asset_id) REFERENCES assets(id),
-                FOREIGN KEY(cash_currency_id) REFERENCES currencies(id),
-                FOREIGN KEY(related_trans) REFERENCES transactions(id)
-            )"
-        )
-        .execute(&self.pool)
-        .await?;
-        sqlx::query!(
-            "CREATE TABLE IF NOT EXISTS ticker (
-                id SERIAL PRIMARY KEY,
-                name TEXT NOT NULL,
-                asset_id INTEGER NOT NULL,
-                source TEXT NOT NULL,
-                priority INTEGER NOT NULL,
-                currency_id INT NOT NULL,
-                factor FLOAT8 NOT NULL DEFAULT 1.0,
-                tz TEXT,
-                cal TEXT,
-                FOREIGN KEY(asset_id) REFERENCES assets(id),
-                FOREIGN KEY(currency_id) REFERENCES currencies(id)
-            )"
-        )
-        .execute(&self.pool)
-        .await?;
-        sqlx::query!(
-            "CREATE TABLE IF NOT EXISTS quotes (
-                id SERIAL PRIMARY KEY,
-                ticker_id INTEGER NOT NULL,
-                price FLOAT8 NOT NULL,
-                time TIMESTAMP WITH TIME ZONE NOT NULL,
-                volume FLOAT8,
-                FOREIGN KEY(ticker_id) REFERENCES ticker(id)
-            )"
-        )
-        .execute(&self.pool)
-        .await?;
-
-        sqlx::query!(
-            "CREATE TABLE IF NOT EXISTS objects (
-            id TEXT PRIMARY KEY,
-            object JSON NOT NULL)"
-        )
-        .execute(&self.pool)
-        .await?;
+    /// Initialize new database, same as `migrate`
+    pub async fn init(&self) -> Result<(), DataError> {
+        self.migrate().await
+    }
 
+    /// Bring the database schema up to date by applying all migrations not yet applied.
+    /// Fails without changing the database if the schema has been created by a newer
+    /// version of this library.
+    pub async fn migrate(&self) -> Result<(), DataError> {
+        let supported = Self::supported_schema_version();
+        if let Some(found) = self.schema_version().await? {
+            if found > supported {
+                return Err(DataError::UnsupportedSchemaVersion(found, supported));
+            }
+        }
+        MIGRATOR.run(&self.pool).await.map_err(sqlx::Error::from)?;
         Ok(())
     }
+
+    /// Return the version of the latest migration applied to the database or `None`
+    /// if no migration has been applied yet
+    pub async fn schema_version(&self) -> Result<Option<i64>, DataError> {
+        let mut conn = self.pool.acquire().await?;
+        conn.ensure_migrations_table()
+            .await
+            .map_err(sqlx::Error::from)?;
+        let applied = conn
+            .list_applied_migrations()
+            .await
+            .map_err(sqlx::Error::from)?;
+        Ok(applied.iter().map(|m| m.version).max())
+    }
+
+    /// Return the latest schema version known to this library
+    pub fn supported_schema_version() -> i64 {
+        MIGRATOR.iter().map(|m| m.version).max().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn migrate_refuses_newer_schema() {
+        // Setup database connection
+        let db_url = std::env::var("FINQL_TEST_DATABASE_URL");
+        assert!(
+            db_url.is_ok(),
+            "environment variable $FINQL_TEST_DATABASE_URL is not set"
+        );
+        let db = PostgresDB::new(&db_url.unwrap()).await.unwrap();
+        db.clean().await.unwrap();
+        assert_eq!(
+            db.schema_version().await.unwrap(),
+            Some(PostgresDB::supported_schema_version())
+        );
+        // applying migrations again is a no-op
+        db.migrate().await.unwrap();
+
+        // pretend a newer library version has migrated the database
+        sqlx::query(
+            "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+                VALUES (99999, 'future', TRUE, '\\x00', 0)",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        assert!(matches!(
+            db.migrate().await,
+            Err(DataError::UnsupportedSchemaVersion(99999, _))
+        ));
+        db.clean().await.unwrap();
+    }
 }
@@ -2,137 +2,27 @@ use async_trait::async_trait;
 use std::str::FromStr;
 use time::Date;
 
-use crate::datatypes::cash_flow::{CashAmount, CashFlow};
 use crate::datatypes::currency::Currency;
-use crate::datatypes::transaction::{Transaction, TransactionType};
-use crate::datatypes::{CurrencyISOCode, DataError, TransactionHandler};
+pub use crate::datatypes::transaction::RawTransaction;
+use crate::datatypes::transaction::{Transaction, TransactionFilter};
+use crate::datatypes::{Account, CurrencyISOCode, DataError, TransactionHandler};
 
 use super::PostgresDB;
 
-pub struct RawTransaction {
-    pub id: Option<i32>,
-    pub trans_type: String,
-    pub asset: Option<i32>,
-    pub cash_amount: f64,
-    pub cash_currency: Currency,
-    pub cash_date: Date,
-    pub related_trans: Option<i32>,
-    pub position: Option<f64>,
-    pub note: Option<String>,
-}
-
-/// Raw transaction type constants
-const CASH: &str = "c";
-const ASSET: &str = "a";
-const DIVIDEND: &str = "d";
-const INTEREST: &str = "i";
-const TAX: &str = "t";
-const FEE: &str = "f";
-
-impl RawTransaction {
-    pub fn to_transaction(&self) -> Result<Transaction, DataError> {
-        let currency = self.cash_currency;
-
-        let cash_flow = CashFlow {
-            amount: CashAmount {
-                amount: self.cash_amount,
-                currency,
-            },
-            date: self.cash_date,
-        };
-        let note = self.note.clone();
-        let transaction_type = match self.trans_type.as_str() {
-            CASH => TransactionType::Cash,
-            ASSET => TransactionType::Asset {
-                asset_id: self
-                    .asset
-                    .ok_or_else(|| DataError::InvalidTransaction("missing asset id".to_string()))?,
-                position: self.position.ok_or_else(|| {
-                    DataError::InvalidTransaction("missing position value".to_string())
-                })?,
-            },
-            DIVIDEND => TransactionType::Dividend {
-                asset_id: self
-                    .asset
-                    .ok_or_else(|| DataError::InvalidTransaction("missing asset id".to_string()))?,
-            },
-            INTEREST => TransactionType::Interest {
-                asset_id: self
-                    .asset
-                    .ok_or_else(|| DataError::InvalidTransaction("missing asset id".to_string()))?,
-            },
-            TAX => TransactionType::Tax {
-                transaction_ref: self.related_trans,
-            },
-            FEE => TransactionType::Fee {
-                transaction_ref: self.related_trans,
-            },
-            unknown => {
-                return Err(DataError::InvalidTransaction(unknown.to_string()));
-            }
-        };
-        Ok(Transaction {
-            id: self.id,
-            transaction_type,
-            cash_flow,
-            note,
-        })
-    }
-
-    pub fn from_transaction(transaction: &Transaction) -> RawTransaction {
-        let cash_amount = transaction.cash_flow.amount.amount;
-        let cash_currency = transaction.cash_flow.amount.currency;
-        let note = transaction.note.clone();
-        let mut raw_transaction = RawTransaction {
-            id: transaction.id,
-            trans_type: String::new(),
-            asset: None,
-            cash_amount,
-            cash_currency,
-            cash_date: transaction.cash_flow.date,
-            related_trans: None,
-            position: None,
-            note,
-        };
-        match transaction.transaction_type {
-            TransactionType::Cash => raw_transaction.trans_type = CASH.to_string(),
-            TransactionType::Asset { asset_id, position } => {
-                raw_transaction.trans_type = ASSET.to_string();
-                raw_transaction.asset = Some(asset_id);
-                raw_transaction.position = Some(position);
-            }
-            TransactionType::Dividend { asset_id } => {
-                raw_transaction.trans_type = DIVIDEND.to_string();
-                raw_transaction.asset = Some(asset_id);
-            }
-            TransactionType::Interest { asset_id } => {
-                raw_transaction.trans_type = INTEREST.to_string();
-                raw_transaction.asset = Some(asset_id);
-            }
-            TransactionType::Tax { transaction_ref } => {
-                raw_transaction.trans_type = TAX.to_string();
-                raw_transaction.related_trans = transaction_ref;
-            }
-            TransactionType::Fee { transaction_ref } => {
-                raw_transaction.trans_type = FEE.to_string();
-                raw_transaction.related_trans = transaction_ref;
-            }
-        };
-        raw_transaction
-    }
-}
-
 /// Handler for globally available data
 #[async_trait]
 impl TransactionHandler for PostgresDB {
     // insert, get, update and delete for transactions
     async fn insert_transaction(&self, transaction: &Transaction) -> Result<i32, DataError> {
+        if let Some(validator) = &self.validator {
+            validator.enforce(transaction, self).await?;
+        }
         let transaction = RawTransaction::from_transaction(transaction);
         let row = sqlx::query!(
             "INSERT INTO transactions (trans_type, asset_id, cash_amount,
                 cash_currency_id, cash_date, related_trans, position,
-                note)
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id",
+                note, account_id, tax_kind, country, acquisition_date)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) RETURNING id",
             transaction.trans_type,
             transaction.asset,
             transaction.cash_amount,
@@ -141,6 +31,10 @@ impl TransactionHandler for PostgresDB {
             transaction.related_trans,
             transaction.position,
             transaction.note,
+            transaction.account,
+            transaction.tax_kind,
+            transaction.country,
+            transaction.acquisition_date,
         )
         .fetch_one(&self.pool)
         .await?;
@@ -160,7 +54,11 @@ impl TransactionHandler for PostgresDB {
                 t.cash_date,
                 t.related_trans,
                 t.position,
-                t.note
+                t.note,
+                t.account_id,
+                t.tax_kind,
+                t.country,
+                t.acquisition_date
                 FROM transactions t
                 JOIN currencies c ON c.id = t.cash_currency_id
                 WHERE t.id = $1",
@@ -183,6 +81,10 @@ impl TransactionHandler for PostgresDB {
             related_trans: row.related_trans,
             position: row.position,
             note: row.note,
+            account: row.account_id,
+            tax_kind: row.tax_kind,
+            country: row.country,
+            acquisition_date: row.acquisition_date,
         };
         Ok(transaction.to_transaction()?)
     }
@@ -201,7 +103,11 @@ impl TransactionHandler for PostgresDB {
                 t.cash_date AS "cash_date!",
                 t.related_trans,
                 t.position,
-                t.note
+                t.note,
+                t.account_id,
+                t.tax_kind,
+                t.country,
+                t.acquisition_date
                 FROM transactions t
                 JOIN currencies c ON c.id = t.cash_currency_id"#
         )
@@ -223,6 +129,83 @@ impl TransactionHandler for PostgresDB {
                 related_trans: row.related_trans,
                 position: row.position,
                 note: row.note,
+                account: row.account_id,
+                tax_kind: row.tax_kind,
+                country: row.country,
+                acquisition_date: row.acquisition_date,
+            };
+            transactions.push(transaction.to_transaction()?);
+        }
+        Ok(transactions)
+    }
+
+    async fn get_transactions(
+        &self,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<Transaction>, DataError> {
+        let type_codes: Option<Vec<String>> = filter.kinds.as_ref().map(|kinds| {
+            kinds
+                .iter()
+                .map(|kind| kind.type_code().to_string())
+                .collect()
+        });
+        let mut transactions = Vec::new();
+        for row in sqlx::query!(
+            r#"SELECT
+                t.id AS "id!",
+                t.trans_type AS "trans_type!",
+                t.asset_id,
+                t.cash_amount AS "cash_amount!",
+                c.id AS "cash_currency_id!",
+                c.iso_code AS "cash_iso_code!",
+                c.rounding_digits AS "cash_rounding_digits!",
+                t.cash_date AS "cash_date!",
+                t.related_trans,
+                t.position,
+                t.note,
+                t.account_id,
+                t.tax_kind,
+                t.country,
+                t.acquisition_date
+                FROM transactions t
+                JOIN currencies c ON c.id = t.cash_currency_id
+                LEFT JOIN transactions r ON r.id = t.related_trans
+                WHERE ($1::DATE IS NULL OR t.cash_date >= $1)
+                    AND ($2::DATE IS NULL OR t.cash_date <= $2)
+                    AND ($3::INT IS NULL OR t.asset_id = $3 OR r.asset_id = $3)
+                    AND ($4::TEXT[] IS NULL OR t.trans_type = ANY($4))
+                    AND ($5::TEXT IS NULL OR STRPOS(LOWER(t.note), LOWER($5)) > 0)
+                    AND ($6::INT[] IS NULL OR t.account_id = ANY($6))
+                ORDER BY t.cash_date, t.id"#,
+            filter.start,
+            filter.end,
+            filter.asset_id,
+            type_codes.as_deref(),
+            filter.note,
+            filter.account_ids.as_deref(),
+        )
+        .fetch_all(&self.pool)
+        .await?
+        {
+            let transaction = RawTransaction {
+                id: Some(row.id),
+                trans_type: row.trans_type,
+                asset: row.asset_id,
+                cash_amount: row.cash_amount,
+                cash_currency: Currency::new(
+                    Some(row.cash_currency_id),
+                    CurrencyISOCode::from_str(&row.cash_iso_code)
+                        .expect("unknown currency asset referenced in db"),
+                    Some(row.cash_rounding_digits),
+                ),
+                cash_date: row.cash_date,
+                related_trans: row.related_trans,
+                position: row.position,
+                note: row.note,
+                account: row.account_id,
+                tax_kind: row.tax_kind,
+                country: row.country,
+                acquisition_date: row.acquisition_date,
             };
             transactions.push(transaction.to_transaction()?);
         }
@@ -245,7 +228,11 @@ impl TransactionHandler for PostgresDB {
                 cash_date=$6,
                 related_trans=$7,
                 position=$8,
-                note=$9
+                note=$9,
+                account_id=$10,
+                tax_kind=$11,
+                country=$12,
+                acquisition_date=$13
             WHERE id=$1",
             transaction.id,
             transaction.trans_type,
@@ -256,6 +243,10 @@ impl TransactionHandler for PostgresDB {
             transaction.related_trans,
             transaction.position,
             transaction.note,
+            transaction.account,
+            transaction.tax_kind,
+            transaction.country,
+            transaction.acquisition_date,
         )
         .execute(&self.pool)
         .await?;
@@ -268,4 +259,172 @@ impl TransactionHandler for PostgresDB {
             .await?;
         Ok(())
     }
+
+    async fn insert_account(&self, account: &Account) -> Result<i32, DataError> {
+        let row = sqlx::query!(
+            "INSERT INTO accounts (name, currency_id, owner, broker)
+                VALUES ($1, $2, $3, $4) RETURNING id",
+            account.name,
+            account.currency.id,
+            account.owner,
+            account.broker,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.id)
+    }
+
+    async fn get_account_by_id(&self, id: i32) -> Result<Account, DataError> {
+        let row = sqlx::query!(
+            "SELECT
+                a.id,
+                a.name,
+                c.id AS currency_id,
+                c.iso_code,
+                c.rounding_digits,
+                a.owner,
+                a.broker
+                FROM accounts a
+                JOIN currencies c ON c.id = a.currency_id
+                WHERE a.id = $1",
+            id,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(Account::new(
+            Some(row.id),
+            row.name,
+            Currency::new(
+                Some(row.currency_id),
+                CurrencyISOCode::from_str(&row.iso_code)?,
+                Some(row.rounding_digits),
+            ),
+            row.owner,
+            row.broker,
+        ))
+    }
+
+    async fn get_all_accounts(&self) -> Result<Vec<Account>, DataError> {
+        let mut accounts = Vec::new();
+        for row in sqlx::query!(
+            "SELECT
+                a.id,
+                a.name,
+                c.id AS currency_id,
+                c.iso_code,
+                c.rounding_digits,
+                a.owner,
+                a.broker
+                FROM accounts a
+                JOIN currencies c ON c.id = a.currency_id
+                ORDER BY a.name"
+        )
+        .fetch_all(&self.pool)
+        .await?
+        {
+            accounts.push(Account::new(
+                Some(row.id),
+                row.name,
+                Currency::new(
+                    Some(row.currency_id),
+                    CurrencyISOCode::from_str(&row.iso_code)?,
+                    Some(row.rounding_digits),
+                ),
+                row.owner,
+                row.broker,
+            ));
+        }
+        Ok(accounts)
+    }
+
+    async fn update_account(&self, account: &Account) -> Result<(), DataError> {
+        let id = account
+            .id
+            .ok_or_else(|| DataError::NotFound("not yet stored to database".to_string()))?;
+        sqlx::query!(
+            "UPDATE accounts SET
+                name=$2,
+                currency_id=$3,
+                owner=$4,
+                broker=$5
+            WHERE id=$1",
+            id,
+            account.name,
+            account.currency.id,
+            account.owner,
+            account.broker,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_account(&self, id: i32) -> Result<(), DataError> {
+        sqlx::query!("DELETE FROM accounts WHERE id=$1;", id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_transactions_for_account(
+        &self,
+        account_id: i32,
+        start: Option<Date>,
+        end: Option<Date>,
+    ) -> Result<Vec<Transaction>, DataError> {
+        let mut transactions = Vec::new();
+        for row in sqlx::query!(
+            r#"SELECT
+                t.id AS "id!",
+                t.trans_type AS "trans_type!",
+                t.asset_id,
+                t.cash_amount AS "cash_amount!",
+                c.id AS "cash_currency_id!",
+                c.iso_code AS "cash_iso_code!",
+                c.rounding_digits AS "cash_rounding_digits!",
+                t.cash_date AS "cash_date!",
+                t.related_trans,
+                t.position,
+                t.note,
+                t.account_id,
+                t.tax_kind,
+                t.country,
+                t.acquisition_date
+                FROM transactions t
+                JOIN currencies c ON c.id = t.cash_currency_id
+                WHERE t.account_id = $1
+                    AND ($2::DATE IS NULL OR t.cash_date >= $2)
+                    AND ($3::DATE IS NULL OR t.cash_date <= $3)
+                ORDER BY t.cash_date, t.id"#,
+            account_id,
+            start,
+            end,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        {
+            let transaction = RawTransaction {
+                id: Some(row.id),
+                trans_type: row.trans_type,
+                asset: row.asset_id,
+                cash_amount: row.cash_amount,
+                cash_currency: Currency::new(
+                    Some(row.cash_currency_id),
+                    CurrencyISOCode::from_str(&row.cash_iso_code)
+                        .expect("unknown currency asset referenced in db"),
+                    Some(row.cash_rounding_digits),
+                ),
+                cash_date: row.cash_date,
+                related_trans: row.related_trans,
+                position: row.position,
+                note: row.note,
+                account: row.account_id,
+                tax_kind: row.tax_kind,
+                country: row.country,
+                acquisition_date: row.acquisition_date,
+            };
+            transactions.push(transaction.to_transaction()?);
+        }
+        Ok(transactions)
+    }
 }
@@ -0,0 +1,556 @@
+//! Risk figures of a portfolio based on the quote histories of its assets
+//!
+//! The current holdings of a `PortfolioPosition` are valued with the historic prices of their
+//! assets at the end of each business day of a calendar, i.e. the figures describe the risk
+//! of keeping the current positions unchanged. Cash is part of the portfolio value, but is
+//! considered to be riskless. Value at risk and expected shortfall are given as positive
+//! amounts of loss in the portfolio's currency, all other figures refer to the returns of the
+//! portfolio value.
+use cal_calc::Calendar;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use time::{Date, OffsetDateTime};
+
+use crate::datatypes::date_time_helper::{date_to_offset_date_time, DateTimeError};
+use crate::datatypes::Currency;
+use crate::helpers::{mean, normal_density, normal_quantile, sample_covariance, sample_variance};
+use crate::market::MarketError;
+use crate::portfolio::PortfolioPosition;
+use crate::time_series::{TimeSeries, TimeValue};
+use crate::Market;
+
+#[derive(Error, Debug)]
+pub enum RiskError {
+    #[error("At least two business days with prices of all assets are required")]
+    NotEnoughData,
+    #[error("No price available for asset {0}")]
+    MissingQuotes(i32),
+    #[error("Invalid confidence level {0}, must be between 0 and 1")]
+    InvalidConfidenceLevel(f64),
+    #[error("Calendar error")]
+    CalendarError(#[from] cal_calc::CalendarError),
+    #[error("Invalid date or time")]
+    DateTimeError(#[from] DateTimeError),
+    #[error("Market error")]
+    MarketError(#[from] MarketError),
+}
+
+/// Parameters of the risk calculation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskConfig {
+    /// Confidence levels of value at risk and expected shortfall, e.g. 0.99
+    pub confidence_levels: Vec<f64>,
+    /// Horizons of value at risk and expected shortfall in business days
+    pub horizons: Vec<usize>,
+    /// Annual risk free rate used for Sharpe and Sortino ratios
+    pub risk_free_rate: f64,
+    /// Number of business days per year used to annualize figures
+    pub periods_per_year: f64,
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        Self {
+            confidence_levels: vec![0.95, 0.99],
+            horizons: vec![1],
+            risk_free_rate: 0.0,
+            periods_per_year: 252.0,
+        }
+    }
+}
+
+impl RiskConfig {
+    /// Value at risk at 95% and 99% for a single day, no risk free rate and 252 business days
+    /// per year
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_confidence_levels(mut self, confidence_levels: &[f64]) -> Self {
+        self.confidence_levels = confidence_levels.to_vec();
+        self
+    }
+
+    pub fn with_horizons(mut self, horizons: &[usize]) -> Self {
+        self.horizons = horizons.to_vec();
+        self
+    }
+
+    pub fn with_risk_free_rate(mut self, risk_free_rate: f64) -> Self {
+        self.risk_free_rate = risk_free_rate;
+        self
+    }
+
+    pub fn with_periods_per_year(mut self, periods_per_year: f64) -> Self {
+        self.periods_per_year = periods_per_year;
+        self
+    }
+}
+
+/// Value at risk and expected shortfall for a confidence level and horizon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValueAtRisk {
+    pub confidence_level: f64,
+    /// Horizon in business days
+    pub horizon: usize,
+    /// Loss not exceeded with the given confidence in the historic scenarios, None if there
+    /// are no scenarios for the horizon
+    pub historical_var: Option<f64>,
+    /// Mean loss of all historic scenarios with losses at or beyond the historical VaR
+    pub historical_es: Option<f64>,
+    /// Value at risk assuming normally distributed asset returns
+    pub parametric_var: f64,
+    /// Expected shortfall assuming normally distributed asset returns
+    pub parametric_es: f64,
+}
+
+/// Largest relative decline of a value series from a previous peak
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Drawdown {
+    /// Decline relative to the peak value, e.g. 0.2 for a loss of 20%
+    pub drawdown: f64,
+    pub peak: Date,
+    pub trough: Date,
+    /// First date the peak value has been reached again, if any
+    pub recovery: Option<Date>,
+}
+
+/// Risk figures of a portfolio
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RiskReport {
+    /// First business day with prices
+    pub start: Date,
+    /// Last business day with prices
+    pub end: Date,
+    /// Value of the current holdings at the last business day
+    pub value: f64,
+    /// Value of the current holdings at each business day
+    pub history: TimeSeries,
+    /// Annualized standard deviation of daily returns
+    pub volatility: f64,
+    /// Annualized excess return over the risk free rate per volatility, None if the volatility
+    /// is zero
+    pub sharpe_ratio: Option<f64>,
+    /// Annualized excess return over the risk free rate per annualized downside deviation,
+    /// None if there are no returns below the risk free rate
+    pub sortino_ratio: Option<f64>,
+    pub value_at_risk: Vec<ValueAtRisk>,
+    /// Maximum drawdown, None if the value never declined
+    pub max_drawdown: Option<Drawdown>,
+}
+
+/// Calculate the maximum drawdown of a series of values ordered by time
+pub fn max_drawdown(series: &TimeSeries) -> Option<Drawdown> {
+    let mut peak = series.series.first()?;
+    // drawdown with peak value and index of trough
+    let mut max: Option<(Drawdown, f64, usize)> = None;
+    for (idx, sample) in series.series.iter().enumerate() {
+        if sample.value >= peak.value {
+            peak = sample;
+            continue;
+        }
+        let drawdown = 1.0 - sample.value / peak.value;
+        if max
+            .as_ref()
+            .is_none_or(|(max, _, _)| drawdown > max.drawdown)
+        {
+            max = Some((
+                Drawdown {
+                    drawdown,
+                    peak: peak.time.date(),
+                    trough: sample.time.date(),
+                    recovery: None,
+                },
+                peak.value,
+                idx,
+            ));
+        }
+    }
+    let (mut max, peak_value, trough) = max?;
+    max.recovery = series.series[trough..]
+        .iter()
+        .find(|sample| sample.value >= peak_value)
+        .map(|sample| sample.time.date());
+    Some(max)
+}
+
+/// Historical value at risk and expected shortfall of a list of losses at the given
+/// confidence level
+fn historical_var(mut losses: Vec<f64>, confidence_level: f64) -> Option<(f64, f64)> {
+    losses.sort_by(|a, b| a.total_cmp(b));
+    let idx = (confidence_level * losses.len() as f64).ceil() as usize;
+    if idx == 0 || idx > losses.len() {
+        return None;
+    }
+    let var = losses[idx - 1];
+    Some((var, mean(&losses[idx - 1..])))
+}
+
+/// Time of the end of day valuation at the given date
+pub(crate) fn valuation_time(date: Date) -> Result<OffsetDateTime, DateTimeError> {
+    date_to_offset_date_time(&date, 20, None)
+}
+
+/// Assets with non-zero positions and their number of units
+pub(crate) fn current_holdings(positions: &PortfolioPosition) -> Vec<(i32, f64)> {
+    positions
+        .assets
+        .iter()
+        .filter(|(_, pos)| pos.position != 0.0)
+        .map(|(asset_id, pos)| (*asset_id, pos.position))
+        .collect()
+}
+
+/// Split adjusted prices of the given assets at the end of the business days of the calendar
+/// from `start` to `end` (both inclusive). Business days without a price for any of the
+/// assets are skipped, at least two business days with prices are required.
+pub(crate) async fn price_history(
+    asset_ids: &[i32],
+    currency: Currency,
+    start: Date,
+    end: Date,
+    calendar: &Calendar,
+    market: &Market,
+) -> Result<(Vec<Date>, Vec<Vec<f64>>), RiskError> {
+    let mut dates = Vec::new();
+    let mut prices: Vec<Vec<f64>> = Vec::new();
+    let mut missing = None;
+    let mut date = if calendar.is_business_day(start) {
+        start
+    } else {
+        calendar.next_bday(start)?
+    };
+    while date <= end {
+        let time = valuation_time(date)?;
+        let mut day_prices = Vec::with_capacity(asset_ids.len());
+        for asset_id in asset_ids {
+            match market
+                .get_split_adjusted_asset_price(*asset_id, currency, time)
+                .await
+            {
+                Ok(price) => day_prices.push(price),
+                Err(err) if err.is_missing_quote() => {
+                    missing = Some(*asset_id);
+                    break;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+        if day_prices.len() == asset_ids.len() {
+            dates.push(date);
+            prices.push(day_prices);
+        }
+        date = calendar.next_bday(date)?;
+    }
+    if let (true, Some(asset_id)) = (dates.is_empty(), missing) {
+        return Err(RiskError::MissingQuotes(asset_id));
+    }
+    if dates.len() < 2 {
+        return Err(RiskError::NotEnoughData);
+    }
+    Ok((dates, prices))
+}
+
+/// Calculate the risk figures of the current holdings of the portfolio from the prices at
+/// the business days of the calendar from `start` to `end` (both inclusive). Prices are
+/// adjusted for splits and converted to the portfolio's currency. Business days without a
+/// price for any of the assets are skipped.
+pub async fn calc_risk(
+    positions: &PortfolioPosition,
+    start: Date,
+    end: Date,
+    calendar: &Calendar,
+    config: &RiskConfig,
+    market: &Market,
+) -> Result<RiskReport, RiskError> {
+    if let Some(level) = config
+        .confidence_levels
+        .iter()
+        .find(|level| **level <= 0.0 || **level >= 1.0)
+    {
+        return Err(RiskError::InvalidConfidenceLevel(*level));
+    }
+    let currency = positions.cash.currency;
+    let holdings = current_holdings(positions);
+    let asset_ids: Vec<i32> = holdings.iter().map(|(asset_id, _)| *asset_id).collect();
+    let (dates, prices) = price_history(&asset_ids, currency, start, end, calendar, market).await?;
+
+    let values: Vec<f64> = prices
+        .iter()
+        .map(|day_prices| {
+            positions.cash.position
+                + holdings
+                    .iter()
+                    .zip(day_prices)
+                    .map(|((_, units), price)| units * price)
+                    .sum::<f64>()
+        })
+        .collect();
+    let history = TimeSeries {
+        series: dates
+            .iter()
+            .zip(&values)
+            .map(|(date, value)| {
+                Ok(TimeValue {
+                    time: valuation_time(*date)?,
+                    value: *value,
+                })
+            })
+            .collect::<Result<_, DateTimeError>>()?,
+        title: "value".to_string(),
+    };
+    let value = values[values.len() - 1];
+
+    // annualized figures of daily returns
+    let returns: Vec<f64> = values.windows(2).map(|v| v[1] / v[0] - 1.0).collect();
+    let ppy = config.periods_per_year;
+    let volatility = if returns.len() > 1 {
+        (sample_variance(&returns) * ppy).sqrt()
+    } else {
+        0.0
+    };
+    let excess_return = mean(&returns) * ppy - config.risk_free_rate;
+    let daily_risk_free = config.risk_free_rate / ppy;
+    let downside: Vec<f64> = returns
+        .iter()
+        .map(|r| (r - daily_risk_free).min(0.0).powi(2))
+        .collect();
+    let downside_deviation = (mean(&downside) * ppy).sqrt();
+
+    // exposures and daily return statistics per asset for the parametric approach
+    let exposures: Vec<f64> = holdings
+        .iter()
+        .zip(&prices[prices.len() - 1])
+        .map(|((_, units), price)| units * price)
+        .collect();
+    let asset_returns: Vec<Vec<f64>> = (0..holdings.len())
+        .map(|asset| {
+            prices
+                .windows(2)
+                .map(|p| p[1][asset] / p[0][asset] - 1.0)
+                .collect()
+        })
+        .collect();
+    let mean_pnl: f64 = exposures
+        .iter()
+        .zip(&asset_returns)
+        .map(|(exposure, returns)| exposure * mean(returns))
+        .sum();
+    let mut pnl_variance = 0.0;
+    if returns.len() > 1 {
+        for (i, returns_i) in asset_returns.iter().enumerate() {
+            for (j, returns_j) in asset_returns.iter().enumerate() {
+                pnl_variance +=
+                    exposures[i] * exposures[j] * sample_covariance(returns_i, returns_j);
+            }
+        }
+    }
+
+    let mut value_at_risk = Vec::new();
+    for horizon in &config.horizons {
+        let horizon = (*horizon).max(1);
+        let losses: Vec<f64> = if values.len() > horizon {
+            values
+                .windows(horizon + 1)
+                .map(|v| v[0] - v[horizon])
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let sigma = (pnl_variance * horizon as f64).sqrt();
+        let mu = mean_pnl * horizon as f64;
+        for confidence_level in &config.confidence_levels {
+            let historical = historical_var(losses.clone(), *confidence_level);
+            let z = normal_quantile(*confidence_level);
+            value_at_risk.push(ValueAtRisk {
+                confidence_level: *confidence_level,
+                horizon,
+                historical_var: historical.map(|(var, _)| var),
+                historical_es: historical.map(|(_, es)| es),
+                parametric_var: z * sigma - mu,
+                parametric_es: sigma * normal_density(z) / (1.0 - confidence_level) - mu,
+            });
+        }
+    }
+
+    Ok(RiskReport {
+        start: dates[0],
+        end: dates[dates.len() - 1],
+        value,
+        volatility,
+        sharpe_ratio: if volatility > 0.0 {
+            Some(excess_return / volatility)
+        } else {
+            None
+        },
+        sortino_ratio: if downside_deviation > 0.0 {
+            Some(excess_return / downside_deviation)
+        } else {
+            None
+        },
+        value_at_risk,
+        max_drawdown: max_drawdown(&history),
+        history,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use crate::memory::{tests::insert_stock_with_quotes, InMemoryDB};
+    use crate::portfolio::Position;
+    use time::macros::date;
+
+    #[tokio::test]
+    async fn risk_of_single_asset_portfolio() {
+        let tol = 1e-10;
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let prices = [
+            (date!(2021 - 01 - 04), 100.0),
+            (date!(2021 - 01 - 05), 102.0),
+            (date!(2021 - 01 - 06), 99.0),
+            (date!(2021 - 01 - 07), 97.0),
+            (date!(2021 - 01 - 08), 101.0),
+            (date!(2021 - 01 - 11), 103.0),
+        ];
+        let asset_id = insert_stock_with_quotes(&db, "BMW AG", eur, &prices).await;
+        let mut positions = PortfolioPosition::new(eur);
+        positions.cash.position = 100.0;
+        let mut position = Position::new(Some(asset_id), eur);
+        position.position = 10.0;
+        positions.assets.insert(asset_id, position);
+
+        let calendar = market.get_calendar("TARGET").unwrap().clone();
+        let config = RiskConfig::new().with_confidence_levels(&[0.8, 0.95]);
+        let report = calc_risk(
+            &positions,
+            date!(2021 - 01 - 01),
+            date!(2021 - 01 - 11),
+            &calendar,
+            &config,
+            &market,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(report.start, date!(2021 - 01 - 04));
+        assert_eq!(report.end, date!(2021 - 01 - 11));
+        assert_fuzzy_eq!(report.value, 1130.0, tol);
+        let values = [1100.0, 1120.0, 1090.0, 1070.0, 1110.0, 1130.0];
+        assert_eq!(report.history.series.len(), values.len());
+        let returns: Vec<f64> = values.windows(2).map(|v| v[1] / v[0] - 1.0).collect();
+        let volatility = (sample_variance(&returns) * 252.0).sqrt();
+        assert_fuzzy_eq!(report.volatility, volatility, tol);
+        assert_fuzzy_eq!(
+            report.sharpe_ratio.unwrap(),
+            mean(&returns) * 252.0 / volatility,
+            tol
+        );
+        assert!(report.sortino_ratio.unwrap() > report.sharpe_ratio.unwrap());
+
+        // daily losses are -40, -20, -20, 20 and 30
+        assert_eq!(report.value_at_risk.len(), 2);
+        let var = &report.value_at_risk[0];
+        assert_eq!(var.horizon, 1);
+        assert_fuzzy_eq!(var.historical_var.unwrap(), 20.0, tol);
+        assert_fuzzy_eq!(var.historical_es.unwrap(), 25.0, tol);
+        // the 95% quantile of five losses is the worst loss
+        let var = &report.value_at_risk[1];
+        assert_fuzzy_eq!(var.historical_var.unwrap(), 30.0, tol);
+        assert_fuzzy_eq!(var.historical_es.unwrap(), 30.0, tol);
+        let var = &report.value_at_risk[0];
+
+        let asset_returns: Vec<f64> = prices.windows(2).map(|p| p[1].1 / p[0].1 - 1.0).collect();
+        let sigma = 1030.0 * sample_variance(&asset_returns).sqrt();
+        let mu = 1030.0 * mean(&asset_returns);
+        assert_fuzzy_eq!(var.parametric_var, 0.8416212335729143 * sigma - mu, 1e-6);
+        assert!(var.parametric_es > var.parametric_var);
+
+        let drawdown = report.max_drawdown.unwrap();
+        assert_fuzzy_eq!(drawdown.drawdown, 50.0 / 1120.0, tol);
+        assert_eq!(drawdown.peak, date!(2021 - 01 - 05));
+        assert_eq!(drawdown.trough, date!(2021 - 01 - 07));
+        assert_eq!(drawdown.recovery, Some(date!(2021 - 01 - 11)));
+    }
+
+    #[tokio::test]
+    async fn risk_edge_cases() {
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let calendar = market.get_calendar("TARGET").unwrap().clone();
+        let prices = [
+            (date!(2021 - 01 - 04), 100.0),
+            (date!(2021 - 01 - 05), 102.0),
+        ];
+        let asset_id = insert_stock_with_quotes(&db, "BMW AG", eur, &prices).await;
+        let no_quotes_id = insert_stock_with_quotes(&db, "Siemens AG", eur, &[]).await;
+        let mut positions = PortfolioPosition::new(eur);
+        let mut position = Position::new(Some(asset_id), eur);
+        position.position = 10.0;
+        positions.assets.insert(asset_id, position);
+        let config = RiskConfig::new();
+
+        // confidence levels must be strictly between 0 and 1
+        let invalid = RiskConfig::new().with_confidence_levels(&[0.95, 1.0]);
+        let err = calc_risk(
+            &positions,
+            date!(2021 - 01 - 01),
+            date!(2021 - 01 - 05),
+            &calendar,
+            &invalid,
+            &market,
+        )
+        .await;
+        assert!(matches!(err, Err(RiskError::InvalidConfidenceLevel(level)) if level == 1.0));
+
+        // a single business day with prices is not enough
+        let err = calc_risk(
+            &positions,
+            date!(2021 - 01 - 02),
+            date!(2021 - 01 - 04),
+            &calendar,
+            &config,
+            &market,
+        )
+        .await;
+        assert!(matches!(err, Err(RiskError::NotEnoughData)));
+
+        // two business days at the boundaries of the range are sufficient
+        let report = calc_risk(
+            &positions,
+            date!(2021 - 01 - 04),
+            date!(2021 - 01 - 05),
+            &calendar,
+            &config,
+            &market,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.start, date!(2021 - 01 - 04));
+        assert_eq!(report.end, date!(2021 - 01 - 05));
+        assert_eq!(report.history.series.len(), 2);
+        assert_eq!(report.volatility, 0.0);
+        assert!(report.sharpe_ratio.is_none());
+
+        // a held asset without any quotes is reported
+        let mut position = Position::new(Some(no_quotes_id), eur);
+        position.position = 5.0;
+        positions.assets.insert(no_quotes_id, position);
+        let err = calc_risk(
+            &positions,
+            date!(2021 - 01 - 01),
+            date!(2021 - 01 - 05),
+            &calendar,
+            &config,
+            &market,
+        )
+        .await;
+        assert!(matches!(err, Err(RiskError::MissingQuotes(id)) if id == no_quotes_id));
+    }
+}
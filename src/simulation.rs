@@ -0,0 +1,561 @@
+//! Monte Carlo simulation of the future value of a portfolio
+//!
+//! Daily log returns of the assets of a `PortfolioPosition` are estimated from their historic
+//! prices at the business days of a calendar. Future paths of the portfolio value are either
+//! generated by a correlated geometric Brownian motion with the estimated drift and covariance
+//! or by bootstrapping, i.e. by drawing historic days at random, which keeps the empirical
+//! distribution and the correlation of the asset returns. Cash is considered to be riskless
+//! without interest. The distribution of the simulated values is returned as percentile bands.
+use cal_calc::Calendar;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use time::Date;
+
+use crate::datatypes::date_time_helper::DateTimeError;
+use crate::helpers::{mean, normal_quantile, sample_covariance};
+use crate::portfolio::PortfolioPosition;
+use crate::risk::{current_holdings, price_history, valuation_time, RiskError};
+use crate::time_series::{TimeSeries, TimeValue};
+use crate::Market;
+
+#[derive(Error, Debug)]
+pub enum SimulationError {
+    #[error("Invalid percentile {0}, must be between 0 and 1")]
+    InvalidPercentile(f64),
+    #[error("Number of paths, periods and period length must be positive")]
+    InvalidPeriods,
+    #[error("Failed to get price history")]
+    PriceHistory(#[from] RiskError),
+    #[error("Calendar error")]
+    CalendarError(#[from] cal_calc::CalendarError),
+    #[error("Invalid date or time")]
+    DateTimeError(#[from] DateTimeError),
+}
+
+/// Model used to generate future asset returns
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ReturnModel {
+    /// Correlated geometric Brownian motion with historic drift and covariance
+    GeometricBrownianMotion,
+    /// Sums of daily returns of randomly drawn historic business days
+    Bootstrap,
+}
+
+/// Parameters of the simulation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationConfig {
+    pub model: ReturnModel,
+    /// Number of simulated paths
+    pub paths: usize,
+    /// Number of simulated periods
+    pub periods: usize,
+    /// Length of a period in business days
+    pub period_length: usize,
+    /// Amount added to the portfolio at the end of each period, withdrawals are negative
+    pub contribution: f64,
+    /// Percentiles of the simulated values to report, e.g. 0.05 for the 5th percentile
+    pub percentiles: Vec<f64>,
+    /// Seed of the random number generator, a random seed is used if not set
+    pub seed: Option<u64>,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            model: ReturnModel::GeometricBrownianMotion,
+            paths: 1000,
+            periods: 120,
+            period_length: 21,
+            contribution: 0.0,
+            percentiles: vec![0.05, 0.25, 0.5, 0.75, 0.95],
+            seed: None,
+        }
+    }
+}
+
+impl SimulationConfig {
+    /// 1000 paths of a geometric Brownian motion over 120 periods of 21 business days, i.e.
+    /// roughly ten years of monthly values, without contributions
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_model(mut self, model: ReturnModel) -> Self {
+        self.model = model;
+        self
+    }
+
+    pub fn with_paths(mut self, paths: usize) -> Self {
+        self.paths = paths;
+        self
+    }
+
+    pub fn with_periods(mut self, periods: usize, period_length: usize) -> Self {
+        self.periods = periods;
+        self.period_length = period_length;
+        self
+    }
+
+    pub fn with_contribution(mut self, contribution: f64) -> Self {
+        self.contribution = contribution;
+        self
+    }
+
+    pub fn with_percentiles(mut self, percentiles: &[f64]) -> Self {
+        self.percentiles = percentiles.to_vec();
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+/// Simulated portfolio values at a percentile
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PercentileBand {
+    pub percentile: f64,
+    pub series: TimeSeries,
+}
+
+/// Result of a Monte Carlo simulation of the portfolio value
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SimulationResult {
+    /// Last business day with historic prices, at which the simulation starts
+    pub start: Date,
+    /// Number of historic daily returns the model is based on
+    pub observations: usize,
+    /// Percentile bands of the portfolio value, starting with the current value
+    pub bands: Vec<PercentileBand>,
+    /// Mean of the simulated portfolio values
+    pub mean: TimeSeries,
+    /// Share of paths where withdrawals have used up the portfolio
+    pub depletion_probability: f64,
+}
+
+/// Lower triangular matrix `l` with `l * l^T = m` for a positive semi-definite matrix `m`.
+/// Columns without variance left are set to zero, which allows for perfectly correlated or
+/// constant assets.
+fn cholesky(m: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let n = m.len();
+    let mut l = vec![vec![0.0; n]; n];
+    for j in 0..n {
+        let diag = m[j][j] - (0..j).map(|k| l[j][k] * l[j][k]).sum::<f64>();
+        if diag <= 1e-12 * m[j][j].abs().max(f64::MIN_POSITIVE) {
+            continue;
+        }
+        l[j][j] = diag.sqrt();
+        for i in j + 1..n {
+            let sum = m[i][j] - (0..j).map(|k| l[i][k] * l[j][k]).sum::<f64>();
+            l[i][j] = sum / l[j][j];
+        }
+    }
+    l
+}
+
+/// Value at the given percentile of sorted values, interpolating linearly between samples
+fn percentile(sorted: &[f64], percentile: f64) -> f64 {
+    let pos = percentile * (sorted.len() - 1) as f64;
+    let idx = pos.floor() as usize;
+    if idx + 1 >= sorted.len() {
+        return sorted[sorted.len() - 1];
+    }
+    sorted[idx] + (pos - idx as f64) * (sorted[idx + 1] - sorted[idx])
+}
+
+/// Simulate the value of the current holdings of the portfolio based on the daily log returns
+/// of its assets at the business days of the calendar from `start` to `end` (both inclusive).
+/// Contributions are invested according to the current weights of the assets and cash within
+/// the portfolio, withdrawals reduce all positions proportionally. A path is depleted once a
+/// withdrawal exceeds its value and remains at zero afterwards.
+pub async fn simulate_portfolio(
+    positions: &PortfolioPosition,
+    start: Date,
+    end: Date,
+    calendar: &Calendar,
+    config: &SimulationConfig,
+    market: &Market,
+) -> Result<SimulationResult, SimulationError> {
+    if let Some(p) = config
+        .percentiles
+        .iter()
+        .find(|p| !(0.0..=1.0).contains(*p))
+    {
+        return Err(SimulationError::InvalidPercentile(*p));
+    }
+    if config.paths == 0 || config.periods == 0 || config.period_length == 0 {
+        return Err(SimulationError::InvalidPeriods);
+    }
+    let holdings = current_holdings(positions);
+    let asset_ids: Vec<i32> = holdings.iter().map(|(asset_id, _)| *asset_id).collect();
+    let (dates, prices) = price_history(
+        &asset_ids,
+        positions.cash.currency,
+        start,
+        end,
+        calendar,
+        market,
+    )
+    .await?;
+    let sim_start = dates[dates.len() - 1];
+
+    // daily log returns per business day and asset
+    let log_returns: Vec<Vec<f64>> = prices
+        .windows(2)
+        .map(|p| {
+            p[1].iter()
+                .zip(&p[0])
+                .map(|(p1, p0)| (p1 / p0).ln())
+                .collect()
+        })
+        .collect();
+    let asset_returns: Vec<Vec<f64>> = (0..asset_ids.len())
+        .map(|asset| log_returns.iter().map(|r| r[asset]).collect())
+        .collect();
+    let drift: Vec<f64> = asset_returns.iter().map(|r| mean(r)).collect();
+    let covariance: Vec<Vec<f64>> = asset_returns
+        .iter()
+        .map(|r_i| {
+            asset_returns
+                .iter()
+                .map(|r_j| {
+                    if log_returns.len() > 1 {
+                        sample_covariance(r_i, r_j)
+                    } else {
+                        0.0
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let cholesky = cholesky(&covariance);
+
+    // current values per asset and weights for investing contributions, cash is stored last
+    let mut initial: Vec<f64> = holdings
+        .iter()
+        .zip(&prices[prices.len() - 1])
+        .map(|((_, units), price)| units * price)
+        .collect();
+    initial.push(positions.cash.position);
+    let initial_value: f64 = initial.iter().sum();
+    let weights: Vec<f64> = if initial_value > 0.0 {
+        initial.iter().map(|v| v / initial_value).collect()
+    } else {
+        let mut weights = vec![0.0; initial.len()];
+        weights[initial.len() - 1] = 1.0;
+        weights
+    };
+
+    let mut rng = match config.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_os_rng(),
+    };
+    let n = asset_ids.len();
+    let length = config.period_length as f64;
+    // simulated portfolio values per period and path
+    let mut values = vec![vec![0.0; config.paths]; config.periods];
+    let mut depleted = 0;
+    let mut period_returns = vec![0.0; n];
+    let mut normals = vec![0.0; n];
+    for path in 0..config.paths {
+        let mut state = initial.clone();
+        let mut is_depleted = false;
+        for period_values in values.iter_mut() {
+            if is_depleted {
+                continue;
+            }
+            match config.model {
+                ReturnModel::GeometricBrownianMotion => {
+                    for z in normals.iter_mut() {
+                        let u: f64 = rng.random();
+                        *z = normal_quantile(u.max(f64::MIN_POSITIVE));
+                    }
+                    for (i, period_return) in period_returns.iter_mut().enumerate() {
+                        let noise: f64 = (0..=i).map(|k| cholesky[i][k] * normals[k]).sum();
+                        *period_return = drift[i] * length + noise * length.sqrt();
+                    }
+                }
+                ReturnModel::Bootstrap => {
+                    period_returns.iter_mut().for_each(|r| *r = 0.0);
+                    for _ in 0..config.period_length {
+                        let day = &log_returns[rng.random_range(0..log_returns.len())];
+                        for (r, day_return) in period_returns.iter_mut().zip(day) {
+                            *r += day_return;
+                        }
+                    }
+                }
+            }
+            for (value, period_return) in state.iter_mut().zip(&period_returns) {
+                *value *= period_return.exp();
+            }
+            let total: f64 = state.iter().sum();
+            if config.contribution >= 0.0 {
+                for (value, weight) in state.iter_mut().zip(&weights) {
+                    *value += config.contribution * weight;
+                }
+            } else if total + config.contribution > 0.0 {
+                let factor = (total + config.contribution) / total;
+                state.iter_mut().for_each(|value| *value *= factor);
+            } else {
+                is_depleted = true;
+                continue;
+            }
+            period_values[path] = state.iter().sum();
+        }
+        if is_depleted {
+            depleted += 1;
+        }
+    }
+
+    // dates at the end of each period, starting with the current value
+    let mut times = vec![valuation_time(sim_start)?];
+    let mut date = sim_start;
+    for _ in 0..config.periods {
+        for _ in 0..config.period_length {
+            date = calendar.next_bday(date)?;
+        }
+        times.push(valuation_time(date)?);
+    }
+    let to_series = |title: String, period_values: Vec<f64>| TimeSeries {
+        series: times
+            .iter()
+            .zip(std::iter::once(initial_value).chain(period_values))
+            .map(|(time, value)| TimeValue { time: *time, value })
+            .collect(),
+        title,
+    };
+    let mut bands: Vec<(f64, Vec<f64>)> = config
+        .percentiles
+        .iter()
+        .map(|p| (*p, Vec::with_capacity(config.periods)))
+        .collect();
+    let mut means = Vec::with_capacity(config.periods);
+    for period_values in values.iter_mut() {
+        period_values.sort_by(|a, b| a.total_cmp(b));
+        for (p, band) in bands.iter_mut() {
+            band.push(percentile(period_values, *p));
+        }
+        means.push(mean(period_values));
+    }
+
+    Ok(SimulationResult {
+        start: sim_start,
+        observations: log_returns.len(),
+        bands: bands
+            .into_iter()
+            .map(|(p, band)| PercentileBand {
+                percentile: p,
+                series: to_series(format!("{}th percentile", p * 100.0), band),
+            })
+            .collect(),
+        mean: to_series("mean".to_string(), means),
+        depletion_probability: depleted as f64 / config.paths as f64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use crate::memory::{tests::insert_stock_with_quotes, InMemoryDB};
+    use crate::portfolio::Position;
+    use time::macros::date;
+
+    #[tokio::test]
+    async fn simulate_constant_growth() {
+        let tol = 1e-8;
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        // prices grow by 1% each business day
+        let asset_id = insert_stock_with_quotes(
+            &db,
+            "Steady Growth",
+            eur,
+            &[
+                (date!(2021 - 01 - 04), 100.0),
+                (date!(2021 - 01 - 05), 101.0),
+                (date!(2021 - 01 - 06), 102.01),
+                (date!(2021 - 01 - 07), 103.0301),
+            ],
+        )
+        .await;
+        let mut positions = PortfolioPosition::new(eur);
+        let mut position = Position::new(Some(asset_id), eur);
+        position.position = 10.0;
+        positions.assets.insert(asset_id, position);
+
+        let calendar = market.get_calendar("TARGET").unwrap().clone();
+        let config = SimulationConfig::new()
+            .with_paths(10)
+            .with_periods(3, 2)
+            .with_contribution(100.0)
+            .with_percentiles(&[0.1, 0.9])
+            .with_seed(42);
+        let result = simulate_portfolio(
+            &positions,
+            date!(2021 - 01 - 04),
+            date!(2021 - 01 - 07),
+            &calendar,
+            &config,
+            &market,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.start, date!(2021 - 01 - 07));
+        assert_eq!(result.observations, 3);
+        assert_eq!(result.depletion_probability, 0.0);
+        let mut expected = vec![1030.301];
+        for _ in 0..3 {
+            expected.push(expected[expected.len() - 1] * 1.0201 + 100.0);
+        }
+        assert_eq!(result.bands.len(), 2);
+        for series in result
+            .bands
+            .iter()
+            .map(|band| &band.series)
+            .chain(std::iter::once(&result.mean))
+        {
+            assert_eq!(series.series.len(), 4);
+            for (sample, value) in series.series.iter().zip(&expected) {
+                assert_fuzzy_eq!(sample.value, *value, tol);
+            }
+        }
+        let dates: Vec<Date> = result.mean.series.iter().map(|s| s.time.date()).collect();
+        assert_eq!(
+            dates,
+            vec![
+                date!(2021 - 01 - 07),
+                date!(2021 - 01 - 11),
+                date!(2021 - 01 - 13),
+                date!(2021 - 01 - 15)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn simulate_savings_plan_from_zero() {
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let positions = PortfolioPosition::new(eur);
+        let calendar = market.get_calendar("TARGET").unwrap().clone();
+        let config = SimulationConfig::new()
+            .with_paths(10)
+            .with_periods(12, 21)
+            .with_contribution(100.0)
+            .with_seed(3);
+        let result = simulate_portfolio(
+            &positions,
+            date!(2021 - 01 - 04),
+            date!(2021 - 01 - 08),
+            &calendar,
+            &config,
+            &market,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.depletion_probability, 0.0);
+        for (period, sample) in result.mean.series.iter().enumerate() {
+            assert_eq!(sample.value, 100.0 * period as f64);
+        }
+        for band in &result.bands {
+            assert_eq!(band.series.series[12].value, 1200.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn simulate_correlated_assets() {
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let dates = [
+            date!(2021 - 01 - 04),
+            date!(2021 - 01 - 05),
+            date!(2021 - 01 - 06),
+            date!(2021 - 01 - 07),
+            date!(2021 - 01 - 08),
+        ];
+        let quotes = |prices: [f64; 5]| dates.iter().copied().zip(prices).collect::<Vec<_>>();
+        let first = insert_stock_with_quotes(
+            &db,
+            "First",
+            eur,
+            &quotes([100.0, 103.0, 99.0, 101.0, 98.0]),
+        )
+        .await;
+        let second =
+            insert_stock_with_quotes(&db, "Second", eur, &quotes([50.0, 51.0, 50.5, 52.0, 50.0]))
+                .await;
+        let mut positions = PortfolioPosition::new(eur);
+        positions.cash.position = 500.0;
+        for (asset_id, units) in [(first, 10.0), (second, 20.0)].iter() {
+            let mut position = Position::new(Some(*asset_id), eur);
+            position.position = *units;
+            positions.assets.insert(*asset_id, position);
+        }
+
+        let calendar = market.get_calendar("TARGET").unwrap().clone();
+        for model in [ReturnModel::GeometricBrownianMotion, ReturnModel::Bootstrap].iter() {
+            let config = SimulationConfig::new()
+                .with_model(*model)
+                .with_paths(200)
+                .with_periods(12, 5)
+                .with_seed(7);
+            let simulate = || {
+                simulate_portfolio(
+                    &positions,
+                    date!(2021 - 01 - 04),
+                    date!(2021 - 01 - 08),
+                    &calendar,
+                    &config,
+                    &market,
+                )
+            };
+            let result = simulate().await.unwrap();
+            assert_eq!(result.bands.len(), 5);
+            for band in &result.bands {
+                assert_eq!(band.series.series.len(), 13);
+                assert_eq!(band.series.series[0].value, 2480.0);
+            }
+            for pair in result.bands.windows(2) {
+                for (lower, upper) in pair[0].series.series.iter().zip(&pair[1].series.series) {
+                    assert!(lower.value <= upper.value);
+                }
+            }
+            assert!(result.bands[0].series.series[12].value < 2480.0);
+            assert!(result.bands[4].series.series[12].value > 2000.0);
+
+            // the same seed reproduces the same paths
+            let again = simulate().await.unwrap();
+            for (a, b) in result.mean.series.iter().zip(&again.mean.series) {
+                assert_eq!(a.value, b.value);
+            }
+        }
+
+        // withdrawals larger than the portfolio value deplete all paths
+        let config = SimulationConfig::new()
+            .with_paths(20)
+            .with_periods(3, 5)
+            .with_contribution(-1000.0)
+            .with_seed(1);
+        let result = simulate_portfolio(
+            &positions,
+            date!(2021 - 01 - 04),
+            date!(2021 - 01 - 08),
+            &calendar,
+            &config,
+            &market,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.depletion_probability, 1.0);
+        assert_eq!(result.mean.series[3].value, 0.0);
+    }
+}
@@ -0,0 +1,768 @@
+use async_trait::async_trait;
+use sqlx::{sqlite::SqliteRow, Row, Sqlite, Transaction};
+use std::str::FromStr;
+use time::Date;
+
+use crate::datatypes::{
+    Asset, AssetHandler, AssetSelector, Commodity, CorporateAction, CorporateActionKind,
+    CorporateActionTarget, Crypto, Currency, CurrencyISOCode, DataError, DataItem,
+    DistributionPolicy, Fund, FundConstituent, Identifier, IdentifierType, Stock,
+};
+
+use super::SqliteDB;
+use crate::bond::Bond;
+
+/// Store the constituents of a fund, replacing all previously stored constituents
+async fn store_fund_constituents(
+    tx: &mut Transaction<'_, Sqlite>,
+    fund_id: i32,
+    constituents: &[FundConstituent],
+) -> Result<(), DataError> {
+    sqlx::query("DELETE FROM fund_constituents WHERE fund_id=?")
+        .bind(fund_id)
+        .execute(&mut **tx)
+        .await?;
+    for constituent in constituents {
+        sqlx::query(
+            "INSERT INTO fund_constituents (fund_id, asset_id, name, weight) VALUES (?, ?, ?, ?)",
+        )
+        .bind(fund_id)
+        .bind(constituent.asset_id)
+        .bind(&constituent.name)
+        .bind(constituent.weight)
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Store the target assets of a corporate action, replacing all previously stored targets
+async fn store_corporate_action_targets(
+    tx: &mut Transaction<'_, Sqlite>,
+    action_id: i32,
+    targets: &[CorporateActionTarget],
+) -> Result<(), DataError> {
+    sqlx::query("DELETE FROM corporate_action_targets WHERE action_id=?")
+        .bind(action_id)
+        .execute(&mut **tx)
+        .await?;
+    for target in targets {
+        sqlx::query(
+            "INSERT INTO corporate_action_targets (action_id, asset_id, ratio, cost_share)
+                VALUES (?, ?, ?, ?)",
+        )
+        .bind(action_id)
+        .bind(target.asset_id)
+        .bind(target.ratio)
+        .bind(target.cost_share)
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
+
+impl SqliteDB {
+    /// Construct a corporate action from a row of table `corporate_actions`
+    async fn corporate_action_from_row(
+        &self,
+        row: &SqliteRow,
+    ) -> Result<CorporateAction, DataError> {
+        let id: i32 = row.try_get("id")?;
+        let kind: String = row.try_get("kind")?;
+        let mut targets = Vec::new();
+        for target in sqlx::query(
+            "SELECT asset_id, ratio, cost_share FROM corporate_action_targets
+                WHERE action_id=? ORDER BY id",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?
+        {
+            targets.push(CorporateActionTarget {
+                asset_id: target.try_get("asset_id")?,
+                ratio: target.try_get("ratio")?,
+                cost_share: target.try_get("cost_share")?,
+            });
+        }
+        Ok(CorporateAction {
+            id: Some(id),
+            kind: CorporateActionKind::from_str(&kind)?,
+            asset_id: row.try_get("asset_id")?,
+            date: row.try_get("action_date")?,
+            targets,
+            note: row.try_get("note")?,
+        })
+    }
+}
+
+/// Handler for globally available Asset data
+#[async_trait]
+impl AssetHandler for SqliteDB {
+    async fn insert_asset(&self, asset: &Asset) -> Result<i32, DataError> {
+        let mut tx = self.pool.begin().await?;
+        let id: i32 = sqlx::query("INSERT INTO assets (asset_class) VALUES (?) RETURNING id")
+            .bind(asset.class())
+            .fetch_one(&mut *tx)
+            .await?
+            .try_get("id")?;
+
+        match asset {
+            Asset::Currency(c) => {
+                sqlx::query(
+                    "INSERT INTO currencies (id, iso_code, rounding_digits) VALUES (?, ?, ?)",
+                )
+                .bind(id)
+                .bind(c.iso_code.to_string())
+                .bind(c.rounding_digits)
+                .execute(&mut *tx)
+                .await?;
+            }
+            Asset::Stock(s) => {
+                sqlx::query(
+                    "INSERT INTO stocks (id, name, isin, wkn, note) VALUES (?, ?, ?, ?, ?)",
+                )
+                .bind(id)
+                .bind(&s.name)
+                .bind(&s.isin)
+                .bind(&s.wkn)
+                .bind(&s.note)
+                .execute(&mut *tx)
+                .await?;
+            }
+            Asset::Bond(b) => {
+                sqlx::query(
+                    "INSERT INTO bonds (id, name, isin, issuer, currency_id, maturity, denomination, spec)
+                    VALUES (?, ?, ?, ?, (SELECT id FROM currencies WHERE iso_code = ?), ?, ?, ?)",
+                )
+                .bind(id)
+                .bind(b.name())
+                .bind(b.isin())
+                .bind(b.issuer())
+                .bind(b.currency().iso_code.to_string())
+                .bind(b.maturity())
+                .bind(b.denomination as i64)
+                .bind(serde_json::to_string(b)?)
+                .execute(&mut *tx)
+                .await?;
+            }
+            Asset::Fund(f) => {
+                sqlx::query(
+                    "INSERT INTO funds (id, name, isin, wkn, ter, distribution, domicile, note)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                )
+                .bind(id)
+                .bind(&f.name)
+                .bind(&f.isin)
+                .bind(&f.wkn)
+                .bind(f.ter)
+                .bind(f.distribution.to_string())
+                .bind(&f.domicile)
+                .bind(&f.note)
+                .execute(&mut *tx)
+                .await?;
+                store_fund_constituents(&mut tx, id, &f.constituents).await?;
+            }
+            Asset::Crypto(c) => {
+                sqlx::query(
+                    "INSERT INTO cryptos (id, name, symbol, chain, decimals, note)
+                    VALUES (?, ?, ?, ?, ?, ?)",
+                )
+                .bind(id)
+                .bind(&c.name)
+                .bind(&c.symbol)
+                .bind(&c.chain)
+                .bind(c.decimals)
+                .bind(&c.note)
+                .execute(&mut *tx)
+                .await?;
+            }
+            Asset::Commodity(c) => {
+                sqlx::query("INSERT INTO commodities (id, name, unit, note) VALUES (?, ?, ?, ?)")
+                    .bind(id)
+                    .bind(&c.name)
+                    .bind(&c.unit)
+                    .bind(&c.note)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    async fn get_asset_id(&self, asset: &Asset) -> Option<i32> {
+        let query = match asset {
+            Asset::Currency(c) => sqlx::query("SELECT id FROM currencies WHERE iso_code = ?")
+                .bind(c.iso_code.to_string()),
+            Asset::Stock(s) => {
+                if let Some(wkn) = &s.wkn {
+                    sqlx::query("SELECT id FROM stocks WHERE wkn = ?").bind(wkn.clone())
+                } else if let Some(isin) = &s.isin {
+                    sqlx::query("SELECT id FROM stocks WHERE isin = ?").bind(isin.clone())
+                } else {
+                    sqlx::query("SELECT id FROM stocks WHERE name = ?").bind(s.name.clone())
+                }
+            }
+            Asset::Bond(b) => {
+                if let Some(isin) = b.isin() {
+                    sqlx::query("SELECT id FROM bonds WHERE isin = ?").bind(isin.to_string())
+                } else {
+                    sqlx::query("SELECT id FROM bonds WHERE name = ?").bind(b.name())
+                }
+            }
+            Asset::Fund(f) => {
+                if let Some(wkn) = &f.wkn {
+                    sqlx::query("SELECT id FROM funds WHERE wkn = ?").bind(wkn.clone())
+                } else if let Some(isin) = &f.isin {
+                    sqlx::query("SELECT id FROM funds WHERE isin = ?").bind(isin.clone())
+                } else {
+                    sqlx::query("SELECT id FROM funds WHERE name = ?").bind(f.name.clone())
+                }
+            }
+            Asset::Crypto(c) => {
+                sqlx::query("SELECT id FROM cryptos WHERE symbol = ? AND chain IS ?")
+                    .bind(c.symbol.clone())
+                    .bind(c.chain.clone())
+            }
+            Asset::Commodity(c) => {
+                sqlx::query("SELECT id FROM commodities WHERE name = ?").bind(c.name.clone())
+            }
+        };
+        let row = query.fetch_one(&self.pool).await.ok()?;
+        row.try_get("id").ok()
+    }
+
+    async fn get_asset_by_id(&self, id: i32) -> Result<Asset, DataError> {
+        let row = sqlx::query("SELECT asset_class FROM assets WHERE id = ?")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+        let asset_class: String = row.try_get("asset_class")?;
+
+        match asset_class.as_str() {
+            "currency" => {
+                let row = sqlx::query(
+                    "SELECT id, iso_code, rounding_digits FROM currencies WHERE id = ?",
+                )
+                .bind(id)
+                .fetch_one(&self.pool)
+                .await?;
+                let iso_code: String = row.try_get("iso_code")?;
+                Ok(Asset::Currency(Currency::new(
+                    Some(row.try_get("id")?),
+                    CurrencyISOCode::new(&iso_code)?,
+                    Some(row.try_get("rounding_digits")?),
+                )))
+            }
+            "stock" => {
+                let row = sqlx::query("SELECT id, name, isin, wkn, note FROM stocks WHERE id = ?")
+                    .bind(id)
+                    .fetch_one(&self.pool)
+                    .await?;
+                Ok(Asset::Stock(Stock::new(
+                    Some(row.try_get("id")?),
+                    row.try_get("name")?,
+                    row.try_get("isin")?,
+                    row.try_get("wkn")?,
+                    row.try_get("note")?,
+                )))
+            }
+            "bond" => {
+                let row = sqlx::query(
+                    "SELECT b.id, b.spec, c.id AS currency_id, c.iso_code, c.rounding_digits
+                        FROM bonds b
+                        JOIN currencies c ON c.id = b.currency_id
+                        WHERE b.id = ?",
+                )
+                .bind(id)
+                .fetch_one(&self.pool)
+                .await?;
+                let spec: String = row.try_get("spec")?;
+                let iso_code: String = row.try_get("iso_code")?;
+                let mut bond: Bond = serde_json::from_str(&spec)?;
+                bond.id = Some(row.try_get("id")?);
+                bond.currency = Currency::new(
+                    Some(row.try_get("currency_id")?),
+                    CurrencyISOCode::new(&iso_code)?,
+                    Some(row.try_get("rounding_digits")?),
+                );
+                Ok(Asset::Bond(Box::new(bond)))
+            }
+            "fund" => {
+                let row = sqlx::query(
+                    "SELECT id, name, isin, wkn, ter, distribution, domicile, note
+                        FROM funds WHERE id = ?",
+                )
+                .bind(id)
+                .fetch_one(&self.pool)
+                .await?;
+                let mut constituents = Vec::new();
+                for constituent in sqlx::query(
+                    "SELECT asset_id, name, weight FROM fund_constituents
+                        WHERE fund_id = ? ORDER BY id",
+                )
+                .bind(id)
+                .fetch_all(&self.pool)
+                .await?
+                {
+                    constituents.push(FundConstituent {
+                        asset_id: constituent.try_get("asset_id")?,
+                        name: constituent.try_get("name")?,
+                        weight: constituent.try_get("weight")?,
+                    });
+                }
+                let distribution: String = row.try_get("distribution")?;
+                Ok(Asset::Fund(Fund {
+                    id: Some(row.try_get("id")?),
+                    name: row.try_get("name")?,
+                    isin: row.try_get("isin")?,
+                    wkn: row.try_get("wkn")?,
+                    ter: row.try_get("ter")?,
+                    distribution: DistributionPolicy::from_str(&distribution)?,
+                    domicile: row.try_get("domicile")?,
+                    note: row.try_get("note")?,
+                    constituents,
+                }))
+            }
+            "crypto" => {
+                let row = sqlx::query(
+                    "SELECT id, name, symbol, chain, decimals, note FROM cryptos WHERE id = ?",
+                )
+                .bind(id)
+                .fetch_one(&self.pool)
+                .await?;
+                Ok(Asset::Crypto(Crypto {
+                    id: Some(row.try_get("id")?),
+                    name: row.try_get("name")?,
+                    symbol: row.try_get("symbol")?,
+                    chain: row.try_get("chain")?,
+                    decimals: row.try_get("decimals")?,
+                    note: row.try_get("note")?,
+                }))
+            }
+            "commodity" => {
+                let row = sqlx::query("SELECT id, name, unit, note FROM commodities WHERE id = ?")
+                    .bind(id)
+                    .fetch_one(&self.pool)
+                    .await?;
+                Ok(Asset::Commodity(Commodity::new(
+                    Some(row.try_get("id")?),
+                    row.try_get("name")?,
+                    row.try_get("unit")?,
+                    row.try_get("note")?,
+                )))
+            }
+            _ => Err(DataError::InvalidAsset(asset_class)),
+        }
+    }
+
+    async fn get_asset_by_isin(&self, isin: &str) -> Result<Asset, DataError> {
+        let row = sqlx::query(
+            "SELECT id FROM stocks WHERE isin = ?1
+                UNION ALL
+                SELECT id FROM bonds WHERE isin = ?1
+                UNION ALL
+                SELECT id FROM funds WHERE isin = ?1",
+        )
+        .bind(isin)
+        .fetch_one(&self.pool)
+        .await?;
+        self.get_asset_by_id(row.try_get("id")?).await
+    }
+
+    async fn get_all_assets(&self) -> Result<Vec<Asset>, DataError> {
+        let mut assets = Vec::new();
+        for row in sqlx::query("SELECT id FROM assets")
+            .fetch_all(&self.pool)
+            .await?
+        {
+            assets.push(self.get_asset_by_id(row.try_get("id")?).await?);
+        }
+        Ok(assets)
+    }
+
+    /// Return AssetSelector for all assets
+    async fn get_asset_list(&self) -> Result<Vec<AssetSelector>, DataError> {
+        let mut assets = Vec::new();
+        for row in sqlx::query(
+            "SELECT
+                a.id,
+                a.asset_class,
+                CASE
+                    WHEN a.asset_class='currency' THEN c.iso_code
+                    WHEN a.asset_class='bond' THEN b.name
+                    WHEN a.asset_class='fund' THEN f.name
+                    WHEN a.asset_class='crypto' THEN cr.name
+                    WHEN a.asset_class='commodity' THEN co.name
+                    ELSE s.name
+                END AS name
+            FROM
+                assets a
+                LEFT JOIN stocks s ON a.id = s.id
+                LEFT JOIN currencies c ON a.id = c.id
+                LEFT JOIN bonds b ON a.id = b.id
+                LEFT JOIN funds f ON a.id = f.id
+                LEFT JOIN cryptos cr ON a.id = cr.id
+                LEFT JOIN commodities co ON a.id = co.id",
+        )
+        .fetch_all(&self.pool)
+        .await?
+        {
+            assets.push(AssetSelector {
+                id: row.try_get("id")?,
+                name: row.try_get("name")?,
+                class: row.try_get("asset_class")?,
+            });
+        }
+        Ok(assets)
+    }
+
+    async fn update_asset(&self, asset: &Asset) -> Result<(), DataError> {
+        match asset {
+            Asset::Currency(c) => {
+                let id = c
+                    .id
+                    .ok_or_else(|| DataError::NotFound("not yet stored to database".to_string()))?;
+                sqlx::query("UPDATE currencies SET iso_code=?, rounding_digits=? WHERE id=?")
+                    .bind(c.iso_code.to_string())
+                    .bind(c.rounding_digits)
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+            }
+            Asset::Stock(s) => {
+                let id = s
+                    .id
+                    .ok_or_else(|| DataError::NotFound("not yet stored to database".to_string()))?;
+                sqlx::query("UPDATE stocks SET name=?, isin=?, wkn=?, note=? WHERE id=?")
+                    .bind(&s.name)
+                    .bind(&s.isin)
+                    .bind(&s.wkn)
+                    .bind(&s.note)
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+            }
+            Asset::Bond(b) => {
+                let id = b
+                    .id
+                    .ok_or_else(|| DataError::NotFound("not yet stored to database".to_string()))?;
+                sqlx::query(
+                    "UPDATE bonds SET name=?, isin=?, issuer=?,
+                        currency_id=(SELECT id FROM currencies WHERE iso_code = ?),
+                        maturity=?, denomination=?, spec=?
+                        WHERE id=?",
+                )
+                .bind(b.name())
+                .bind(b.isin())
+                .bind(b.issuer())
+                .bind(b.currency().iso_code.to_string())
+                .bind(b.maturity())
+                .bind(b.denomination as i64)
+                .bind(serde_json::to_string(b)?)
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+            }
+            Asset::Fund(f) => {
+                let id = f
+                    .id
+                    .ok_or_else(|| DataError::NotFound("not yet stored to database".to_string()))?;
+                let mut tx = self.pool.begin().await?;
+                sqlx::query(
+                    "UPDATE funds SET name=?, isin=?, wkn=?, ter=?, distribution=?, domicile=?,
+                        note=? WHERE id=?",
+                )
+                .bind(&f.name)
+                .bind(&f.isin)
+                .bind(&f.wkn)
+                .bind(f.ter)
+                .bind(f.distribution.to_string())
+                .bind(&f.domicile)
+                .bind(&f.note)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+                store_fund_constituents(&mut tx, id, &f.constituents).await?;
+                tx.commit().await?;
+            }
+            Asset::Crypto(c) => {
+                let id = c
+                    .id
+                    .ok_or_else(|| DataError::NotFound("not yet stored to database".to_string()))?;
+                sqlx::query(
+                    "UPDATE cryptos SET name=?, symbol=?, chain=?, decimals=?, note=? WHERE id=?",
+                )
+                .bind(&c.name)
+                .bind(&c.symbol)
+                .bind(&c.chain)
+                .bind(c.decimals)
+                .bind(&c.note)
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+            }
+            Asset::Commodity(c) => {
+                let id = c
+                    .id
+                    .ok_or_else(|| DataError::NotFound("not yet stored to database".to_string()))?;
+                sqlx::query("UPDATE commodities SET name=?, unit=?, note=? WHERE id=?")
+                    .bind(&c.name)
+                    .bind(&c.unit)
+                    .bind(&c.note)
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_asset(&self, id: i32) -> Result<(), DataError> {
+        let row = sqlx::query("SELECT asset_class FROM assets WHERE id=?")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+        let asset_class: String = row.try_get("asset_class")?;
+        let table = match asset_class.as_str() {
+            "currency" => "currencies",
+            "stock" => "stocks",
+            "bond" => "bonds",
+            "fund" => "funds",
+            "crypto" => "cryptos",
+            "commodity" => "commodities",
+            _ => {
+                return Err(DataError::InvalidAsset(
+                    "Could not delete unknown asset".to_string(),
+                ))
+            }
+        };
+        let mut tx = self.pool.begin().await?;
+        if table == "funds" {
+            store_fund_constituents(&mut tx, id, &[]).await?;
+        }
+        sqlx::query("DELETE FROM asset_identifiers WHERE asset_id=?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&format!("DELETE FROM {table} WHERE id=?"))
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM assets WHERE id=?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn add_asset_identifier(
+        &self,
+        asset_id: i32,
+        identifier: &Identifier,
+    ) -> Result<(), DataError> {
+        identifier.validate()?;
+        sqlx::query("INSERT INTO asset_identifiers (asset_id, id_type, value) VALUES (?, ?, ?)")
+            .bind(asset_id)
+            .bind(identifier.id_type.to_string())
+            .bind(&identifier.value)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_asset_identifiers(&self, asset_id: i32) -> Result<Vec<Identifier>, DataError> {
+        let mut identifiers = Vec::new();
+        for row in
+            sqlx::query("SELECT id_type, value FROM asset_identifiers WHERE asset_id=? ORDER BY id")
+                .bind(asset_id)
+                .fetch_all(&self.pool)
+                .await?
+        {
+            let id_type: String = row.try_get("id_type")?;
+            identifiers.push(Identifier {
+                id_type: IdentifierType::from_str(&id_type)?,
+                value: row.try_get("value")?,
+            });
+        }
+        Ok(identifiers)
+    }
+
+    async fn delete_asset_identifier(
+        &self,
+        asset_id: i32,
+        identifier: &Identifier,
+    ) -> Result<(), DataError> {
+        sqlx::query("DELETE FROM asset_identifiers WHERE asset_id=? AND id_type=? AND value=?")
+            .bind(asset_id)
+            .bind(identifier.id_type.to_string())
+            .bind(&identifier.value)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn find_asset_by_identifier(&self, identifier: &Identifier) -> Result<Asset, DataError> {
+        for key in identifier.lookup_keys() {
+            let row = sqlx::query(
+                "SELECT asset_id AS id FROM asset_identifiers WHERE id_type = ?1 AND value = ?2
+                    UNION ALL
+                    SELECT id FROM stocks WHERE (?1 = 'ISIN' AND isin = ?2) OR (?1 = 'WKN' AND wkn = ?2)
+                    UNION ALL
+                    SELECT id FROM bonds WHERE ?1 = 'ISIN' AND isin = ?2
+                    UNION ALL
+                    SELECT id FROM funds WHERE (?1 = 'ISIN' AND isin = ?2) OR (?1 = 'WKN' AND wkn = ?2)
+                    LIMIT 1",
+            )
+            .bind(key.id_type.to_string())
+            .bind(&key.value)
+            .fetch_optional(&self.pool)
+            .await?;
+            if let Some(row) = row {
+                return self.get_asset_by_id(row.try_get("id")?).await;
+            }
+        }
+        Err(DataError::NotFound(format!("asset with {identifier}")))
+    }
+
+    async fn insert_corporate_action(&self, action: &CorporateAction) -> Result<i32, DataError> {
+        action.validate()?;
+        let mut tx = self.pool.begin().await?;
+        let row = sqlx::query(
+            "INSERT INTO corporate_actions (kind, asset_id, action_date, note)
+                VALUES (?, ?, ?, ?) RETURNING id",
+        )
+        .bind(action.kind.to_string())
+        .bind(action.asset_id)
+        .bind(action.date)
+        .bind(&action.note)
+        .fetch_one(&mut *tx)
+        .await?;
+        let id: i32 = row.try_get("id")?;
+        store_corporate_action_targets(&mut tx, id, &action.targets).await?;
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    async fn get_corporate_action_by_id(&self, id: i32) -> Result<CorporateAction, DataError> {
+        let row = sqlx::query(
+            "SELECT id, kind, asset_id, action_date, note FROM corporate_actions WHERE id=?",
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        self.corporate_action_from_row(&row).await
+    }
+
+    async fn get_corporate_actions(
+        &self,
+        start: Option<Date>,
+        end: Option<Date>,
+    ) -> Result<Vec<CorporateAction>, DataError> {
+        let mut actions = Vec::new();
+        for row in sqlx::query(
+            "SELECT id, kind, asset_id, action_date, note FROM corporate_actions
+                WHERE (?1 IS NULL OR action_date >= ?1)
+                    AND (?2 IS NULL OR action_date <= ?2)
+                ORDER BY action_date, id",
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?
+        {
+            actions.push(self.corporate_action_from_row(&row).await?);
+        }
+        Ok(actions)
+    }
+
+    async fn update_corporate_action(&self, action: &CorporateAction) -> Result<(), DataError> {
+        let id = action.get_id()?;
+        action.validate()?;
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "UPDATE corporate_actions SET kind=?, asset_id=?, action_date=?, note=? WHERE id=?",
+        )
+        .bind(action.kind.to_string())
+        .bind(action.asset_id)
+        .bind(action.date)
+        .bind(&action.note)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        store_corporate_action_targets(&mut tx, id, &action.targets).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_corporate_action(&self, id: i32) -> Result<(), DataError> {
+        let mut tx = self.pool.begin().await?;
+        store_corporate_action_targets(&mut tx, id, &[]).await?;
+        sqlx::query("DELETE FROM corporate_actions WHERE id=?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_all_currencies(&self) -> Result<Vec<Currency>, DataError> {
+        let mut currencies = Vec::new();
+        for row in sqlx::query("SELECT id, iso_code, rounding_digits FROM currencies")
+            .fetch_all(&self.pool)
+            .await?
+        {
+            let iso_code: String = row.try_get("iso_code")?;
+            currencies.push(Currency::new(
+                Some(row.try_get("id")?),
+                CurrencyISOCode::new(&iso_code)?,
+                Some(row.try_get("rounding_digits")?),
+            ));
+        }
+        Ok(currencies)
+    }
+
+    async fn get_currency_list(&self) -> Result<Vec<AssetSelector>, DataError> {
+        let mut currencies = Vec::new();
+        for row in sqlx::query("SELECT id, iso_code FROM currencies")
+            .fetch_all(&self.pool)
+            .await?
+        {
+            currencies.push(AssetSelector {
+                id: row.try_get("id")?,
+                name: row.try_get("iso_code")?,
+                class: "currency".to_string(),
+            });
+        }
+        Ok(currencies)
+    }
+
+    async fn get_or_new_currency(&self, iso_code: CurrencyISOCode) -> Result<Currency, DataError> {
+        self.get_or_new_currency_with_digits(iso_code, 2).await
+    }
+
+    async fn get_or_new_currency_with_digits(
+        &self,
+        iso_code: CurrencyISOCode,
+        rounding_digits: i32,
+    ) -> Result<Currency, DataError> {
+        let row = sqlx::query("SELECT id, rounding_digits FROM currencies WHERE iso_code=?")
+            .bind(iso_code.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+
+        if let Some(row) = row {
+            Ok(Currency::new(
+                Some(row.try_get("id")?),
+                iso_code,
+                Some(row.try_get("rounding_digits")?),
+            ))
+        } else {
+            let mut currency = Currency::new(None, iso_code, Some(rounding_digits));
+            let id = self.insert_asset(&Asset::Currency(currency)).await?;
+            currency.set_id(id)?;
+            Ok(currency)
+        }
+    }
+}
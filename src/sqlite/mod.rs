@@ -0,0 +1,518 @@
+//! Implementation of sqlite3 data handler
+//!
+//! `SqliteDB` stores all data in a single sqlite3 database file, using the same tables as
+//! the PostgreSQL backend (see `database/CreateTables_sqlite.sql`). This module is only
+//! available if the cargo feature `sqlite` is enabled.
+use std::str::FromStr;
+use std::sync::Arc;
+
+use sqlx::sqlite::{Sqlite, SqliteConnectOptions, SqlitePoolOptions};
+use time::format_description::BorrowedFormatItem;
+use time::macros::format_description;
+use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
+
+use crate::datatypes::{DataError, Validator};
+
+pub mod asset_handler;
+pub mod object_handler;
+pub mod quote_handler;
+pub mod transaction_handler;
+
+/// Format of time stamps stored in the database. All times are stored in UTC with a
+/// fixed number of digits, such that the lexicographical order of stored time stamps
+/// equals their chronological order.
+const TIME_FORMAT: &[BorrowedFormatItem<'static>] =
+    format_description!("[year]-[month]-[day] [hour]:[minute]:[second].[subsecond digits:6]");
+
+/// Convert time to its representation in the database
+fn to_db_time(time: OffsetDateTime) -> Result<String, DataError> {
+    time.to_offset(UtcOffset::UTC)
+        .format(TIME_FORMAT)
+        .map_err(|e| DataError::DataAccessFailure(e.to_string()))
+}
+
+/// Convert time stored in the database back to `OffsetDateTime`
+fn from_db_time(time: &str) -> Result<OffsetDateTime, DataError> {
+    PrimitiveDateTime::parse(time, TIME_FORMAT)
+        .map(|time| time.assume_utc())
+        .map_err(|e| DataError::DataAccessFailure(e.to_string()))
+}
+
+/// Struct to handle connections to sqlite3 databases
+#[derive(Clone)]
+pub struct SqliteDB {
+    /// pool is made public to allow extending this struct outside of the library
+    pub pool: sqlx::Pool<Sqlite>,
+    /// Validator new transactions must pass, if any
+    validator: Option<Arc<Validator>>,
+}
+
+impl SqliteDB {
+    /// Open the database given by `connection_string`, e.g. `sqlite://finql.db` or
+    /// `sqlite::memory:`. A new database file is created if it does not exist yet.
+    pub async fn new(connection_string: &str) -> Result<Self, sqlx::Error> {
+        let options = SqliteConnectOptions::from_str(connection_string)?
+            .create_if_missing(true)
+            .foreign_keys(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await?;
+        Ok(Self {
+            pool,
+            validator: None,
+        })
+    }
+
+    /// Reject new transactions failing the validator's checks on insert
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validator = Some(Arc::new(validator));
+        self
+    }
+
+    /// Clean database by dropping all tables and than run init
+    pub async fn clean(&self) -> Result<(), sqlx::Error> {
+        for table in [
+            "objects",
+            "quotes",
+            "ticker",
+            "transactions",
+            "accounts",
+            "stocks",
+            "corporate_action_targets",
+            "corporate_actions",
+            "asset_identifiers",
+            "cryptos",
+            "commodities",
+            "fund_constituents",
+            "funds",
+            "bonds",
+            "currencies",
+            "assets",
+        ] {
+            sqlx::query(&format!("DROP TABLE IF EXISTS {table}"))
+                .execute(&self.pool)
+                .await?;
+        }
+        self.init().await
+    }
+
+    /// Initialize new database by creating table
+    pub async fn init(&self) -> Result<(), sqlx::Error> {
+        sqlx::raw_sql(include_str!("../../database/CreateTables_sqlite.sql"))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bond::Bond;
+    use crate::datatypes::{
+        date_time_helper::make_offset_time, Account, Asset, AssetHandler, CashFlow, Commodity,
+        CorporateAction, CorporateActionKind, CorporateActionTarget, CountryCode, Crypto,
+        CurrencyISOCode, DistributionPolicy, Fund, FundConstituent, Identifier, IdentifierType,
+        ObjectHandler, Quote, QuoteHandler, Stock, TaxKind, Ticker, Transaction, TransactionFilter,
+        TransactionHandler, TransactionKind, TransactionType,
+    };
+    use time::{Date, Month};
+
+    async fn new_db() -> SqliteDB {
+        let db = SqliteDB::new("sqlite::memory:").await.unwrap();
+        db.init().await.unwrap();
+        db
+    }
+
+    #[test]
+    fn db_time_is_sortable() {
+        let time = make_offset_time(2020, 1, 2, 20, 0, 0).unwrap();
+        let later = time + time::Duration::milliseconds(500);
+        assert_eq!(to_db_time(time).unwrap(), "2020-01-02 20:00:00.000000");
+        assert!(to_db_time(time).unwrap() < to_db_time(later).unwrap());
+        assert_eq!(from_db_time(&to_db_time(later).unwrap()).unwrap(), later);
+    }
+
+    #[tokio::test]
+    async fn assets_and_quotes() {
+        let db = new_db().await;
+        let asset_id = db
+            .insert_asset(&Asset::Stock(Stock::new(
+                None,
+                "BASF AG".to_string(),
+                Some("DE000BASF111".to_string()),
+                Some("BASF11".to_string()),
+                None,
+            )))
+            .await
+            .unwrap();
+        let eur = db
+            .get_or_new_currency(CurrencyISOCode::new("EUR").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_or_new_currency(CurrencyISOCode::new("EUR").unwrap())
+                .await
+                .unwrap(),
+            eur
+        );
+        assert_eq!(db.get_all_assets().await.unwrap().len(), 2);
+        assert_eq!(
+            db.get_asset_by_isin("DE000BASF111").await.unwrap().name(),
+            "BASF AG"
+        );
+
+        let bond: Bond = serde_json::from_str(
+            r#"{
+                "isin": "DE0001234567",
+                "bond_type": "bond",
+                "currency": "EUR",
+                "coupon" : {
+                    "coupon_type": "fixed",
+                    "rate": 5,
+                    "coupon_date": "01.04",
+                    "period": "6M",
+                    "day_count_convention": "act/365"
+                },
+                "business_day_rule": "none",
+                "calendar": "TARGET",
+                "issue_date": [2019, 274],
+                "maturity": [2021, 274],
+                "denomination": 1000
+            }"#,
+        )
+        .unwrap();
+        let bond = Asset::Bond(Box::new(bond));
+        let bond_id = db.insert_asset(&bond).await.unwrap();
+        assert_eq!(db.get_asset_id(&bond).await, Some(bond_id));
+        match db.get_asset_by_isin("DE0001234567").await.unwrap() {
+            Asset::Bond(bond) => {
+                assert_eq!(bond.id, Some(bond_id));
+                assert_eq!(bond.currency().id, eur.id);
+                assert_eq!(bond.denomination, 1000);
+            }
+            _ => panic!("expected a bond"),
+        }
+        assert!(db
+            .get_asset_list()
+            .await
+            .unwrap()
+            .iter()
+            .any(|a| a.class == "bond" && a.name == "DE0001234567 5% 2021-10-01"));
+        db.delete_asset(bond_id).await.unwrap();
+
+        let mut fund = Fund::new(
+            None,
+            "DAX ETF".to_string(),
+            Some("DE0005933931".to_string()),
+            None,
+            Some(0.0015),
+            DistributionPolicy::Distributing,
+            Some("DE".to_string()),
+        );
+        fund.constituents = vec![FundConstituent {
+            asset_id: Some(asset_id),
+            name: "BASF AG".to_string(),
+            weight: 0.05,
+        }];
+        let fund_id = db.insert_asset(&Asset::Fund(fund)).await.unwrap();
+        match db.get_asset_by_isin("DE0005933931").await.unwrap() {
+            Asset::Fund(mut fund) => {
+                assert_eq!(fund.id, Some(fund_id));
+                assert_eq!(fund.distribution, DistributionPolicy::Distributing);
+                assert_eq!(fund.constituents[0].asset_id, Some(asset_id));
+                fund.constituents.clear();
+                db.update_asset(&Asset::Fund(fund)).await.unwrap();
+            }
+            _ => panic!("expected a fund"),
+        }
+        match db.get_asset_by_id(fund_id).await.unwrap() {
+            Asset::Fund(fund) => assert!(fund.constituents.is_empty()),
+            _ => panic!("expected a fund"),
+        }
+        db.delete_asset(fund_id).await.unwrap();
+
+        let wintershall_id = db
+            .insert_asset(&Asset::Stock(Stock::new(
+                None,
+                "Wintershall Dea AG".to_string(),
+                None,
+                None,
+                None,
+            )))
+            .await
+            .unwrap();
+        let mut spin_off = CorporateAction::new(
+            None,
+            CorporateActionKind::SpinOff,
+            asset_id,
+            Date::from_calendar_date(2023, Month::May, 2).unwrap(),
+        );
+        spin_off.targets = vec![CorporateActionTarget {
+            asset_id: wintershall_id,
+            ratio: 0.25,
+            cost_share: 0.1,
+        }];
+        let action_id = db.insert_corporate_action(&spin_off).await.unwrap();
+        let mut stored = db.get_corporate_action_by_id(action_id).await.unwrap();
+        assert_eq!(stored.kind, CorporateActionKind::SpinOff);
+        assert_eq!(stored.targets, spin_off.targets);
+        stored.targets[0].cost_share = 0.2;
+        db.update_corporate_action(&stored).await.unwrap();
+        let actions = db
+            .get_corporate_actions(Some(spin_off.date), None)
+            .await
+            .unwrap();
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].targets[0].cost_share, 0.2);
+        assert!(db
+            .get_corporate_actions(None, spin_off.date.previous_day())
+            .await
+            .unwrap()
+            .is_empty());
+        db.delete_corporate_action(action_id).await.unwrap();
+        db.delete_asset(wintershall_id).await.unwrap();
+
+        let btc = Asset::Crypto(Crypto::new(
+            None,
+            "Bitcoin".to_string(),
+            "BTC".to_string(),
+            None,
+            8,
+        ));
+        let btc_id = db.insert_asset(&btc).await.unwrap();
+        assert_eq!(db.get_asset_id(&btc).await, Some(btc_id));
+        match db.get_asset_by_id(btc_id).await.unwrap() {
+            Asset::Crypto(crypto) => {
+                assert_eq!(crypto.symbol, "BTC");
+                assert_eq!(crypto.chain, None);
+                assert_eq!(crypto.decimals, 8);
+            }
+            _ => panic!("expected a crypto currency"),
+        }
+        let gold = Asset::Commodity(Commodity::new(
+            None,
+            "Gold".to_string(),
+            "troy ounce".to_string(),
+            None,
+        ));
+        let gold_id = db.insert_asset(&gold).await.unwrap();
+        assert_eq!(db.get_asset_id(&gold).await, Some(gold_id));
+        let assets = db.get_asset_list().await.unwrap();
+        assert!(assets
+            .iter()
+            .any(|a| a.class == "crypto" && a.name == "Bitcoin"));
+        assert!(assets
+            .iter()
+            .any(|a| a.class == "commodity" && a.name == "Gold"));
+        let cusip = Identifier::new(IdentifierType::Cusip, "037833100").unwrap();
+        let sedol = Identifier::new(IdentifierType::Sedol, "0263494").unwrap();
+        db.add_asset_identifier(btc_id, &sedol).await.unwrap();
+        assert!(db.add_asset_identifier(gold_id, &sedol).await.is_err());
+        assert_eq!(db.get_asset_identifiers(btc_id).await.unwrap(), vec![sedol]);
+        assert!(db.find_asset_by_identifier(&cusip).await.is_err());
+        // the ISIN GB0002634946 contains the SEDOL
+        let isin = Identifier::new(IdentifierType::Isin, "GB0002634946").unwrap();
+        assert_eq!(
+            db.find_asset_by_identifier(&isin).await.unwrap().name(),
+            "Bitcoin"
+        );
+        db.delete_asset(btc_id).await.unwrap();
+        db.delete_asset(gold_id).await.unwrap();
+
+        let mut ticker = Ticker {
+            id: None,
+            asset: asset_id,
+            name: "BAS.DE".to_string(),
+            currency: eur,
+            source: "manual".to_string(),
+            priority: 10,
+            factor: 1.0,
+            tz: None,
+            cal: None,
+        };
+        let low_prio_id = db.insert_ticker(&ticker).await.unwrap();
+        ticker.name = "BAS.F".to_string();
+        ticker.priority = 1;
+        let high_prio_id = db.insert_ticker(&ticker).await.unwrap();
+        assert_eq!(
+            db.get_ticker_by_id(high_prio_id).await.unwrap().currency,
+            eur
+        );
+
+        let quote = |ticker, price, day| Quote {
+            id: None,
+            ticker,
+            price,
+            time: make_offset_time(2020, 1, day, 20, 0, 0).unwrap(),
+            volume: None,
+        };
+        db.insert_quote(&quote(low_prio_id, 10.0, 2)).await.unwrap();
+        db.insert_quote(&quote(low_prio_id, 11.0, 3)).await.unwrap();
+        db.insert_quote(&quote(high_prio_id, 11.5, 3))
+            .await
+            .unwrap();
+        db.insert_quote(&quote(low_prio_id, 11.0, 3)).await.unwrap();
+
+        let time = make_offset_time(2020, 1, 4, 0, 0, 0).unwrap();
+        let (last_quote, currency) = db
+            .get_last_quote_before_by_id(asset_id, time)
+            .await
+            .unwrap();
+        assert_eq!(last_quote.ticker, high_prio_id);
+        assert_eq!(
+            last_quote.time,
+            make_offset_time(2020, 1, 3, 20, 0, 0).unwrap()
+        );
+        assert_eq!(currency, eur);
+
+        db.remove_duplicates().await.unwrap();
+        let quotes = db.get_all_quotes_for_ticker(low_prio_id).await.unwrap();
+        assert_eq!(quotes.len(), 2);
+        assert_eq!(
+            db.get_latest_quote_date_for_ticker(low_prio_id)
+                .await
+                .unwrap(),
+            Some(make_offset_time(2020, 1, 3, 20, 0, 0).unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn transactions_and_objects() {
+        let db = new_db().await;
+        let eur = db
+            .get_or_new_currency(CurrencyISOCode::new("EUR").unwrap())
+            .await
+            .unwrap();
+        let date = Date::from_calendar_date(2020, Month::January, 15).unwrap();
+        let account_id = db
+            .insert_account(&Account::new(
+                None,
+                "Depot".to_string(),
+                eur,
+                Some("Alice".to_string()),
+                None,
+            ))
+            .await
+            .unwrap();
+        let cash_id = db
+            .insert_transaction(&Transaction {
+                id: None,
+                transaction_type: TransactionType::Cash,
+                cash_flow: CashFlow::new(1000.0, eur, date),
+                note: Some("deposit".to_string()),
+                account_id: Some(account_id),
+            })
+            .await
+            .unwrap();
+        let fee = Transaction {
+            id: None,
+            transaction_type: TransactionType::Fee {
+                transaction_ref: Some(cash_id),
+            },
+            cash_flow: CashFlow::new(-5.0, eur, date),
+            note: None,
+            account_id: None,
+        };
+        let fee_id = db.insert_transaction(&fee).await.unwrap();
+        // fee still references the cash transaction
+        assert!(db.delete_transaction(cash_id).await.is_err());
+        let stored_fee = db.get_transaction_by_id(fee_id).await.unwrap();
+        assert_eq!(stored_fee.cash_flow.amount, fee.cash_flow.amount);
+        assert_eq!(stored_fee.cash_flow.date, date);
+        assert_eq!(db.get_all_transactions().await.unwrap().len(), 2);
+        let account_transactions = db
+            .get_transactions_for_account(account_id, Some(date), Some(date))
+            .await
+            .unwrap();
+        assert_eq!(account_transactions.len(), 1);
+        assert_eq!(account_transactions[0].id, Some(cash_id));
+        assert!(db
+            .get_transactions_for_account(account_id, None, date.previous_day())
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(db.get_all_accounts().await.unwrap()[0].currency, eur);
+        let filter = TransactionFilter {
+            note: Some("DEPO".to_string()),
+            kinds: Some(vec![TransactionKind::Cash]),
+            account_ids: Some(vec![account_id]),
+            ..Default::default()
+        };
+        let transactions = db.get_transactions(&filter).await.unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].id, Some(cash_id));
+        let filter = TransactionFilter {
+            kinds: Some(vec![TransactionKind::Fee, TransactionKind::Tax]),
+            start: date.next_day(),
+            ..Default::default()
+        };
+        assert!(db.get_transactions(&filter).await.unwrap().is_empty());
+
+        let tax_id = db
+            .insert_transaction(&Transaction {
+                id: None,
+                transaction_type: TransactionType::Tax {
+                    transaction_ref: Some(cash_id),
+                    kind: TaxKind::Reclaimable,
+                    country: Some(CountryCode::new("ch").unwrap()),
+                },
+                cash_flow: CashFlow::new(-20.0, eur, date),
+                note: None,
+                account_id: Some(account_id),
+            })
+            .await
+            .unwrap();
+        match db
+            .get_transaction_by_id(tax_id)
+            .await
+            .unwrap()
+            .transaction_type
+        {
+            TransactionType::Tax { kind, country, .. } => {
+                assert_eq!(kind, TaxKind::Reclaimable);
+                assert_eq!(country.unwrap().to_string(), "CH");
+            }
+            _ => panic!("expected tax transaction"),
+        }
+        let transfer_id = db
+            .insert_transaction(&Transaction {
+                id: None,
+                transaction_type: TransactionType::Transfer {
+                    asset_id: None,
+                    position: 0.0,
+                    transaction_ref: Some(cash_id),
+                    acquisition_date: date.previous_day(),
+                },
+                cash_flow: CashFlow::new(-100.0, eur, date),
+                note: None,
+                account_id: Some(account_id),
+            })
+            .await
+            .unwrap();
+        match db
+            .get_transaction_by_id(transfer_id)
+            .await
+            .unwrap()
+            .transaction_type
+        {
+            TransactionType::Transfer {
+                asset_id,
+                transaction_ref,
+                acquisition_date,
+                ..
+            } => {
+                assert_eq!(asset_id, None);
+                assert_eq!(transaction_ref, Some(cash_id));
+                assert_eq!(acquisition_date, date.previous_day());
+            }
+            _ => panic!("expected transfer transaction"),
+        }
+
+        db.store_object("settings", &vec![1, 2, 3]).await.unwrap();
+        db.update_object("settings", &vec![4]).await.unwrap();
+        let object: Vec<i32> = db.get_object("settings").await.unwrap();
+        assert_eq!(object, vec![4]);
+    }
+}
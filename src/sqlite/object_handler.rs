@@ -0,0 +1,51 @@
+//! Implementation of sqlite3 object handler
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::Row;
+
+use super::SqliteDB;
+use crate::datatypes::{DataError, ObjectHandler};
+
+/// Handler for general, serializable objects stored as JSON text
+#[async_trait]
+impl ObjectHandler for SqliteDB {
+    async fn store_object<T: Serialize + Sync>(
+        &self,
+        id: &str,
+        object: &T,
+    ) -> Result<(), DataError> {
+        let object_json = serde_json::to_string(object)?;
+
+        sqlx::query("INSERT INTO objects (id, object) VALUES (?, ?)")
+            .bind(id)
+            .bind(object_json)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn update_object<T: Serialize + Sync>(
+        &self,
+        id: &str,
+        object: &T,
+    ) -> Result<(), DataError> {
+        let object_json = serde_json::to_string(object)?;
+
+        sqlx::query("UPDATE objects SET object=? WHERE id=?")
+            .bind(object_json)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_object<T: DeserializeOwned>(&self, id: &str) -> Result<T, DataError> {
+        let row = sqlx::query("SELECT object FROM objects WHERE id=?")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+        let object_json: String = row.try_get("object")?;
+        let object: T = serde_json::from_str(&object_json)?;
+        Ok(object)
+    }
+}
@@ -0,0 +1,365 @@
+//! Implementation for quote handler with Sqlite3 database as backend
+use async_trait::async_trait;
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::str::FromStr;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+use crate::datatypes::{
+    AssetHandler, Currency, CurrencyISOCode, DataError, Quote, QuoteHandler, Ticker,
+};
+
+use super::{from_db_time, to_db_time, SqliteDB};
+
+/// Columns of ticker joined with their currency
+const TICKER_COLUMNS: &str = "SELECT
+        t.id,
+        t.name,
+        t.asset_id,
+        t.source,
+        t.priority,
+        t.factor,
+        t.tz,
+        t.cal,
+        c.id AS currency_id,
+        c.iso_code AS currency_iso_code,
+        c.rounding_digits AS currency_rounding_digits
+    FROM ticker t
+    JOIN currencies c ON c.id = t.currency_id";
+
+fn ticker_from_row(row: &SqliteRow) -> Result<Ticker, DataError> {
+    let iso_code: String = row.try_get("currency_iso_code")?;
+    Ok(Ticker {
+        id: Some(row.try_get("id")?),
+        name: row.try_get("name")?,
+        asset: row.try_get("asset_id")?,
+        source: row.try_get("source")?,
+        priority: row.try_get("priority")?,
+        currency: Currency::new(
+            Some(row.try_get("currency_id")?),
+            CurrencyISOCode::from_str(&iso_code)?,
+            Some(row.try_get("currency_rounding_digits")?),
+        ),
+        factor: row.try_get("factor")?,
+        tz: row.try_get("tz")?,
+        cal: row.try_get("cal")?,
+    })
+}
+
+fn quote_from_row(row: &SqliteRow) -> Result<Quote, DataError> {
+    let time: String = row.try_get("time")?;
+    Ok(Quote {
+        id: Some(row.try_get("id")?),
+        ticker: row.try_get("ticker_id")?,
+        price: row.try_get("price")?,
+        time: from_db_time(&time)?,
+        volume: row.try_get("volume")?,
+    })
+}
+
+/// Sqlite implementation of quote handler
+#[async_trait]
+impl QuoteHandler for SqliteDB {
+    fn into_arc_dispatch(self: Arc<Self>) -> Arc<dyn AssetHandler + Send + Sync> {
+        self
+    }
+
+    // insert, get, update and delete for market data sources
+    async fn insert_ticker(&self, ticker: &Ticker) -> Result<i32, DataError> {
+        let row = sqlx::query(
+            "INSERT INTO ticker (name, asset_id, source, priority, currency_id, factor, tz, cal)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING id",
+        )
+        .bind(&ticker.name)
+        .bind(ticker.asset)
+        .bind(&ticker.source)
+        .bind(ticker.priority)
+        .bind(ticker.currency.id)
+        .bind(ticker.factor)
+        .bind(&ticker.tz)
+        .bind(&ticker.cal)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.try_get("id")?)
+    }
+
+    async fn get_ticker_id(&self, ticker: &str) -> Option<i32> {
+        let row = sqlx::query("SELECT id FROM ticker WHERE name=?")
+            .bind(ticker)
+            .fetch_one(&self.pool)
+            .await
+            .ok()?;
+        row.try_get("id").ok()
+    }
+
+    async fn insert_if_new_ticker(&self, ticker: &Ticker) -> Result<i32, DataError> {
+        match self.get_ticker_id(&ticker.name).await {
+            Some(id) => Ok(id),
+            None => self.insert_ticker(ticker).await,
+        }
+    }
+
+    async fn get_ticker_by_id(&self, id: i32) -> Result<Ticker, DataError> {
+        let row = sqlx::query(&format!("{TICKER_COLUMNS} WHERE t.id = ?"))
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+        ticker_from_row(&row)
+    }
+
+    async fn get_all_ticker(&self) -> Result<Vec<Ticker>, DataError> {
+        sqlx::query(TICKER_COLUMNS)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(ticker_from_row)
+            .collect()
+    }
+
+    async fn get_all_ticker_for_source(&self, source: &str) -> Result<Vec<Ticker>, DataError> {
+        sqlx::query(&format!("{TICKER_COLUMNS} WHERE t.source = ?"))
+            .bind(source)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(ticker_from_row)
+            .collect()
+    }
+
+    async fn get_all_ticker_for_asset(&self, asset_id: i32) -> Result<Vec<Ticker>, DataError> {
+        sqlx::query(&format!("{TICKER_COLUMNS} WHERE t.asset_id = ?"))
+            .bind(asset_id)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(ticker_from_row)
+            .collect()
+    }
+
+    async fn update_ticker(&self, ticker: &Ticker) -> Result<(), DataError> {
+        let id = ticker
+            .id
+            .ok_or_else(|| DataError::NotFound("not yet stored to database".to_string()))?;
+        sqlx::query(
+            "UPDATE ticker SET name = ?, asset_id = ?, source = ?, priority = ?, currency_id = ?,
+                factor = ?, tz = ?, cal = ?
+                WHERE id = ?",
+        )
+        .bind(&ticker.name)
+        .bind(ticker.asset)
+        .bind(&ticker.source)
+        .bind(ticker.priority)
+        .bind(ticker.currency.id)
+        .bind(ticker.factor)
+        .bind(&ticker.tz)
+        .bind(&ticker.cal)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_ticker(&self, id: i32) -> Result<(), DataError> {
+        sqlx::query("DELETE FROM ticker WHERE id=?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // insert, get, update and delete for market data sources
+    async fn insert_quote(&self, quote: &Quote) -> Result<i32, DataError> {
+        let row = sqlx::query(
+            "INSERT INTO quotes (ticker_id, price, time, volume)
+                VALUES (?, ?, ?, ?) RETURNING id",
+        )
+        .bind(quote.ticker)
+        .bind(quote.price)
+        .bind(to_db_time(quote.time)?)
+        .bind(quote.volume)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.try_get("id")?)
+    }
+
+    async fn get_last_fx_quote_before(
+        &self,
+        curr: &CurrencyISOCode,
+        time: OffsetDateTime,
+    ) -> Result<(Quote, Currency), DataError> {
+        let row = sqlx::query(
+            "SELECT
+                   q.id,
+                   q.ticker_id,
+                   q.price,
+                   q.time,
+                   q.volume,
+                   qc.id AS currency_id,
+                   qc.iso_code,
+                   qc.rounding_digits
+                FROM quotes q
+                JOIN ticker t ON t.id = q.ticker_id
+                JOIN currencies c ON c.id = t.asset_id
+                JOIN currencies qc ON qc.id = t.currency_id
+                WHERE
+                    c.iso_code = ?
+                    AND q.time <= ?
+                ORDER BY q.time DESC, t.priority ASC
+                LIMIT 1",
+        )
+        .bind(curr.to_string())
+        .bind(to_db_time(time)?)
+        .fetch_one(&self.pool)
+        .await?;
+        let iso_code: String = row.try_get("iso_code")?;
+        let currency = Currency::new(
+            Some(row.try_get("currency_id")?),
+            CurrencyISOCode::new(&iso_code)?,
+            Some(row.try_get("rounding_digits")?),
+        );
+        Ok((quote_from_row(&row)?, currency))
+    }
+
+    async fn get_last_quote_before_by_id(
+        &self,
+        asset_id: i32,
+        time: OffsetDateTime,
+    ) -> Result<(Quote, Currency), DataError> {
+        let row = sqlx::query(
+            "SELECT
+                   q.id,
+                   q.ticker_id,
+                   q.price,
+                   q.time,
+                   q.volume,
+                   c.id AS currency_id,
+                   c.iso_code,
+                   c.rounding_digits
+                FROM quotes q
+                JOIN ticker t ON t.id = q.ticker_id
+                JOIN currencies c ON c.id = t.currency_id
+                WHERE t.asset_id = ? AND q.time <= ?
+                ORDER BY q.time DESC, t.priority ASC
+                LIMIT 1",
+        )
+        .bind(asset_id)
+        .bind(to_db_time(time)?)
+        .fetch_one(&self.pool)
+        .await?;
+        let iso_code: String = row.try_get("iso_code")?;
+        let currency = Currency::new(
+            Some(row.try_get("currency_id")?),
+            CurrencyISOCode::new(&iso_code)?,
+            Some(row.try_get("rounding_digits")?),
+        );
+        Ok((quote_from_row(&row)?, currency))
+    }
+
+    async fn get_quotes_in_range_by_id(
+        &self,
+        asset_id: i32,
+        start: OffsetDateTime,
+        end: OffsetDateTime,
+    ) -> Result<Vec<(Quote, i32)>, DataError> {
+        let mut quotes = Vec::new();
+        for row in sqlx::query(
+            "SELECT q.id, q.ticker_id, q.price, q.time, q.volume, t.currency_id
+                FROM quotes q
+                JOIN ticker t ON t.id = q.ticker_id
+                WHERE t.asset_id = ? AND q.time >= ? AND q.time <= ?
+                ORDER BY q.time DESC, t.priority ASC",
+        )
+        .bind(asset_id)
+        .bind(to_db_time(start)?)
+        .bind(to_db_time(end)?)
+        .fetch_all(&self.pool)
+        .await?
+        {
+            quotes.push((quote_from_row(&row)?, row.try_get("currency_id")?));
+        }
+        Ok(quotes)
+    }
+
+    async fn get_all_quotes_for_ticker(&self, ticker_id: i32) -> Result<Vec<Quote>, DataError> {
+        sqlx::query(
+            "SELECT id, ticker_id, price, time, volume FROM quotes
+                WHERE ticker_id=? ORDER BY time ASC",
+        )
+        .bind(ticker_id)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(quote_from_row)
+        .collect()
+    }
+
+    async fn get_latest_quote_date_for_ticker(
+        &self,
+        ticker_id: i32,
+    ) -> Result<Option<OffsetDateTime>, DataError> {
+        let row = sqlx::query("SELECT MAX(time) AS time FROM quotes WHERE ticker_id=?")
+            .bind(ticker_id)
+            .fetch_one(&self.pool)
+            .await?;
+        let time: Option<String> = row.try_get("time")?;
+        time.map(|time| from_db_time(&time)).transpose()
+    }
+
+    async fn update_quote(&self, quote: &Quote) -> Result<(), DataError> {
+        let id = quote
+            .id
+            .ok_or_else(|| DataError::NotFound("not yet stored to database".to_string()))?;
+        sqlx::query("UPDATE quotes SET ticker_id=?, price=?, time=?, volume=? WHERE id=?")
+            .bind(quote.ticker)
+            .bind(quote.price)
+            .bind(to_db_time(quote.time)?)
+            .bind(quote.volume)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_quote(&self, id: i32) -> Result<(), DataError> {
+        sqlx::query("DELETE FROM quotes WHERE id=?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_quotes_for_ticker_id_in_range(
+        &self,
+        ticker_id: i32,
+        start: OffsetDateTime,
+        end: OffsetDateTime,
+    ) -> Result<(), DataError> {
+        sqlx::query("DELETE FROM quotes WHERE ticker_id=? AND time > ? AND time <= ?")
+            .bind(ticker_id)
+            .bind(to_db_time(start)?)
+            .bind(to_db_time(end)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_duplicates(&self) -> Result<(), DataError> {
+        sqlx::query(
+            "DELETE FROM quotes
+            WHERE id IN
+            (SELECT q2.id
+            FROM
+                quotes q1,
+                quotes q2
+            WHERE
+                q1.id < q2.id
+            AND q1.ticker_id = q2.ticker_id
+            AND q1.time = q2.time
+            AND q1.price = q2.price)",
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,295 @@
+use async_trait::async_trait;
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::str::FromStr;
+use time::Date;
+
+use crate::datatypes::currency::Currency;
+use crate::datatypes::transaction::{RawTransaction, Transaction, TransactionFilter};
+use crate::datatypes::{Account, CurrencyISOCode, DataError, TransactionHandler};
+
+use super::SqliteDB;
+
+/// Columns of transactions joined with their cash currency
+const TRANSACTION_COLUMNS: &str = "SELECT
+        t.id,
+        t.trans_type,
+        t.asset_id,
+        t.cash_amount,
+        c.id AS cash_currency_id,
+        c.iso_code AS cash_iso_code,
+        c.rounding_digits AS cash_rounding_digits,
+        t.cash_date,
+        t.related_trans,
+        t.position,
+        t.note,
+        t.account_id,
+        t.tax_kind,
+        t.country,
+        t.acquisition_date
+    FROM transactions t
+    JOIN currencies c ON c.id = t.cash_currency_id";
+
+/// Columns of accounts joined with their currency
+const ACCOUNT_COLUMNS: &str = "SELECT
+        a.id,
+        a.name,
+        c.id AS currency_id,
+        c.iso_code,
+        c.rounding_digits,
+        a.owner,
+        a.broker
+    FROM accounts a
+    JOIN currencies c ON c.id = a.currency_id";
+
+fn account_from_row(row: &SqliteRow) -> Result<Account, DataError> {
+    let iso_code: String = row.try_get("iso_code")?;
+    Ok(Account::new(
+        Some(row.try_get("id")?),
+        row.try_get("name")?,
+        Currency::new(
+            Some(row.try_get("currency_id")?),
+            CurrencyISOCode::from_str(&iso_code)?,
+            Some(row.try_get("rounding_digits")?),
+        ),
+        row.try_get("owner")?,
+        row.try_get("broker")?,
+    ))
+}
+
+fn transaction_from_row(row: &SqliteRow) -> Result<Transaction, DataError> {
+    let iso_code: String = row.try_get("cash_iso_code")?;
+    let transaction = RawTransaction {
+        id: Some(row.try_get("id")?),
+        trans_type: row.try_get("trans_type")?,
+        asset: row.try_get("asset_id")?,
+        cash_amount: row.try_get("cash_amount")?,
+        cash_currency: Currency::new(
+            Some(row.try_get("cash_currency_id")?),
+            CurrencyISOCode::from_str(&iso_code)?,
+            Some(row.try_get("cash_rounding_digits")?),
+        ),
+        cash_date: row.try_get("cash_date")?,
+        related_trans: row.try_get("related_trans")?,
+        position: row.try_get("position")?,
+        note: row.try_get("note")?,
+        account: row.try_get("account_id")?,
+        tax_kind: row.try_get("tax_kind")?,
+        country: row.try_get("country")?,
+        acquisition_date: row.try_get("acquisition_date")?,
+    };
+    transaction.to_transaction()
+}
+
+/// Handler for globally available data
+#[async_trait]
+impl TransactionHandler for SqliteDB {
+    // insert, get, update and delete for transactions
+    async fn insert_transaction(&self, transaction: &Transaction) -> Result<i32, DataError> {
+        if let Some(validator) = &self.validator {
+            validator.enforce(transaction, self).await?;
+        }
+        let transaction = RawTransaction::from_transaction(transaction);
+        let row = sqlx::query(
+            "INSERT INTO transactions (trans_type, asset_id, cash_amount,
+                cash_currency_id, cash_date, related_trans, position,
+                note, account_id, tax_kind, country, acquisition_date)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING id",
+        )
+        .bind(&transaction.trans_type)
+        .bind(transaction.asset)
+        .bind(transaction.cash_amount)
+        .bind(transaction.cash_currency.id)
+        .bind(transaction.cash_date)
+        .bind(transaction.related_trans)
+        .bind(transaction.position)
+        .bind(&transaction.note)
+        .bind(transaction.account)
+        .bind(&transaction.tax_kind)
+        .bind(&transaction.country)
+        .bind(transaction.acquisition_date)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.try_get("id")?)
+    }
+
+    async fn get_transaction_by_id(&self, id: i32) -> Result<Transaction, DataError> {
+        let row = sqlx::query(&format!("{TRANSACTION_COLUMNS} WHERE t.id = ?"))
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+        transaction_from_row(&row)
+    }
+
+    async fn get_all_transactions(&self) -> Result<Vec<Transaction>, DataError> {
+        sqlx::query(TRANSACTION_COLUMNS)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(transaction_from_row)
+            .collect()
+    }
+
+    async fn get_transactions(
+        &self,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<Transaction>, DataError> {
+        let mut query = format!(
+            "{TRANSACTION_COLUMNS}
+            LEFT JOIN transactions r ON r.id = t.related_trans
+            WHERE (?1 IS NULL OR t.cash_date >= ?1)
+                AND (?2 IS NULL OR t.cash_date <= ?2)
+                AND (?3 IS NULL OR t.asset_id = ?3 OR r.asset_id = ?3)
+                AND (?4 IS NULL OR INSTR(LOWER(t.note), LOWER(?4)) > 0)"
+        );
+        // sqlite does not support array parameters, therefore lists are inserted as literals
+        if let Some(kinds) = &filter.kinds {
+            let type_codes: Vec<String> = kinds
+                .iter()
+                .map(|kind| format!("'{}'", kind.type_code()))
+                .collect();
+            query.push_str(&format!(" AND t.trans_type IN ({})", type_codes.join(",")));
+        }
+        if let Some(account_ids) = &filter.account_ids {
+            let account_ids: Vec<String> = account_ids.iter().map(|id| id.to_string()).collect();
+            query.push_str(&format!(" AND t.account_id IN ({})", account_ids.join(",")));
+        }
+        query.push_str(" ORDER BY t.cash_date, t.id");
+        sqlx::query(&query)
+            .bind(filter.start)
+            .bind(filter.end)
+            .bind(filter.asset_id)
+            .bind(&filter.note)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(transaction_from_row)
+            .collect()
+    }
+
+    async fn update_transaction(&self, transaction: &Transaction) -> Result<(), DataError> {
+        if transaction.id.is_none() {
+            return Err(DataError::NotFound(
+                "not yet stored to database".to_string(),
+            ));
+        }
+        let transaction = RawTransaction::from_transaction(transaction);
+        sqlx::query(
+            "UPDATE transactions SET
+                trans_type=?,
+                asset_id=?,
+                cash_amount=?,
+                cash_currency_id=?,
+                cash_date=?,
+                related_trans=?,
+                position=?,
+                note=?,
+                account_id=?,
+                tax_kind=?,
+                country=?,
+                acquisition_date=?
+            WHERE id=?",
+        )
+        .bind(&transaction.trans_type)
+        .bind(transaction.asset)
+        .bind(transaction.cash_amount)
+        .bind(transaction.cash_currency.id)
+        .bind(transaction.cash_date)
+        .bind(transaction.related_trans)
+        .bind(transaction.position)
+        .bind(&transaction.note)
+        .bind(transaction.account)
+        .bind(&transaction.tax_kind)
+        .bind(&transaction.country)
+        .bind(transaction.acquisition_date)
+        .bind(transaction.id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_transaction(&self, id: i32) -> Result<(), DataError> {
+        sqlx::query("DELETE FROM transactions WHERE id=?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn insert_account(&self, account: &Account) -> Result<i32, DataError> {
+        let row = sqlx::query(
+            "INSERT INTO accounts (name, currency_id, owner, broker)
+                VALUES (?, ?, ?, ?) RETURNING id",
+        )
+        .bind(&account.name)
+        .bind(account.currency.id)
+        .bind(&account.owner)
+        .bind(&account.broker)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.try_get("id")?)
+    }
+
+    async fn get_account_by_id(&self, id: i32) -> Result<Account, DataError> {
+        let row = sqlx::query(&format!("{ACCOUNT_COLUMNS} WHERE a.id = ?"))
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+        account_from_row(&row)
+    }
+
+    async fn get_all_accounts(&self) -> Result<Vec<Account>, DataError> {
+        sqlx::query(&format!("{ACCOUNT_COLUMNS} ORDER BY a.name"))
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(account_from_row)
+            .collect()
+    }
+
+    async fn update_account(&self, account: &Account) -> Result<(), DataError> {
+        let id = account
+            .id
+            .ok_or_else(|| DataError::NotFound("not yet stored to database".to_string()))?;
+        sqlx::query("UPDATE accounts SET name=?, currency_id=?, owner=?, broker=? WHERE id=?")
+            .bind(&account.name)
+            .bind(account.currency.id)
+            .bind(&account.owner)
+            .bind(&account.broker)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_account(&self, id: i32) -> Result<(), DataError> {
+        sqlx::query("DELETE FROM accounts WHERE id=?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_transactions_for_account(
+        &self,
+        account_id: i32,
+        start: Option<Date>,
+        end: Option<Date>,
+    ) -> Result<Vec<Transaction>, DataError> {
+        sqlx::query(&format!(
+            "{TRANSACTION_COLUMNS}
+            WHERE t.account_id = ?
+                AND (?2 IS NULL OR t.cash_date >= ?2)
+                AND (?3 IS NULL OR t.cash_date <= ?3)
+            ORDER BY t.cash_date, t.id"
+        ))
+        .bind(account_id)
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(transaction_from_row)
+        .collect()
+    }
+}
@@ -6,9 +6,10 @@ use time::Date;
 
 use crate::datatypes::{
     date_time_helper::{date_to_offset_date_time, DateTimeError},
-    CashFlow, Currency, Transaction, TransactionType,
+    CashFlow, Currency, DataError, TaxKind, Transaction, TransactionType,
 };
 use crate::portfolio::calc_delta_position;
+use crate::tax::{IncomeCategory, SharedTaxModel};
 use crate::time_series::TimeValue;
 use crate::{portfolio::PortfolioPosition, time_period::TimePeriod, Market};
 
@@ -20,6 +21,8 @@ pub enum StrategyError {
     DateConversionError(#[from] DateTimeError),
     #[error("Failed to apply time period")]
     TimePeriodError(#[from] crate::time_period::TimePeriodError),
+    #[error("Failed to calculate position")]
+    PositionError(#[from] crate::portfolio::PositionError),
 }
 
 #[derive(Default, Debug, Clone)]
@@ -51,7 +54,28 @@ impl StockTransactionFee {
 #[derive(Default, Debug, Clone)]
 pub struct StockTransactionCosts {
     pub fee: StockTransactionFee,
+    /// Flat tax rate on dividends, only used if no tax model is given
     pub tax_rate: f64,
+    /// Tax model for dividends and, in `calc_strategy`, for realized gains of sales
+    pub tax_model: Option<SharedTaxModel>,
+}
+
+impl StockTransactionCosts {
+    /// Calculate the tax due on income or gains of the given category
+    pub fn calc_tax(
+        &self,
+        date: Date,
+        category: IncomeCategory,
+        amount: f64,
+    ) -> Result<f64, StrategyError> {
+        match &self.tax_model {
+            Some(tax_model) => Ok(tax_model
+                .lock()
+                .map_err(|_| DataError::DataAccessFailure("tax model is poisoned".to_string()))?
+                .tax(date, category, amount)),
+            None => Ok(self.tax_rate * amount),
+        }
+    }
 }
 
 #[async_trait]
@@ -62,6 +86,10 @@ pub trait Strategy: Send + Sync {
         date: Date,
     ) -> Result<Vec<Transaction>, StrategyError>;
     fn next_day(&self, date: Date) -> Result<Date, StrategyError>;
+    /// Tax model applied to sales within the strategy's positions
+    fn tax_model(&self) -> Option<SharedTaxModel> {
+        None
+    }
 }
 
 fn cash_flow_idx(date: Date, cash_flows: &[CashFlow]) -> Option<usize> {
@@ -106,7 +134,10 @@ impl Strategy for StaticInSingleStock {
             let mut dividend = self.dividends[idx];
             dividend.amount.amount *= position.assets[&self.asset_id].position;
             let mut tax = dividend;
-            tax.amount.amount = -self.costs.tax_rate * dividend.amount.amount;
+            tax.amount.amount =
+                -self
+                    .costs
+                    .calc_tax(date, IncomeCategory::Dividend, dividend.amount.amount)?;
             let dividend_transaction = Transaction {
                 id: None,
                 transaction_type: TransactionType::Dividend {
@@ -114,6 +145,7 @@ impl Strategy for StaticInSingleStock {
                 },
                 cash_flow: dividend,
                 note: None,
+                account_id: None,
             };
             trace!(
                 "StaticInSingleStock: added transaction {:?}",
@@ -126,9 +158,12 @@ impl Strategy for StaticInSingleStock {
                     id: None,
                     transaction_type: TransactionType::Tax {
                         transaction_ref: None,
+                        kind: TaxKind::Domestic,
+                        country: None,
                     },
                     cash_flow: tax,
                     note: None,
+                    account_id: None,
                 };
                 trace!(
                     "StaticInSingleStock: added transaction {:?}",
@@ -148,11 +183,14 @@ impl Strategy for StaticInSingleStock {
         let one_day = "1D".parse::<TimePeriod>().unwrap();
         Ok(one_day.add_to(date, None)?)
     }
+
+    fn tax_model(&self) -> Option<SharedTaxModel> {
+        self.costs.tax_model.clone()
+    }
 }
 
 pub struct ReInvestInSingleStock {
     asset_id: i32,
-    ticker_id: i32,
     market: Market,
     dividends: Vec<CashFlow>,
     costs: StockTransactionCosts,
@@ -161,14 +199,12 @@ pub struct ReInvestInSingleStock {
 impl ReInvestInSingleStock {
     pub fn new(
         asset_id: i32,
-        ticker_id: i32,
         market: Market,
         dividends: Vec<CashFlow>,
         costs: StockTransactionCosts,
     ) -> ReInvestInSingleStock {
         ReInvestInSingleStock {
             asset_id,
-            ticker_id,
             market,
             dividends,
             costs,
@@ -189,7 +225,10 @@ impl Strategy for ReInvestInSingleStock {
             let mut dividend = self.dividends[idx];
             dividend.amount.amount *= position.assets[&self.asset_id].position;
             let mut tax = dividend;
-            tax.amount.amount = -self.costs.tax_rate * dividend.amount.amount;
+            tax.amount.amount =
+                -self
+                    .costs
+                    .calc_tax(date, IncomeCategory::Dividend, dividend.amount.amount)?;
             let available_cash =
                 dividend.amount.amount + tax.amount.amount + position.cash.position;
             let dividend_transaction = Transaction {
@@ -199,6 +238,7 @@ impl Strategy for ReInvestInSingleStock {
                 },
                 cash_flow: dividend,
                 note: None,
+                account_id: None,
             };
             trace!(
                 "ReinvestInSingleStock: added transaction {:?}",
@@ -210,9 +250,12 @@ impl Strategy for ReInvestInSingleStock {
                     id: None,
                     transaction_type: TransactionType::Tax {
                         transaction_ref: None,
+                        kind: TaxKind::Domestic,
+                        country: None,
                     },
                     cash_flow: tax,
                     note: None,
+                    account_id: None,
                 };
                 trace!(
                     "ReinvestInSingleStock: added transaction {:?}",
@@ -244,6 +287,7 @@ impl Strategy for ReInvestInSingleStock {
                         date,
                     ),
                     note: None,
+                    account_id: None,
                 };
                 trace!(
                     "ReinvestInSingleStock: added transaction {:?}",
@@ -258,6 +302,7 @@ impl Strategy for ReInvestInSingleStock {
                         },
                         cash_flow: CashFlow::new(-fee, position.cash.currency, date),
                         note: None,
+                        account_id: None,
                     };
                     trace!(
                         "ReinvestInSingleStock: added transaction {:?}",
@@ -277,6 +322,10 @@ impl Strategy for ReInvestInSingleStock {
         let one_day = "1D".parse::<TimePeriod>().unwrap();
         Ok(one_day.add_to(date, None)?)
     }
+
+    fn tax_model(&self) -> Option<SharedTaxModel> {
+        self.costs.tax_model.clone()
+    }
 }
 
 impl ReInvestInSingleStock {
@@ -293,18 +342,19 @@ impl ReInvestInSingleStock {
 
 pub async fn calc_strategy(
     currency: Currency,
-    start_transactions: &Vec<Transaction>,
+    start_transactions: &[Transaction],
     strategy: &dyn Strategy,
     start: Date,
     end: Date,
     market: Market,
-) -> Vec<TimeValue> {
+) -> Result<Vec<TimeValue>, StrategyError> {
     debug!("Calc strategy: start={start}, end={end}");
     let mut current_date = start;
     let mut total_return = Vec::new();
-    let mut transactions = start_transactions.clone();
+    let mut transactions = start_transactions.to_vec();
 
     let mut position = PortfolioPosition::new(currency);
+    position.tax_model = strategy.tax_model();
     calc_delta_position(
         &mut position,
         &transactions,
@@ -312,21 +362,20 @@ pub async fn calc_strategy(
         Some(start),
         market.clone(),
     )
-    .await
-    .unwrap();
+    .await?;
 
     position
-        .add_quote(date_to_offset_date_time(&start, 20, None).unwrap(), &market)
+        .add_quote(date_to_offset_date_time(&start, 20, None)?, &market)
         .await;
     debug!("Initial position: {position:?}, current_date={current_date:?}, end={end:?}");
 
     while current_date < end {
         // Update list of transactions with new strategic transactions for the current day
-        let mut new_transactions = strategy.apply(&position, current_date).await.unwrap();
+        let mut new_transactions = strategy.apply(&position, current_date).await?;
         transactions.append(&mut new_transactions);
 
         // roll position forward to next day
-        let next_date = min(end, strategy.next_day(current_date).unwrap());
+        let next_date = min(end, strategy.next_day(current_date)?);
 
         // Calculate new position including new transactions
         debug!(
@@ -340,15 +389,17 @@ pub async fn calc_strategy(
             Some(next_date),
             market.clone(),
         )
-        .await
-        .unwrap();
+        .await?;
+        for year in current_date.year()..next_date.year() {
+            position.apply_vorabpauschale(year, &market).await?;
+        }
         debug!(
             "CalcStrategy: cash position after applying new transactions: {}",
             position.cash.position
         );
 
         current_date = next_date;
-        let current_time = date_to_offset_date_time(&current_date, 20, None).unwrap();
+        let current_time = date_to_offset_date_time(&current_date, 20, None)?;
         position.add_quote(current_time, &market).await;
         let totals = position.calc_totals();
         total_return.push(TimeValue {
@@ -356,5 +407,5 @@ pub async fn calc_strategy(
             time: current_time,
         });
     }
-    total_return
+    Ok(total_return)
 }
@@ -0,0 +1,364 @@
+//! Tax models calculating the tax due on capital income
+//!
+//! A `TaxModel` is informed about each taxable event, i.e. realized gains or losses, dividends,
+//! interest or the German Vorabpauschale, in date order and returns the tax due for this event.
+//! Models may keep state, e.g. the used part of a yearly allowance or loss carry-forwards, and
+//! might refund tax paid earlier in the same year by returning a negative tax amount.
+//! The Vorabpauschale of accumulating funds is registered at the end of each year by
+//! `PortfolioPosition::apply_vorabpauschale`, which is done automatically by `calc_strategy`.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use time::Date;
+
+use crate::datatypes::Asset;
+
+/// Solidarity surcharge in percent of the income tax
+const SOLIDARITY_SURCHARGE: f64 = 0.055;
+/// Flat income tax rate on capital income in Germany (Abgeltungssteuer)
+const FLAT_TAX_RATE: f64 = 0.25;
+/// Fraction of the base return taken into account for the Vorabpauschale
+const BASE_RETURN_FACTOR: f64 = 0.7;
+
+/// Category of taxable capital income
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IncomeCategory {
+    /// Realized gain or loss from the sale of stocks
+    StockSale,
+    /// Realized gain or loss from the sale of any other asset, e.g. bonds or funds
+    OtherSale,
+    Dividend,
+    Interest,
+    /// Advance lump sum for accumulating funds in Germany
+    Vorabpauschale,
+}
+
+impl IncomeCategory {
+    /// Category of realized gains or losses from the sale of the given asset
+    pub fn sale_of(asset: &Asset) -> Self {
+        match asset {
+            Asset::Stock(_) => IncomeCategory::StockSale,
+            _ => IncomeCategory::OtherSale,
+        }
+    }
+}
+
+pub trait TaxModel: fmt::Debug + Send {
+    /// Register taxable income, or a loss if `amount` is negative, and return the tax due,
+    /// which is negative if tax paid before is refunded. Events are expected in date order.
+    fn tax(&mut self, date: Date, category: IncomeCategory, amount: f64) -> f64;
+
+    /// Vorabpauschale per unit of an accumulating fund held for `months_held` months of
+    /// `year`, given the unit prices at the start and the end of the year. Models without a
+    /// Vorabpauschale return zero.
+    fn vorabpauschale(
+        &self,
+        _year: i32,
+        _price_start: f64,
+        _price_end: f64,
+        _months_held: u8,
+    ) -> f64 {
+        0.0
+    }
+}
+
+/// Tax model shared between strategies and portfolio positions
+pub type SharedTaxModel = Arc<Mutex<dyn TaxModel>>;
+
+/// Wrap a tax model to be shared, e.g. between a strategy and the simulated positions
+pub fn shared_tax_model<T: TaxModel + 'static>(model: T) -> SharedTaxModel {
+    Arc::new(Mutex::new(model))
+}
+
+/// Flat tax rate on all income, losses are refunded immediately at the same rate
+#[derive(Debug, Clone, Default)]
+pub struct FlatTax {
+    pub rate: f64,
+}
+
+impl FlatTax {
+    pub fn new(rate: f64) -> Self {
+        Self { rate }
+    }
+}
+
+impl TaxModel for FlatTax {
+    fn tax(&mut self, _date: Date, _category: IncomeCategory, amount: f64) -> f64 {
+        self.rate * amount
+    }
+}
+
+/// German tax on capital income (Abgeltungssteuer) with solidarity surcharge and optional
+/// church tax. Income is reduced by the yearly saver's allowance (Sparerpauschbetrag).
+/// Losses from stock sales are only offset against gains from stock sales, all other losses
+/// against any income. Losses not offset within a year are carried forward in two separate
+/// loss pots. As done by German banks, tax is calculated on the accumulated income of the
+/// current year, i.e. losses refund tax paid before in the same year.
+#[derive(Debug, Clone)]
+pub struct GermanTax {
+    /// Saver's allowance per year
+    allowance: f64,
+    /// Church tax in percent of the income tax, usually 8% or 9%
+    church_tax_rate: f64,
+    /// Year of the last registered event
+    year: Option<i32>,
+    /// Stock losses carried forward from previous years, positive amount
+    stock_loss_carried: f64,
+    /// General losses carried forward from previous years, positive amount
+    general_loss_carried: f64,
+    /// Net gains from stock sales in the current year
+    stock_income: f64,
+    /// Net income other than stock sales in the current year
+    other_income: f64,
+    /// Tax paid in the current year
+    tax_paid: f64,
+    /// Base rate (Basiszins) of the Vorabpauschale per year
+    base_rates: BTreeMap<i32, f64>,
+    /// Partial exemption (Teilfreistellung) of funds
+    partial_exemption: f64,
+}
+
+impl GermanTax {
+    /// Create new tax model with the given saver's allowance, currently 1000 EUR for single
+    /// and 2000 EUR for jointly assessed persons
+    pub fn new(allowance: f64) -> Self {
+        Self {
+            allowance,
+            church_tax_rate: 0.0,
+            year: None,
+            stock_loss_carried: 0.0,
+            general_loss_carried: 0.0,
+            stock_income: 0.0,
+            other_income: 0.0,
+            tax_paid: 0.0,
+            base_rates: BTreeMap::new(),
+            partial_exemption: 0.0,
+        }
+    }
+
+    /// Apply church tax with the given rate, e.g. 0.08 or 0.09 depending on the federal state
+    pub fn with_church_tax(mut self, church_tax_rate: f64) -> Self {
+        self.church_tax_rate = church_tax_rate;
+        self
+    }
+
+    /// Start with losses carried forward from previous years, given as positive amounts
+    pub fn with_loss_pots(mut self, stock_losses: f64, general_losses: f64) -> Self {
+        self.stock_loss_carried = stock_losses;
+        self.general_loss_carried = general_losses;
+        self
+    }
+
+    /// Use the given base rate (Basiszins) to calculate the Vorabpauschale of funds for the
+    /// given year, e.g. 0.0255 for 2023. No Vorabpauschale is due for years without base rate.
+    pub fn with_base_rate(mut self, year: i32, base_rate: f64) -> Self {
+        self.base_rates.insert(year, base_rate);
+        self
+    }
+
+    /// Apply the partial exemption (Teilfreistellung) to the Vorabpauschale of all funds,
+    /// e.g. 0.3 for equity funds
+    pub fn with_partial_exemption(mut self, partial_exemption: f64) -> Self {
+        self.partial_exemption = partial_exemption;
+        self
+    }
+
+    /// Income tax rate, which is reduced if church tax is due, since church tax is deductible
+    pub fn income_tax_rate(&self) -> f64 {
+        1.0 / (1.0 / FLAT_TAX_RATE + self.church_tax_rate)
+    }
+
+    /// Total tax rate including solidarity surcharge and church tax
+    pub fn tax_rate(&self) -> f64 {
+        self.income_tax_rate() * (1.0 + SOLIDARITY_SURCHARGE + self.church_tax_rate)
+    }
+
+    /// Current stock loss pot, i.e. stock losses not offset so far
+    pub fn stock_loss_pot(&self) -> f64 {
+        self.pots_and_base().0
+    }
+
+    /// Current general loss pot, i.e. other losses not offset so far
+    pub fn general_loss_pot(&self) -> f64 {
+        self.pots_and_base().1
+    }
+
+    /// Tax paid in the current year
+    pub fn tax_paid(&self) -> f64 {
+        self.tax_paid
+    }
+
+    /// Calculate stock loss pot, general loss pot and tax base of the current year
+    fn pots_and_base(&self) -> (f64, f64, f64) {
+        let stock = self.stock_income - self.stock_loss_carried;
+        let other = self.other_income - self.general_loss_carried;
+        let total = stock.max(0.0) + other;
+        let base = (total - self.allowance).max(0.0);
+        ((-stock).max(0.0), (-total).max(0.0), base)
+    }
+
+    /// Carry losses forward and reset the yearly figures if a new year has started
+    fn start_year(&mut self, year: i32) {
+        match self.year {
+            Some(current) if current >= year => {}
+            Some(_) => {
+                let (stock_pot, general_pot, _) = self.pots_and_base();
+                self.stock_loss_carried = stock_pot;
+                self.general_loss_carried = general_pot;
+                self.stock_income = 0.0;
+                self.other_income = 0.0;
+                self.tax_paid = 0.0;
+                self.year = Some(year);
+            }
+            None => self.year = Some(year),
+        }
+    }
+}
+
+impl TaxModel for GermanTax {
+    fn tax(&mut self, date: Date, category: IncomeCategory, amount: f64) -> f64 {
+        self.start_year(date.year());
+        match category {
+            IncomeCategory::StockSale => self.stock_income += amount,
+            _ => self.other_income += amount,
+        }
+        let tax_due = self.pots_and_base().2 * self.tax_rate();
+        let tax = tax_due - self.tax_paid;
+        self.tax_paid = tax_due;
+        tax
+    }
+
+    fn vorabpauschale(&self, year: i32, price_start: f64, price_end: f64, months_held: u8) -> f64 {
+        match self.base_rates.get(&year) {
+            Some(base_rate) => vorabpauschale(
+                price_start,
+                price_end,
+                0.0,
+                *base_rate,
+                months_held,
+                self.partial_exemption,
+            ),
+            None => 0.0,
+        }
+    }
+}
+
+/// Calculate the German Vorabpauschale of an accumulating (or partially distributing) fund
+/// for one calendar year. It is the base return, i.e. 70% of the base rate (Basiszins) on the
+/// value at the start of the year, limited to the increase in value including distributions,
+/// minus the distributions. For funds bought during the year, the amount is reduced by one
+/// twelfth for each full month before the purchase. The result is reduced by the partial
+/// exemption (Teilfreistellung), e.g. 0.3 for equity funds. The Vorabpauschale is taxed on
+/// the first business day of the following year.
+pub fn vorabpauschale(
+    value_start: f64,
+    value_end: f64,
+    distributions: f64,
+    base_rate: f64,
+    months_held: u8,
+    partial_exemption: f64,
+) -> f64 {
+    let base_return = value_start * base_rate * BASE_RETURN_FACTOR;
+    let increase = value_end - value_start + distributions;
+    let lump_sum = (base_return.min(increase) - distributions).max(0.0);
+    lump_sum * f64::from(months_held.min(12)) / 12.0 * (1.0 - partial_exemption)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::Month;
+
+    fn date(year: i32, month: Month) -> Date {
+        Date::from_calendar_date(year, month, 1).unwrap()
+    }
+
+    #[test]
+    fn german_tax_rates() {
+        let tol = 1e-6;
+        let tax = GermanTax::new(1000.0);
+        assert_fuzzy_eq!(tax.tax_rate(), 0.26375, tol);
+        let tax = tax.with_church_tax(0.09);
+        assert_fuzzy_eq!(tax.income_tax_rate(), 0.244499, tol);
+        assert_fuzzy_eq!(tax.tax_rate(), 0.279951, tol);
+    }
+
+    #[test]
+    fn german_tax_allowance_and_loss_pots() {
+        let tol = 1e-6;
+        let rate = 0.26375;
+        let mut tax = GermanTax::new(1000.0);
+        // allowance is used up first
+        let due = tax.tax(date(2021, Month::March), IncomeCategory::Dividend, 800.0);
+        assert_fuzzy_eq!(due, 0.0, tol);
+        let due = tax.tax(date(2021, Month::April), IncomeCategory::StockSale, 1200.0);
+        assert_fuzzy_eq!(due, 1000.0 * rate, tol);
+        // stock losses refund tax paid on stock gains
+        let due = tax.tax(date(2021, Month::May), IncomeCategory::StockSale, -1500.0);
+        assert_fuzzy_eq!(due, -1000.0 * rate, tol);
+        assert_fuzzy_eq!(tax.stock_loss_pot(), 300.0, tol);
+        // but not on other income
+        let due = tax.tax(date(2021, Month::June), IncomeCategory::Interest, 500.0);
+        assert_fuzzy_eq!(due, 300.0 * rate, tol);
+        // general losses are offset against any income
+        let due = tax.tax(date(2021, Month::July), IncomeCategory::OtherSale, -2000.0);
+        assert_fuzzy_eq!(due, -300.0 * rate, tol);
+        assert_fuzzy_eq!(tax.general_loss_pot(), 700.0, tol);
+
+        // loss pots are carried forward, the allowance is renewed
+        let due = tax.tax(
+            date(2022, Month::January),
+            IncomeCategory::StockSale,
+            2500.0,
+        );
+        assert_fuzzy_eq!(due, (2500.0 - 300.0 - 700.0 - 1000.0) * rate, tol);
+        assert_fuzzy_eq!(tax.stock_loss_pot(), 0.0, tol);
+        assert_fuzzy_eq!(tax.general_loss_pot(), 0.0, tol);
+        assert_fuzzy_eq!(tax.tax_paid(), 500.0 * rate, tol);
+    }
+
+    #[test]
+    fn german_tax_vorabpauschale() {
+        let tol = 1e-6;
+        let tax = GermanTax::new(1000.0)
+            .with_base_rate(2023, 0.0255)
+            .with_partial_exemption(0.3);
+        assert_fuzzy_eq!(tax.vorabpauschale(2023, 100.0, 110.0, 12), 1.2495, tol);
+        assert_fuzzy_eq!(tax.vorabpauschale(2022, 100.0, 110.0, 12), 0.0, tol);
+        assert_fuzzy_eq!(
+            FlatTax::new(0.25).vorabpauschale(2023, 100.0, 110.0, 12),
+            0.0,
+            tol
+        );
+    }
+
+    #[test]
+    fn vorabpauschale_calculation() {
+        let tol = 1e-6;
+        // base return limits the lump sum
+        assert_fuzzy_eq!(
+            vorabpauschale(10_000.0, 11_000.0, 0.0, 0.0255, 12, 0.0),
+            178.5,
+            tol
+        );
+        // increase in value limits the lump sum, reduced by distributions and exemption
+        assert_fuzzy_eq!(
+            vorabpauschale(10_000.0, 10_050.0, 20.0, 0.0255, 12, 0.3),
+            35.0,
+            tol
+        );
+        // purchase during the year and no increase in value
+        assert_fuzzy_eq!(
+            vorabpauschale(10_000.0, 11_000.0, 0.0, 0.0255, 6, 0.0),
+            89.25,
+            tol
+        );
+        assert_fuzzy_eq!(
+            vorabpauschale(10_000.0, 9_000.0, 0.0, 0.0255, 12, 0.0),
+            0.0,
+            tol
+        );
+    }
+}
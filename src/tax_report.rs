@@ -0,0 +1,729 @@
+//! Report of realized gains and income for tax statements, e.g. per fiscal year
+//!
+//! The report lists every sale realized against the purchase lots selected by a cost basis
+//! method, all dividend and interest payments together with the tax withheld on them, any
+//! other taxes and all fees within a date range. All amounts are converted to the report's
+//! currency with the fx rates provided by the `Market`. Taxes and fees are given as cash flows,
+//! i.e. they are negative if paid.
+use std::collections::BTreeMap;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+use time::Date;
+
+use crate::datatypes::{
+    Currency, Transaction, TransactionFilter, TransactionHandler, TransactionType,
+};
+use crate::portfolio::{
+    calc_delta_position, calc_transfer_basis, CostBasisMethod, PortfolioPosition, PositionError,
+};
+use crate::Market;
+
+/// Sale of (a part of) a position realized against a single purchase lot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaleLine {
+    pub asset_id: i32,
+    pub asset_name: String,
+    /// Id of the sale transaction
+    pub transaction_id: Option<i32>,
+    pub sale_date: Date,
+    pub acquisition_date: Date,
+    /// Number of units sold, negative if a short position has been closed
+    pub position: f64,
+    /// Purchase value of the units sold, converted with the fx rate at acquisition date
+    pub cost: f64,
+    /// Proceeds of the sale, converted with the fx rate at sale date
+    pub proceeds: f64,
+    pub gain: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IncomeKind {
+    Dividend,
+    Interest,
+}
+
+/// Dividend or interest payment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncomeLine {
+    pub kind: IncomeKind,
+    pub asset_id: i32,
+    pub asset_name: String,
+    pub transaction_id: Option<i32>,
+    pub date: Date,
+    /// Payment before tax
+    pub gross: f64,
+    /// Sum of all tax transactions within the report period referring to the payment
+    pub withheld_tax: f64,
+    pub net: f64,
+}
+
+/// Fee or tax transaction not related to any dividend or interest payment within the report
+/// period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChargeLine {
+    pub transaction_id: Option<i32>,
+    /// Transaction the charge refers to, if any
+    pub related_transaction_id: Option<i32>,
+    pub asset_id: Option<i32>,
+    pub asset_name: Option<String>,
+    pub date: Date,
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaxReportTotals {
+    pub proceeds: f64,
+    pub cost: f64,
+    /// Sum of all positive realized gains
+    pub gains: f64,
+    /// Sum of all realized losses
+    pub losses: f64,
+    pub realized_gain: f64,
+    pub dividends: f64,
+    pub interest: f64,
+    pub withheld_tax: f64,
+    pub other_tax: f64,
+    pub fees: f64,
+}
+
+/// Realized gains and income within a date range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaxReport {
+    /// First date of the report period (inclusive)
+    pub start: Date,
+    /// Last date of the report period (inclusive)
+    pub end: Date,
+    pub currency: Currency,
+    pub cost_basis: CostBasisMethod,
+    pub sales: Vec<SaleLine>,
+    pub income: Vec<IncomeLine>,
+    pub fees: Vec<ChargeLine>,
+    pub other_taxes: Vec<ChargeLine>,
+    pub totals: TaxReportTotals,
+}
+
+/// Flat record with the columns of the CSV representation of a tax report
+#[derive(Serialize)]
+struct CsvRecord<'a> {
+    record_type: &'a str,
+    date: String,
+    transaction_id: Option<i32>,
+    related_transaction_id: Option<i32>,
+    asset_id: Option<i32>,
+    asset_name: Option<&'a str>,
+    acquisition_date: Option<String>,
+    position: Option<f64>,
+    cost: Option<f64>,
+    proceeds: Option<f64>,
+    gain: Option<f64>,
+    amount: Option<f64>,
+    withheld_tax: Option<f64>,
+}
+
+impl<'a> CsvRecord<'a> {
+    fn new(record_type: &'a str, date: Date) -> Self {
+        Self {
+            record_type,
+            date: date.to_string(),
+            transaction_id: None,
+            related_transaction_id: None,
+            asset_id: None,
+            asset_name: None,
+            acquisition_date: None,
+            position: None,
+            cost: None,
+            proceeds: None,
+            gain: None,
+            amount: None,
+            withheld_tax: None,
+        }
+    }
+
+    fn from_charge(record_type: &'a str, charge: &'a ChargeLine) -> Self {
+        Self {
+            transaction_id: charge.transaction_id,
+            related_transaction_id: charge.related_transaction_id,
+            asset_id: charge.asset_id,
+            asset_name: charge.asset_name.as_deref(),
+            amount: Some(charge.amount),
+            ..Self::new(record_type, charge.date)
+        }
+    }
+}
+
+impl TaxReport {
+    /// Serialize the report to JSON
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Write all lines of the report as a single CSV table, the type of each line is
+    /// given in the first column ("sale", "dividend", "interest", "fee" or "tax")
+    pub fn write_csv<W: io::Write>(&self, writer: W) -> Result<(), csv::Error> {
+        let mut writer = csv::Writer::from_writer(writer);
+        for sale in &self.sales {
+            writer.serialize(CsvRecord {
+                transaction_id: sale.transaction_id,
+                asset_id: Some(sale.asset_id),
+                asset_name: Some(&sale.asset_name),
+                acquisition_date: Some(sale.acquisition_date.to_string()),
+                position: Some(sale.position),
+                cost: Some(sale.cost),
+                proceeds: Some(sale.proceeds),
+                gain: Some(sale.gain),
+                ..CsvRecord::new("sale", sale.sale_date)
+            })?;
+        }
+        for income in &self.income {
+            let record_type = match income.kind {
+                IncomeKind::Dividend => "dividend",
+                IncomeKind::Interest => "interest",
+            };
+            writer.serialize(CsvRecord {
+                transaction_id: income.transaction_id,
+                asset_id: Some(income.asset_id),
+                asset_name: Some(&income.asset_name),
+                amount: Some(income.gross),
+                withheld_tax: Some(income.withheld_tax),
+                ..CsvRecord::new(record_type, income.date)
+            })?;
+        }
+        for fee in &self.fees {
+            writer.serialize(CsvRecord::from_charge("fee", fee))?;
+        }
+        for tax in &self.other_taxes {
+            writer.serialize(CsvRecord::from_charge("tax", tax))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Serialize the report to a CSV table as described in `write_csv`
+    pub fn to_csv(&self) -> Result<String, csv::Error> {
+        let mut buffer = Vec::new();
+        self.write_csv(&mut buffer)?;
+        String::from_utf8(buffer)
+            .map_err(|err| csv::Error::from(io::Error::new(io::ErrorKind::InvalidData, err)))
+    }
+
+    fn calc_totals(&mut self) {
+        let mut totals = TaxReportTotals::default();
+        for sale in &self.sales {
+            totals.proceeds += sale.proceeds;
+            totals.cost += sale.cost;
+            if sale.gain >= 0.0 {
+                totals.gains += sale.gain;
+            } else {
+                totals.losses += sale.gain;
+            }
+        }
+        totals.realized_gain = totals.gains + totals.losses;
+        for income in &self.income {
+            match income.kind {
+                IncomeKind::Dividend => totals.dividends += income.gross,
+                IncomeKind::Interest => totals.interest += income.gross,
+            }
+            totals.withheld_tax += income.withheld_tax;
+        }
+        totals.other_tax = self.other_taxes.iter().map(|tax| tax.amount).sum();
+        totals.fees = self.fees.iter().map(|fee| fee.amount).sum();
+        self.totals = totals;
+    }
+}
+
+/// Asset names looked up in the market's database, fetched only once per asset
+struct AssetNames<'a> {
+    market: &'a Market,
+    names: BTreeMap<i32, String>,
+}
+
+impl AssetNames<'_> {
+    async fn get(&mut self, asset_id: i32) -> Result<String, PositionError> {
+        if let Some(name) = self.names.get(&asset_id) {
+            return Ok(name.clone());
+        }
+        let name = self.market.db().get_asset_by_id(asset_id).await?.name();
+        self.names.insert(asset_id, name.clone());
+        Ok(name)
+    }
+}
+
+/// Calculate the tax report for the date range from `start` to `end` (both inclusive) from a
+/// list of transactions ordered by cash flow date. The list must contain all transactions
+/// before `start` as well, since sales are realized against purchase lots opened earlier.
+pub async fn calc_tax_report(
+    currency: Currency,
+    transactions: &[Transaction],
+    start: Date,
+    end: Date,
+    cost_basis: CostBasisMethod,
+    market: &Market,
+) -> Result<TaxReport, PositionError> {
+    let positions = PortfolioPosition::with_cost_basis(currency, cost_basis);
+    tax_report(positions, transactions, start, end, market).await
+}
+
+async fn tax_report(
+    mut positions: PortfolioPosition,
+    transactions: &[Transaction],
+    start: Date,
+    end: Date,
+    market: &Market,
+) -> Result<TaxReport, PositionError> {
+    let currency = positions.cash.currency;
+    let next_day = end.next_day().ok_or(PositionError::InvalidDate)?;
+    calc_delta_position(
+        &mut positions,
+        transactions,
+        None,
+        Some(next_day),
+        market.clone(),
+    )
+    .await?;
+
+    let by_id: BTreeMap<i32, &Transaction> = transactions
+        .iter()
+        .filter_map(|trans| trans.id.map(|id| (id, trans)))
+        .collect();
+    let in_period =
+        |trans: &Transaction| trans.cash_flow.date >= start && trans.cash_flow.date <= end;
+    // taxes within the report period by the id of the transaction they refer to
+    let mut taxes: BTreeMap<i32, Vec<&Transaction>> = BTreeMap::new();
+    for trans in transactions.iter().filter(|trans| in_period(trans)) {
+        if let TransactionType::Tax {
+            transaction_ref: Some(id),
+            ..
+        } = trans.transaction_type
+        {
+            taxes.entry(id).or_default().push(trans);
+        }
+    }
+    let mut names = AssetNames {
+        market,
+        names: BTreeMap::new(),
+    };
+    let mut report = TaxReport {
+        start,
+        end,
+        currency,
+        cost_basis: positions.cost_basis.unwrap_or_default(),
+        sales: Vec::new(),
+        income: Vec::new(),
+        fees: Vec::new(),
+        other_taxes: Vec::new(),
+        totals: TaxReportTotals::default(),
+    };
+
+    for gain in positions.realized_gains() {
+        if gain.date < start || gain.date > end {
+            continue;
+        }
+        let sale_currency = gain
+            .transaction_id
+            .and_then(|id| by_id.get(&id))
+            .map_or(currency, |trans| trans.cash_flow.amount.currency);
+        let lot_currency = gain
+            .lot_transaction_id
+            .and_then(|id| by_id.get(&id))
+            .map_or(sale_currency, |trans| trans.cash_flow.amount.currency);
+        let proceeds = market
+            .convert(gain.proceeds, sale_currency, currency, gain.date)
+            .await?;
+        let cost = -market
+            .convert(gain.purchase_value, lot_currency, currency, gain.lot_date)
+            .await?;
+        report.sales.push(SaleLine {
+            asset_id: gain.asset_id,
+            asset_name: names.get(gain.asset_id).await?,
+            transaction_id: gain.transaction_id,
+            sale_date: gain.date,
+            acquisition_date: gain.lot_date,
+            position: -gain.position,
+            cost,
+            proceeds,
+            gain: proceeds - cost,
+        });
+    }
+
+    for trans in transactions {
+        let date = trans.cash_flow.date;
+        if !in_period(trans) {
+            continue;
+        }
+        let amount = market
+            .convert(
+                trans.cash_flow.amount.amount,
+                trans.cash_flow.amount.currency,
+                currency,
+                date,
+            )
+            .await?;
+        match trans.transaction_type {
+            TransactionType::Dividend { asset_id } | TransactionType::Interest { asset_id } => {
+                let kind = if let TransactionType::Dividend { .. } = trans.transaction_type {
+                    IncomeKind::Dividend
+                } else {
+                    IncomeKind::Interest
+                };
+                let mut withheld_tax = 0.0;
+                for tax in trans.id.and_then(|id| taxes.get(&id)).into_iter().flatten() {
+                    withheld_tax += market
+                        .convert(
+                            tax.cash_flow.amount.amount,
+                            tax.cash_flow.amount.currency,
+                            currency,
+                            tax.cash_flow.date,
+                        )
+                        .await?;
+                }
+                report.income.push(IncomeLine {
+                    kind,
+                    asset_id,
+                    asset_name: names.get(asset_id).await?,
+                    transaction_id: trans.id,
+                    date,
+                    gross: amount,
+                    withheld_tax,
+                    net: amount + withheld_tax,
+                });
+            }
+            TransactionType::Fee { transaction_ref }
+            | TransactionType::Tax {
+                transaction_ref, ..
+            } => {
+                let related = transaction_ref.and_then(|id| by_id.get(&id));
+                let is_fee = matches!(trans.transaction_type, TransactionType::Fee { .. });
+                if !is_fee
+                    && related.is_some_and(|related| {
+                        in_period(related)
+                            && matches!(
+                                related.transaction_type,
+                                TransactionType::Dividend { .. } | TransactionType::Interest { .. }
+                            )
+                    })
+                {
+                    // already reported as withheld tax of the income line
+                    continue;
+                }
+                let asset_id = related.and_then(|related| related.transaction_type.asset_id());
+                let asset_name = match asset_id {
+                    Some(asset_id) => Some(names.get(asset_id).await?),
+                    None => None,
+                };
+                let charge = ChargeLine {
+                    transaction_id: trans.id,
+                    related_transaction_id: transaction_ref,
+                    asset_id,
+                    asset_name,
+                    date,
+                    amount,
+                };
+                if is_fee {
+                    report.fees.push(charge);
+                } else {
+                    report.other_taxes.push(charge);
+                }
+            }
+            _ => {}
+        }
+    }
+    report.calc_totals();
+    Ok(report)
+}
+
+/// Calculate the tax report for all transactions booked to a selected group of accounts,
+/// e.g. a single depot, or for all transactions if no accounts are given
+pub async fn calc_tax_report_for_accounts(
+    currency: Currency,
+    account_ids: Option<&[i32]>,
+    start: Date,
+    end: Date,
+    cost_basis: CostBasisMethod,
+    db: &(dyn TransactionHandler + Send + Sync),
+    market: &Market,
+) -> Result<TaxReport, PositionError> {
+    let filter = TransactionFilter {
+        end: Some(end),
+        account_ids: account_ids.map(|ids| ids.to_vec()),
+        ..Default::default()
+    };
+    let transactions = db.get_transactions(&filter).await?;
+    let mut positions = PortfolioPosition::with_cost_basis(currency, cost_basis);
+    if account_ids.is_some() {
+        positions.transfers =
+            calc_transfer_basis(currency, Some(end), Some(cost_basis), db, market).await?;
+    }
+    tax_report(positions, &transactions, start, end, market).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use time::Month;
+
+    use crate::datatypes::{
+        date_time_helper::make_offset_time, Account, CashFlow, Quote, QuoteHandler, TaxKind, Ticker,
+    };
+    use crate::memory::{tests::insert_stock_with_quotes, InMemoryDB};
+
+    #[tokio::test]
+    async fn tax_report_for_fiscal_year() {
+        let tol = 1e-6;
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let usd = market.get_currency_from_str("USD").await.unwrap();
+        // 1 USD = 0.8 EUR in 2020 and 0.9 EUR in 2021
+        let fx_ticker = db
+            .insert_ticker(&Ticker {
+                id: None,
+                asset: usd.id.unwrap(),
+                name: "USDEUR".to_string(),
+                currency: eur,
+                source: "manual".to_string(),
+                priority: 1,
+                factor: 1.0,
+                tz: None,
+                cal: None,
+            })
+            .await
+            .unwrap();
+        for (year, price) in [(2020, 0.8), (2021, 0.9)] {
+            db.insert_quote(&Quote {
+                id: None,
+                ticker: fx_ticker,
+                price,
+                time: make_offset_time(year, 1, 1, 0, 0, 0).unwrap(),
+                volume: None,
+            })
+            .await
+            .unwrap();
+        }
+        let asset_id = insert_stock_with_quotes(&db, "Apple Inc.", usd, &[]).await;
+        let depot = db
+            .insert_account(&Account::new(None, "Depot".to_string(), eur, None, None))
+            .await
+            .unwrap();
+        let other = db
+            .insert_account(&Account::new(None, "Other".to_string(), eur, None, None))
+            .await
+            .unwrap();
+        let date = |year, month| Date::from_calendar_date(year, month, 15).unwrap();
+        let transactions = [
+            (
+                TransactionType::Asset {
+                    asset_id,
+                    position: 10.0,
+                },
+                -1_000.0,
+                date(2020, Month::March),
+                depot,
+            ),
+            (
+                TransactionType::Asset {
+                    asset_id,
+                    position: 10.0,
+                },
+                -1_500.0,
+                date(2021, Month::February),
+                depot,
+            ),
+            (
+                TransactionType::Dividend { asset_id },
+                100.0,
+                date(2021, Month::May),
+                depot,
+            ),
+            (
+                TransactionType::Tax {
+                    transaction_ref: Some(3),
+                    kind: TaxKind::Domestic,
+                    country: None,
+                },
+                -15.0,
+                date(2021, Month::May),
+                depot,
+            ),
+            (
+                TransactionType::Asset {
+                    asset_id,
+                    position: -15.0,
+                },
+                2_400.0,
+                date(2021, Month::August),
+                depot,
+            ),
+            (
+                TransactionType::Fee {
+                    transaction_ref: Some(5),
+                },
+                -10.0,
+                date(2021, Month::August),
+                depot,
+            ),
+            (
+                TransactionType::Dividend { asset_id },
+                50.0,
+                date(2021, Month::September),
+                other,
+            ),
+        ];
+        for (transaction_type, amount, date, account_id) in transactions {
+            db.insert_transaction(&Transaction {
+                id: None,
+                transaction_type,
+                cash_flow: CashFlow::new(amount, usd, date),
+                note: None,
+                account_id: Some(account_id),
+            })
+            .await
+            .unwrap();
+        }
+
+        let report = calc_tax_report_for_accounts(
+            eur,
+            Some(&[depot]),
+            date(2021, Month::January),
+            date(2021, Month::December),
+            CostBasisMethod::Fifo,
+            &db,
+            &market,
+        )
+        .await
+        .unwrap();
+        // first lot bought in 2020 at 0.8, second lot in 2021 at 0.9
+        assert_eq!(report.sales.len(), 2);
+        let first = &report.sales[0];
+        assert_eq!(first.acquisition_date, date(2020, Month::March));
+        assert_fuzzy_eq!(first.position, 10.0, tol);
+        assert_fuzzy_eq!(first.cost, 800.0, tol);
+        assert_fuzzy_eq!(first.proceeds, 1_440.0, tol);
+        assert_fuzzy_eq!(first.gain, 640.0, tol);
+        let second = &report.sales[1];
+        assert_eq!(second.acquisition_date, date(2021, Month::February));
+        assert_fuzzy_eq!(second.cost, 675.0, tol);
+        assert_fuzzy_eq!(second.gain, 720.0 - 675.0, tol);
+        assert_eq!(second.asset_name, "Apple Inc.");
+
+        assert_eq!(report.income.len(), 1);
+        assert_eq!(report.income[0].kind, IncomeKind::Dividend);
+        assert_fuzzy_eq!(report.income[0].gross, 90.0, tol);
+        assert_fuzzy_eq!(report.income[0].withheld_tax, -13.5, tol);
+        assert!(report.other_taxes.is_empty());
+        assert_eq!(report.fees.len(), 1);
+        assert_eq!(report.fees[0].asset_id, Some(asset_id));
+
+        let totals = &report.totals;
+        assert_fuzzy_eq!(totals.realized_gain, 685.0, tol);
+        assert_fuzzy_eq!(totals.losses, 0.0, tol);
+        assert_fuzzy_eq!(totals.dividends, 90.0, tol);
+        assert_fuzzy_eq!(totals.withheld_tax, -13.5, tol);
+        assert_fuzzy_eq!(totals.fees, -9.0, tol);
+
+        let csv = report.to_csv().unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].starts_with("record_type,date,transaction_id"));
+        assert!(lines[1].starts_with(&format!(
+            "sale,2021-08-15,5,,{asset_id},Apple Inc.,2020-03-15,10.0,"
+        )));
+        assert!(lines[3].starts_with("dividend,2021-05-15,3,"));
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["sales"].as_array().unwrap().len(), 2);
+        assert_fuzzy_eq!(json["totals"]["dividends"].as_f64().unwrap(), 90.0, tol);
+
+        // without account restriction, the dividend booked to the other account is included
+        let report = calc_tax_report_for_accounts(
+            eur,
+            None,
+            date(2021, Month::January),
+            date(2021, Month::December),
+            CostBasisMethod::Fifo,
+            &db,
+            &market,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.income.len(), 2);
+        assert_fuzzy_eq!(report.totals.dividends, 135.0, tol);
+    }
+
+    #[tokio::test]
+    async fn withheld_tax_at_period_boundaries() {
+        let tol = 1e-6;
+        let db = InMemoryDB::new();
+        let market = Market::new(Arc::new(db.clone())).await;
+        let eur = market.get_currency_from_str("EUR").await.unwrap();
+        let asset_id = insert_stock_with_quotes(&db, "BASF SE", eur, &[]).await;
+        let tax_on = |transaction_ref| TransactionType::Tax {
+            transaction_ref: Some(transaction_ref),
+            kind: TaxKind::Domestic,
+            country: None,
+        };
+        let transactions: Vec<Transaction> = vec![
+            (
+                TransactionType::Dividend { asset_id },
+                100.0,
+                Date::from_calendar_date(2020, Month::December, 20).unwrap(),
+            ),
+            // tax on the dividend of the previous year
+            (
+                tax_on(1),
+                -15.0,
+                Date::from_calendar_date(2021, Month::January, 10).unwrap(),
+            ),
+            (
+                TransactionType::Dividend { asset_id },
+                200.0,
+                Date::from_calendar_date(2021, Month::December, 20).unwrap(),
+            ),
+            (
+                tax_on(3),
+                -30.0,
+                Date::from_calendar_date(2021, Month::December, 20).unwrap(),
+            ),
+            // tax booked after the end of the period
+            (
+                tax_on(3),
+                -5.0,
+                Date::from_calendar_date(2022, Month::January, 10).unwrap(),
+            ),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (transaction_type, amount, date))| Transaction {
+            id: Some(idx as i32 + 1),
+            transaction_type,
+            cash_flow: CashFlow::new(amount, eur, date),
+            note: None,
+            account_id: None,
+        })
+        .collect();
+
+        let report = calc_tax_report(
+            eur,
+            &transactions,
+            Date::from_calendar_date(2021, Month::January, 1).unwrap(),
+            Date::from_calendar_date(2021, Month::December, 31).unwrap(),
+            CostBasisMethod::Fifo,
+            &market,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.income.len(), 1);
+        assert_eq!(report.income[0].transaction_id, Some(3));
+        assert_fuzzy_eq!(report.income[0].withheld_tax, -30.0, tol);
+        assert_fuzzy_eq!(report.income[0].net, 170.0, tol);
+        // the tax on last year's dividend is reported on its own line
+        assert_eq!(report.other_taxes.len(), 1);
+        assert_eq!(report.other_taxes[0].transaction_id, Some(2));
+        assert_eq!(report.other_taxes[0].related_transaction_id, Some(1));
+        assert_eq!(report.other_taxes[0].asset_id, Some(asset_id));
+        assert_fuzzy_eq!(report.other_taxes[0].amount, -15.0, tol);
+        assert_fuzzy_eq!(report.totals.withheld_tax, -30.0, tol);
+        assert_fuzzy_eq!(report.totals.other_tax, -15.0, tol);
+    }
+}